@@ -4,8 +4,24 @@ use serde::Deserialize;
 use std::env;
 use std::fs;
 use std::io;
+use std::path::Path;
 use std::path::PathBuf;
 
+/// The directory containing architecture-specific files.
+const ARCH_DIR: &str = "arch";
+
+/// Returns the list of supported architectures, which are the ones having a directory in
+/// [`ARCH_DIR`].
+fn supported_archs() -> io::Result<Vec<String>> {
+	let mut archs = fs::read_dir(ARCH_DIR)?
+		.filter_map(Result::ok)
+		.filter(|e| e.path().is_dir())
+		.filter_map(|e| e.file_name().into_string().ok())
+		.collect::<Vec<_>>();
+	archs.sort();
+	Ok(archs)
+}
+
 /// Structure representing the content of the target JSON file.
 ///
 /// This structure contains only the fields that are of interest.
@@ -33,7 +49,17 @@ impl Target {
 		let Ok(arch) = env::var("CARGO_CFG_TARGET_ARCH") else {
 			return Ok(None);
 		};
-		let target_path = PathBuf::from(format!("arch/{arch}/{arch}.json"));
+		let target_path = PathBuf::from(format!("{ARCH_DIR}/{arch}/{arch}.json"));
+		if !Path::new(ARCH_DIR).join(&arch).is_dir() {
+			let archs = supported_archs()?;
+			return Err(io::Error::new(
+				io::ErrorKind::Unsupported,
+				format!(
+					"unsupported architecture `{arch}` (supported: {})",
+					archs.join(", ")
+				),
+			));
+		}
 
 		// Read and parse target file
 		let content = fs::read_to_string(target_path)?;
@@ -52,7 +78,7 @@ impl Target {
 
 	/// Returns the path to the linker script of the target.
 	pub fn get_linker_script_path(&self) -> PathBuf {
-		PathBuf::from(format!("arch/{}/linker.ld", self.name))
+		PathBuf::from(format!("{ARCH_DIR}/{}/linker.ld", self.name))
 	}
 
 	/// Returns the target's triplet.
@@ -1,5 +1,6 @@
 //! CPU-specific features.
 
+pub mod per_cpu;
 pub mod sse;
 
 use core::ffi::c_void;
@@ -0,0 +1,185 @@
+//! Per-CPU variables hold one instance of a value for each CPU, so that each CPU can access its
+//! own instance without synchronization.
+//!
+//! The instance of the current CPU may only be accessed while the current context cannot be
+//! preempted, since it could otherwise be moved to another CPU in the middle of the access. This
+//! is enforced by requiring a [`PreemptGuard`].
+//!
+//! The kernel does not have a preemption counter yet: preemption happens on the timer interrupt,
+//! thus disabling interrupts is used to disable preemption.
+
+use crate::errno::AllocResult;
+use crate::util::container::vec::Vec;
+use crate::util::lock::int_disable;
+use crate::util::lock::int_restore;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+
+/// Returns the number of CPUs on the system.
+pub fn get_cores_count() -> usize {
+	// TODO When implementing multicore, use the number of CPUs found in the MADT
+	1
+}
+
+/// Returns the index of the CPU running the current context, in range `0..get_cores_count()`.
+///
+/// The returned index is meaningful only as long as the current context cannot be preempted.
+pub fn get_current_cpu() -> usize {
+	// TODO When implementing multicore, retrieve the index from the CPU's local data through
+	// %gs
+	0
+}
+
+/// Guard preventing the current context from being preempted, and thus moved to another CPU,
+/// as long as it is alive.
+///
+/// Guards can be nested. Preemption is enabled again when the last one is dropped, if it was
+/// enabled before the first one was created.
+pub struct PreemptGuard {
+	/// The guard must be dropped on the CPU it has been created on.
+	_not_send: PhantomData<*const ()>,
+}
+
+impl Default for PreemptGuard {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl PreemptGuard {
+	/// Disables preemption until the returned guard is dropped.
+	pub fn new() -> Self {
+		int_disable();
+		Self {
+			_not_send: PhantomData,
+		}
+	}
+
+	/// Returns the index of the current CPU.
+	///
+	/// Contrary to [`get_current_cpu`], the index remains valid as long as the guard is alive.
+	#[inline]
+	pub fn get_cpu(&self) -> usize {
+		get_current_cpu()
+	}
+}
+
+impl Drop for PreemptGuard {
+	fn drop(&mut self) {
+		unsafe {
+			int_restore();
+		}
+	}
+}
+
+/// A variable having one instance for each CPU.
+///
+/// Each CPU accesses its own instance through [`PerCpu::get`] and [`PerCpu::get_mut`]. Since
+/// nothing else can run on the current CPU while preemption is disabled, types with interior
+/// mutability such as `Cell` can be used without synchronization.
+pub struct PerCpu<T> {
+	/// The instances, indexed by CPU.
+	data: Vec<UnsafeCell<T>>,
+}
+
+// Each instance is accessed only by the CPU it belongs to, except through `iter`, which
+// requires `T: Sync`
+unsafe impl<T: Send> Sync for PerCpu<T> {}
+
+impl<T> PerCpu<T> {
+	/// Creates a new instance.
+	///
+	/// `init` is called for each CPU with its index, and returns the instance of this CPU.
+	pub fn new<F: FnMut(usize) -> T>(mut init: F) -> AllocResult<Self> {
+		let count = get_cores_count();
+		let mut data = Vec::with_capacity(count)?;
+		for cpu in 0..count {
+			data.push(UnsafeCell::new(init(cpu)))?;
+		}
+		Ok(Self {
+			data,
+		})
+	}
+
+	/// Returns a reference to the instance of the current CPU.
+	///
+	/// The reference cannot outlive `guard`, so that the current context cannot be moved to
+	/// another CPU while using it.
+	#[inline]
+	pub fn get<'a>(&'a self, guard: &'a PreemptGuard) -> &'a T {
+		unsafe { &*self.data[guard.get_cpu()].get() }
+	}
+
+	/// Returns a mutable reference to the instance of the current CPU.
+	///
+	/// The reference cannot outlive `guard`, so that the current context cannot be moved to
+	/// another CPU while using it.
+	///
+	/// # Safety
+	///
+	/// No other reference to the instance of the current CPU may be alive at the same time,
+	/// including from another guard.
+	#[inline]
+	#[allow(clippy::mut_from_ref)]
+	pub unsafe fn get_mut<'a>(&'a self, guard: &'a PreemptGuard) -> &'a mut T {
+		&mut *self.data[guard.get_cpu()].get()
+	}
+}
+
+impl<T: Sync> PerCpu<T> {
+	/// Returns an iterator over the instances of all CPUs, in order of CPU index.
+	///
+	/// This is useful to aggregate values, such as statistics.
+	pub fn iter(&self) -> impl Iterator<Item = &T> {
+		self.data.iter().map(|i| unsafe { &*i.get() })
+	}
+}
+
+impl<T> fmt::Debug for PerCpu<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("PerCpu").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use core::cell::Cell;
+	use core::sync::atomic::AtomicUsize;
+	use core::sync::atomic::Ordering;
+
+	#[test_case]
+	fn per_cpu_get() {
+		let var = PerCpu::new(|cpu| Cell::new(cpu * 10)).unwrap();
+		let guard = PreemptGuard::new();
+		let val = var.get(&guard);
+		assert_eq!(val.get(), guard.get_cpu() * 10);
+		val.set(42);
+		assert_eq!(var.get(&guard).get(), 42);
+
+		// Nested guards access the same instance
+		let nested = PreemptGuard::new();
+		assert_eq!(var.get(&nested).get(), 42);
+		drop(nested);
+		drop(guard);
+
+		let guard = PreemptGuard::new();
+		unsafe {
+			*var.get_mut(&guard) = Cell::new(1);
+		}
+		assert_eq!(var.get(&guard).get(), 1);
+	}
+
+	#[test_case]
+	fn per_cpu_iter() {
+		let var = PerCpu::new(|_| AtomicUsize::new(0)).unwrap();
+		for _ in 0..5 {
+			let guard = PreemptGuard::new();
+			var.get(&guard).fetch_add(1, Ordering::Relaxed);
+		}
+		assert_eq!(var.iter().count(), get_cores_count());
+		let total: usize = var.iter().map(|v| v.load(Ordering::Relaxed)).sum();
+		assert_eq!(total, 5);
+	}
+}
@@ -2,9 +2,11 @@
 
 use crate::crypto::chacha20;
 use crate::errno::EResult;
+use crate::util::container::hashmap;
 use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
 use crate::util::lock::IntMutex;
+use core::mem::MaybeUninit;
 
 /// The size of the entropy buffer in bytes.
 const ENTROPY_BUFFER_SIZE: usize = 32768;
@@ -17,9 +19,9 @@ const ENTROPY_THRESHOLD: usize = 1024;
 pub struct EntropyPool {
 	/// Data pending to be treated. This buffer is used as a cache when input data is not large
 	/// enough.
-	pending: RingBuffer<u8, Vec<u8>>,
+	pending: RingBuffer<u8, Vec<MaybeUninit<u8>>>,
 	/// The buffer containing entropy.
-	buff: RingBuffer<u8, Vec<u8>>,
+	buff: RingBuffer<u8, Vec<MaybeUninit<u8>>>,
 
 	/// The ChaCha20 counter.
 	counter: u64,
@@ -32,8 +34,8 @@ impl EntropyPool {
 	/// Creates a new instance.
 	pub fn new() -> EResult<Self> {
 		Ok(Self {
-			pending: RingBuffer::new(crate::vec![0; 56]?),
-			buff: RingBuffer::new(crate::vec![0; ENTROPY_BUFFER_SIZE]?),
+			pending: RingBuffer::with_capacity(56)?,
+			buff: RingBuffer::with_capacity(ENTROPY_BUFFER_SIZE)?,
 
 			counter: 0,
 
@@ -145,7 +147,16 @@ pub static ENTROPY_POOL: IntMutex<Option<EntropyPool>> = IntMutex::new(None);
 
 /// Initializes randomness sources.
 pub fn init() -> EResult<()> {
-	*ENTROPY_POOL.lock() = Some(EntropyPool::new()?);
+	let mut pool = EntropyPool::new()?;
+	// Hash maps derive their keys from these, without accessing the pool
+	let mut keys = [0u8; 16];
+	pool.read(&mut keys, true);
+	let (k0, k1) = keys.split_at(8);
+	hashmap::init_sip_keys(
+		u64::from_le_bytes(k0.try_into().unwrap()),
+		u64::from_le_bytes(k1.try_into().unwrap()),
+	);
+	*ENTROPY_POOL.lock() = Some(pool);
 
 	Ok(())
 }
@@ -5,6 +5,7 @@ use crate::memory;
 use crate::multiboot;
 use crate::util::DisplayableStr;
 use core::ffi::c_void;
+use core::fmt;
 use core::ptr::null_mut;
 
 /// Fills the slice `stack` with the callstack starting at `frame`.
@@ -32,14 +33,14 @@ pub fn get_callstack(mut frame: *mut usize, stack: &mut [*mut c_void]) {
 	}
 }
 
-/// Prints a callstack, including symbols' names and addresses.
+/// Writes a callstack to `out`, including symbols' names, offsets and addresses.
 ///
 /// `stack` is the callstack to print.
 ///
 /// If the callstack is empty, the function just prints `Empty`.
-pub fn print_callstack(stack: &[*mut c_void]) {
+pub fn print_callstack<W: fmt::Write>(out: &mut W, stack: &[*mut c_void]) {
 	if stack.is_empty() || stack[0].is_null() {
-		crate::println!("Empty");
+		let _ = writeln!(out, "Empty");
 		return;
 	}
 
@@ -49,14 +50,18 @@ pub fn print_callstack(stack: &[*mut c_void]) {
 			break;
 		}
 
-		let name = elf::get_function_name(
+		let func = elf::get_function_name(
 			memory::kern_to_virt(boot_info.elf_sections),
 			boot_info.elf_num as usize,
 			boot_info.elf_shndx as usize,
 			boot_info.elf_entsize as usize,
 			*pc,
-		)
-		.unwrap_or(b"???");
-		crate::println!("{i}: {pc:p} -> {}", DisplayableStr(name));
+		);
+		let _ = match func {
+			Some((name, off)) => {
+				writeln!(out, "{i}: {pc:p} -> {}+{off:#x}", DisplayableStr(name))
+			}
+			None => writeln!(out, "{i}: {pc:p}"),
+		};
 	}
 }
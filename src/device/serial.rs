@@ -2,6 +2,7 @@
 
 use crate::io;
 use crate::util::lock::Mutex;
+use core::fmt;
 
 /// The offset of COM1 registers.
 pub const COM1: u16 = 0x3f8;
@@ -156,6 +157,13 @@ impl Serial {
 	}
 }
 
+impl fmt::Write for Serial {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.write(s.as_bytes());
+		Ok(())
+	}
+}
+
 /// The list of serial ports.
 static mut PORTS: [Option<Mutex<Serial>>; 4] = [None, None, None, None];
 
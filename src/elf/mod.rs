@@ -455,7 +455,33 @@ pub fn get_symbol_name(strtab_section: &ELF32SectionHeader, offset: u32) -> &'st
 	}
 }
 
-/// Returns the name of the kernel function for the given instruction pointer.
+/// Returns the symbol containing the address `addr`, or else the nearest symbol preceding it.
+///
+/// `symbols` is an iterator over the `(value, size, symbol)` tuples of the symbol table.
+///
+/// On success, the function returns the symbol and the offset of `addr` relative to it.
+fn nearest_symbol<T, I: Iterator<Item = (usize, usize, T)>>(
+	symbols: I,
+	addr: usize,
+) -> Option<(T, usize)> {
+	let mut nearest: Option<(usize, T)> = None;
+	for (value, size, sym) in symbols {
+		if addr < value {
+			continue;
+		}
+		if addr - value < size {
+			return Some((sym, addr - value));
+		}
+		if nearest.as_ref().map_or(true, |(v, _)| value > *v) {
+			nearest = Some((value, sym));
+		}
+	}
+
+	nearest.map(|(value, sym)| (sym, addr - value))
+}
+
+/// Returns the name of the kernel function for the given instruction pointer, along with the
+/// offset of the instruction in the function.
 ///
 /// Arguments:
 /// - `sections` is a pointer to the ELF sections of the kernel in the virtual memory.
@@ -464,6 +490,8 @@ pub fn get_symbol_name(strtab_section: &ELF32SectionHeader, offset: u32) -> &'st
 /// - `entsize` is the size of section entries.
 /// - `inst` is the pointer to the instruction on the virtual memory.
 ///
+/// If no function contains the instruction, the nearest function preceding it is returned.
+///
 /// If the name cannot be retrieved, the function returns `None`.
 pub fn get_function_name(
 	sections: *const c_void,
@@ -471,7 +499,7 @@ pub fn get_function_name(
 	shndx: usize,
 	entsize: usize,
 	inst: *const c_void,
-) -> Option<&'static [u8]> {
+) -> Option<(&'static [u8], usize)> {
 	let strtab_section = get_section(
 		sections,
 		sections_count,
@@ -479,7 +507,7 @@ pub fn get_function_name(
 		entsize,
 		".strtab".as_bytes(),
 	)?;
-	let mut func_name: Option<&'static [u8]> = None;
+	let mut func: Option<(&'static [u8], usize)> = None;
 
 	foreach_sections(
 		sections,
@@ -494,28 +522,19 @@ pub fn get_function_name(
 			let ptr = memory::kern_to_virt(hdr.sh_addr as *const u8);
 			debug_assert!(hdr.sh_entsize > 0);
 
-			let mut i: usize = 0;
-			while i < hdr.sh_size as usize {
-				let sym = unsafe { &*(ptr.add(i) as *const ELF32Sym) };
-
-				let value = sym.st_value as usize;
-				let size = sym.st_size as usize;
-				if (inst as usize) >= value && (inst as usize) < (value + size) {
-					if sym.st_name != 0 {
-						func_name = Some(get_symbol_name(strtab_section, sym.st_name));
-					}
-
-					return false;
-				}
-
-				i += hdr.sh_entsize as usize;
-			}
+			let syms = (0..hdr.sh_size as usize)
+				.step_by(hdr.sh_entsize as usize)
+				.map(|i| unsafe { &*(ptr.add(i) as *const ELF32Sym) })
+				.filter(|sym| sym.st_name != 0)
+				.map(|sym| (sym.st_value as usize, sym.st_size as usize, sym.st_name));
+			func = nearest_symbol(syms, inst as usize)
+				.map(|(name, off)| (get_symbol_name(strtab_section, name), off));
 
-			true
+			false
 		},
 	);
 
-	func_name
+	func
 }
 
 /// Returns the kernel symbol with the name `name`.
@@ -572,3 +591,25 @@ pub fn get_kernel_symbol(
 
 	symbol
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn nearest_symbol_lookup() {
+		let symbols = [(0x3000, 0x100, "c"), (0x1000, 0x80, "a"), (0x2000, 0, "b")];
+		let lookup = |addr| nearest_symbol(symbols.iter().copied(), addr);
+
+		// Inside a symbol
+		assert_eq!(lookup(0x1000), Some(("a", 0)));
+		assert_eq!(lookup(0x1042), Some(("a", 0x42)));
+		assert_eq!(lookup(0x30ff), Some(("c", 0xff)));
+		// Past the end of a symbol, or in a symbol without a size
+		assert_eq!(lookup(0x1080), Some(("a", 0x80)));
+		assert_eq!(lookup(0x2010), Some(("b", 0x10)));
+		assert_eq!(lookup(0x4000), Some(("c", 0x1000)));
+		// Before any symbol
+		assert_eq!(lookup(0xfff), None);
+	}
+}
@@ -62,10 +62,9 @@ impl<'a> ELFParser<'a> {
 			return Err(errno!(EINVAL));
 		}
 
-		if self.image.len() < size_of::<ELF32ELFHeader>() {
-			return Err(errno!(EINVAL));
-		}
-		let ehdr = self.get_header();
+		// The header is aligned as long as the image is
+		let ehdr = unsafe { util::reinterpret::<ELF32ELFHeader>(self.image) }
+			.ok_or_else(|| errno!(EINVAL))?;
 
 		// TODO Check e_machine
 		// TODO Check e_version
@@ -93,7 +92,8 @@ impl<'a> ELFParser<'a> {
 				// Safe because in range of the slice
 				util::reinterpret::<ELF32ProgramHeader>(&self.image[off..])
 			}
-			.unwrap();
+			// Fails if the table or its entries are misaligned
+			.ok_or_else(|| errno!(EINVAL))?;
 
 			phdr.is_valid(self.image.len())?;
 		}
@@ -104,7 +104,8 @@ impl<'a> ELFParser<'a> {
 				// Safe because in range of the slice
 				util::reinterpret::<ELF32SectionHeader>(&self.image[off..])
 			}
-			.unwrap();
+			// Fails if the table or its entries are misaligned
+			.ok_or_else(|| errno!(EINVAL))?;
 
 			shdr.is_valid(self.image.len())?;
 		}
@@ -284,3 +285,54 @@ impl<'a> ELFParser<'a> {
 			.next()
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use core::ptr;
+
+	/// An ELF image, aligned for its headers.
+	#[repr(C, align(4))]
+	struct Image([u8; 0x100]);
+
+	/// Returns an image with a program header table at offset `phoff`.
+	fn image_with_phoff(phoff: u32) -> Image {
+		let mut ident = [0; EI_NIDENT];
+		ident[..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+		ident[EI_CLASS] = ELFCLASS32;
+		ident[EI_DATA] = ELFDATA2LSB;
+		let ehdr = ELF32ELFHeader {
+			e_ident: ident,
+			e_type: 0,
+			e_machine: 0,
+			e_version: 0,
+			e_entry: 0,
+			e_phoff: phoff,
+			e_shoff: 0x40,
+			e_flags: 0,
+			e_ehsize: size_of::<ELF32ELFHeader>() as _,
+			e_phentsize: size_of::<ELF32ProgramHeader>() as _,
+			e_phnum: 1,
+			e_shentsize: size_of::<ELF32SectionHeader>() as _,
+			e_shnum: 1,
+			e_shstrndx: 0,
+		};
+		let mut image = Image([0; 0x100]);
+		unsafe {
+			ptr::write(image.0.as_mut_ptr() as *mut ELF32ELFHeader, ehdr);
+		}
+		image
+	}
+
+	#[test_case]
+	fn elf_misaligned_headers() {
+		let image = image_with_phoff(0x80);
+		assert!(ELFParser::new(&image.0).is_ok());
+		// A misaligned table is rejected instead of panicking
+		let image = image_with_phoff(0x81);
+		assert_eq!(
+			ELFParser::new(&image.0).err().unwrap().as_int(),
+			errno::EINVAL
+		);
+	}
+}
@@ -5,6 +5,7 @@ pub mod socket;
 
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
+use crate::errno::EResult;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
 use crate::file::FileLocation;
@@ -26,6 +27,15 @@ pub trait Buffer: IO + Any {
 	/// Returns the capacity in bytes of the buffer.
 	fn get_capacity(&self) -> usize;
 
+	/// Sets the capacity in bytes of the buffer to at least `size`, keeping the data in it.
+	///
+	/// On success, the function returns the new capacity.
+	///
+	/// If the buffer cannot be resized, the function returns `EINVAL`.
+	fn set_capacity(&mut self, _size: usize) -> EResult<usize> {
+		Err(errno!(EINVAL))
+	}
+
 	/// Increments the number of open ends.
 	///
 	/// Arguments:
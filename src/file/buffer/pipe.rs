@@ -2,9 +2,11 @@
 //! and another writing, with a buffer in between.
 
 use super::Buffer;
+use crate::errno::EResult;
 use crate::file::buffer::BlockHandler;
 use crate::file::Errno;
 use crate::limits;
+use crate::memory;
 use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
@@ -14,16 +16,21 @@ use crate::util::container::vec::Vec;
 use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
+use crate::util::math;
 use crate::util::ptr::arc::Arc;
 use crate::util::TryDefault;
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::mem::MaybeUninit;
+
+/// The maximum capacity of a pipe that an unprivileged process can set, in bytes.
+pub const PIPE_MAX_SIZE: usize = 1048576;
 
 /// Structure representing a buffer buffer.
 #[derive(Debug)]
 pub struct PipeBuffer {
 	/// The buffer's buffer.
-	buffer: RingBuffer<u8, Vec<u8>>,
+	buffer: RingBuffer<u8, Vec<MaybeUninit<u8>>>,
 
 	/// The number of reading ends attached to the pipe.
 	read_ends: u32,
@@ -49,7 +56,7 @@ impl PipeBuffer {
 impl TryDefault for PipeBuffer {
 	fn try_default() -> Result<Self, Self::Error> {
 		Ok(Self {
-			buffer: RingBuffer::new(crate::vec![0; limits::PIPE_BUF]?),
+			buffer: RingBuffer::with_capacity(limits::PIPE_BUF)?,
 
 			read_ends: 0,
 			write_ends: 0,
@@ -64,6 +71,29 @@ impl Buffer for PipeBuffer {
 		self.buffer.get_size()
 	}
 
+	/// Sets the capacity of the pipe to at least `size` bytes, keeping the data in it.
+	///
+	/// The capacity is rounded up to a power of two number of pages.
+	///
+	/// If the data in the pipe does not fit in the new capacity, the function returns `EBUSY`.
+	///
+	/// On success, the function returns the new capacity.
+	fn set_capacity(&mut self, size: usize) -> EResult<usize> {
+		let capacity = math::ceil_div(size, memory::PAGE_SIZE)
+			.max(1)
+			.checked_next_power_of_two()
+			.and_then(|pages| pages.checked_mul(memory::PAGE_SIZE))
+			.ok_or_else(|| errno!(EINVAL))?;
+		if capacity < self.get_data_len() {
+			return Err(errno!(EBUSY));
+		}
+		self.buffer.resize(capacity)?;
+		// Writers waiting for space may be able to write now
+		self.block_handler.wake_processes(io::POLLOUT);
+
+		Ok(capacity)
+	}
+
 	fn increment_open(&mut self, read: bool, write: bool) {
 		if read {
 			self.read_ends += 1;
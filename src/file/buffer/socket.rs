@@ -3,16 +3,30 @@
 use super::Buffer;
 use crate::errno::AllocResult;
 use crate::errno::Errno;
+use crate::file::buffer;
 use crate::file::buffer::BlockHandler;
+use crate::file::perm::AccessProfile;
+use crate::file::FileLocation;
 use crate::net::osi;
+use crate::net::sockaddr::get_unix_path;
+use crate::net::sockaddr::SockAddr;
 use crate::net::SocketDesc;
 use crate::net::SocketDomain;
 use crate::net::SocketType;
+use crate::process::mem_space::ptr::SyscallPtr;
 use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::ioctl;
+use crate::time::clock;
+use crate::time::clock::CLOCK_REALTIME;
+use crate::time::unit::TimeUnit;
+use crate::time::unit::Timestamp;
+use crate::time::unit::TimestampScale;
+use crate::time::unit::Timeval;
+use crate::util::container::hashmap::HashMap;
 use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
+use crate::util::io;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
 use crate::util::lock::Mutex;
@@ -20,13 +34,353 @@ use crate::util::ptr::arc::Arc;
 use crate::util::TryDefault;
 use core::cmp::min;
 use core::ffi::c_int;
+use core::ffi::c_short;
 use core::ffi::c_void;
+use core::mem::size_of;
+use core::mem::MaybeUninit;
 
-/// The maximum size of a socket's buffers.
+/// The default size of a socket's buffers.
 const BUFFER_SIZE: usize = 65536;
+/// The minimum size of a socket's buffers, when set through socket options.
+const MIN_BUFFER_SIZE: usize = 256;
+/// The maximum size of a socket's buffers, when set through socket options.
+const MAX_BUFFER_SIZE: usize = 1 << 20;
+
+/// Socket address family: Unspecified.
+const AF_UNSPEC: c_short = 0;
+
+/// Shutdown receive side of the connection.
+pub const SHUT_RD: c_int = 0;
+/// Shutdown transmit side of the connection.
+pub const SHUT_WR: c_int = 1;
+/// Both sides are shutdown.
+pub const SHUT_RDWR: c_int = 2;
 
 /// Socket option level: Socket
-const SOL_SOCKET: c_int = 1;
+pub const SOL_SOCKET: c_int = 1;
+
+/// Socket option: The socket's type (read-only).
+const SO_TYPE: c_int = 3;
+/// Socket option: The size of the transmit buffer.
+const SO_SNDBUF: c_int = 7;
+/// Socket option: The size of the receive buffer.
+const SO_RCVBUF: c_int = 8;
+/// Socket option: Attach the time of reception to received messages.
+const SO_TIMESTAMP: c_int = 29;
+/// Control message type: The time of reception of a message, as a `timeval`.
+pub const SCM_TIMESTAMP: c_int = SO_TIMESTAMP;
+/// Socket option: Tells whether the socket is listening (read-only).
+const SO_ACCEPTCONN: c_int = 30;
+/// Socket option: The socket's domain (read-only).
+const SO_DOMAIN: c_int = 39;
+
+/// Reads an integer socket option value from `optval`.
+///
+/// If the buffer is too small, the function returns an error.
+fn read_opt_int(optval: &[u8]) -> Result<c_int, Errno> {
+	let val = optval
+		.get(..size_of::<c_int>())
+		.ok_or_else(|| errno!(EINVAL))?;
+	Ok(c_int::from_ne_bytes(val.try_into().unwrap()))
+}
+
+/// Writes the integer socket option value `val` into `optval`.
+///
+/// If the buffer is too small, the value is truncated.
+///
+/// The function returns the size of the option value.
+fn write_opt_int(optval: &mut [u8], val: c_int) -> usize {
+	let bytes = val.to_ne_bytes();
+	let len = min(optval.len(), bytes.len());
+	optval[..len].copy_from_slice(&bytes[..len]);
+
+	len
+}
+
+/// Resizes the given socket buffer to `size` bytes, clamped to the allowed range.
+///
+/// The data already in the buffer is kept. Thus, the buffer cannot shrink below the size of the
+/// data it contains.
+///
+/// If the buffer has been shutdown, the function does nothing.
+fn resize_buffer(
+	buffer: &mut Option<RingBuffer<u8, Vec<MaybeUninit<u8>>>>,
+	size: usize,
+) -> AllocResult<()> {
+	let Some(old) = buffer else {
+		return Ok(());
+	};
+	let size = size.clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE);
+
+	old.resize(size)
+}
+
+/// Returns the current time, used to timestamp received data.
+fn now() -> Timestamp {
+	clock::current_time(CLOCK_REALTIME, TimestampScale::Nanosecond).unwrap_or(0)
+}
+
+/// Returns the family of the given socket address, read from its leading field.
+///
+/// If the address is too short, the function returns an error.
+fn get_sockaddr_family(sockaddr: &[u8]) -> Result<c_short, Errno> {
+	let family = sockaddr
+		.get(..size_of::<c_short>())
+		.ok_or_else(|| errno!(EINVAL))?;
+	Ok(c_short::from_ne_bytes(family.try_into().unwrap()))
+}
+
+/// Returns the domain of the given socket address, read from its leading family field.
+///
+/// If the address is too short or the family is unknown, the function returns an error.
+fn get_sockaddr_domain(sockaddr: &[u8]) -> Result<SocketDomain, Errno> {
+	SocketDomain::try_from(get_sockaddr_family(sockaddr)? as u32)
+}
+
+/// A socket bound to a local (`AF_UNIX`) address.
+struct Binding {
+	/// The socket's descriptor.
+	desc: SocketDesc,
+	/// The address the socket is bound to.
+	sockname: Vec<u8>,
+	/// The socket's inbox, through which other sockets reach it.
+	inbox: Arc<Mutex<Inbox>>,
+}
+
+/// The local (`AF_UNIX`) addresses sockets are bound to. The key is the path of the address.
+///
+/// The entry is removed when the socket is closed, so that the address can be reused.
+static UNIX_ADDRESSES: Mutex<HashMap<Vec<u8>, Binding>> = Mutex::new(HashMap::new());
+
+/// A datagram, or a record, in the receive buffer of a socket.
+struct Datagram {
+	/// The length of the datagram in bytes.
+	len: usize,
+	/// The address of the sender. Empty for records.
+	src: Vec<u8>,
+	/// The time at which the datagram has been received, in nanoseconds.
+	timestamp: Timestamp,
+}
+
+/// A message received on a socket, as returned by [`Socket::recv_msg`].
+#[derive(Debug, Default)]
+pub struct Message {
+	/// The number of bytes read.
+	pub len: usize,
+	/// Tells whether the datagram or record was larger than the buffer, in which case the rest
+	/// of it has been discarded.
+	pub truncated: bool,
+	/// The address of the sender, if known.
+	pub src: Option<SockAddr>,
+	/// The time at which the message has been received, if the `SO_TIMESTAMP` option is enabled.
+	pub timestamp: Option<Timeval>,
+}
+
+/// The receiving side of a socket, where other sockets deliver data and connections.
+///
+/// It is locked separately from the socket so that a socket never has to lock another one, which
+/// would deadlock if the other one was doing the same at once. For the same reason, at most one
+/// inbox is locked at a time, and a socket is never locked while holding an inbox.
+struct Inbox {
+	/// The buffer containing received data. If `None`, reception has been shutdown.
+	receive_buffer: Option<RingBuffer<u8, Vec<MaybeUninit<u8>>>>,
+	/// For datagram sockets, the datagrams in the receive buffer, oldest first.
+	///
+	/// Sequenced-packet sockets store their records the same way, with an empty address.
+	datagrams: Vec<Datagram>,
+	/// For stream sockets, the time at which data has last been received, in nanoseconds.
+	received_at: Timestamp,
+	/// For connected datagram sockets, the address of the peer, which is the only one datagrams
+	/// are received from. If empty, datagrams are received from any address.
+	source: Vec<u8>,
+	/// Tells whether the peer has shut down transmission.
+	peer_closed: bool,
+
+	/// If the socket is listening, the maximum number of pending connections. Else, `None`.
+	backlog: Option<usize>,
+	/// The queue of pending connections, each one being the socket to be returned by `accept`.
+	pending: Vec<Arc<Mutex<Socket>>>,
+	/// The number of incoming connections dropped because the queue was full.
+	dropped_connections: usize,
+
+	/// The socket's block handler.
+	block_handler: BlockHandler,
+}
+
+impl Inbox {
+	/// Creates a new instance.
+	fn new() -> AllocResult<Self> {
+		Ok(Self {
+			receive_buffer: Some(RingBuffer::with_capacity(BUFFER_SIZE)?),
+			datagrams: Vec::new(),
+			received_at: 0,
+			source: Vec::new(),
+			peer_closed: false,
+
+			backlog: None,
+			pending: Vec::new(),
+			dropped_connections: 0,
+
+			block_handler: BlockHandler::new(),
+		})
+	}
+
+	/// Returns the length of the data available for reading.
+	///
+	/// If reception has been shutdown, the function returns zero.
+	fn get_data_len(&self) -> usize {
+		self.receive_buffer
+			.as_ref()
+			.map(RingBuffer::get_data_len)
+			.unwrap_or(0)
+	}
+
+	/// Returns the free space in the receive buffer.
+	///
+	/// If reception has been shutdown, the function returns zero.
+	fn get_available_len(&self) -> usize {
+		self.receive_buffer
+			.as_ref()
+			.map(RingBuffer::get_available_len)
+			.unwrap_or(0)
+	}
+
+	/// Delivers the data in `buf`, coming from the address `src`, to a socket of type `type_`.
+	///
+	/// See [`Socket::receive_from`].
+	fn receive_from(&mut self, type_: SocketType, buf: &[u8], src: &[u8]) -> usize {
+		if !self.source.is_empty() && self.source.as_slice() != src {
+			return 0;
+		}
+		let Some(receive_buffer) = &mut self.receive_buffer else {
+			return 0;
+		};
+
+		if type_ == SocketType::SockDgram {
+			if receive_buffer.get_available_len() < buf.len() {
+				return 0;
+			}
+			let Ok(src) = Vec::from_slice(src) else {
+				return 0;
+			};
+			let datagram = Datagram {
+				len: buf.len(),
+				src,
+				timestamp: now(),
+			};
+			if self.datagrams.push(datagram).is_err() {
+				return 0;
+			}
+		}
+		let len = receive_buffer.write(buf);
+		if len > 0 {
+			self.received_at = now();
+			self.block_handler.wake_processes(io::POLLIN);
+		}
+		len
+	}
+
+	/// Delivers the record in `buf` to the sequenced-packet socket, from its peer.
+	///
+	/// A record is never split: if it doesn't fit in the available space of the receive buffer,
+	/// nothing is delivered and the function returns `EAGAIN`. If it is larger than the receive
+	/// buffer itself, the function returns `EMSGSIZE`.
+	///
+	/// The function returns the number of bytes received.
+	fn receive_record(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+		let Some(receive_buffer) = &mut self.receive_buffer else {
+			return Err(errno!(EPIPE));
+		};
+		if buf.len() > receive_buffer.get_size() {
+			return Err(errno!(EMSGSIZE));
+		}
+		// TODO block until there is room in the receive buffer, unless non-blocking
+		if buf.len() > receive_buffer.get_available_len() {
+			return Err(errno!(EAGAIN));
+		}
+
+		self.datagrams.push(Datagram {
+			len: buf.len(),
+			src: Vec::new(),
+			timestamp: now(),
+		})?;
+		receive_buffer.write(buf);
+		self.block_handler.wake_processes(io::POLLIN);
+		Ok(buf.len())
+	}
+
+	/// Reads the oldest datagram in the receive buffer into `buf`.
+	///
+	/// If `buf` is too small, the rest of the datagram is discarded.
+	///
+	/// The function returns the number of bytes read and the datagram. If no datagram is
+	/// available, the function returns `None`.
+	fn pop_datagram(&mut self, buf: &mut [u8]) -> Option<(usize, Datagram)> {
+		let receive_buffer = self.receive_buffer.as_mut()?;
+		if self.datagrams.is_empty() {
+			return None;
+		}
+		let datagram = self.datagrams.remove(0);
+		let len = datagram.len;
+
+		let buf_len = min(len, buf.len());
+		let read = receive_buffer.read(&mut buf[..buf_len]);
+		// Discard the truncated part
+		let mut chunk = [0u8; 256];
+		let mut remaining = len - read;
+		while remaining > 0 {
+			let chunk_len = min(remaining, chunk.len());
+			let n = receive_buffer.read(&mut chunk[..chunk_len]);
+			if n == 0 {
+				break;
+			}
+			remaining -= n;
+		}
+
+		Some((read, datagram))
+	}
+
+	/// Queues the incoming connection `conn`, to be returned by `accept`.
+	///
+	/// If the queue is full, the connection is dropped, the counter of dropped connections is
+	/// incremented and the function returns `false`.
+	///
+	/// If the socket is not listening, the function returns an error.
+	fn push_pending(&mut self, conn: &Arc<Mutex<Socket>>) -> Result<bool, Errno> {
+		let Some(backlog) = self.backlog else {
+			return Err(errno!(ECONNREFUSED));
+		};
+		if self.pending.len() >= backlog {
+			self.dropped_connections += 1;
+			return Ok(false);
+		}
+
+		self.pending.push(conn.clone())?;
+		self.block_handler.wake_processes(io::POLLIN);
+		Ok(true)
+	}
+}
+
+/// The other end of a connected local stream socket.
+struct Peer {
+	/// The location of the peer socket.
+	loc: FileLocation,
+	/// The peer's inbox, which receives the data written on this end.
+	inbox: Arc<Mutex<Inbox>>,
+}
+
+/// The state of a socket regarding I/O readiness, as returned by [`Socket::readiness`].
+#[derive(Clone, Copy, Debug)]
+pub struct Readiness {
+	/// The number of bytes available for reading.
+	pub readable: usize,
+	/// The number of bytes that can be written without blocking.
+	pub writable: usize,
+	/// Tells whether the peer will not send any more data.
+	pub peer_closed: bool,
+	/// The error on the connection, if any.
+	pub error: Option<Errno>,
+}
 
 /// Structure representing a socket.
 pub struct Socket {
@@ -35,38 +389,86 @@ pub struct Socket {
 	/// The socket's network stack corresponding to the descriptor.
 	stack: Option<osi::Stack>,
 
-	/// The buffer containing received data. If `None`, reception has been shutdown.
-	receive_buffer: Option<RingBuffer<u8, Vec<u8>>>,
+	/// The receiving side of the socket.
+	inbox: Arc<Mutex<Inbox>>,
 	/// The buffer containing data to be transmitted. If `None`, transmission has been shutdown.
-	transmit_buffer: Option<RingBuffer<u8, Vec<u8>>>,
+	transmit_buffer: Option<RingBuffer<u8, Vec<MaybeUninit<u8>>>>,
 
 	/// The number of entities owning a reference to the socket. When this count reaches zero, the
 	/// socket is closed.
 	open_count: u32,
-
-	/// The socket's block handler.
-	block_handler: BlockHandler,
+	/// The number of open ends allowed to write on the socket. When this count reaches zero, the
+	/// peer is considered gone and reads return EOF once the receive buffer is drained.
+	write_ends: u32,
 
 	/// The address the socket is bound to.
 	sockname: Vec<u8>,
+	/// The address of the socket's peer. If empty, the socket has no peer.
+	peername: Vec<u8>,
+	/// For connected local stream sockets, the peer socket, which receives the data written on
+	/// this one.
+	peer: Option<Peer>,
+
+	/// Tells whether the socket is in non-blocking mode.
+	non_blocking: bool,
+	/// Tells whether received messages are timestamped (`SO_TIMESTAMP`).
+	timestamp: bool,
 }
 
 impl Socket {
 	/// Creates a new instance.
-	pub fn new(desc: SocketDesc) -> AllocResult<Arc<Mutex<Self>>> {
-		Arc::new(Mutex::new(Self {
+	///
+	/// `access_profile` is the access profile of the agent creating the socket.
+	///
+	/// If the agent is not allowed to use the domain or the type, the function returns `EACCES`.
+	/// If the descriptor doesn't describe a supported socket, the function returns an error (see
+	/// [`SocketDesc::check`]).
+	pub fn new(
+		desc: SocketDesc,
+		access_profile: &AccessProfile,
+	) -> Result<Arc<Mutex<Self>>, Errno> {
+		if !access_profile.can_use_sock_domain(&desc.domain)
+			|| !access_profile.can_use_sock_type(&desc.type_)
+		{
+			return Err(errno!(EACCES));
+		}
+		desc.check()?;
+		Ok(Arc::new(Mutex::new(Self::new_unchecked(desc)?))?)
+	}
+
+	/// Creates a new instance without checking the descriptor.
+	fn new_unchecked(desc: SocketDesc) -> AllocResult<Self> {
+		Ok(Self {
 			desc,
 			stack: None,
 
-			receive_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
-			transmit_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
+			inbox: Arc::new(Mutex::new(Inbox::new()?))?,
+			transmit_buffer: Some(RingBuffer::with_capacity(BUFFER_SIZE)?),
 
 			open_count: 0,
-
-			block_handler: BlockHandler::new(),
+			write_ends: 0,
 
 			sockname: Vec::new(),
-		}))
+			peername: Vec::new(),
+			peer: None,
+
+			non_blocking: false,
+			timestamp: false,
+		})
+	}
+
+	/// Creates the socket to be returned by `accept` for a connection to a listening socket.
+	///
+	/// Arguments:
+	/// - `desc` is the descriptor of the listening socket.
+	/// - `sockname` is the address the listening socket is bound to.
+	/// - `peername` is the address of the peer initiating the connection.
+	fn new_connection(desc: &SocketDesc, sockname: &[u8], peername: &[u8]) -> AllocResult<Self> {
+		// The descriptor has been checked when creating the listening socket
+		let mut sock = Self::new_unchecked(desc.clone())?;
+		sock.sockname = Vec::from_slice(sockname)?;
+		sock.peername = Vec::from_slice(peername)?;
+		Ok(sock)
 	}
 
 	/// Returns the socket's descriptor.
@@ -81,6 +483,87 @@ impl Socket {
 		self.stack.as_ref()
 	}
 
+	/// Returns the length of the data available for reading on the socket.
+	///
+	/// If reception has been shutdown, the function returns zero.
+	pub fn get_data_len(&self) -> usize {
+		self.inbox.lock().get_data_len()
+	}
+
+	/// Returns the inbox of the socket's peer.
+	///
+	/// If the socket has no peer, or if the peer does not exist anymore, the function returns
+	/// `None`.
+	fn peer_inbox(&self) -> Option<&Arc<Mutex<Inbox>>> {
+		let peer = self.peer.as_ref()?;
+		buffer::get(&peer.loc)?;
+		Some(&peer.inbox)
+	}
+
+	/// Returns the number of bytes that can be written on the socket without blocking.
+	///
+	/// For a connected local stream socket, this is the free space in the peer's receive buffer.
+	/// If transmission has been shutdown or if the peer is gone, the function returns zero.
+	pub fn get_writable_len(&self) -> usize {
+		let Some(transmit_buffer) = &self.transmit_buffer else {
+			return 0;
+		};
+		match &self.peer {
+			Some(_) => self
+				.peer_inbox()
+				.map(|inbox| inbox.lock().get_available_len())
+				.unwrap_or(0),
+			None => transmit_buffer.get_available_len(),
+		}
+	}
+
+	/// Returns the readiness state of the socket, taking the peer's inbox lock only once.
+	///
+	/// If the socket is connected to a local peer that does not exist anymore, the connection is
+	/// considered reset.
+	pub fn readiness(&self) -> Readiness {
+		let (readable, peer_closed) = {
+			let inbox = self.inbox.lock();
+			(inbox.get_data_len(), inbox.peer_closed)
+		};
+		let mut readiness = Readiness {
+			readable,
+			writable: 0,
+			peer_closed: false,
+			error: None,
+		};
+		if self.peer.is_none() {
+			readiness.writable = self
+				.transmit_buffer
+				.as_ref()
+				.map(RingBuffer::get_available_len)
+				.unwrap_or(0);
+			return readiness;
+		}
+		let available = self
+			.peer_inbox()
+			.map(|inbox| inbox.lock().get_available_len());
+		match available {
+			Some(available) => {
+				if self.transmit_buffer.is_some() {
+					readiness.writable = available;
+				}
+				readiness.peer_closed = peer_closed;
+			}
+			None => {
+				readiness.peer_closed = true;
+				readiness.error = Some(errno!(ECONNRESET));
+			}
+		}
+		readiness
+	}
+
+	/// Tells whether the socket is in non-blocking mode.
+	#[inline(always)]
+	pub fn is_non_blocking(&self) -> bool {
+		self.non_blocking
+	}
+
 	/// Reads the given socket option.
 	///
 	/// Arguments:
@@ -91,12 +574,32 @@ impl Socket {
 	/// The function returns a value to be returned by the syscall on success.
 	pub fn get_opt(
 		&self,
-		_level: c_int,
-		_optname: c_int,
-		_optval: &mut [u8],
+		level: c_int,
+		optname: c_int,
+		optval: &mut [u8],
 	) -> Result<c_int, Errno> {
-		// TODO
-		todo!()
+		let val = match (level, optname) {
+			(SOL_SOCKET, SO_TYPE) => self.desc.type_.get_id() as _,
+			(SOL_SOCKET, SO_SNDBUF) => self
+				.transmit_buffer
+				.as_ref()
+				.map(RingBuffer::get_size)
+				.unwrap_or(0) as _,
+			(SOL_SOCKET, SO_RCVBUF) => self
+				.inbox
+				.lock()
+				.receive_buffer
+				.as_ref()
+				.map(RingBuffer::get_size)
+				.unwrap_or(0) as _,
+			(SOL_SOCKET, SO_ACCEPTCONN) => self.is_listening() as _,
+			(SOL_SOCKET, SO_TIMESTAMP) => self.timestamp as _,
+			(SOL_SOCKET, SO_DOMAIN) => self.desc.domain.get_id() as _,
+
+			_ => return Err(errno!(ENOPROTOOPT)),
+		};
+		write_opt_int(optval, val);
+		Ok(0)
 	}
 
 	/// Writes the given socket option.
@@ -109,11 +612,24 @@ impl Socket {
 	/// The function returns a value to be returned by the syscall on success.
 	pub fn set_opt(
 		&mut self,
-		_level: c_int,
-		_optname: c_int,
-		_optval: &[u8],
+		level: c_int,
+		optname: c_int,
+		optval: &[u8],
 	) -> Result<c_int, Errno> {
-		// TODO
+		match (level, optname) {
+			(SOL_SOCKET, SO_SNDBUF) => {
+				let size = read_opt_int(optval)?.max(0) as usize;
+				resize_buffer(&mut self.transmit_buffer, size)?;
+			}
+			(SOL_SOCKET, SO_RCVBUF) => {
+				let size = read_opt_int(optval)?.max(0) as usize;
+				resize_buffer(&mut self.inbox.lock().receive_buffer, size)?;
+			}
+			(SOL_SOCKET, SO_TIMESTAMP) => self.timestamp = read_opt_int(optval)? != 0,
+
+			// Includes read-only options
+			_ => return Err(errno!(ENOPROTOOPT)),
+		}
 		Ok(0)
 	}
 
@@ -133,6 +649,22 @@ impl Socket {
 		!self.sockname.is_empty()
 	}
 
+	/// Checks that `sockaddr` is a valid address for the socket's domain.
+	///
+	/// On success, the function returns the address, stripped of trailing bytes beyond the
+	/// domain's sockaddr structure.
+	fn check_sockaddr<'a>(&self, sockaddr: &'a [u8]) -> Result<&'a [u8], Errno> {
+		let domain = get_sockaddr_domain(sockaddr)?;
+		if domain != self.desc.domain {
+			return Err(errno!(EAFNOSUPPORT));
+		}
+		if !domain.is_sockaddr_len_valid(sockaddr.len()) {
+			return Err(errno!(EINVAL));
+		}
+		let len = min(sockaddr.len(), domain.get_sockaddr_len());
+		Ok(&sockaddr[..len])
+	}
+
 	/// Binds the socket to the given address.
 	///
 	/// `sockaddr` is the new socket name.
@@ -143,22 +675,469 @@ impl Socket {
 		if self.is_bound() {
 			return Err(errno!(EINVAL));
 		}
-		// TODO check if address is already in used (EADDRINUSE)
+		let sockaddr = self.check_sockaddr(sockaddr)?;
+		let sockname = Vec::from_slice(sockaddr)?;
+
+		if self.desc.domain == SocketDomain::AfUnix {
+			// TODO create the socket file when the path is not abstract
+			let path = Vec::from_slice(get_unix_path(sockaddr))?;
+			let binding = Binding {
+				desc: self.desc.clone(),
+				sockname: Vec::from_slice(sockaddr)?,
+				inbox: self.inbox.clone(),
+			};
+			let mut addresses = UNIX_ADDRESSES.lock();
+			if addresses.contains_key(&path) {
+				return Err(errno!(EADDRINUSE));
+			}
+			addresses.insert(path, binding)?;
+		}
+		// TODO check if address is already in used (EADDRINUSE) for other domains
 		// TODO check the requested network interface exists (EADDRNOTAVAIL)
 		// TODO check address against stack's domain
 
-		self.sockname = Vec::from_slice(sockaddr)?;
+		self.sockname = sockname;
+		Ok(())
+	}
+
+	/// Releases the local address the socket is bound to, if any, so that another socket can
+	/// bind to it.
+	fn unbind(&mut self) {
+		if self.desc.domain != SocketDomain::AfUnix || !self.is_bound() {
+			return;
+		}
+		let Ok(path) = Vec::from_slice(get_unix_path(&self.sockname)) else {
+			return;
+		};
+		let mut addresses = UNIX_ADDRESSES.lock();
+		// Accepted sockets share the address of the listening socket, which keeps it
+		let owned = addresses
+			.get(&path)
+			.map(|binding| binding.inbox.as_ptr() == self.inbox.as_ptr())
+			.unwrap_or(false);
+		if owned {
+			addresses.remove(&path);
+		}
+	}
+
+	/// Tells whether the socket has a peer.
+	#[inline(always)]
+	pub fn is_connected(&self) -> bool {
+		!self.peername.is_empty() || self.peer.is_some()
+	}
+
+	/// Connects the socket to the given address.
+	///
+	/// For datagram sockets, no connection is established: `sockaddr` becomes the default
+	/// destination of sent datagrams and only datagrams coming from it are received. Connecting
+	/// again changes the peer, and connecting to an `AF_UNSPEC` address removes it.
+	///
+	/// For stream sockets, see [`Self::connect_stream`].
+	///
+	/// `loc` is the location of the socket.
+	///
+	/// If the address is invalid, the function returns an error.
+	pub fn connect(&mut self, sockaddr: &[u8], loc: &FileLocation) -> Result<(), Errno> {
+		if self.desc.type_.is_stream() {
+			return self.connect_stream(sockaddr, loc);
+		}
+
+		if get_sockaddr_family(sockaddr)? == AF_UNSPEC {
+			self.peername = Vec::new();
+			self.inbox.lock().source = Vec::new();
+			return Ok(());
+		}
+		let sockaddr = self.check_sockaddr(sockaddr)?;
+		if matches!(
+			self.desc.domain,
+			SocketDomain::AfInet | SocketDomain::AfInet6
+		) {
+			SockAddr::from_bytes(self.desc.domain, sockaddr)?;
+		}
+
+		self.peername = Vec::from_slice(sockaddr)?;
+		self.inbox.lock().source = Vec::from_slice(sockaddr)?;
+		Ok(())
+	}
+
+	/// Connects the stream socket to the listening socket bound at `sockaddr`.
+	///
+	/// The connection is queued on the listening socket, which returns the other end when
+	/// accepting it. Data written on each end is then received by the other.
+	///
+	/// `loc` is the location of the socket, which the other end uses to send data back.
+	///
+	/// Only local sockets are supported for now.
+	fn connect_stream(&mut self, sockaddr: &[u8], loc: &FileLocation) -> Result<(), Errno> {
+		if self.desc.domain != SocketDomain::AfUnix {
+			// TODO establish the connection through the network stack
+			return Err(errno!(EOPNOTSUPP));
+		}
+		if self.is_listening() {
+			return Err(errno!(EINVAL));
+		}
+		if self.is_connected() {
+			return Err(errno!(EISCONN));
+		}
+		let sockaddr = self.check_sockaddr(sockaddr)?;
+		let peername = Vec::from_slice(sockaddr)?;
+
+		let path = Vec::from_slice(get_unix_path(sockaddr))?;
+		let (desc, sockname, target_inbox) = {
+			let addresses = UNIX_ADDRESSES.lock();
+			let target = addresses.get(&path).ok_or_else(|| errno!(ECONNREFUSED))?;
+			(
+				target.desc.clone(),
+				Vec::from_slice(&target.sockname)?,
+				target.inbox.clone(),
+			)
+		};
+		// A socket cannot be connected to itself
+		if target_inbox.as_ptr() == self.inbox.as_ptr() {
+			return Err(errno!(ECONNREFUSED));
+		}
+		if desc.type_ != self.desc.type_ {
+			return Err(errno!(EPROTOTYPE));
+		}
+
+		// The other end is complete before being queued, so that it never has to be locked
+		// while this socket is
+		let mut conn = Socket::new_connection(&desc, &sockname, &self.sockname)?;
+		conn.peer = Some(Peer {
+			loc: loc.clone(),
+			inbox: self.inbox.clone(),
+		});
+		let conn_inbox = conn.inbox.clone();
+		let conn = Arc::new(Mutex::new(conn))?;
+		let queued = target_inbox.lock().push_pending(&conn)?;
+		// TODO block until there is room in the queue, unless non-blocking
+		if !queued {
+			return Err(errno!(EAGAIN));
+		}
+
+		let conn_loc = buffer::register(None, conn)?;
+		self.peer = Some(Peer {
+			loc: conn_loc,
+			inbox: conn_inbox,
+		});
+		self.peername = peername;
+		Ok(())
+	}
+
+	/// Sends the data in `buf` to the address `dest`.
+	///
+	/// If `dest` is `None`, the data is sent to the socket's peer.
+	///
+	/// Stream sockets can only send to their peer. Giving them a destination returns `EISCONN` if
+	/// they are connected, or `EOPNOTSUPP` otherwise.
+	///
+	/// If no destination is given and the socket has no peer, or if the address is invalid, the
+	/// function returns an error.
+	///
+	/// The function returns the number of bytes sent.
+	pub fn send_to(&mut self, buf: &[u8], dest: Option<&[u8]>) -> Result<usize, Errno> {
+		if self.desc.type_.is_stream() {
+			if dest.is_some() {
+				return Err(if self.is_connected() {
+					errno!(EISCONN)
+				} else {
+					errno!(EOPNOTSUPP)
+				});
+			}
+			if self.peer.is_some() {
+				return self.send_to_peer(buf);
+			}
+		}
+		if self.transmit_buffer.is_none() {
+			return Err(errno!(EPIPE));
+		}
+		let dest = match dest {
+			Some(dest) => self.check_sockaddr(dest)?,
+			None if !self.is_connected() => return Err(errno!(EDESTADDRREQ)),
+			None => self.peername.as_slice(),
+		};
+		if self.desc.domain == SocketDomain::AfUnix {
+			let path = Vec::from_slice(get_unix_path(dest))?;
+			return self.send_to_unix(buf, &path);
+		}
+		let transmit_buffer = self.transmit_buffer.as_mut().unwrap();
+
+		// TODO hand the data to the network stack, along with its destination
+		Ok(transmit_buffer.write(buf))
+	}
+
+	/// Sends the data in `buf` to the peer of a connected local stream socket.
+	///
+	/// If the socket is not connected, or if the peer cannot receive anymore, the function returns
+	/// an error.
+	///
+	/// The function returns the number of bytes sent.
+	fn send_to_peer(&mut self, buf: &[u8]) -> Result<usize, Errno> {
+		if self.transmit_buffer.is_none() {
+			return Err(errno!(EPIPE));
+		}
+		if self.peer.is_none() {
+			return Err(errno!(ENOTCONN));
+		}
+		let mut peer = self.peer_inbox().ok_or_else(|| errno!(EPIPE))?.lock();
+
+		if self.desc.type_ == SocketType::SockSeqpacket {
+			return peer.receive_record(buf);
+		}
+		let Some(receive_buffer) = &mut peer.receive_buffer else {
+			return Err(errno!(EPIPE));
+		};
+		let len = receive_buffer.write(buf);
+		if len > 0 {
+			peer.received_at = now();
+			peer.block_handler.wake_processes(io::POLLIN);
+		}
+		Ok(len)
+	}
+
+	/// Sends the datagram in `buf` to the local socket bound at `path`.
+	///
+	/// If no socket is bound at this path, the function returns an error.
+	///
+	/// The function returns the number of bytes sent.
+	fn send_to_unix(&mut self, buf: &[u8], path: &[u8]) -> Result<usize, Errno> {
+		let path = Vec::from_slice(path)?;
+		let (type_, target_inbox) = UNIX_ADDRESSES
+			.lock()
+			.get(&path)
+			.map(|target| (target.desc.type_, target.inbox.clone()))
+			.ok_or_else(|| errno!(ECONNREFUSED))?;
+		if type_ != self.desc.type_ {
+			return Err(errno!(EPROTOTYPE));
+		}
+		// The target may be the socket itself, whose inbox is not locked here
+		let len = target_inbox.lock().receive_from(type_, buf, &self.sockname);
+		// TODO block until there is room in the receive buffer, unless non-blocking
+		if len < buf.len() {
+			return Err(errno!(EAGAIN));
+		}
+		Ok(len)
+	}
+
+	/// Delivers the data in `buf`, coming from the address `src`, to the socket.
+	///
+	/// If the socket is a connected datagram socket and `src` is not its peer, the data is
+	/// dropped.
+	///
+	/// For datagram sockets, a datagram that doesn't fit in the receive buffer is dropped as a
+	/// whole. Else, its boundaries and `src` are kept to be returned by [`Self::recv_from`].
+	///
+	/// The function returns the number of bytes received.
+	pub fn receive_from(&mut self, buf: &[u8], src: &[u8]) -> usize {
+		self.inbox.lock().receive_from(self.desc.type_, buf, src)
+	}
+
+	/// Receives a datagram or a record on the socket and writes it into `buf`.
+	///
+	/// If `buf` is too small, the rest of the datagram is discarded.
+	///
+	/// If no datagram is available, the function returns `EAGAIN`. If reception has been
+	/// shutdown, the function returns an empty message.
+	fn recv_datagram(&mut self, buf: &mut [u8]) -> Result<Message, Errno> {
+		let (read, datagram) = {
+			let mut inbox = self.inbox.lock();
+			if inbox.receive_buffer.is_none() {
+				return Ok(Message::default());
+			}
+
+			// TODO block until a datagram arrives, unless non-blocking
+			let popped = inbox.pop_datagram(buf).ok_or_else(|| errno!(EAGAIN))?;
+			inbox.block_handler.wake_processes(io::POLLOUT);
+			popped
+		};
+		if let Some(peer) = self.peer_inbox() {
+			peer.lock().block_handler.wake_processes(io::POLLOUT);
+		}
+
+		// The address has been validated when the sender bound to it
+		let src = (!datagram.src.is_empty())
+			.then(|| SockAddr::from_bytes(self.desc.domain, &datagram.src))
+			.transpose()?;
+		Ok(Message {
+			len: read,
+			truncated: read < datagram.len,
+			src,
+			timestamp: self
+				.timestamp
+				.then(|| Timeval::from_nano(datagram.timestamp)),
+		})
+	}
+
+	/// Receives a datagram on the socket and writes it into `buf`.
+	///
+	/// If `buf` is too small, the rest of the datagram is discarded.
+	///
+	/// If the socket is not a datagram socket, the function returns `EOPNOTSUPP`. If no datagram
+	/// is available, the function returns `EAGAIN`.
+	///
+	/// The function returns the number of bytes read and the address of the sender, or `None` if
+	/// the sender is not bound to an address. If reception has been shutdown, the function returns
+	/// zero and `None`.
+	pub fn recv_from(&mut self, buf: &mut [u8]) -> Result<(usize, Option<SockAddr>), Errno> {
+		if self.desc.type_ != SocketType::SockDgram {
+			return Err(errno!(EOPNOTSUPP));
+		}
+		let msg = self.recv_datagram(buf)?;
+		Ok((msg.len, msg.src))
+	}
+
+	/// Receives a record on the sequenced-packet socket and writes it into `buf`.
+	///
+	/// If `buf` is too small, the record is truncated: the rest of it is discarded and the
+	/// function reports it, like the `MSG_TRUNC` flag.
+	///
+	/// If the socket is not a sequenced-packet socket, the function returns `EOPNOTSUPP`. If no
+	/// record is available, the function returns `EAGAIN`.
+	///
+	/// The function returns the number of bytes read and whether the record has been truncated.
+	/// If reception has been shutdown, the function returns zero.
+	pub fn recv_record(&mut self, buf: &mut [u8]) -> Result<(usize, bool), Errno> {
+		if self.desc.type_ != SocketType::SockSeqpacket {
+			return Err(errno!(EOPNOTSUPP));
+		}
+		let msg = self.recv_datagram(buf)?;
+		Ok((msg.len, msg.truncated))
+	}
+
+	/// Receives a message on the socket and writes its data into `buf`.
+	///
+	/// For datagram and sequenced-packet sockets, one datagram or record is received, as with
+	/// [`Self::recv_from`] and [`Self::recv_record`]. For stream sockets, the data is read as
+	/// with [`IO::read`], and the message is timestamped with the time data was last received.
+	///
+	/// The timestamp is set only if the `SO_TIMESTAMP` option is enabled.
+	pub fn recv_msg(&mut self, buf: &mut [u8]) -> Result<Message, Errno> {
+		if matches!(
+			self.desc.type_,
+			SocketType::SockDgram | SocketType::SockSeqpacket
+		) {
+			return self.recv_datagram(buf);
+		}
+		let (len, _) = self.read(0, buf)?;
+		let timestamp = self
+			.timestamp
+			.then(|| Timeval::from_nano(self.inbox.lock().received_at));
+		Ok(Message {
+			len: len as _,
+			timestamp,
+			..Default::default()
+		})
+	}
+
+	/// Tells whether the socket is listening for incoming connections.
+	#[inline(always)]
+	pub fn is_listening(&self) -> bool {
+		self.inbox.lock().backlog.is_some()
+	}
+
+	/// Returns the number of incoming connections that have been dropped because the queue of
+	/// pending connections was full.
+	#[inline(always)]
+	pub fn get_dropped_connections(&self) -> usize {
+		self.inbox.lock().dropped_connections
+	}
+
+	/// Marks the socket as passive, accepting incoming connections.
+	///
+	/// `backlog` is the maximum number of pending connections. Further connections are dropped
+	/// until pending ones are accepted.
+	///
+	/// If the socket is not connection-based, or if it is not bound, the function returns an
+	/// error.
+	pub fn listen(&mut self, backlog: usize) -> Result<(), Errno> {
+		if !self.desc.type_.is_stream() {
+			return Err(errno!(EINVAL));
+		}
+		if !self.is_bound() {
+			return Err(errno!(EDESTADDRREQ));
+		}
+
+		// Calling `listen` again only updates the backlog
+		self.inbox.lock().backlog = Some(backlog);
 		Ok(())
 	}
 
+	/// Queues an incoming connection from the peer at address `peer`.
+	///
+	/// The function creates the socket to be returned by `accept` for the connection. It shares
+	/// the descriptor and address of the listening socket.
+	///
+	/// If the queue is full, the connection is dropped, the counter of dropped connections is
+	/// incremented and the function returns `None`.
+	///
+	/// If the socket is not listening, the function returns an error.
+	pub fn push_pending(&mut self, peer: &[u8]) -> Result<Option<Arc<Mutex<Socket>>>, Errno> {
+		let sock = Socket::new_connection(&self.desc, &self.sockname, peer)?;
+		let sock = Arc::new(Mutex::new(sock))?;
+		let queued = self.inbox.lock().push_pending(&sock)?;
+		Ok(queued.then_some(sock))
+	}
+
+	/// Accepts the oldest pending connection and returns the socket connected to its peer.
+	///
+	/// If the socket is not listening, the function returns an error. If no connection is
+	/// pending, the function returns `EAGAIN`.
+	pub fn accept(&mut self) -> Result<Arc<Mutex<Socket>>, Errno> {
+		let mut inbox = self.inbox.lock();
+		if inbox.backlog.is_none() {
+			return Err(errno!(EINVAL));
+		}
+		if inbox.pending.is_empty() {
+			return Err(errno!(EAGAIN));
+		}
+
+		Ok(inbox.pending.remove(0))
+	}
+
 	/// Shuts down the receive side of the socket.
+	///
+	/// Buffered data is discarded and further reads return EOF immediately.
 	pub fn shutdown_receive(&mut self) {
-		self.receive_buffer = None;
+		let mut inbox = self.inbox.lock();
+		inbox.receive_buffer = None;
+		inbox.datagrams.clear();
+		inbox.block_handler.wake_processes(io::POLLIN);
 	}
 
 	/// Shuts down the transmit side of the socket.
+	///
+	/// Reads on the other end return EOF once the data that has already been sent is drained.
 	pub fn shutdown_transmit(&mut self) {
 		self.transmit_buffer = None;
+		self.inbox
+			.lock()
+			.block_handler
+			.wake_processes(io::POLLIN | io::POLLOUT | io::POLLHUP);
+		// Readers on the other end may now get EOF
+		if let Some(peer) = self.peer_inbox() {
+			let mut peer = peer.lock();
+			peer.peer_closed = true;
+			peer.block_handler.wake_processes(io::POLLIN | io::POLLHUP);
+		}
+	}
+
+	/// Shuts down part of the connection, according to `how`.
+	///
+	/// `how` is one of `SHUT_RD`, `SHUT_WR` or `SHUT_RDWR`. If invalid, the function returns an
+	/// error.
+	pub fn shutdown(&mut self, how: c_int) -> Result<(), Errno> {
+		match how {
+			SHUT_RD => self.shutdown_receive(),
+			SHUT_WR => self.shutdown_transmit(),
+
+			SHUT_RDWR => {
+				self.shutdown_receive();
+				self.shutdown_transmit();
+			}
+
+			_ => return Err(errno!(EINVAL)),
+		}
+		Ok(())
 	}
 }
 
@@ -170,19 +1149,13 @@ impl TryDefault for Socket {
 			protocol: 0,
 		};
 
-		Ok(Self {
-			desc,
-			stack: None,
-
-			receive_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
-			transmit_buffer: Some(RingBuffer::new(crate::vec![0; BUFFER_SIZE]?)),
-
-			open_count: 0,
-
-			block_handler: BlockHandler::new(),
+		Self::new_unchecked(desc)
+	}
+}
 
-			sockname: Default::default(),
-		})
+impl Drop for Socket {
+	fn drop(&mut self) {
+		self.unbind();
 	}
 }
 
@@ -192,29 +1165,71 @@ impl Buffer for Socket {
 		todo!()
 	}
 
-	fn increment_open(&mut self, _read: bool, _write: bool) {
+	fn increment_open(&mut self, _read: bool, write: bool) {
 		self.open_count += 1;
+
+		if write {
+			self.write_ends += 1;
+		}
 	}
 
-	fn decrement_open(&mut self, _read: bool, _write: bool) {
+	fn decrement_open(&mut self, _read: bool, write: bool) {
+		if write {
+			self.write_ends -= 1;
+
+			if self.write_ends == 0 {
+				// Wake readers so that they observe EOF
+				self.inbox
+					.lock()
+					.block_handler
+					.wake_processes(io::POLLIN | io::POLLHUP);
+			}
+		}
+
 		self.open_count -= 1;
 		if self.open_count == 0 {
+			// The address can be reused as soon as the socket is closed
+			self.unbind();
 			// TODO close the socket
 		}
 	}
 
 	fn add_waiting_process(&mut self, proc: &mut Process, mask: u32) -> Result<(), Errno> {
-		self.block_handler.add_waiting_process(proc, mask)
+		self.inbox
+			.lock()
+			.block_handler
+			.add_waiting_process(proc, mask)
 	}
 
 	fn ioctl(
 		&mut self,
-		_mem_space: Arc<IntMutex<MemSpace>>,
-		_request: ioctl::Request,
-		_argp: *const c_void,
+		mem_space: Arc<IntMutex<MemSpace>>,
+		request: ioctl::Request,
+		argp: *const c_void,
 	) -> Result<u32, Errno> {
-		// TODO
-		todo!();
+		match request.get_old_format() {
+			ioctl::FIONREAD => {
+				let mut mem_space_guard = mem_space.lock();
+				let count_ptr: SyscallPtr<c_int> = (argp as usize).into();
+				let count_ref = count_ptr
+					.get_mut(&mut mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				*count_ref = self.get_data_len() as _;
+			}
+
+			ioctl::FIONBIO => {
+				let mem_space_guard = mem_space.lock();
+				let val_ptr: SyscallPtr<c_int> = (argp as usize).into();
+				let val = val_ptr
+					.get(&mem_space_guard)?
+					.ok_or_else(|| errno!(EFAULT))?;
+				self.non_blocking = *val != 0;
+			}
+
+			_ => return Err(errno!(ENOTTY)),
+		}
+
+		Ok(0)
 	}
 }
 
@@ -224,17 +1239,54 @@ impl IO for Socket {
 	}
 
 	/// Note: This implemention ignores the offset.
-	fn read(&mut self, _: u64, _buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+	fn read(&mut self, _: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
 		if !self.desc.type_.is_stream() {
 			// TODO error
 		}
 
-		// TODO
-		todo!();
+		// No more data can arrive once every writing end is closed, or once the peer has shut
+		// down transmission or is gone
+		let closed = self.write_ends == 0 || (self.peer.is_some() && self.readiness().peer_closed);
+		let (len, eof) = {
+			let mut inbox = self.inbox.lock();
+			let Some(receive_buffer) = &mut inbox.receive_buffer else {
+				// Reception has been shutdown
+				return Ok((0, true));
+			};
+			if receive_buffer.is_empty() && !closed && self.non_blocking {
+				return Err(errno!(EAGAIN));
+			}
+			let len = if matches!(
+				self.desc.type_,
+				SocketType::SockDgram | SocketType::SockSeqpacket
+			) {
+				// The sender's address is not returned
+				inbox.pop_datagram(buf).map(|(len, _)| len).unwrap_or(0)
+			} else {
+				receive_buffer.read(buf)
+			};
+			let eof = closed && inbox.get_data_len() == 0;
+
+			inbox.block_handler.wake_processes(io::POLLOUT);
+			(len, eof)
+		};
+		// Room has been made for the peer to send more data
+		if let (Some(peer), true) = (self.peer_inbox(), len > 0) {
+			peer.lock().block_handler.wake_processes(io::POLLOUT);
+		}
+
+		Ok((len as _, eof))
 	}
 
 	/// Note: This implemention ignores the offset.
-	fn write(&mut self, _: u64, _buf: &[u8]) -> Result<u64, Errno> {
+	fn write(&mut self, _: u64, buf: &[u8]) -> Result<u64, Errno> {
+		if !self.desc.type_.is_stream() {
+			return Ok(self.send_to(buf, None)? as _);
+		}
+		if self.peer.is_some() {
+			return Ok(self.send_to_peer(buf)? as _);
+		}
+
 		// A destination address is required
 		let Some(_stack) = self.stack.as_ref() else {
 			return Err(errno!(EDESTADDRREQ));
@@ -244,8 +1296,1247 @@ impl IO for Socket {
 		todo!();
 	}
 
-	fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
-		// TODO
-		todo!();
+	fn poll(&mut self, mask: u32) -> Result<u32, Errno> {
+		let readiness = self.readiness();
+		let mut result = 0;
+
+		// Once the peer is closed, reading does not block since it returns EOF
+		if mask & io::POLLIN != 0
+			&& (readiness.readable > 0
+				|| readiness.peer_closed
+				|| !self.inbox.lock().pending.is_empty())
+		{
+			result |= io::POLLIN;
+		}
+		if mask & io::POLLOUT != 0 && readiness.writable > 0 {
+			result |= io::POLLOUT;
+		}
+		if mask & io::POLLHUP != 0 && readiness.peer_closed {
+			result |= io::POLLHUP;
+		}
+		if mask & io::POLLERR != 0 && readiness.error.is_some() {
+			result |= io::POLLERR;
+		}
+
+		Ok(result)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::errno;
+	use crate::memory::vmem;
+	use crate::net::Address;
+	use crate::process::mem_space::MapConstraint;
+	use crate::process::mem_space::MapResidence;
+	use crate::process::mem_space::MAPPING_FLAG_USER;
+	use crate::process::mem_space::MAPPING_FLAG_WRITE;
+	use core::ffi::c_ulong;
+	use core::num::NonZeroUsize;
+
+	/// A location for sockets that are not registered.
+	const LOC: FileLocation = FileLocation::Virtual {
+		id: u32::MAX,
+	};
+
+	#[test_case]
+	fn socket_data_len() {
+		let mut sock = Socket::try_default().unwrap();
+		assert_eq!(sock.get_data_len(), 0);
+
+		let buf = [42u8; 100];
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&buf);
+		assert_eq!(sock.get_data_len(), 100);
+
+		sock.shutdown_receive();
+		assert_eq!(sock.get_data_len(), 0);
+	}
+
+	#[test_case]
+	fn socket_ioctl() {
+		let mut sock = Socket::try_default().unwrap();
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&[42u8; 100]);
+
+		let mut mem_space = MemSpace::new().unwrap();
+		let arg = mem_space
+			.map(
+				MapConstraint::None,
+				NonZeroUsize::new(1).unwrap(),
+				MAPPING_FLAG_USER | MAPPING_FLAG_WRITE,
+				MapResidence::Normal,
+			)
+			.unwrap() as *mut c_int;
+		let vmem = mem_space.get_vmem().clone();
+		let mem_space = Arc::new(IntMutex::new(mem_space)).unwrap();
+		let call = |sock: &mut Socket, request: u32, val: c_int| unsafe {
+			vmem::switch(&*vmem, || {
+				// Going through the memory space allocates the page on first access
+				let ptr: SyscallPtr<c_int> = (arg as usize).into();
+				*ptr.get_mut(&mut mem_space.lock()).unwrap().unwrap() = val;
+				let res = sock.ioctl(mem_space.clone(), (request as c_ulong).into(), arg as _);
+				res.map(|_| *arg)
+			})
+		};
+
+		// The number of bytes available is written back
+		assert_eq!(call(&mut sock, ioctl::FIONREAD, -1).unwrap(), 100);
+		let mut buf = [0u8; 60];
+		sock.read(0, &mut buf).unwrap();
+		assert_eq!(call(&mut sock, ioctl::FIONREAD, -1).unwrap(), 40);
+
+		// The value is read, not written
+		assert_eq!(call(&mut sock, ioctl::FIONBIO, 1).unwrap(), 1);
+		assert!(sock.is_non_blocking());
+		assert_eq!(call(&mut sock, ioctl::FIONBIO, 0).unwrap(), 0);
+		assert!(!sock.is_non_blocking());
+
+		assert_eq!(call(&mut sock, 0, 0).unwrap_err().as_int(), errno::ENOTTY);
+		// Invalid pointer
+		assert_eq!(
+			sock.ioctl(mem_space, (ioctl::FIONREAD as c_ulong).into(), 0x10 as _)
+				.unwrap_err()
+				.as_int(),
+			errno::EFAULT
+		);
+	}
+
+	#[test_case]
+	fn socket_opt_timestamp() {
+		let sock = inet_dgram_socket();
+		let mut sock = sock.lock();
+		let mut val = [0u8; size_of::<c_int>()];
+		sock.get_opt(SOL_SOCKET, SO_TIMESTAMP, &mut val).unwrap();
+		assert_eq!(c_int::from_ne_bytes(val), 0);
+
+		// Without the option, messages are not timestamped
+		let mut buf = [0u8; 16];
+		assert_eq!(sock.receive_from(&[1, 2, 3], &inet_sockaddr()), 3);
+		let msg = sock.recv_msg(&mut buf).unwrap();
+		assert_eq!(msg.len, 3);
+		assert!(msg.timestamp.is_none());
+
+		sock.set_opt(SOL_SOCKET, SO_TIMESTAMP, &1i32.to_ne_bytes())
+			.unwrap();
+		sock.get_opt(SOL_SOCKET, SO_TIMESTAMP, &mut val).unwrap();
+		assert_eq!(c_int::from_ne_bytes(val), 1);
+		let before = Timeval::from_nano(now());
+		assert_eq!(sock.receive_from(&[4, 5], &inet_sockaddr()), 2);
+		let msg = sock.recv_msg(&mut buf).unwrap();
+		assert_eq!(msg.len, 2);
+		assert!(!msg.truncated);
+		let timestamp = msg.timestamp.unwrap();
+		assert!(timestamp.to_nano() >= before.to_nano());
+
+		sock.set_opt(SOL_SOCKET, SO_TIMESTAMP, &0i32.to_ne_bytes())
+			.unwrap();
+		assert_eq!(sock.receive_from(&[6], &inet_sockaddr()), 1);
+		assert!(sock.recv_msg(&mut buf).unwrap().timestamp.is_none());
+	}
+
+	#[test_case]
+	fn socket_opt_rcvbuf() {
+		let mut sock = Socket::try_default().unwrap();
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&[1, 2, 3]);
+
+		sock.set_opt(SOL_SOCKET, SO_RCVBUF, &4096i32.to_ne_bytes())
+			.unwrap();
+		let mut val = [0u8; size_of::<c_int>()];
+		sock.get_opt(SOL_SOCKET, SO_RCVBUF, &mut val).unwrap();
+		assert_eq!(c_int::from_ne_bytes(val), 4096);
+		assert_eq!(
+			sock.inbox
+				.lock()
+				.receive_buffer
+				.as_ref()
+				.unwrap()
+				.get_size(),
+			4096
+		);
+		// The transmit buffer is untouched
+		sock.get_opt(SOL_SOCKET, SO_SNDBUF, &mut val).unwrap();
+		assert_eq!(c_int::from_ne_bytes(val) as usize, BUFFER_SIZE);
+
+		// Pending data is kept
+		let mut buf = [0u8; 4];
+		assert_eq!(sock.read(0, &mut buf).unwrap().0, 3);
+		assert_eq!(&buf[..3], &[1, 2, 3]);
+
+		// Clamped
+		sock.set_opt(SOL_SOCKET, SO_RCVBUF, &c_int::MAX.to_ne_bytes())
+			.unwrap();
+		assert_eq!(
+			sock.inbox
+				.lock()
+				.receive_buffer
+				.as_ref()
+				.unwrap()
+				.get_size(),
+			MAX_BUFFER_SIZE
+		);
+	}
+
+	#[test_case]
+	fn socket_opt_readonly() {
+		let mut sock = Socket::try_default().unwrap();
+		let mut val = [0u8; size_of::<c_int>()];
+		sock.get_opt(SOL_SOCKET, SO_TYPE, &mut val).unwrap();
+		assert_eq!(
+			c_int::from_ne_bytes(val),
+			SocketType::SockRaw.get_id() as c_int
+		);
+		sock.get_opt(SOL_SOCKET, SO_DOMAIN, &mut val).unwrap();
+		assert_eq!(
+			c_int::from_ne_bytes(val),
+			SocketDomain::AfUnix.get_id() as c_int
+		);
+
+		assert_eq!(
+			sock.set_opt(SOL_SOCKET, SO_TYPE, &1i32.to_ne_bytes())
+				.unwrap_err()
+				.as_int(),
+			errno::ENOPROTOOPT
+		);
+		assert_eq!(
+			sock.get_opt(SOL_SOCKET, 0xffff, &mut val)
+				.unwrap_err()
+				.as_int(),
+			errno::ENOPROTOOPT
+		);
+	}
+
+	#[test_case]
+	fn socket_read_eof() {
+		let mut sock = Socket::try_default().unwrap();
+		// Reading end and writing end
+		sock.increment_open(true, false);
+		sock.increment_open(false, true);
+
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&[1, 2, 3, 4]);
+		let mut buf = [0u8; 2];
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(buf, [1, 2]);
+
+		// The peer goes away
+		sock.decrement_open(false, true);
+
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (2, true));
+		assert_eq!(buf, [3, 4]);
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, true));
+	}
+
+	#[test_case]
+	fn socket_read_non_blocking() {
+		let mut sock = Socket::try_default().unwrap();
+		sock.increment_open(true, false);
+		sock.increment_open(false, true);
+		let mut buf = [0u8; 4];
+
+		// Blocking
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, false));
+
+		sock.non_blocking = true;
+		assert_eq!(sock.read(0, &mut buf).unwrap_err().as_int(), errno::EAGAIN);
+		// Available data is read normally
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&[1, 2]);
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(sock.read(0, &mut buf).unwrap_err().as_int(), errno::EAGAIN);
+
+		// The peer goes away: EOF instead
+		sock.decrement_open(false, true);
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, true));
+	}
+
+	#[test_case]
+	fn socket_read_outcome() {
+		let mut sock = socket_with_data(&[1, 2, 3]);
+		let mut buf = [0u8; 2];
+
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::Data(2)
+		);
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::Data(1)
+		);
+		// Blocking, no data yet
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::Data(0)
+		);
+		sock.non_blocking = true;
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::WouldBlock
+		);
+
+		// The peer goes away
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(&[4]);
+		sock.decrement_open(false, true);
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::Data(1)
+		);
+		assert_eq!(
+			sock.read_outcome(0, &mut buf).unwrap(),
+			io::ReadOutcome::Eof
+		);
+	}
+
+	/// Returns a socket with one reading end and one writing end, and `data` pending for reading.
+	fn socket_with_data(data: &[u8]) -> Socket {
+		let mut sock = Socket::try_default().unwrap();
+		sock.increment_open(true, false);
+		sock.increment_open(false, true);
+		sock.inbox
+			.lock()
+			.receive_buffer
+			.as_mut()
+			.unwrap()
+			.write(data);
+		sock
+	}
+
+	#[test_case]
+	fn socket_shutdown_read() {
+		let mut sock = socket_with_data(&[1, 2, 3, 4]);
+		sock.shutdown(SHUT_RD).unwrap();
+
+		// Buffered data is discarded
+		let mut buf = [0u8; 4];
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, true));
+		assert_eq!(sock.get_data_len(), 0);
+		// Transmission is still possible
+		assert!(sock.transmit_buffer.is_some());
+	}
+
+	#[test_case]
+	fn socket_shutdown_write() {
+		let mut sock = socket_with_data(&[1, 2, 3, 4]);
+		sock.shutdown(SHUT_WR).unwrap();
+
+		// Reception is not affected
+		let mut buf = [0u8; 2];
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, false));
+		// This end cannot send anymore
+		assert_eq!(sock.write(0, &[1]).unwrap_err().as_int(), errno::EPIPE);
+	}
+
+	#[test_case]
+	fn socket_shutdown_rdwr() {
+		let mut sock = socket_with_data(&[1, 2, 3, 4]);
+		sock.shutdown(SHUT_RDWR).unwrap();
+
+		let mut buf = [0u8; 4];
+		assert_eq!(sock.read(0, &mut buf).unwrap(), (0, true));
+		assert_eq!(sock.write(0, &[1]).unwrap_err().as_int(), errno::EPIPE);
+	}
+
+	#[test_case]
+	fn socket_shutdown_invalid() {
+		let mut sock = socket_with_data(&[1, 2]);
+		assert_eq!(sock.shutdown(3).unwrap_err().as_int(), errno::EINVAL);
+		assert_eq!(sock.shutdown(-1).unwrap_err().as_int(), errno::EINVAL);
+		assert_eq!(sock.get_data_len(), 2);
+	}
+
+	/// Returns an IPv4 socket address for `127.0.0.1:8080`.
+	fn inet_sockaddr() -> [u8; 16] {
+		let mut sockaddr = [0u8; 16];
+		sockaddr[..2].copy_from_slice(&(SocketDomain::AfInet.get_id() as c_short).to_ne_bytes());
+		sockaddr[2..4].copy_from_slice(&8080u16.to_be_bytes());
+		sockaddr[4..8].copy_from_slice(&[127, 0, 0, 1]);
+		sockaddr
+	}
+
+	#[test_case]
+	fn socket_bind_inet() {
+		let sock = Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfInet,
+				type_: SocketType::SockStream,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap();
+		let mut sock = sock.lock();
+
+		let sockaddr = inet_sockaddr();
+		sock.bind(&sockaddr).unwrap();
+		assert!(sock.is_bound());
+
+		let mut buf = [0u8; 16];
+		assert_eq!(sock.read_sockname(&mut buf), sockaddr.len());
+		assert_eq!(buf, sockaddr);
+
+		// Already bound
+		assert!(sock.bind(&sockaddr).is_err());
+	}
+
+	#[test_case]
+	fn socket_listen_accept() {
+		let sock = Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfInet,
+				type_: SocketType::SockStream,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap();
+		let mut sock = sock.lock();
+
+		// Not bound
+		assert_eq!(sock.listen(1).unwrap_err().as_int(), errno::EDESTADDRREQ);
+		sock.bind(&inet_sockaddr()).unwrap();
+		// Not listening
+		assert_eq!(
+			sock.accept().map(|_| ()).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		sock.listen(1).unwrap();
+		assert_eq!(
+			sock.accept().map(|_| ()).unwrap_err().as_int(),
+			errno::EAGAIN
+		);
+
+		// The second connection exceeds the backlog
+		let peer = [1u8, 2, 3, 4];
+		assert!(sock.push_pending(&peer).unwrap().is_some());
+		assert!(sock.push_pending(&[5, 6, 7, 8]).unwrap().is_none());
+
+		let conn = sock.accept().unwrap();
+		let conn = conn.lock();
+		assert_eq!(conn.peername.as_slice(), &peer);
+		assert_eq!(conn.sockname.as_slice(), sock.sockname.as_slice());
+		assert!(!conn.is_listening());
+		assert_eq!(
+			sock.accept().map(|_| ()).unwrap_err().as_int(),
+			errno::EAGAIN
+		);
+	}
+
+	#[test_case]
+	fn socket_backlog_overflow() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_backlog_overflow");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+
+		let mut val = [0u8; 4];
+		server
+			.lock()
+			.get_opt(SOL_SOCKET, SO_ACCEPTCONN, &mut val)
+			.unwrap();
+		assert_eq!(c_int::from_ne_bytes(val), 0);
+		server.lock().listen(2).unwrap();
+		server
+			.lock()
+			.get_opt(SOL_SOCKET, SO_ACCEPTCONN, &mut val)
+			.unwrap();
+		assert_eq!(c_int::from_ne_bytes(val), 1);
+
+		// Two connections fit in the queue, the next three are refused
+		let mut clients = Vec::new();
+		let mut refused = 0;
+		for _ in 0..5 {
+			let (client, client_loc) = unix_socket(SocketType::SockStream);
+			match client.lock().connect(&sockaddr, &client_loc) {
+				Ok(()) => {}
+				Err(e) => {
+					assert_eq!(e.as_int(), errno::EAGAIN);
+					refused += 1;
+				}
+			}
+			clients.push((client, client_loc)).unwrap();
+		}
+		assert_eq!(refused, 3);
+		assert_eq!(server.lock().get_dropped_connections(), refused);
+
+		// Accepting makes room for a new connection
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		clients.push((client, client_loc)).unwrap();
+		assert_eq!(server.lock().get_dropped_connections(), refused);
+
+		for (client, loc) in clients.iter() {
+			if let Some(conn_loc) = client.lock().peer.as_ref().map(|peer| peer.loc.clone()) {
+				buffer::release(&conn_loc);
+			}
+			buffer::release(loc);
+		}
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_listen_dgram() {
+		let sock = inet_dgram_socket();
+		let mut sock = sock.lock();
+		sock.bind(&inet_sockaddr()).unwrap();
+		assert_eq!(sock.listen(1).unwrap_err().as_int(), errno::EINVAL);
+	}
+
+	/// Returns a datagram socket in the IPv4 domain.
+	fn inet_dgram_socket() -> Arc<Mutex<Socket>> {
+		Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfInet,
+				type_: SocketType::SockDgram,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap()
+	}
+
+	#[test_case]
+	fn socket_connect_dgram() {
+		let sock = inet_dgram_socket();
+		let mut sock = sock.lock();
+		let peer = inet_sockaddr();
+
+		// No destination
+		assert_eq!(
+			sock.send_to(&[1, 2, 3], None).unwrap_err().as_int(),
+			errno::EDESTADDRREQ
+		);
+
+		sock.connect(&peer, &LOC).unwrap();
+		assert!(sock.is_connected());
+		assert_eq!(sock.send_to(&[1, 2, 3], None).unwrap(), 3);
+		assert_eq!(sock.write(0, &[4, 5]).unwrap(), 2);
+
+		// Only datagrams from the peer are received
+		let mut other = inet_sockaddr();
+		other[2..4].copy_from_slice(&8081u16.to_be_bytes());
+		assert_eq!(sock.receive_from(&[1, 2], &other), 0);
+		assert_eq!(sock.receive_from(&[1, 2], &peer), 2);
+		assert_eq!(sock.get_data_len(), 2);
+
+		// Re-point to another peer
+		sock.connect(&other, &LOC).unwrap();
+		assert_eq!(sock.receive_from(&[3], &peer), 0);
+		assert_eq!(sock.receive_from(&[3], &other), 1);
+	}
+
+	#[test_case]
+	fn socket_disconnect_dgram() {
+		let sock = inet_dgram_socket();
+		let mut sock = sock.lock();
+		let peer = inet_sockaddr();
+		sock.connect(&peer, &LOC).unwrap();
+
+		let unspec = [0u8; 16];
+		sock.connect(&unspec, &LOC).unwrap();
+		assert!(!sock.is_connected());
+		assert_eq!(
+			sock.send_to(&[1], None).unwrap_err().as_int(),
+			errno::EDESTADDRREQ
+		);
+		// An explicit destination is still allowed
+		assert_eq!(sock.send_to(&[1], Some(&peer)).unwrap(), 1);
+
+		// Datagrams from anyone are received again
+		let mut other = inet_sockaddr();
+		other[4..8].copy_from_slice(&[10, 0, 0, 1]);
+		assert_eq!(sock.receive_from(&[1], &other), 1);
+	}
+
+	#[test_case]
+	fn socket_sockaddr_truncated() {
+		let domains = [
+			SocketDomain::AfUnix,
+			SocketDomain::AfInet,
+			SocketDomain::AfInet6,
+			SocketDomain::AfNetlink,
+			SocketDomain::AfPacket,
+		];
+		for domain in domains {
+			let sock = Socket::new(
+				SocketDesc {
+					domain,
+					type_: SocketType::SockDgram,
+					protocol: 0,
+				},
+				&AccessProfile::KERNEL,
+			)
+			.unwrap();
+			let mut sock = sock.lock();
+
+			let mut sockaddr = [0u8; 128];
+			sockaddr[..2].copy_from_slice(&(domain.get_id() as c_short).to_ne_bytes());
+			let len = domain.get_sockaddr_len();
+			// Only the family, or less
+			let truncated = if domain.is_sockaddr_variable() {
+				1
+			} else {
+				len - 1
+			};
+
+			assert_eq!(
+				sock.bind(&sockaddr[..truncated]).unwrap_err().as_int(),
+				errno::EINVAL
+			);
+			assert_eq!(
+				sock.connect(&sockaddr[..truncated], &LOC)
+					.unwrap_err()
+					.as_int(),
+				errno::EINVAL
+			);
+			if domain.is_sockaddr_variable() {
+				// Oversized
+				assert_eq!(
+					sock.bind(&sockaddr[..len + 1]).unwrap_err().as_int(),
+					errno::EINVAL
+				);
+			}
+
+			// Trailing bytes are dropped
+			let full = if domain.is_sockaddr_variable() {
+				len
+			} else {
+				sockaddr.len()
+			};
+			sock.bind(&sockaddr[..full]).unwrap();
+			assert_eq!(sock.sockname.len(), len);
+		}
+	}
+
+	/// Returns a local socket address for the given path.
+	fn unix_sockaddr(path: &[u8]) -> Vec<u8> {
+		let mut sockaddr = crate::vec![0u8; SocketDomain::AfUnix.get_sockaddr_len()].unwrap();
+		sockaddr[..2].copy_from_slice(&(SocketDomain::AfUnix.get_id() as c_short).to_ne_bytes());
+		sockaddr[2..(2 + path.len())].copy_from_slice(path);
+		sockaddr
+	}
+
+	/// Creates a local socket of the given type and registers it, returning it along with its
+	/// location.
+	fn unix_socket(type_: SocketType) -> (Arc<Mutex<Socket>>, FileLocation) {
+		let sock = Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfUnix,
+				type_,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap();
+		let loc = buffer::register(None, sock.clone()).unwrap();
+		// Simulate an open file descriptor
+		sock.lock().increment_open(true, true);
+		(sock, loc)
+	}
+
+	#[test_case]
+	fn socket_connect_unix() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_connect_unix");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+
+		// Nobody is bound to the address yet
+		assert_eq!(
+			client
+				.lock()
+				.connect(&sockaddr, &client_loc)
+				.unwrap_err()
+				.as_int(),
+			errno::ECONNREFUSED
+		);
+		server.lock().bind(&sockaddr).unwrap();
+		// Not listening
+		assert_eq!(
+			client
+				.lock()
+				.connect(&sockaddr, &client_loc)
+				.unwrap_err()
+				.as_int(),
+			errno::ECONNREFUSED
+		);
+		server.lock().listen(1).unwrap();
+
+		// The address is taken
+		{
+			let (other, other_loc) = unix_socket(SocketType::SockStream);
+			assert_eq!(
+				other.lock().bind(&sockaddr).unwrap_err().as_int(),
+				errno::EADDRINUSE
+			);
+			buffer::release(&other_loc);
+		}
+
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		assert!(client.lock().is_connected());
+		assert_eq!(
+			client
+				.lock()
+				.connect(&sockaddr, &client_loc)
+				.unwrap_err()
+				.as_int(),
+			errno::EISCONN
+		);
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+
+		// Stream sockets only send to their peer
+		assert_eq!(
+			client
+				.lock()
+				.send_to(b"hello", Some(&sockaddr))
+				.unwrap_err()
+				.as_int(),
+			errno::EISCONN
+		);
+		assert_eq!(
+			client.lock().recv_from(&mut [0; 16]).unwrap_err().as_int(),
+			errno::EOPNOTSUPP
+		);
+
+		// Client to server
+		assert_eq!(client.lock().write(0, b"hello").unwrap(), 5);
+		let mut buf = [0u8; 16];
+		let (len, _) = conn.lock().read(0, &mut buf).unwrap();
+		assert_eq!(&buf[..len as usize], b"hello");
+		assert_eq!(client.lock().get_data_len(), 0);
+
+		// Server to client
+		assert_eq!(conn.lock().write(0, b"world").unwrap(), 5);
+		let (len, _) = client.lock().read(0, &mut buf).unwrap();
+		assert_eq!(&buf[..len as usize], b"world");
+
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_connect_unix_locked() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_connect_unix_locked");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+
+		// Connecting does not lock the listening socket
+		let conn = {
+			let mut server = server.lock();
+			client.lock().connect(&sockaddr, &client_loc).unwrap();
+			server.accept().unwrap()
+		};
+		conn.lock().increment_open(true, true);
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+
+		// Both ends can be used at the same time, since neither locks the other
+		{
+			let mut client = client.lock();
+			let mut conn = conn.lock();
+			assert_eq!(client.write(0, b"ping").unwrap(), 4);
+			assert_eq!(conn.write(0, b"pong").unwrap(), 4);
+			assert_eq!(client.readiness().writable, BUFFER_SIZE - 4);
+			assert_eq!(conn.readiness().readable, 4);
+
+			let mut buf = [0u8; 4];
+			assert_eq!(conn.read(0, &mut buf).unwrap(), (4, false));
+			assert_eq!(&buf, b"ping");
+			assert_eq!(client.read(0, &mut buf).unwrap(), (4, false));
+			assert_eq!(&buf, b"pong");
+
+			client.shutdown(SHUT_WR).unwrap();
+			assert!(conn.readiness().peer_closed);
+		}
+
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_unbind_on_close() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_unbind_on_close");
+		let (sock, loc) = unix_socket(SocketType::SockDgram);
+		let (other, other_loc) = unix_socket(SocketType::SockDgram);
+		let (sender, sender_loc) = unix_socket(SocketType::SockDgram);
+		sock.lock().bind(&sockaddr).unwrap();
+		assert_eq!(
+			other.lock().bind(&sockaddr).unwrap_err().as_int(),
+			errno::EADDRINUSE
+		);
+
+		// Closing the socket releases its address
+		sock.lock().decrement_open(true, true);
+		other.lock().bind(&sockaddr).unwrap();
+		// Datagrams sent to the address go to the new socket
+		assert_eq!(
+			sender.lock().send_to(&[1, 2, 3], Some(&sockaddr)).unwrap(),
+			3
+		);
+		assert_eq!(sock.lock().get_data_len(), 0);
+		assert_eq!(other.lock().get_data_len(), 3);
+
+		// So does dropping it
+		buffer::release(&other_loc);
+		drop(other);
+		let (third, third_loc) = unix_socket(SocketType::SockDgram);
+		third.lock().bind(&sockaddr).unwrap();
+
+		buffer::release(&third_loc);
+		buffer::release(&sender_loc);
+		buffer::release(&loc);
+	}
+
+	#[test_case]
+	fn socket_unbind_accepted() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_unbind_accepted");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+
+		// The accepted socket shares the address of the listening socket, which keeps it
+		conn.lock().decrement_open(true, true);
+		let (other, other_loc) = unix_socket(SocketType::SockStream);
+		assert_eq!(
+			other.lock().bind(&sockaddr).unwrap_err().as_int(),
+			errno::EADDRINUSE
+		);
+
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+		buffer::release(&other_loc);
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_readiness() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_readiness");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+
+		// After a write
+		assert_eq!(client.lock().write(0, b"hello").unwrap(), 5);
+		let readiness = conn.lock().readiness();
+		assert_eq!(readiness.readable, conn.lock().get_data_len());
+		assert_eq!(readiness.readable, 5);
+		assert_eq!(readiness.writable, conn.lock().get_writable_len());
+		assert!(!readiness.peer_closed);
+		assert!(readiness.error.is_none());
+		let readiness = client.lock().readiness();
+		assert_eq!(readiness.readable, 0);
+		assert_eq!(readiness.writable, BUFFER_SIZE - 5);
+		assert_eq!(readiness.writable, client.lock().get_writable_len());
+
+		// After the peer shuts down transmission
+		client.lock().shutdown(SHUT_WR).unwrap();
+		let readiness = conn.lock().readiness();
+		assert_eq!(readiness.readable, 5);
+		assert!(readiness.peer_closed);
+		assert!(readiness.error.is_none());
+		let mask = io::POLLIN | io::POLLOUT | io::POLLHUP | io::POLLERR;
+		assert_eq!(
+			conn.lock().poll(mask).unwrap(),
+			io::POLLIN | io::POLLOUT | io::POLLHUP
+		);
+
+		// After the peer is gone
+		buffer::release(&conn_loc);
+		let readiness = client.lock().readiness();
+		assert_eq!(readiness.writable, 0);
+		assert!(readiness.peer_closed);
+		assert_eq!(readiness.error.map(|e| e.as_int()), Some(errno::ECONNRESET));
+		assert_eq!(
+			client.lock().poll(mask).unwrap(),
+			io::POLLIN | io::POLLHUP | io::POLLERR
+		);
+
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_shutdown_write_peer() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_shutdown_write_peer");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+
+		assert_eq!(client.lock().write(0, b"abcd").unwrap(), 4);
+		client.lock().shutdown(SHUT_WR).unwrap();
+
+		// The peer drains pending data, then gets EOF
+		let mut buf = [0u8; 2];
+		assert_eq!(conn.lock().read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(conn.lock().read(0, &mut buf).unwrap(), (2, true));
+		assert_eq!(conn.lock().read(0, &mut buf).unwrap(), (0, true));
+		// The end that shut down transmission can still receive
+		assert_eq!(conn.lock().write(0, b"ef").unwrap(), 2);
+		assert_eq!(client.lock().read(0, &mut buf).unwrap(), (2, false));
+		assert_eq!(&buf, b"ef");
+
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_writable_len() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_writable_len");
+		let (server, server_loc) = unix_socket(SocketType::SockStream);
+		let (client, client_loc) = unix_socket(SocketType::SockStream);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		assert_eq!(server.lock().poll(io::POLLIN).unwrap(), io::POLLIN);
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+		assert_eq!(server.lock().poll(io::POLLIN).unwrap(), 0);
+
+		let mask = io::POLLIN | io::POLLOUT;
+		assert_eq!(client.lock().get_writable_len(), BUFFER_SIZE);
+		assert_eq!(client.lock().poll(mask).unwrap(), io::POLLOUT);
+
+		// Fill the peer's receive buffer
+		let buf = [42u8; 4096];
+		let mut total = 0;
+		loop {
+			let len = client.lock().write(0, &buf).unwrap() as usize;
+			if len == 0 {
+				break;
+			}
+			total += len;
+		}
+		assert_eq!(total, BUFFER_SIZE);
+		assert_eq!(client.lock().get_writable_len(), 0);
+		assert_eq!(client.lock().poll(mask).unwrap(), 0);
+		assert_eq!(conn.lock().poll(mask).unwrap(), mask);
+
+		// Free space on the reading side
+		let mut buf = [0u8; 100];
+		assert_eq!(conn.lock().read(0, &mut buf).unwrap().0, 100);
+		assert_eq!(client.lock().get_writable_len(), 100);
+		assert_eq!(client.lock().poll(mask).unwrap(), io::POLLOUT);
+
+		// No more writing once transmission is shutdown
+		client.lock().shutdown(SHUT_WR).unwrap();
+		assert_eq!(client.lock().get_writable_len(), 0);
+		assert_eq!(client.lock().poll(mask).unwrap(), 0);
+
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_seqpacket() {
+		let sockaddr = unix_sockaddr(b"/tmp/socket_seqpacket");
+		let (server, server_loc) = unix_socket(SocketType::SockSeqpacket);
+		let (client, client_loc) = unix_socket(SocketType::SockSeqpacket);
+		server.lock().bind(&sockaddr).unwrap();
+		server.lock().listen(1).unwrap();
+		client.lock().connect(&sockaddr, &client_loc).unwrap();
+		let conn = server.lock().accept().unwrap();
+		conn.lock().increment_open(true, true);
+		let mut buf = [0u8; 16];
+
+		// Records keep their boundaries
+		assert_eq!(client.lock().write(0, b"ab").unwrap(), 2);
+		assert_eq!(client.lock().write(0, b"cde").unwrap(), 3);
+		assert_eq!(conn.lock().recv_record(&mut buf).unwrap(), (2, false));
+		assert_eq!(&buf[..2], b"ab");
+		// Exact fit
+		assert_eq!(conn.lock().recv_record(&mut buf[..3]).unwrap(), (3, false));
+		assert_eq!(&buf[..3], b"cde");
+		assert_eq!(
+			conn.lock().recv_record(&mut buf).unwrap_err().as_int(),
+			errno::EAGAIN
+		);
+
+		// Truncated
+		assert_eq!(client.lock().write(0, b"hello world").unwrap(), 11);
+		assert_eq!(client.lock().write(0, b"next").unwrap(), 4);
+		assert_eq!(conn.lock().recv_record(&mut buf[..5]).unwrap(), (5, true));
+		assert_eq!(&buf[..5], b"hello");
+		// The rest of the truncated record is discarded
+		let (len, _) = conn.lock().read(0, &mut buf).unwrap();
+		assert_eq!(&buf[..len as usize], b"next");
+
+		// Backpressure: a record that doesn't fit is not sent at all
+		let record = [42u8; 5000];
+		let mut sent = 0;
+		loop {
+			match client.lock().write(0, &record) {
+				Ok(len) => sent += len as usize,
+				Err(e) => {
+					assert_eq!(e.as_int(), errno::EAGAIN);
+					break;
+				}
+			}
+		}
+		assert_eq!(sent, BUFFER_SIZE / 5000 * 5000);
+		assert_eq!(conn.lock().get_data_len(), sent);
+		// Room is made by receiving a record
+		let mut record_buf = [0u8; 5000];
+		assert_eq!(
+			conn.lock().recv_record(&mut record_buf).unwrap(),
+			(5000, false)
+		);
+		assert_eq!(client.lock().write(0, &record).unwrap(), 5000);
+		// A record larger than the receive buffer can never be sent
+		let large = crate::vec![0u8; BUFFER_SIZE + 1].unwrap();
+		assert_eq!(
+			client.lock().write(0, &large).unwrap_err().as_int(),
+			errno::EMSGSIZE
+		);
+
+		// Only sequenced-packet sockets have records
+		let (stream, stream_loc) = unix_socket(SocketType::SockStream);
+		assert_eq!(
+			stream.lock().recv_record(&mut buf).unwrap_err().as_int(),
+			errno::EOPNOTSUPP
+		);
+		buffer::release(&stream_loc);
+
+		let conn_loc = client.lock().peer.as_ref().unwrap().loc.clone();
+		buffer::release(&conn_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_send_to_stream() {
+		let sock = Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfInet,
+				type_: SocketType::SockStream,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap();
+		assert_eq!(
+			sock.lock()
+				.send_to(&[1], Some(&inet_sockaddr()))
+				.unwrap_err()
+				.as_int(),
+			errno::EOPNOTSUPP
+		);
+	}
+
+	#[test_case]
+	fn socket_send_to_unix() {
+		let server_addr = unix_sockaddr(b"/tmp/socket_send_to_unix_server");
+		let client_addr = unix_sockaddr(b"/tmp/socket_send_to_unix_client");
+		let (server, server_loc) = unix_socket(SocketType::SockDgram);
+		let (client, client_loc) = unix_socket(SocketType::SockDgram);
+
+		// Nobody is bound to the address yet
+		assert_eq!(
+			client
+				.lock()
+				.send_to(b"hello", Some(&server_addr))
+				.unwrap_err()
+				.as_int(),
+			errno::ECONNREFUSED
+		);
+		server.lock().bind(&server_addr).unwrap();
+		client.lock().bind(&client_addr).unwrap();
+
+		let mut client_guard = client.lock();
+		assert_eq!(
+			client_guard.send_to(b"hello", Some(&server_addr)).unwrap(),
+			5
+		);
+		assert_eq!(
+			client_guard.send_to(b"world", Some(&server_addr)).unwrap(),
+			5
+		);
+		drop(client_guard);
+
+		// Datagrams are received one at a time, along with their source address
+		let mut buf = [0u8; 16];
+		let (len, src) = server.lock().recv_from(&mut buf).unwrap();
+		assert_eq!(&buf[..len], b"hello");
+		let src = src.unwrap();
+		assert_eq!(
+			src.addr,
+			Address::Unix(Vec::from_slice(b"/tmp/socket_send_to_unix_client").unwrap())
+		);
+		// The rest of a truncated datagram is discarded
+		let (len, _) = server.lock().recv_from(&mut buf[..3]).unwrap();
+		assert_eq!(&buf[..len], b"wor");
+		assert_eq!(server.lock().get_data_len(), 0);
+		assert_eq!(
+			server.lock().recv_from(&mut buf).unwrap_err().as_int(),
+			errno::EAGAIN
+		);
+
+		// Reply to the source address
+		let mut src_buf = [0u8; 110];
+		let src_len = src.to_bytes(&mut src_buf).unwrap();
+		assert_eq!(
+			server
+				.lock()
+				.send_to(b"ok", Some(&src_buf[..src_len]))
+				.unwrap(),
+			2
+		);
+		let (len, src) = client.lock().recv_from(&mut buf).unwrap();
+		assert_eq!(&buf[..len], b"ok");
+		assert_eq!(
+			src.unwrap().addr,
+			Address::Unix(Vec::from_slice(b"/tmp/socket_send_to_unix_server").unwrap())
+		);
+
+		// The address of an unbound sender is unknown
+		let (anon, anon_loc) = unix_socket(SocketType::SockDgram);
+		assert_eq!(anon.lock().send_to(b"hi", Some(&server_addr)).unwrap(), 2);
+		let (len, src) = server.lock().recv_from(&mut buf).unwrap();
+		assert_eq!(&buf[..len], b"hi");
+		assert!(src.is_none());
+
+		buffer::release(&anon_loc);
+		buffer::release(&client_loc);
+		buffer::release(&server_loc);
+	}
+
+	#[test_case]
+	fn socket_new_stream() {
+		let desc = SocketDesc {
+			domain: SocketDomain::AfInet,
+			type_: SocketType::SockStream,
+			protocol: 0,
+		};
+		assert!(Socket::new(desc.clone(), &AccessProfile::KERNEL).is_ok());
+		// Explicit protocol
+		let desc = SocketDesc {
+			protocol: 6,
+			..desc
+		};
+		assert!(Socket::new(desc, &AccessProfile::new(1000, 1000)).is_ok());
+	}
+
+	#[test_case]
+	fn socket_new_invalid() {
+		let new = |domain, type_, protocol| {
+			let desc = SocketDesc {
+				domain,
+				type_,
+				protocol,
+			};
+			Socket::new(desc, &AccessProfile::KERNEL)
+				.map(|_| ())
+				.unwrap_err()
+				.as_int()
+		};
+		assert_eq!(
+			new(SocketDomain::AfInet, SocketType::SockStream, 999),
+			errno::EPROTONOSUPPORT
+		);
+		assert_eq!(
+			new(SocketDomain::AfUnix, SocketType::SockStream, 1),
+			errno::EPROTONOSUPPORT
+		);
+		// UDP over a stream
+		assert_eq!(
+			new(SocketDomain::AfInet6, SocketType::SockStream, 17),
+			errno::EPROTOTYPE
+		);
+		assert_eq!(
+			new(SocketDomain::AfInet, SocketType::SockSeqpacket, 0),
+			errno::ESOCKTNOSUPPORT
+		);
+
+		// Not privileged
+		let desc = SocketDesc {
+			domain: SocketDomain::AfPacket,
+			type_: SocketType::SockRaw,
+			protocol: 0,
+		};
+		assert_eq!(
+			Socket::new(desc, &AccessProfile::new(1000, 1000))
+				.map(|_| ())
+				.unwrap_err()
+				.as_int(),
+			errno::EACCES
+		);
+	}
+
+	#[test_case]
+	fn socket_bind_invalid() {
+		let sock = Socket::new(
+			SocketDesc {
+				domain: SocketDomain::AfInet,
+				type_: SocketType::SockStream,
+				protocol: 0,
+			},
+			&AccessProfile::KERNEL,
+		)
+		.unwrap();
+		let mut sock = sock.lock();
+		let sockaddr = inet_sockaddr();
+
+		// Too short
+		assert_eq!(sock.bind(&[]).unwrap_err().as_int(), errno::EINVAL);
+		assert_eq!(
+			sock.bind(&sockaddr[..8]).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		// Unknown family
+		assert_eq!(
+			sock.bind(&[0xff, 0xff, 0, 0]).unwrap_err().as_int(),
+			errno::EAFNOSUPPORT
+		);
+		assert!(!sock.is_bound());
 	}
 }
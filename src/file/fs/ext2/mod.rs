@@ -46,6 +46,7 @@ use crate::memory::malloc;
 use crate::time::clock;
 use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::unit::TimestampScale;
+use crate::util::container::bitfield::Bitfield;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
@@ -396,19 +397,9 @@ impl Superblock {
 	///
 	/// If every elements are set, the function returns `None`.
 	fn search_bitmap_blk(bitmap: &[u8]) -> Option<u32> {
-		for (i, b) in bitmap.iter().enumerate() {
-			if *b == 0xff {
-				continue;
-			}
-
-			for j in 0..8 {
-				if (*b >> j) & 0b1 == 0 {
-					return Some((i * 8 + j) as _);
-				}
-			}
-		}
-
-		None
+		Bitfield::from_buffer(bitmap, bitmap.len() * 8)
+			.find_clear()
+			.map(|i| i as _)
 	}
 
 	/// Searches into a bitmap starting at block `start`.
@@ -830,7 +821,7 @@ impl Filesystem for Ext2Fs {
 				}
 
 				// Creating entries with types
-				let mut final_entries = HashMap::new();
+				let mut final_entries = HashMap::default();
 
 				for (inode, entry_type, name) in entries {
 					let entry_type = match entry_type {
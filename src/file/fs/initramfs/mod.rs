@@ -84,7 +84,7 @@ pub fn load(data: &[u8]) -> Result<(), Errno> {
 		let file_type = hdr.get_type();
 		let content = match file_type {
 			FileType::Regular => FileContent::Regular,
-			FileType::Directory => FileContent::Directory(HashMap::new()),
+			FileType::Directory => FileContent::Directory(HashMap::default()),
 			FileType::Link => FileContent::Link(entry.get_content().try_into()?),
 			FileType::Fifo => FileContent::Fifo,
 			FileType::Socket => FileContent::Socket,
@@ -63,7 +63,7 @@ impl ProcFS {
 			procs: HashMap::new(),
 		};
 
-		let mut entries = HashMap::new();
+		let mut entries = HashMap::default();
 
 		// Create /proc/meminfo
 		let node = MemInfo {};
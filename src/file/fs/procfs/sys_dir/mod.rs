@@ -31,7 +31,7 @@ impl SysDir {
 	///
 	/// The function adds every nodes to the given kernfs `fs`.
 	pub fn new(fs: &mut KernFS) -> Result<Self, Errno> {
-		let mut entries = HashMap::new();
+		let mut entries = HashMap::default();
 
 		// TODO Add every nodes
 		// TODO On fail, remove previously inserted nodes
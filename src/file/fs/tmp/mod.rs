@@ -65,7 +65,8 @@ impl TmpFS {
 		};
 
 		// Adding the root node
-		let root_node = DummyKernFSNode::new(0o777, 0, 0, FileContent::Directory(HashMap::new()));
+		let root_node =
+			DummyKernFSNode::new(0o777, 0, 0, FileContent::Directory(HashMap::default()));
 		fs.update_size(get_used_size(&root_node) as _, |fs| {
 			fs.fs.set_root(Box::new(root_node)?)?;
 			Ok(())
@@ -240,3 +241,47 @@ impl FilesystemType for TmpFsType {
 		)?))?)
 	}
 }
+
+/// Creates a regular file with the given content on a new tmpfs mounted at `path`, for testing
+/// purpose.
+///
+/// The function returns the location of the file.
+#[cfg(test)]
+pub fn test_file(path: &[u8], content: &[u8]) -> crate::file::FileLocation {
+	use crate::file::mountpoint;
+	use crate::file::mountpoint::MountSource;
+	use crate::file::vfs;
+	use crate::file::FileLocation;
+	use crate::util::io::DummyIO;
+
+	let source = MountSource::NoDev(String::try_from(path).unwrap());
+	let fs_type: Arc<dyn FilesystemType> = Arc::new(TmpFsType {}).unwrap();
+	let path = Path::from_str(path, false).unwrap();
+	let mountpoint = mountpoint::create(source, Some(fs_type), 0, path).unwrap();
+	let location = {
+		let mountpoint = mountpoint.lock();
+		let fs_mutex = mountpoint.get_filesystem();
+		let mut fs = fs_mutex.lock();
+		let root = fs.get_root_inode(&mut DummyIO {}).unwrap();
+		let name = String::try_from(b"file").unwrap();
+		let file = fs
+			.add_file(
+				&mut DummyIO {},
+				root,
+				name,
+				0,
+				0,
+				0o666,
+				FileContent::Regular,
+			)
+			.unwrap();
+		FileLocation::Filesystem {
+			mountpoint_id: mountpoint.get_id(),
+			inode: file.get_location().get_inode(),
+		}
+	};
+
+	let file = vfs::get_file_by_location(&location).unwrap();
+	file.lock().write(0, content).unwrap();
+	location
+}
@@ -36,6 +36,7 @@ use crate::time::clock::CLOCK_MONOTONIC;
 use crate::time::unit::Timestamp;
 use crate::time::unit::TimestampScale;
 use crate::util::container::hashmap::HashMap;
+use crate::util::container::hashmap::SipHasherBuilder;
 use crate::util::container::string::String;
 use crate::util::io::IO;
 use crate::util::lock::IntMutex;
@@ -223,7 +224,10 @@ pub enum FileContent {
 	///
 	/// The hashmap contains the list of entries. The key is the name of the entry and the value
 	/// is the entry itself.
-	Directory(HashMap<String, DirEntry>),
+	///
+	/// Since entry names are chosen by userspace, entries are hashed with a randomly keyed
+	/// [`SipHasherBuilder`].
+	Directory(HashMap<String, DirEntry, SipHasherBuilder>),
 	/// The file is a link. The data is the link's target.
 	Link(String),
 	/// The file is a FIFO.
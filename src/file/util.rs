@@ -38,7 +38,7 @@ pub fn create_dirs(path: &Path) -> EResult<usize> {
 				name.try_clone()?,
 				&AccessProfile::KERNEL,
 				0o755,
-				FileContent::Directory(HashMap::new()),
+				FileContent::Directory(HashMap::default()),
 			) {
 				Ok(_) => created_count += 1,
 				Err(e) if e.as_int() != errno::EEXIST => return Err(e),
@@ -87,7 +87,7 @@ pub fn copy_file(old: &mut File, new_parent: &mut File, new_name: String) -> ERe
 				new_name,
 				&ap,
 				mode,
-				FileContent::Directory(HashMap::new()),
+				FileContent::Directory(HashMap::default()),
 			)?;
 			let mut new = new_mutex.lock();
 
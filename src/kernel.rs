@@ -276,6 +276,9 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 	// From here, the kernel considers that memory management has been fully
 	// initialized
 
+	// Hash maps draw their keys from the entropy pool, so it must be ready before any is created
+	crypto::init().unwrap_or_else(|e| panic!("Failed to initialize cryptography! ({e})"));
+
 	// Performing kernel self-tests
 	#[cfg(test)]
 	kernel_selftest();
@@ -311,7 +314,6 @@ pub extern "C" fn kernel_main(magic: u32, multiboot_ptr: *const c_void) -> ! {
 	println!("Initializing devices management...");
 	device::init().unwrap_or_else(|e| panic!("Failed to initialize devices management! ({e})"));
 	net::osi::init().unwrap_or_else(|e| panic!("Failed to initialize network! ({e})"));
-	crypto::init().unwrap_or_else(|e| panic!("Failed to initialize cryptography! ({e})"));
 
 	let root = args_parser.get_root_dev();
 	println!("Initializing files management...");
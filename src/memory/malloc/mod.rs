@@ -5,6 +5,8 @@
 //! malloc interface.
 //!
 //! The module also provides the structure `Alloc` which safely manages a memory allocation.
+//!
+//! Allocations can be attributed to a subsystem with an [`AllocTag`], for memory accounting.
 
 mod block;
 mod chunk;
@@ -20,12 +22,15 @@ use core::cmp::min;
 use core::cmp::Ordering;
 use core::ffi::c_void;
 use core::mem::size_of;
+use core::mem::size_of_val;
 use core::num::NonZeroUsize;
 use core::ops::Index;
 use core::ops::IndexMut;
 use core::ptr;
 use core::ptr::drop_in_place;
 use core::slice;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering as AtomicOrdering;
 
 /// The allocator's mutex.
 static MUTEX: IntMutex<()> = IntMutex::new(());
@@ -138,6 +143,37 @@ pub unsafe fn free(mut ptr: NonNull<c_void>) {
 	}
 }
 
+/// A tag attributing allocations to a subsystem, for memory accounting.
+///
+/// Tags are meant to be declared as statics, one per subsystem.
+#[derive(Debug)]
+pub struct AllocTag {
+	/// The name of the subsystem.
+	name: &'static str,
+	/// The number of bytes currently allocated under the tag.
+	usage: AtomicUsize,
+}
+
+impl AllocTag {
+	/// Creates a new tag for the subsystem with the given name.
+	pub const fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			usage: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns the name of the subsystem.
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+
+	/// Returns the number of bytes currently allocated under the tag.
+	pub fn usage(&self) -> usize {
+		self.usage.load(AtomicOrdering::Relaxed)
+	}
+}
+
 /// Structure representing a kernelside allocation.
 ///
 /// The structure holds one or more elements of the given type. Freeing the
@@ -146,6 +182,8 @@ pub unsafe fn free(mut ptr: NonNull<c_void>) {
 pub struct Alloc<T> {
 	/// Slice representing the allocation.
 	slice: NonNull<[T]>,
+	/// The tag the allocated bytes are attributed to, if any.
+	tag: Option<&'static AllocTag>,
 }
 
 impl<T> Alloc<T> {
@@ -164,6 +202,19 @@ impl<T> Alloc<T> {
 	/// Since the memory is not initialized, objects in the allocation might be
 	/// in an inconsistent state.
 	pub unsafe fn new(size: NonZeroUsize) -> AllocResult<Self> {
+		Self::new_tagged(size, None)
+	}
+
+	/// Same as `new`, except the allocated bytes are attributed to `tag`, if any.
+	///
+	/// # Safety
+	///
+	/// Since the memory is not initialized, objects in the allocation might be
+	/// in an inconsistent state.
+	pub unsafe fn new_tagged(
+		size: NonZeroUsize,
+		tag: Option<&'static AllocTag>,
+	) -> AllocResult<Self> {
 		let len = size
 			.checked_mul(size_of::<T>().try_into().unwrap())
 			.ok_or_else(|| AllocError)?;
@@ -173,9 +224,13 @@ impl<T> Alloc<T> {
 			size.get(),
 		))
 		.unwrap();
+		if let Some(tag) = tag {
+			tag.usage.fetch_add(len.get(), AtomicOrdering::Relaxed);
+		}
 
 		Ok(Self {
 			slice,
+			tag,
 		})
 	}
 
@@ -220,6 +275,11 @@ impl<T> Alloc<T> {
 		self.slice.len()
 	}
 
+	/// Returns the tag the allocation is attributed to, if any.
+	pub fn tag(&self) -> Option<&'static AllocTag> {
+		self.tag
+	}
+
 	/// Changes the size of the memory allocation.
 	///
 	/// All new elements are uninitialized.
@@ -236,9 +296,14 @@ impl<T> Alloc<T> {
 		let len = n
 			.checked_mul(size_of::<T>().try_into().unwrap())
 			.ok_or_else(|| AllocError)?;
+		let old_len = size_of_val(self.slice.as_ref());
 		let ptr = realloc(self.slice.cast(), len)?;
 		self.slice =
 			NonNull::new(slice::from_raw_parts_mut::<T>(ptr.cast().as_mut(), n.get())).unwrap();
+		if let Some(tag) = self.tag {
+			tag.usage.fetch_add(len.get(), AtomicOrdering::Relaxed);
+			tag.usage.fetch_sub(old_len, AtomicOrdering::Relaxed);
+		}
 
 		Ok(())
 	}
@@ -335,6 +400,10 @@ impl<T> IndexMut<usize> for Alloc<T> {
 
 impl<T> Drop for Alloc<T> {
 	fn drop(&mut self) {
+		if let Some(tag) = self.tag {
+			let len = size_of_val(unsafe { self.slice.as_ref() });
+			tag.usage.fetch_sub(len, AtomicOrdering::Relaxed);
+		}
 		unsafe {
 			free(self.slice.cast());
 		}
@@ -538,4 +607,25 @@ mod test {
 
 		assert_eq!(usage, buddy::allocated_pages_count());
 	}
+
+	#[test_case]
+	fn alloc_tagged() {
+		static TAG: AllocTag = AllocTag::new("alloc_tagged");
+
+		let mut alloc = unsafe {
+			Alloc::<u32>::new_tagged(NonZeroUsize::new(8).unwrap(), Some(&TAG)).unwrap()
+		};
+		assert!(ptr::eq(alloc.tag().unwrap(), &TAG));
+		assert_eq!(TAG.usage(), 8 * size_of::<u32>());
+		unsafe {
+			alloc.realloc(NonZeroUsize::new(100).unwrap()).unwrap();
+		}
+		assert_eq!(TAG.usage(), 100 * size_of::<u32>());
+		unsafe {
+			alloc.realloc(NonZeroUsize::new(2).unwrap()).unwrap();
+		}
+		assert_eq!(TAG.usage(), 2 * size_of::<u32>());
+		drop(alloc);
+		assert_eq!(TAG.usage(), 0);
+	}
 }
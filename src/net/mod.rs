@@ -15,6 +15,9 @@ use crate::file::perm::ROOT_GID;
 use crate::file::perm::ROOT_UID;
 use crate::net::sockaddr::SockAddrIn;
 use crate::net::sockaddr::SockAddrIn6;
+use crate::net::sockaddr::SockAddrLl;
+use crate::net::sockaddr::SockAddrNl;
+use crate::net::sockaddr::SockAddrUn;
 use crate::util::container::hashmap::HashMap;
 use crate::util::container::string::String;
 use crate::util::container::vec::Vec;
@@ -22,6 +25,7 @@ use crate::util::lock::Mutex;
 use crate::util::ptr::arc::Arc;
 use buff::BuffList;
 use core::cmp::Ordering;
+use core::ffi::c_short;
 use core::mem::size_of;
 
 /// Type representing a Media Access Control (MAC) address.
@@ -36,6 +40,8 @@ pub enum Address {
 	IPv4([u8; 4]),
 	/// Internet Protocol version 6.
 	IPv6([u8; 16]),
+	/// The path of a local socket. Abstract addresses start with a nul byte.
+	Unix(Vec<u8>),
 }
 
 /// An address/subnet mask pair to be bound to an interface.
@@ -255,12 +261,34 @@ impl SocketDomain {
 	}
 
 	/// Returns the size of the sockaddr structure for the domain.
+	///
+	/// For variable-length addresses (see [`Self::is_sockaddr_variable`]), this is the maximum
+	/// size.
 	pub fn get_sockaddr_len(&self) -> usize {
 		match self {
+			Self::AfUnix => size_of::<SockAddrUn>(),
 			Self::AfInet => size_of::<SockAddrIn>(),
 			Self::AfInet6 => size_of::<SockAddrIn6>(),
-			// TODO add others
-			_ => 0,
+			Self::AfNetlink => size_of::<SockAddrNl>(),
+			Self::AfPacket => size_of::<SockAddrLl>(),
+		}
+	}
+
+	/// Tells whether the sockaddr structure for the domain is variable-length.
+	pub fn is_sockaddr_variable(&self) -> bool {
+		matches!(self, Self::AfUnix)
+	}
+
+	/// Tells whether `len` is a valid length for a sockaddr structure of the domain.
+	///
+	/// Fixed-size addresses may be followed by unused bytes, but variable-length addresses may
+	/// not exceed the size of the structure.
+	pub fn is_sockaddr_len_valid(&self, len: usize) -> bool {
+		let sockaddr_len = self.get_sockaddr_len();
+		if self.is_sockaddr_variable() {
+			(size_of::<c_short>()..=sockaddr_len).contains(&len)
+		} else {
+			len >= sockaddr_len
 		}
 	}
 }
@@ -331,8 +359,15 @@ impl AccessProfile {
 	}
 }
 
+/// Protocol: Transmission Control Protocol.
+const IPPROTO_TCP: i32 = 6;
+/// Protocol: User Datagram Protocol.
+const IPPROTO_UDP: i32 = 17;
+/// The number of netlink protocols.
+const NETLINK_PROTOCOLS_COUNT: i32 = 32;
+
 /// Socket network stack descriptor.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct SocketDesc {
 	/// The socket's domain.
 	pub domain: SocketDomain,
@@ -341,3 +376,43 @@ pub struct SocketDesc {
 	/// The socket's protocol. `0` means using the default protocol for the domain/type pair.
 	pub protocol: i32,
 }
+
+impl SocketDesc {
+	/// Checks that the descriptor describes a supported socket.
+	///
+	/// The protocol may be `0` to use the default protocol for the domain/type pair.
+	///
+	/// If the domain doesn't support the type, the function returns `ESOCKTNOSUPPORT`. If the
+	/// protocol is unknown, the function returns `EPROTONOSUPPORT`. If the protocol is meant for
+	/// another type, the function returns `EPROTOTYPE`.
+	pub fn check(&self) -> Result<(), Errno> {
+		use SocketDomain::*;
+		use SocketType::*;
+
+		let valid = match (self.domain, self.type_) {
+			(AfUnix, SockStream | SockDgram | SockSeqpacket) => self.protocol == 0,
+			(AfInet | AfInet6, SockStream) => matches!(self.protocol, 0 | IPPROTO_TCP),
+			(AfInet | AfInet6, SockDgram) => matches!(self.protocol, 0 | IPPROTO_UDP),
+			// The protocol is written in the IP header, thus it is required
+			(AfInet | AfInet6, SockRaw) => (1..=u8::MAX as i32).contains(&self.protocol),
+			(AfNetlink, SockDgram | SockRaw) => {
+				(0..NETLINK_PROTOCOLS_COUNT).contains(&self.protocol)
+			}
+			// The protocol is an EtherType
+			(AfPacket, SockDgram | SockRaw) => (0..=u16::MAX as i32).contains(&self.protocol),
+
+			_ => return Err(errno!(ESOCKTNOSUPPORT)),
+		};
+		if valid {
+			return Ok(());
+		}
+
+		let other_type = matches!(self.domain, AfInet | AfInet6)
+			&& matches!(self.protocol, IPPROTO_TCP | IPPROTO_UDP);
+		if other_type {
+			Err(errno!(EPROTOTYPE))
+		} else {
+			Err(errno!(EPROTONOSUPPORT))
+		}
+	}
+}
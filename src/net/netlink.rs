@@ -4,6 +4,7 @@ use crate::errno::Errno;
 use crate::util::container::ring_buffer::RingBuffer;
 use crate::util::container::vec::Vec;
 use core::mem::size_of;
+use core::mem::MaybeUninit;
 
 /// Netlink message header.
 #[repr(C)]
@@ -27,9 +28,9 @@ pub struct Handle {
 	pub family: i32,
 
 	/// The buffer for read operations.
-	read_buff: RingBuffer<u8, Vec<u8>>,
+	read_buff: RingBuffer<u8, Vec<MaybeUninit<u8>>>,
 	/// The buffer for write operations.
-	write_buff: RingBuffer<u8, Vec<u8>>,
+	write_buff: RingBuffer<u8, Vec<MaybeUninit<u8>>>,
 }
 
 impl Handle {
@@ -38,8 +39,8 @@ impl Handle {
 		Ok(Self {
 			family: 0,
 
-			read_buff: RingBuffer::new(crate::vec![0; 16384]?),
-			write_buff: RingBuffer::new(crate::vec![0; 16384]?),
+			read_buff: RingBuffer::with_capacity(16384)?,
+			write_buff: RingBuffer::with_capacity(16384)?,
 		})
 	}
 }
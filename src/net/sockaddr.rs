@@ -2,7 +2,13 @@
 //! on sockets.
 
 use super::Address;
+use super::SocketDomain;
+use crate::errno::Errno;
+use crate::util::container::vec::Vec;
 use core::ffi::c_short;
+use core::mem::size_of;
+use core::ptr;
+use core::slice;
 
 /// Structure providing connection informations for sockets with IPv4.
 #[repr(C)]
@@ -43,6 +49,65 @@ pub struct SockAddrIn6 {
 	sin6_scope_id: u32,
 }
 
+/// Structure providing connection informations for local (UNIX) sockets.
+///
+/// The address is variable-length: userspace may pass only the beginning of the structure.
+#[repr(C)]
+#[derive(Clone)]
+pub struct SockAddrUn {
+	/// The family of the socket.
+	sun_family: c_short,
+	/// The path of the socket.
+	sun_path: [u8; 108],
+}
+
+/// Structure providing connection informations for netlink sockets.
+#[repr(C)]
+#[derive(Clone)]
+pub struct SockAddrNl {
+	/// The family of the socket.
+	nl_family: c_short,
+	/// Padding.
+	nl_pad: u16,
+	/// The port ID.
+	nl_pid: u32,
+	/// The multicast groups mask.
+	nl_groups: u32,
+}
+
+/// Structure providing connection informations for packet sockets.
+#[repr(C)]
+#[derive(Clone)]
+pub struct SockAddrLl {
+	/// The family of the socket.
+	sll_family: c_short,
+	/// The physical-layer protocol.
+	sll_protocol: u16,
+	/// The index of the network interface.
+	sll_ifindex: i32,
+	/// The ARP hardware type.
+	sll_hatype: u16,
+	/// The packet type.
+	sll_pkttype: u8,
+	/// The length of the physical-layer address.
+	sll_halen: u8,
+	/// The physical-layer address.
+	sll_addr: [u8; 8],
+}
+
+/// Returns the path of the given local socket address, which identifies the socket bound to it.
+///
+/// Bytes after the path's terminating nul byte are not part of the address, except for abstract
+/// addresses, which start with a nul byte.
+pub fn get_unix_path(sockaddr: &[u8]) -> &[u8] {
+	let path = &sockaddr[size_of::<c_short>()..];
+	let len = match path.first() {
+		Some(0) => path.len(),
+		_ => path.iter().position(|b| *b == 0).unwrap_or(path.len()),
+	};
+	&path[..len]
+}
+
 /// A unified structure which contains data passed from userspace.
 #[derive(Debug)]
 pub struct SockAddr {
@@ -78,3 +143,223 @@ impl From<SockAddrIn6> for SockAddr {
 		}
 	}
 }
+
+impl SockAddr {
+	/// Parses the socket address of domain `domain` from the raw structure `buf`, as passed by
+	/// userspace.
+	///
+	/// Local addresses have no port, which is set to zero.
+	///
+	/// If the domain is not a local or Internet domain, or if its family doesn't match the one in
+	/// `buf`, the function returns `EAFNOSUPPORT`. If `buf` is too short, or too long for a local
+	/// address, the function returns `EINVAL`.
+	pub fn from_bytes(domain: SocketDomain, buf: &[u8]) -> Result<Self, Errno> {
+		if !matches!(
+			domain,
+			SocketDomain::AfUnix | SocketDomain::AfInet | SocketDomain::AfInet6
+		) {
+			return Err(errno!(EAFNOSUPPORT));
+		}
+		if !domain.is_sockaddr_len_valid(buf.len()) {
+			return Err(errno!(EINVAL));
+		}
+		let family = c_short::from_ne_bytes([buf[0], buf[1]]);
+		if family as u32 != domain.get_id() {
+			return Err(errno!(EAFNOSUPPORT));
+		}
+
+		// The buffer is large enough, but may not be aligned
+		let addr = match domain {
+			SocketDomain::AfUnix => Self {
+				port: 0,
+				addr: Address::Unix(Vec::from_slice(get_unix_path(buf))?),
+			},
+			SocketDomain::AfInet => {
+				unsafe { ptr::read_unaligned(buf.as_ptr() as *const SockAddrIn) }.into()
+			}
+			SocketDomain::AfInet6 => {
+				unsafe { ptr::read_unaligned(buf.as_ptr() as *const SockAddrIn6) }.into()
+			}
+			_ => unreachable!(),
+		};
+		Ok(addr)
+	}
+
+	/// Writes the raw structure of the socket address into `out`, as expected by userspace.
+	///
+	/// Local addresses are variable-length: only the family and the path are written, followed by
+	/// a terminating nul byte unless the address is abstract.
+	///
+	/// If `out` is too small to hold the structure, the function returns `EINVAL`.
+	///
+	/// The function returns the size of the structure.
+	pub fn to_bytes(&self, out: &mut [u8]) -> Result<usize, Errno> {
+		fn write<T>(val: &T, out: &mut [u8]) -> Result<usize, Errno> {
+			let len = size_of::<T>();
+			let out = out.get_mut(..len).ok_or_else(|| errno!(EINVAL))?;
+			let bytes = unsafe { slice::from_raw_parts(val as *const T as *const u8, len) };
+			out.copy_from_slice(bytes);
+			Ok(len)
+		}
+
+		match &self.addr {
+			Address::IPv4(addr) => write(
+				&SockAddrIn {
+					sin_family: SocketDomain::AfInet.get_id() as _,
+					sin_port: self.port as _,
+					sin_addr: u32::from_be_bytes(*addr),
+					sin_zero: [0; 8],
+				},
+				out,
+			),
+			Address::IPv6(addr) => write(
+				&SockAddrIn6 {
+					sin6_family: SocketDomain::AfInet6.get_id() as _,
+					sin6_port: self.port as _,
+					sin6_flowinfo: 0,
+					sin6_addr: In6Addr {
+						__s6_addr: *addr,
+					},
+					sin6_scope_id: 0,
+				},
+				out,
+			),
+			Address::Unix(path) => {
+				let family_len = size_of::<c_short>();
+				let nul = !matches!(path.first(), Some(0) | None);
+				let len = family_len + path.len() + nul as usize;
+				if len > size_of::<SockAddrUn>() {
+					return Err(errno!(EINVAL));
+				}
+				let out = out.get_mut(..len).ok_or_else(|| errno!(EINVAL))?;
+				let family = SocketDomain::AfUnix.get_id() as c_short;
+				out[..family_len].copy_from_slice(&family.to_ne_bytes());
+				out[family_len..(family_len + path.len())].copy_from_slice(path);
+				if nul {
+					out[len - 1] = 0;
+				}
+				Ok(len)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::errno;
+
+	#[test_case]
+	fn sockaddr_in_bytes() {
+		let addr = SockAddr {
+			port: 8080,
+			addr: Address::IPv4([127, 0, 0, 1]),
+		};
+		let mut buf = [0u8; size_of::<SockAddrIn>() + 4];
+		let len = addr.to_bytes(&mut buf).unwrap();
+		assert_eq!(len, size_of::<SockAddrIn>());
+
+		let parsed = SockAddr::from_bytes(SocketDomain::AfInet, &buf[..len]).unwrap();
+		assert_eq!(parsed.port, 8080);
+		assert_eq!(parsed.addr, Address::IPv4([127, 0, 0, 1]));
+		// Trailing bytes are ignored
+		let parsed = SockAddr::from_bytes(SocketDomain::AfInet, &buf).unwrap();
+		assert_eq!(parsed.addr, Address::IPv4([127, 0, 0, 1]));
+
+		// Truncated
+		assert_eq!(
+			SockAddr::from_bytes(SocketDomain::AfInet, &buf[..len - 1])
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+		assert_eq!(
+			addr.to_bytes(&mut buf[..len - 1]).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+	}
+
+	#[test_case]
+	fn sockaddr_in6_bytes() {
+		let ip = [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+		let addr = SockAddr {
+			port: 443,
+			addr: Address::IPv6(ip),
+		};
+		let mut buf = [0u8; size_of::<SockAddrIn6>()];
+		let len = addr.to_bytes(&mut buf).unwrap();
+
+		let parsed = SockAddr::from_bytes(SocketDomain::AfInet6, &buf[..len]).unwrap();
+		assert_eq!(parsed.port, 443);
+		assert_eq!(parsed.addr, Address::IPv6(ip));
+	}
+
+	#[test_case]
+	fn sockaddr_bytes_family() {
+		let addr = SockAddr {
+			port: 80,
+			addr: Address::IPv4([10, 0, 0, 1]),
+		};
+		let mut buf = [0u8; size_of::<SockAddrIn6>()];
+		addr.to_bytes(&mut buf).unwrap();
+
+		// Family mismatch
+		assert_eq!(
+			SockAddr::from_bytes(SocketDomain::AfInet6, &buf)
+				.unwrap_err()
+				.as_int(),
+			errno::EAFNOSUPPORT
+		);
+		assert_eq!(
+			SockAddr::from_bytes(SocketDomain::AfUnix, &buf)
+				.unwrap_err()
+				.as_int(),
+			errno::EAFNOSUPPORT
+		);
+		// Unsupported domain
+		assert_eq!(
+			SockAddr::from_bytes(SocketDomain::AfNetlink, &buf)
+				.unwrap_err()
+				.as_int(),
+			errno::EAFNOSUPPORT
+		);
+	}
+
+	#[test_case]
+	fn sockaddr_un_bytes() {
+		let addr = SockAddr {
+			port: 0,
+			addr: Address::Unix(Vec::from_slice(b"/tmp/sock").unwrap()),
+		};
+		let mut buf = [0xffu8; size_of::<SockAddrUn>()];
+		let len = addr.to_bytes(&mut buf).unwrap();
+		// The family, the path and the terminating nul byte
+		assert_eq!(len, 2 + 9 + 1);
+		assert_eq!(&buf[2..len], b"/tmp/sock\0");
+
+		let parsed = SockAddr::from_bytes(SocketDomain::AfUnix, &buf[..len]).unwrap();
+		assert_eq!(parsed.port, 0);
+		assert_eq!(parsed.addr, addr.addr);
+		// Bytes after the nul byte are not part of the path
+		let parsed = SockAddr::from_bytes(SocketDomain::AfUnix, &buf).unwrap();
+		assert_eq!(parsed.addr, addr.addr);
+
+		// Abstract addresses have no terminating nul byte
+		let addr = SockAddr {
+			port: 0,
+			addr: Address::Unix(Vec::from_slice(b"\0abstract").unwrap()),
+		};
+		let len = addr.to_bytes(&mut buf).unwrap();
+		assert_eq!(len, 2 + 9);
+		let parsed = SockAddr::from_bytes(SocketDomain::AfUnix, &buf[..len]).unwrap();
+		assert_eq!(parsed.addr, addr.addr);
+
+		// Too long
+		assert_eq!(
+			SockAddr::from_bytes(SocketDomain::AfUnix, &[0; size_of::<SockAddrUn>() + 1])
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+	}
+}
@@ -4,58 +4,185 @@
 //! from. This is an undesirable state which requires to reboot the host
 //! machine.
 
-use crate::{cpu, logger, power};
+use crate::{cpu, logger, power, vga};
+use core::fmt;
+use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::Ordering;
+
+/// Tells whether the kernel is currently panicking.
+static IN_PANIC: AtomicBool = AtomicBool::new(false);
+
+/// The maximum number of frames printed in the callstack of a kernel panic.
+#[cfg(config_debug_debug)]
+const CALLSTACK_DEPTH: usize = 32;
+
+/// Captures the callstack from the current frame and writes it to `out`.
+///
+/// The function is inlined so that the callstack starts at the caller's frame.
+#[cfg(config_debug_debug)]
+#[inline(always)]
+fn print_callstack_now(out: &mut PanicWriter) {
+	use crate::debug;
+	use core::ffi::c_void;
+	use core::ptr::null_mut;
+
+	let ebp = unsafe { crate::register_get!("ebp") as *mut _ };
+	let mut callstack: [*mut c_void; CALLSTACK_DEPTH] = [null_mut::<c_void>(); CALLSTACK_DEPTH];
+	debug::get_callstack(ebp, &mut callstack);
+	debug::print_callstack(out, &callstack);
+}
+
+/// Tells whether the panic handler writes its report to the COM1 serial port directly.
+///
+/// If so, other writers must not mirror their output to the port, since the report would be
+/// printed twice.
+pub fn owns_serial() -> bool {
+	cfg!(config_debug_qemu) && IN_PANIC.load(Ordering::SeqCst)
+}
+
+/// Marks the kernel as panicking using the flag `in_panic`.
+///
+/// If the flag was already set, meaning the panic handler has been re-entered, the function
+/// returns `true`.
+fn enter_panic(in_panic: &AtomicBool) -> bool {
+	in_panic.swap(true, Ordering::SeqCst)
+}
+
+/// Returns the names and values of the general-purpose registers.
+///
+/// The function is inlined so that values are read from the caller's context.
+#[inline(always)]
+fn get_registers() -> [(&'static str, u32); 8] {
+	unsafe {
+		[
+			("eax", crate::register_get!("eax")),
+			("ebx", crate::register_get!("ebx")),
+			("ecx", crate::register_get!("ecx")),
+			("edx", crate::register_get!("edx")),
+			("esi", crate::register_get!("esi")),
+			("edi", crate::register_get!("edi")),
+			("ebp", crate::register_get!("ebp")),
+			("esp", crate::register_get!("esp")),
+		]
+	}
+}
+
+/// Writes `s` on the first line of the screen and, under QEMU, to the COM1 serial port.
+///
+/// No lock is taken, so that this can be used when the panic handler is re-entered while the
+/// first panic may hold the logger's or the TTY's lock.
+fn print_raw(s: &str) {
+	#[cfg(config_debug_qemu)]
+	if let Some(serial) = crate::device::serial::get(crate::device::serial::COM1) {
+		unsafe { serial.get_mut_payload() }.write(s.as_bytes());
+	}
+
+	let color = vga::entry_color(vga::COLOR_WHITE, vga::COLOR_RED);
+	for (x, c) in s.chars().take(vga::WIDTH as usize).enumerate() {
+		vga::putchar_color(c, color, x as _, 0);
+	}
+}
+
+/// Writer printing the panic report through the logger and, under QEMU, to the COM1 serial port
+/// directly, so that it can be captured from the emulator's serial console.
+///
+/// Since the kernel is halting, the port's lock is bypassed so that a panic happening while it
+/// is held cannot deadlock. The TTY does not mirror the report to the port then (see
+/// [`owns_serial`]).
+struct PanicWriter;
+
+impl Write for PanicWriter {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		#[cfg(config_debug_qemu)]
+		if let Some(serial) = crate::device::serial::get(crate::device::serial::COM1) {
+			unsafe { serial.get_mut_payload() }.write(s.as_bytes());
+		}
+		logger::LOGGER.lock().write_str(s)
+	}
+}
+
+/// Writes the report of the panic described by `panic_info` to `out`, with the values of the
+/// registers `regs` at the time of the panic.
+fn print_report<W: Write>(
+	out: &mut W,
+	panic_info: &PanicInfo,
+	regs: &[(&'static str, u32)],
+) -> fmt::Result {
+	writeln!(out, "--- KERNEL PANIC ---\n")?;
+	writeln!(
+		out,
+		"Kernel has been forced to halt due to internal problem, sorry :/"
+	)?;
+	if let Some(msg) = panic_info.message() {
+		write!(out, "Reason: {msg}")?;
+	}
+	if let Some(loc) = panic_info.location() {
+		writeln!(out, " (location: {loc})")?;
+	} else {
+		writeln!(out)?;
+	}
+	writeln!(
+		out,
+		"If you believe this is a bug on the kernel side, please feel free to report it."
+	)?;
+
+	for (name, val) in regs {
+		write!(out, "{name}: {val:#010x} ")?;
+	}
+	writeln!(out)?;
+	let (cr0, cr2, cr3, cr4) = unsafe {
+		(
+			cpu::cr0_get(),
+			cpu::cr2_get(),
+			cpu::cr3_get(),
+			cpu::cr4_get(),
+		)
+	};
+	writeln!(
+		out,
+		"cr0: {cr0:#010x} cr2: {cr2:p} cr3: {cr3:p} cr4: {cr4:#010x}\n"
+	)
+}
 
 /// Called on Rust panic.
 #[panic_handler]
 fn panic(panic_info: &PanicInfo) -> ! {
+	// Captured first, to be as close as possible to the state at the time of the panic
+	let regs = get_registers();
 	crate::cli!();
+
+	// If the panic handler itself faulted, printing anything more risks faulting again
+	if enter_panic(&IN_PANIC) {
+		print_raw("--- DOUBLE PANIC ---");
+		power::halt();
+	}
+
 	logger::LOGGER.lock().silent = false;
+	let mut out = PanicWriter;
 
 	#[cfg(test)]
 	{
 		use crate::selftest;
 
 		if selftest::is_running() {
-			crate::println!("FAILED\n");
-			crate::println!("Error: {panic_info}\n");
+			let _ = writeln!(out, "FAILED\n");
+			let _ = writeln!(out, "Error: {panic_info}\n");
 
 			#[cfg(config_debug_qemu)]
 			selftest::qemu::exit(selftest::qemu::FAILURE);
 			#[cfg(not(config_debug_qemu))]
-			halt();
+			power::halt();
 		}
 	}
 
-	crate::println!("--- KERNEL PANIC ---\n");
-	crate::println!("Kernel has been forced to halt due to internal problem, sorry :/");
-	if let Some(msg) = panic_info.message() {
-		crate::print!("Reason: {msg}");
-	}
-	if let Some(loc) = panic_info.location() {
-		crate::println!(" (location: {loc})");
-	} else {
-		crate::println!();
-	}
-	crate::println!(
-		"If you believe this is a bug on the kernel side, please feel free to report it."
-	);
-
-	let cr2 = unsafe { cpu::cr2_get() };
-	crate::println!("cr2: {cr2:p}\n");
+	let _ = print_report(&mut out, panic_info, &regs);
 
 	#[cfg(config_debug_debug)]
 	{
-		use crate::debug;
-		use core::ffi::c_void;
-		use core::ptr::null_mut;
-
-		crate::println!("--- Callstack ---");
-		let ebp = unsafe { crate::register_get!("ebp") as *mut _ };
-		let mut callstack: [*mut c_void; 8] = [null_mut::<c_void>(); 8];
-		debug::get_callstack(ebp, &mut callstack);
-		debug::print_callstack(&callstack);
+		let _ = writeln!(out, "--- Callstack ---");
+		print_callstack_now(&mut out);
 	}
 
 	power::halt();
@@ -66,3 +193,17 @@ fn panic(panic_info: &PanicInfo) -> ! {
 /// only when panicking.
 #[lang = "eh_personality"]
 fn eh_personality() {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn panic_reentry() {
+		let in_panic = AtomicBool::new(false);
+		assert!(!enter_panic(&in_panic));
+		// Re-entering the handler
+		assert!(enter_panic(&in_panic));
+		assert!(enter_panic(&in_panic));
+	}
+}
@@ -18,6 +18,7 @@ use crate::process::EResult;
 use crate::util::io::IO;
 use crate::util::lock::*;
 use crate::util::ptr::arc::Arc;
+use core::cmp::min;
 use core::ffi::c_void;
 use core::fmt;
 use core::num::NonZeroUsize;
@@ -122,6 +123,11 @@ impl MemMapping {
 		self.flags
 	}
 
+	/// Returns the mapping's residence.
+	pub fn get_residence(&self) -> &MapResidence {
+		&self.residence
+	}
+
 	/// Returns a reference to the virtual memory context handler associated
 	/// with the mapping.
 	pub fn get_vmem(&self) -> &Arc<dyn VMem> {
@@ -294,6 +300,44 @@ impl MemMapping {
 		Ok(())
 	}
 
+	/// Extends the mapping by `pages` pages toward lower addresses.
+	///
+	/// The new pages are mapped the same way as with [`Self::map_default`].
+	///
+	/// The residence of the mapping must be normal. The caller must ensure the memory below the
+	/// mapping is free.
+	pub fn grow_down(&mut self, pages: NonZeroUsize) -> AllocResult<()> {
+		debug_assert!(self.residence.is_normal());
+		let old_begin = self.begin;
+		let old_size = self.size;
+		self.begin = unsafe { self.begin.sub(pages.get() * memory::PAGE_SIZE) };
+		self.size = self.size.saturating_add(pages.get());
+
+		let use_default = self.flags & super::MAPPING_FLAG_NOLAZY == 0;
+		for i in 0..pages.get() {
+			let res = if use_default {
+				let virt_ptr = unsafe { self.begin.add(i * memory::PAGE_SIZE) };
+				let flags = self.get_vmem_flags(false, i);
+				self.vmem.map(get_default_page(), virt_ptr, flags)
+			} else {
+				self.map(i)
+			};
+
+			if let Err(e) = res {
+				// Undo
+				for j in 0..i {
+					self.free_phys_page(j);
+				}
+				oom::wrap(|| self.vmem.unmap_range(self.begin, i));
+				self.begin = old_begin;
+				self.size = old_size;
+				return Err(e);
+			}
+		}
+
+		Ok(())
+	}
+
 	/// Frees the physical page at offset `offset` of the mapping.
 	///
 	/// If the page is shared, it is not freed but the reference counter is decreased.
@@ -397,6 +441,122 @@ impl MemMapping {
 		(prev, gap, next)
 	}
 
+	/// Splits the mapping in up to three mappings, isolating a range of pages.
+	///
+	/// Arguments:
+	/// - `begin` is the index of the first page of the range.
+	/// - `size` is the number of pages in the range.
+	///
+	/// The function returns the mapping before the range, the mapping covering the range and the
+	/// mapping after the range.
+	///
+	/// Contrary to `partial_unmap`, pages remain mapped.
+	///
+	/// The range must be in bounds.
+	pub fn split(self, begin: usize, size: NonZeroUsize) -> (Option<Self>, Self, Option<Self>) {
+		debug_assert!(begin + size.get() <= self.size.get());
+
+		// The mapping located before the range
+		let prev = NonZeroUsize::new(begin).map(|begin| Self {
+			begin: self.begin,
+			size: begin,
+			flags: self.flags,
+
+			residence: self.residence.clone(),
+
+			vmem: self.vmem.clone(),
+		});
+
+		// The mapping covering the range
+		let curr = {
+			let mut residence = self.residence.clone();
+			residence.offset_add(begin);
+
+			Self {
+				begin: unsafe { self.begin.add(begin * memory::PAGE_SIZE) },
+				size,
+				flags: self.flags,
+
+				residence,
+
+				vmem: self.vmem.clone(),
+			}
+		};
+
+		// The mapping located after the range
+		let end = begin + size.get();
+		let next = NonZeroUsize::new(self.size.get() - end).map(|size| {
+			let mut residence = self.residence.clone();
+			residence.offset_add(end);
+
+			Self {
+				begin: unsafe { self.begin.add(end * memory::PAGE_SIZE) },
+				size,
+				flags: self.flags,
+
+				residence,
+
+				vmem: self.vmem.clone(),
+			}
+		});
+
+		(prev, curr, next)
+	}
+
+	/// Drops the physical pages in the range of `size` pages beginning at page offset `begin`.
+	///
+	/// The pages are replaced with the default page, so that the next access reads zeros.
+	///
+	/// Only private anonymous pages are dropped, since the content of other pages cannot be
+	/// recovered.
+	pub fn discard(&mut self, begin: usize, size: usize) -> AllocResult<()> {
+		let private = self.flags & super::MAPPING_FLAG_SHARED == 0;
+		if !private || !self.residence.is_normal() {
+			return Ok(());
+		}
+
+		let end = min(begin + size, self.size.get());
+		for i in begin..end {
+			let Some(phys_ptr) = self.get_physical_page(i) else {
+				continue;
+			};
+			let virt_ptr = (self.begin as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let flags = self.get_vmem_flags(false, i);
+			self.vmem.map(get_default_page(), virt_ptr, flags)?;
+			self.residence.free_page(i, phys_ptr);
+		}
+
+		Ok(())
+	}
+
+	/// Allocates the physical pages in the range of `size` pages beginning at page offset
+	/// `begin`, so that accessing them does not fault.
+	///
+	/// Pages that are already allocated are left untouched. Mappings that are not allocated
+	/// lazily are skipped.
+	pub fn populate(&mut self, begin: usize, size: usize) -> AllocResult<()> {
+		if !self.residence.is_normal() {
+			return Ok(());
+		}
+
+		let end = min(begin + size, self.size.get());
+		for i in begin..end {
+			if self.get_physical_page(i).is_none() {
+				self.map(i)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Sets the mapping's flags and updates the virtual memory context accordingly.
+	pub fn set_flags(&mut self, flags: u8) {
+		self.flags = flags;
+		for i in 0..self.size.get() {
+			self.update_vmem(i);
+		}
+	}
+
 	/// Updates the virtual memory context according to the mapping for the page
 	/// at offset `offset`.
 	pub fn update_vmem(&mut self, offset: usize) {
@@ -470,13 +630,20 @@ impl MemMapping {
 
 	/// Synchronizes the data on the memory mapping back to the filesystem.
 	///
+	/// Arguments:
+	/// - `begin` is the index of the first page to synchronize.
+	/// - `size` is the number of pages to synchronize.
+	///
+	/// The range is truncated to the end of the mapping. Data beyond the end of the file is not
+	/// written back.
+	///
 	/// The function does nothing if:
 	/// - The mapping is not shared
 	/// - The mapping is not associated with a file
 	/// - The associated file has been removed or cannot be accessed
 	///
 	/// If the mapping is lock, the function returns [`crate::errno::EBUSY`].
-	pub fn fs_sync(&self) -> EResult<()> {
+	pub fn fs_sync(&self, begin: usize, size: usize) -> EResult<()> {
 		if self.flags & super::MAPPING_FLAG_SHARED == 0 {
 			return Ok(());
 		}
@@ -497,16 +664,27 @@ impl MemMapping {
 			vmem::switch(&*self.vmem, || {
 				let mut file = file_mutex.lock();
 
-				// TODO Make use of dirty flag if present on the current architecure to update
-				// only pages that have been modified
-				let slice = slice::from_raw_parts(
-					self.begin as *mut u8,
+				// The offsets of the range in the mapping, in bytes
+				let begin = min(begin, self.size.get()) * memory::PAGE_SIZE;
+				let end = min(
+					begin + size * memory::PAGE_SIZE,
 					self.size.get() * memory::PAGE_SIZE,
 				);
+				// Do not extend the file
+				let file_end = file.get_size().saturating_sub(*off);
+				let end = min(end as u64, file_end) as usize;
+				if begin >= end {
+					return Ok(());
+				}
+
+				// TODO Make use of dirty flag if present on the current architecure to update
+				// only pages that have been modified
+				let slice =
+					slice::from_raw_parts((self.begin as *const u8).add(begin), end - begin);
 
 				let mut i = 0;
 				while i < slice.len() {
-					let l = file.write(*off, &slice[i..])?;
+					let l = file.write(*off + (begin + i) as u64, &slice[i..])?;
 					i += l as usize;
 				}
 
@@ -12,6 +12,7 @@ pub mod ptr;
 use crate::errno::AllocError;
 use crate::errno::Errno;
 use crate::file::perm::AccessProfile;
+use crate::file::vfs;
 use crate::file::FileLocation;
 use crate::idt;
 use crate::memory;
@@ -26,6 +27,7 @@ use crate::process::AllocResult;
 use crate::util;
 use crate::util::container::map::Map;
 use crate::util::container::vec::Vec;
+use crate::util::io::IO;
 use crate::util::lock::Mutex;
 use crate::util::math;
 use crate::util::ptr::arc::Arc;
@@ -38,6 +40,7 @@ use core::mem::size_of;
 use core::num::NonZeroUsize;
 use core::ptr::null_mut;
 use core::ptr::NonNull;
+use core::slice;
 use gap::MemGap;
 use mapping::MemMapping;
 
@@ -56,12 +59,75 @@ pub const MAPPING_FLAG_NOLAZY: u8 = 0b01000;
 /// If the mapping is associated with a file, modifications made to the mapping are update to the
 /// file.
 pub const MAPPING_FLAG_SHARED: u8 = 0b10000;
+/// Flag telling that a memory mapping grows toward lower addresses when the memory right below
+/// it is accessed, such as stacks.
+///
+/// Only mappings with a normal residence can grow.
+pub const MAPPING_FLAG_GROWSDOWN: u8 = 0b100000;
+
+/// The maximum size of a mapping growing downward, in pages.
+const GROWSDOWN_MAX_PAGES: usize = 2048;
+/// The size of the range below a mapping growing downward in which no other mapping can be placed,
+/// in pages.
+const GROWSDOWN_GUARD_PAGES: usize = 256;
 
 /// The physical pages reference counter.
 pub static PHYSICAL_REF_COUNTER: Mutex<PhysRefCounter> = Mutex::new(PhysRefCounter::new());
 
 // TODO when reaching the last reference to the open file, close it on unmap
 
+/// Anonymous memory shared between the mappings created from the same `mmap` call, including
+/// the ones inherited across `fork`.
+///
+/// Physical pages are allocated the first time they are requested, then kept until the object is
+/// dropped, so that every mapping sees the same memory.
+pub struct AnonSharedObject {
+	/// The physical pages of the object, by page offset. `None` if not allocated yet.
+	pages: Mutex<Vec<Option<NonNull<c_void>>>>,
+}
+
+impl AnonSharedObject {
+	/// Creates a new object of `size` pages.
+	pub fn new(size: NonZeroUsize) -> AllocResult<Self> {
+		Ok(Self {
+			pages: Mutex::new(crate::vec![None; size.get()]?),
+		})
+	}
+
+	/// Returns the physical page at offset `off`, allocating and zeroing it if necessary.
+	///
+	/// The caller receives a reference to the page, which must be released with
+	/// [`MapResidence::free`].
+	fn get_page(&self, off: usize) -> AllocResult<NonNull<c_void>> {
+		let mut pages = self.pages.lock();
+		let ptr = match pages[off] {
+			Some(ptr) => ptr,
+			None => {
+				// The reference held by the object
+				let mut ptr = MapResidence::alloc()?;
+				let virt_ptr = memory::kern_to_virt(unsafe { ptr.as_mut() }) as *mut u8;
+				unsafe {
+					virt_ptr.write_bytes(0, memory::PAGE_SIZE);
+				}
+				pages[off] = Some(ptr);
+				ptr
+			}
+		};
+
+		// The reference held by the caller
+		PHYSICAL_REF_COUNTER.lock().increment(ptr.as_ptr())?;
+		Ok(ptr)
+	}
+}
+
+impl Drop for AnonSharedObject {
+	fn drop(&mut self) {
+		for ptr in self.pages.lock().iter().flatten() {
+			MapResidence::free(ptr.as_ptr());
+		}
+	}
+}
+
 // TODO Disallow clone and use a special function + Drop to increment/decrement reference counters
 /// Enumeration of map residences.
 ///
@@ -78,6 +144,15 @@ pub enum MapResidence {
 		pages: Arc<Vec<NonNull<[u8; memory::PAGE_SIZE]>>>,
 	},
 
+	/// The mapping resides in anonymous memory that is shared with the mappings it is inherited
+	/// into.
+	AnonShared {
+		/// The object holding the physical pages.
+		obj: Arc<AnonSharedObject>,
+		/// The page offset of the mapping in the object.
+		page_off: usize,
+	},
+
 	/// The mapping resides in a file.
 	File {
 		/// The location of the file.
@@ -110,7 +185,10 @@ impl MapResidence {
 				off, ..
 			} => *off += pages as u64 * memory::PAGE_SIZE as u64,
 
-			Self::Swap {
+			Self::AnonShared {
+				page_off, ..
+			}
+			| Self::Swap {
 				page_off, ..
 			} => *page_off += pages,
 
@@ -159,12 +237,33 @@ impl MapResidence {
 				}
 			}
 
+			MapResidence::AnonShared {
+				obj,
+				page_off,
+			} => obj.get_page(page_off + off),
+
 			MapResidence::File {
-				location: _,
-				off: _,
+				location,
+				off: file_off,
 			} => {
-				// TODO get physical page for this offset
-				todo!();
+				let mut ptr = Self::alloc()?;
+				let virt_ptr = memory::kern_to_virt(unsafe { ptr.as_mut() }) as *mut u8;
+				let page = unsafe { slice::from_raw_parts_mut(virt_ptr, memory::PAGE_SIZE) };
+				page.fill(0);
+				// TODO share pages between mappings of the same file (page cache)
+				// Past the end of the file, or if it cannot be read, the page reads as zeros
+				if let Ok(file_mutex) = vfs::get_file_by_location(location) {
+					let mut file = file_mutex.lock();
+					let page_off = file_off + (off * memory::PAGE_SIZE) as u64;
+					let mut i = 0;
+					while i < page.len() {
+						match file.read(page_off + i as u64, &mut page[i..]) {
+							Ok((len, _)) if len > 0 => i += len as usize,
+							_ => break,
+						}
+					}
+				}
+				Ok(ptr)
 			}
 
 			MapResidence::Swap {
@@ -189,13 +288,13 @@ impl MapResidence {
 				}
 			}
 
+			MapResidence::AnonShared {
+				..
+			} => Self::free(ptr),
+
 			MapResidence::File {
-				location: _,
-				off: _,
-			} => {
-				// TODO
-				todo!();
-			}
+				..
+			} => Self::free(ptr),
 
 			MapResidence::Swap {
 				..
@@ -283,19 +382,44 @@ impl MemSpace {
 	/// Arguments:
 	/// - `gaps` is the binary tree storing gaps, sorted by pointer to their respective beginnings.
 	/// - `gaps_size` is the binary tree storing pointers to gaps, sorted by gap sizes.
+	/// - `mappings` is the binary tree storing mappings.
 	/// - `size` is the minimum size of the gap.
 	///
+	/// Gaps in which a mapping placed at the beginning would lie in the guard range of a mapping
+	/// growing downward are skipped.
+	///
 	/// If no gap large enough is available, the function returns `None`.
 	fn gap_get<'a>(
 		gaps: &'a Map<*mut c_void, MemGap>,
 		gaps_size: &Map<(NonZeroUsize, *mut c_void), ()>,
+		mappings: &Map<*mut c_void, MemMapping>,
 		size: NonZeroUsize,
 	) -> Option<&'a MemGap> {
-		let ((_, ptr), _) = gaps_size.range((size, null_mut::<c_void>())..).next()?;
-		let gap = gaps.get(*ptr).unwrap();
-		debug_assert!(gap.get_size() >= size);
+		gaps_size
+			.range((size, null_mut::<c_void>())..)
+			.map(|((_, ptr), _)| gaps.get(*ptr).unwrap())
+			.inspect(|gap| debug_assert!(gap.get_size() >= size))
+			.find(|gap| !Self::in_guard_range(mappings, gap.get_begin(), size))
+	}
 
-		Some(gap)
+	/// Tells whether the range of `size` pages beginning at `ptr` lies, even partially, in the
+	/// [`GROWSDOWN_GUARD_PAGES`] pages right below a mapping growing downward.
+	///
+	/// Arguments:
+	/// - `mappings` is the binary tree storing mappings.
+	/// - `ptr` is the beginning of the range.
+	/// - `size` is the size of the range in pages.
+	fn in_guard_range(
+		mappings: &Map<*mut c_void, MemMapping>,
+		ptr: *const c_void,
+		size: NonZeroUsize,
+	) -> bool {
+		let end = ptr as usize + size.get() * memory::PAGE_SIZE;
+		let Some((begin, mapping)) = mappings.range((end as *mut c_void)..).next() else {
+			return false;
+		};
+		let guard_end = end.saturating_add(GROWSDOWN_GUARD_PAGES * memory::PAGE_SIZE);
+		mapping.get_flags() & MAPPING_FLAG_GROWSDOWN != 0 && (*begin as usize) < guard_end
 	}
 
 	/// Returns a reference to the gap containing the pointer `ptr`.
@@ -383,7 +507,8 @@ impl MemSpace {
 	///
 	/// On success, the function returns a pointer to the newly mapped virtual memory.
 	///
-	/// If the given pointer is not page-aligned, the function returns an error.
+	/// If the given pointer is not page-aligned, or if the mapping would lie in the guard range
+	/// below a mapping growing downward, the function returns an error.
 	pub fn map(
 		&mut self,
 		map_constraint: MapConstraint,
@@ -405,6 +530,10 @@ impl MemSpace {
 		// Mapping informations matching mapping constraints
 		let (gap, addr) = match map_constraint {
 			MapConstraint::Fixed(addr) => {
+				// Keep room for mappings growing downward
+				if Self::in_guard_range(&self.mappings, addr, size) {
+					return Err(AllocError);
+				}
 				self.unmap(addr, size, false)?;
 				let gap = Self::gap_by_ptr(&self.gaps, addr);
 
@@ -415,26 +544,26 @@ impl MemSpace {
 			// match the address returned by the `mmap` syscall)
 			MapConstraint::Hint(addr) => {
 				// Getting the gap for the pointer
-				let mut gap = Self::gap_by_ptr(&self.gaps, addr).ok_or_else(|| AllocError)?;
+				let gap = Self::gap_by_ptr(&self.gaps, addr).ok_or_else(|| AllocError)?;
 
 				// The offset in the gap
 				let off = (addr as usize - gap.get_begin() as usize) / memory::PAGE_SIZE;
 				// The end of the gap
 				let end = unsafe { size.unchecked_add(off) };
 
-				if end > gap.get_size() {
+				if end > gap.get_size() || Self::in_guard_range(&self.mappings, addr, size) {
 					// Hint cannot be satisfied. Get a gap large enough
-					gap = Self::gap_get(&self.gaps, &self.gaps_size, size)
+					let gap = Self::gap_get(&self.gaps, &self.gaps_size, &self.mappings, size)
 						.ok_or_else(|| AllocError)?;
+					(Some(gap), gap.get_begin())
+				} else {
+					(Some(gap), addr)
 				}
-
-				let addr = unsafe { gap.get_begin().add(off * memory::PAGE_SIZE) };
-				(Some(gap), addr)
 			}
 
 			MapConstraint::None => {
-				let gap =
-					Self::gap_get(&self.gaps, &self.gaps_size, size).ok_or_else(|| AllocError)?;
+				let gap = Self::gap_get(&self.gaps, &self.gaps_size, &self.mappings, size)
+					.ok_or_else(|| AllocError)?;
 				(Some(gap), gap.get_begin())
 			}
 		};
@@ -486,7 +615,8 @@ impl MemSpace {
 	pub fn unmap_stack(&mut self, ptr: *const c_void, size: NonZeroUsize) -> AllocResult<()> {
 		// Safe because the new pointer stays in the range of the allocated mapping
 		let ptr = unsafe { ptr.sub(size.get() * memory::PAGE_SIZE) };
-		self.unmap(ptr, size, false)
+		self.unmap(ptr, size, false)?;
+		Ok(())
 	}
 
 	/// Returns a reference to the memory mapping containing the given virtual
@@ -533,6 +663,61 @@ impl MemSpace {
 		})
 	}
 
+	/// Tells whether a mapping beginning at `ptr` can grow downward, that is whether the page
+	/// right below it is free.
+	pub fn can_grow_down(&self, ptr: *const c_void) -> bool {
+		let Some(below) = (ptr as usize).checked_sub(memory::PAGE_SIZE) else {
+			return false;
+		};
+		Self::gap_by_ptr(&self.gaps, below as _).is_some()
+	}
+
+	/// Extends the mapping growing downward right above `ptr` so that it contains `ptr`.
+	///
+	/// The mapping can only grow over free memory, up to [`GROWSDOWN_MAX_PAGES`] pages.
+	///
+	/// The function returns `true` if a mapping has been extended.
+	fn grow_down(&mut self, ptr: *const c_void) -> AllocResult<bool> {
+		let new_begin = util::down_align(ptr, memory::PAGE_SIZE) as *mut c_void;
+		let Some((begin, mapping)) = self.mappings.range(new_begin..).next() else {
+			return Ok(false);
+		};
+		let begin = *begin;
+		if mapping.get_flags() & MAPPING_FLAG_GROWSDOWN == 0 {
+			return Ok(false);
+		}
+		let Some(pages) =
+			NonZeroUsize::new((begin as usize - new_begin as usize) / memory::PAGE_SIZE)
+		else {
+			return Ok(false);
+		};
+		if mapping.get_size().get() + pages.get() > GROWSDOWN_MAX_PAGES {
+			return Ok(false);
+		}
+		// Since the mapping is the next one, the gap extends up to it
+		let Some(gap) = Self::gap_by_ptr(&self.gaps, new_begin).cloned() else {
+			return Ok(false);
+		};
+
+		let mut mapping = self.mappings.remove(&begin).unwrap();
+		if let Err(e) = mapping.grow_down(pages) {
+			oom::wrap(|| self.mappings.insert(begin, mapping.clone()).map(|_| ()));
+			return Err(e);
+		}
+		oom::wrap(|| self.mappings.insert(new_begin, mapping.clone()).map(|_| ()));
+
+		// Shrink the gap
+		self.gap_remove(gap.get_begin());
+		let off = (new_begin as usize - gap.get_begin() as usize) / memory::PAGE_SIZE;
+		let (left_gap, _) = gap.consume(off, pages.get());
+		if let Some(new_gap) = left_gap {
+			oom::wrap(|| self.gap_insert(new_gap.clone()));
+		}
+
+		self.vmem_usage += pages.get();
+		Ok(true)
+	}
+
 	/// Returns a mutable reference to the memory mapping containing the given
 	/// virtual address `ptr`.
 	///
@@ -541,6 +726,14 @@ impl MemSpace {
 		Self::get_mapping_mut_for_(&mut self.mappings, ptr)
 	}
 
+	/// Tells whether at least one page in the range of `size` pages beginning at `ptr` is mapped.
+	pub fn is_range_mapped(&self, ptr: *const c_void, size: NonZeroUsize) -> bool {
+		(0..size.get()).any(|i| {
+			let page_ptr = (ptr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			Self::get_mapping_for_(&self.mappings, page_ptr).is_some()
+		})
+	}
+
 	// TODO Optimize (currently O(n log n))
 	/// Unmaps the given mapping of memory.
 	///
@@ -555,12 +748,22 @@ impl MemSpace {
 	/// After this function returns, the access to the mapping of memory shall
 	/// be revoked and further attempts to access it shall result in a page
 	/// fault.
-	pub fn unmap(&mut self, ptr: *const c_void, size: NonZeroUsize, brk: bool) -> AllocResult<()> {
+	///
+	/// The function returns the number of pages that were mapped in the chunk. Pages that were
+	/// not mapped are ignored, so that the number is lower than `size` if the chunk has gaps, and
+	/// zero if nothing was mapped.
+	pub fn unmap(
+		&mut self,
+		ptr: *const c_void,
+		size: NonZeroUsize,
+		brk: bool,
+	) -> AllocResult<usize> {
 		if !ptr.is_aligned_to(memory::PAGE_SIZE) {
 			return Err(AllocError);
 		}
 
 		// Removing every mappings in the chunk to unmap
+		let mut freed = 0;
 		let mut i = 0;
 		while i < size.get() {
 			// The pointer of the page
@@ -634,10 +837,11 @@ impl MemSpace {
 				});
 			}
 
+			freed += pages;
 			i += pages;
 		}
 
-		Ok(())
+		Ok(freed)
 	}
 
 	// TODO Optimize (use MMU)
@@ -665,7 +869,10 @@ impl MemSpace {
 					return false;
 				}
 
-				i += mapping.get_size().get() * memory::PAGE_SIZE;
+				// Skip to the end of the mapping
+				let end =
+					mapping.get_begin() as usize + mapping.get_size().get() * memory::PAGE_SIZE;
+				i = end - ptr as usize;
 			} else {
 				return false;
 			}
@@ -809,21 +1016,185 @@ impl MemSpace {
 	/// - `prot` is a set of mapping flags
 	/// - `access_profile` is the access profile to check permissions
 	///
+	/// Only the `MAPPING_FLAG_WRITE` and `MAPPING_FLAG_EXEC` flags are modified. Mappings that
+	/// are only partially covered by the range are split.
+	///
+	/// If `addr` is not page-aligned or `len` is zero, the function returns `EINVAL`.
+	///
+	/// If a page in the range is not mapped, the function returns `ENOMEM` and no mapping is
+	/// modified.
+	///
 	/// If a mapping to be modified is associated with a file, and the file doesn't have the
 	/// matching permissions, the function returns an error.
 	pub fn set_prot(
 		&mut self,
-		_addr: *mut c_void,
-		_len: usize,
-		_prot: u8,
-		_access_profile: &AccessProfile,
+		addr: *mut c_void,
+		len: usize,
+		prot: u8,
+		access_profile: &AccessProfile,
 	) -> Result<(), Errno> {
-		// TODO Iterate on mappings in the range:
-		//		If the mapping is shared and associated to a file, check file permissions match
-		// `prot` (only write)
-		//		Split the mapping if needed
-		//		Set permissions
-		//		Update vmem
+		if !addr.is_aligned_to(memory::PAGE_SIZE) || len == 0 {
+			return Err(errno!(EINVAL));
+		}
+		let pages = math::ceil_div(len, memory::PAGE_SIZE);
+		let prot = prot & (MAPPING_FLAG_WRITE | MAPPING_FLAG_EXEC);
+
+		// Check the whole range can be modified before modifying anything
+		let mut i = 0;
+		while i < pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping =
+				Self::get_mapping_for_(&self.mappings, page_ptr).ok_or_else(|| errno!(ENOMEM))?;
+
+			// Writing to a shared file mapping writes to the file
+			let shared = mapping.get_flags() & MAPPING_FLAG_SHARED != 0;
+			if shared && prot & MAPPING_FLAG_WRITE != 0 {
+				if let MapResidence::File {
+					location, ..
+				} = mapping.get_residence()
+				{
+					let file_mutex = vfs::get_file_by_location(location)?;
+					let file = file_mutex.lock();
+					if !access_profile.can_write_file(&file) {
+						return Err(errno!(EACCES));
+					}
+				}
+			}
+
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			i += mapping.get_size().get() - begin;
+		}
+
+		let mut i = 0;
+		while i < pages {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping_ptr = Self::get_mapping_for_(&self.mappings, page_ptr)
+				.unwrap()
+				.get_begin();
+			let mapping = self.mappings.remove(&mapping_ptr).unwrap();
+
+			// The offset in the mapping of the beginning of pages to modify
+			let begin = (page_ptr as usize - mapping_ptr as usize) / memory::PAGE_SIZE;
+			// The number of pages to modify in the mapping
+			let size = min(pages - i, mapping.get_size().get() - begin);
+			let flags = (mapping.get_flags() & !(MAPPING_FLAG_WRITE | MAPPING_FLAG_EXEC)) | prot;
+
+			let (prev, mut curr, next) = mapping.split(begin, NonZeroUsize::new(size).unwrap());
+			curr.set_flags(flags);
+
+			for m in [prev, Some(curr), next].into_iter().flatten() {
+				oom::wrap(|| {
+					let map = m.clone();
+					self.mappings.insert(map.get_begin(), map)?;
+
+					Ok(())
+				});
+			}
+
+			i += size;
+		}
+
+		Ok(())
+	}
+
+	/// Checks that every page in the range of `size` pages beginning at `addr` is mapped.
+	///
+	/// If not, the function returns `ENOMEM`.
+	pub fn check_mapped(&self, addr: *const c_void, size: NonZeroUsize) -> Result<(), Errno> {
+		let mut i = 0;
+		while i < size.get() {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping =
+				Self::get_mapping_for_(&self.mappings, page_ptr).ok_or_else(|| errno!(ENOMEM))?;
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			i += mapping.get_size().get() - begin;
+		}
+
+		Ok(())
+	}
+
+	/// Synchronizes the mappings in the given range of memory back to their files.
+	///
+	/// Arguments:
+	/// - `addr` is the address to the beginning of the range. It must be page-aligned.
+	/// - `size` is the size of the range in pages.
+	///
+	/// If a page in the range is not mapped, the function returns `ENOMEM` before synchronizing
+	/// anything.
+	pub fn sync(&self, addr: *const c_void, size: NonZeroUsize) -> Result<(), Errno> {
+		self.check_mapped(addr, size)?;
+
+		let mut i = 0;
+		while i < size.get() {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping = Self::get_mapping_for_(&self.mappings, page_ptr).unwrap();
+
+			// The offset in the mapping of the beginning of pages to synchronize
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			// The number of pages to synchronize in the mapping
+			let pages = min(size.get() - i, mapping.get_size().get() - begin);
+			mapping.fs_sync(begin, pages)?;
+
+			i += pages;
+		}
+
+		Ok(())
+	}
+
+	/// Drops the physical pages in the given range of memory, so that they read as zeros on the
+	/// next access. See [`MemMapping::discard`].
+	///
+	/// Arguments:
+	/// - `addr` is the address to the beginning of the range. It must be page-aligned.
+	/// - `size` is the size of the range in pages.
+	///
+	/// If a page in the range is not mapped, the function returns `ENOMEM` before dropping
+	/// anything.
+	pub fn discard(&mut self, addr: *const c_void, size: NonZeroUsize) -> Result<(), Errno> {
+		self.check_mapped(addr, size)?;
+
+		let mut i = 0;
+		while i < size.get() {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping = Self::get_mapping_mut_for_(&mut self.mappings, page_ptr).unwrap();
+
+			// The offset in the mapping of the beginning of pages to drop
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			// The number of pages to drop in the mapping
+			let pages = min(size.get() - i, mapping.get_size().get() - begin);
+			mapping.discard(begin, pages)?;
+
+			i += pages;
+		}
+
+		Ok(())
+	}
+
+	/// Allocates the physical pages in the given range of memory in advance. See
+	/// [`MemMapping::populate`].
+	///
+	/// Arguments:
+	/// - `addr` is the address to the beginning of the range. It must be page-aligned.
+	/// - `size` is the size of the range in pages.
+	///
+	/// If a page in the range is not mapped, the function returns `ENOMEM` before allocating
+	/// anything.
+	pub fn populate(&mut self, addr: *const c_void, size: NonZeroUsize) -> Result<(), Errno> {
+		self.check_mapped(addr, size)?;
+
+		let mut i = 0;
+		while i < size.get() {
+			let page_ptr = (addr as usize + i * memory::PAGE_SIZE) as *const c_void;
+			let mapping = Self::get_mapping_mut_for_(&mut self.mappings, page_ptr).unwrap();
+
+			// The offset in the mapping of the beginning of pages to allocate
+			let begin = (page_ptr as usize - mapping.get_begin() as usize) / memory::PAGE_SIZE;
+			// The number of pages to allocate in the mapping
+			let pages = min(size.get() - i, mapping.get_size().get() - begin);
+			mapping.populate(begin, pages)?;
+
+			i += pages;
+		}
 
 		Ok(())
 	}
@@ -905,7 +1276,8 @@ impl MemSpace {
 	/// If the process should continue, the function returns `true`, else `false`.
 	pub fn handle_page_fault(&mut self, virt_addr: *const c_void, code: u32) -> bool {
 		if code & vmem::x86::PAGE_FAULT_PRESENT == 0 {
-			return false;
+			// The access may be right below a mapping growing downward
+			return oom::wrap(|| self.grow_down(virt_addr));
 		}
 
 		let Some(mapping) = Self::get_mapping_mut_for_(&mut self.mappings, virt_addr) else {
@@ -961,3 +1333,313 @@ impl Drop for MemSpace {
 		}
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::errno;
+	use core::slice;
+
+	#[test_case]
+	fn unmap_partial() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(4).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let ptr = mem_space
+			.map(MapConstraint::None, size, flags, MapResidence::Normal)
+			.unwrap();
+
+		// Unmap the two middle pages
+		let mid = unsafe { ptr.add(memory::PAGE_SIZE) };
+		let freed = mem_space
+			.unmap(mid, NonZeroUsize::new(2).unwrap(), false)
+			.unwrap();
+		assert_eq!(freed, 2);
+		assert_eq!(mem_space.get_vmem_usage(), 2);
+
+		// The first and last pages are still mapped, with their original flags
+		for begin in [0, 3] {
+			let page_ptr = unsafe { ptr.add(begin * memory::PAGE_SIZE) };
+			let mapping = mem_space.get_mapping_mut_for(page_ptr).unwrap();
+			assert_eq!(mapping.get_begin(), page_ptr);
+			assert_eq!(mapping.get_size().get(), 1);
+			assert_eq!(mapping.get_flags(), flags);
+			assert!(mem_space.can_access(page_ptr as _, memory::PAGE_SIZE, true, true));
+		}
+		// The middle pages fault
+		for begin in [1, 2] {
+			let page_ptr = unsafe { ptr.add(begin * memory::PAGE_SIZE) };
+			assert!(mem_space.get_mapping_mut_for(page_ptr).is_none());
+			assert!(mem_space.get_vmem().translate(page_ptr).is_none());
+		}
+		assert!(!mem_space.can_access(ptr as _, 4 * memory::PAGE_SIZE, true, false));
+	}
+
+	#[test_case]
+	fn unmap_freed_pages() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let ptr = mem_space
+			.map(
+				MapConstraint::None,
+				NonZeroUsize::new(6).unwrap(),
+				flags,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		// Make holes at pages 1 and 4
+		for page in [1, 4] {
+			let page_ptr = unsafe { ptr.add(page * memory::PAGE_SIZE) };
+			let freed = mem_space
+				.unmap(page_ptr, NonZeroUsize::new(1).unwrap(), false)
+				.unwrap();
+			assert_eq!(freed, 1);
+		}
+		assert_eq!(mem_space.get_vmem_usage(), 4);
+
+		// Only the pages that were present are counted
+		let freed = mem_space
+			.unmap(ptr, NonZeroUsize::new(6).unwrap(), false)
+			.unwrap();
+		assert_eq!(freed, 4);
+		assert_eq!(mem_space.get_vmem_usage(), 0);
+		// Nothing is left to unmap, which still succeeds
+		let freed = mem_space
+			.unmap(ptr, NonZeroUsize::new(6).unwrap(), false)
+			.unwrap();
+		assert_eq!(freed, 0);
+		assert_eq!(mem_space.get_vmem_usage(), 0);
+	}
+
+	#[test_case]
+	fn set_prot_invalid() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let ap = AccessProfile::KERNEL;
+		let size = NonZeroUsize::new(4).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let ptr = mem_space
+			.map(MapConstraint::None, size, flags, MapResidence::Normal)
+			.unwrap();
+
+		// Misaligned address
+		let res = mem_space.set_prot(unsafe { ptr.add(1) }, memory::PAGE_SIZE, 0, &ap);
+		assert_eq!(res.unwrap_err().as_int(), errno::EINVAL);
+		// Zero length
+		let res = mem_space.set_prot(ptr, 0, 0, &ap);
+		assert_eq!(res.unwrap_err().as_int(), errno::EINVAL);
+		// Partially unmapped range
+		let res = mem_space.set_prot(ptr, 5 * memory::PAGE_SIZE, 0, &ap);
+		assert_eq!(res.unwrap_err().as_int(), errno::ENOMEM);
+		assert_eq!(
+			mem_space.get_mapping_mut_for(ptr).unwrap().get_flags(),
+			flags
+		);
+	}
+
+	#[test_case]
+	fn grow_down() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(2).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE | MAPPING_FLAG_GROWSDOWN;
+		let begin = unsafe { memory::ALLOC_BEGIN.add(16 * memory::PAGE_SIZE) };
+		let ptr = mem_space
+			.map(
+				MapConstraint::Fixed(begin),
+				size,
+				flags,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		let usage = mem_space.get_vmem_usage();
+		let code = vmem::x86::PAGE_FAULT_USER | vmem::x86::PAGE_FAULT_WRITE;
+
+		// Access right below the mapping
+		let below = unsafe { ptr.sub(1) };
+		assert!(mem_space.handle_page_fault(below, code));
+		let mapping = mem_space.get_mapping_mut_for(below).unwrap();
+		assert_eq!(mapping.get_begin(), unsafe { ptr.sub(memory::PAGE_SIZE) });
+		assert_eq!(mapping.get_size().get(), 3);
+		assert_eq!(mem_space.get_vmem_usage(), usage + 1);
+
+		// Several pages below
+		let far = unsafe { ptr.sub(4 * memory::PAGE_SIZE) };
+		assert!(mem_space.handle_page_fault(far, code));
+		let mapping = mem_space.get_mapping_mut_for(far).unwrap();
+		assert_eq!(mapping.get_begin(), far);
+		assert_eq!(mapping.get_size().get(), 6);
+
+		// Beyond the limit
+		let too_far = unsafe { ptr.sub(GROWSDOWN_MAX_PAGES * memory::PAGE_SIZE) };
+		assert!(!mem_space.handle_page_fault(too_far, code));
+
+		// Mappings that don't grow
+		let ptr = mem_space
+			.map(
+				MapConstraint::Fixed(unsafe { begin.add(64 * memory::PAGE_SIZE) }),
+				size,
+				MAPPING_FLAG_USER,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		assert!(!mem_space.handle_page_fault(unsafe { ptr.sub(1) }, code));
+	}
+
+	#[test_case]
+	fn grow_down_guard() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(1).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let begin = unsafe { memory::ALLOC_BEGIN.add(1024 * memory::PAGE_SIZE) };
+		let ptr = mem_space
+			.map(
+				MapConstraint::Fixed(begin),
+				size,
+				flags | MAPPING_FLAG_GROWSDOWN,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		let guard_begin = unsafe { ptr.sub(GROWSDOWN_GUARD_PAGES * memory::PAGE_SIZE) };
+
+		// Right below the mapping
+		let below = unsafe { ptr.sub(memory::PAGE_SIZE) };
+		assert!(mem_space
+			.map(
+				MapConstraint::Fixed(below),
+				size,
+				flags,
+				MapResidence::Normal
+			)
+			.is_err());
+		assert!(!mem_space.is_range_mapped(below, size));
+		// Right below the guard range
+		let outside = unsafe { guard_begin.sub(memory::PAGE_SIZE) };
+		let res = mem_space.map(
+			MapConstraint::Fixed(outside),
+			size,
+			flags,
+			MapResidence::Normal,
+		);
+		assert_eq!(res.unwrap(), outside);
+		// A hint in the guard range is not followed
+		let res = mem_space
+			.map(
+				MapConstraint::Hint(below),
+				size,
+				flags,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		assert!(res < guard_begin || res > ptr);
+		// The gap between the guard range and the mapping below is the smallest, yet it is skipped
+		let res = mem_space
+			.map(MapConstraint::None, size, flags, MapResidence::Normal)
+			.unwrap();
+		assert!(res < guard_begin || res > ptr);
+		assert!(!mem_space.is_range_mapped(
+			guard_begin,
+			NonZeroUsize::new(GROWSDOWN_GUARD_PAGES).unwrap()
+		));
+
+		// The mapping can still grow over its guard range
+		let code = vmem::x86::PAGE_FAULT_USER | vmem::x86::PAGE_FAULT_WRITE;
+		assert!(mem_space.handle_page_fault(below, code));
+	}
+
+	#[test_case]
+	fn map_anonymous_shared() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(2).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE | MAPPING_FLAG_SHARED;
+		let residence = MapResidence::AnonShared {
+			obj: Arc::new(AnonSharedObject::new(size).unwrap()).unwrap(),
+			page_off: 0,
+		};
+		let a = mem_space
+			.map(MapConstraint::None, size, flags, residence.clone())
+			.unwrap();
+		let b = mem_space
+			.map(MapConstraint::None, size, flags, residence)
+			.unwrap();
+
+		// Both mappings use the same physical memory
+		for i in 0..size.get() {
+			let a_page = mem_space
+				.get_mapping_mut_for(a)
+				.unwrap()
+				.get_physical_page(i);
+			let b_page = mem_space
+				.get_mapping_mut_for(b)
+				.unwrap()
+				.get_physical_page(i);
+			assert!(a_page.is_some());
+			assert_eq!(a_page, b_page);
+		}
+	}
+
+	#[test_case]
+	fn discard_anonymous() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(2).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let ptr = mem_space
+			.map(MapConstraint::None, size, flags, MapResidence::Normal)
+			.unwrap();
+		let len = 2 * memory::PAGE_SIZE;
+
+		mem_space.populate(ptr, size).unwrap();
+		assert!(mem_space
+			.get_mapping_mut_for(ptr)
+			.unwrap()
+			.get_physical_page(1)
+			.is_some());
+		unsafe {
+			vmem::switch(mem_space.get_vmem().as_ref(), || {
+				slice::from_raw_parts_mut(ptr as *mut u8, len).fill(0xff);
+			});
+		}
+
+		mem_space.discard(ptr, size).unwrap();
+		assert!(mem_space
+			.get_mapping_mut_for(ptr)
+			.unwrap()
+			.get_physical_page(0)
+			.is_none());
+		let zero = unsafe {
+			vmem::switch(mem_space.get_vmem().as_ref(), || {
+				slice::from_raw_parts(ptr as *const u8, len)
+					.iter()
+					.all(|b| *b == 0)
+			})
+		};
+		assert!(zero);
+
+		// Unmapped pages in the range
+		let res = mem_space.discard(ptr, NonZeroUsize::new(3).unwrap());
+		assert_eq!(res.unwrap_err().as_int(), errno::ENOMEM);
+	}
+
+	#[test_case]
+	fn set_prot_sub_range() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(4).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE;
+		let ptr = mem_space
+			.map(MapConstraint::None, size, flags, MapResidence::Normal)
+			.unwrap();
+
+		// Make the two middle pages read-only
+		let mid = unsafe { ptr.add(memory::PAGE_SIZE) };
+		mem_space
+			.set_prot(mid, 2 * memory::PAGE_SIZE, 0, &AccessProfile::KERNEL)
+			.unwrap();
+
+		let expected = [(0, 1, flags), (1, 2, MAPPING_FLAG_USER), (3, 1, flags)];
+		for (begin, size, flags) in expected {
+			let page_ptr = unsafe { ptr.add(begin * memory::PAGE_SIZE) };
+			let mapping = mem_space.get_mapping_mut_for(page_ptr).unwrap();
+			assert_eq!(mapping.get_begin(), page_ptr);
+			assert_eq!(mapping.get_size().get(), size);
+			assert_eq!(mapping.get_flags(), flags);
+		}
+	}
+}
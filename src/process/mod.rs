@@ -302,7 +302,7 @@ static mut SCHEDULER: MaybeUninit<Arc<IntMutex<Scheduler>>> = MaybeUninit::unini
 pub fn init() -> Result<(), Errno> {
 	TSS::init();
 
-	let cores_count = 1; // TODO
+	let cores_count = cpu::per_cpu::get_cores_count();
 	unsafe {
 		PID_MANAGER.write(Mutex::new(PIDManager::new()?));
 		SCHEDULER.write(Scheduler::new(cores_count)?);
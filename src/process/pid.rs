@@ -25,23 +25,19 @@ impl PIDManager {
 	/// Creates a new instance.
 	pub fn new() -> AllocResult<Self> {
 		let mut s = Self {
-			allocator: IDAllocator::new(MAX_PID as _)?,
+			allocator: IDAllocator::new_range(INIT_PID as _, MAX_PID as _)?,
 		};
-		s.allocator.set_used((INIT_PID - 1) as _);
+		s.allocator.set_used(INIT_PID as _);
 		Ok(s)
 	}
 
 	/// Returns a unused PID and marks it as used.
 	#[must_use = "not freeing a PID shall cause a leak"]
 	pub fn get_unique_pid(&mut self) -> AllocResult<Pid> {
-		match self.allocator.alloc(None) {
-			Ok(i) => {
-				debug_assert!(i <= MAX_PID as _);
-
-				Ok((i + 1) as _)
-			}
-			Err(e) => Err(e),
-		}
+		let pid = self.allocator.alloc(None)?;
+		debug_assert!(pid <= MAX_PID as _);
+
+		Ok(pid as _)
 	}
 
 	/// Releases the given PID `pid` to make it available for other processes.
@@ -51,6 +47,6 @@ impl PIDManager {
 		debug_assert!(pid >= 1);
 		debug_assert!(pid <= MAX_PID as _);
 
-		self.allocator.free((pid - 1) as _)
+		self.allocator.free(pid as _)
 	}
 }
@@ -9,6 +9,7 @@
 //! This number represents the number of ticks during which the process keeps
 //! running until switching to the next process.
 
+use crate::cpu::per_cpu;
 use crate::errno::AllocResult;
 use crate::event;
 use crate::event::CallbackHook;
@@ -340,7 +341,7 @@ impl Scheduler {
 			}
 
 			// The current core ID
-			let core_id = 0; // TODO
+			let core_id = per_cpu::get_current_cpu() as _;
 			sched.get_tmp_stack(core_id)
 		};
 
@@ -28,18 +28,19 @@ pub fn connect(sockfd: c_int, addr: SyscallSlice<u8>, addrlen: isize) -> Result<
 	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
 	let open_file_mutex = fd.get_open_file();
 	let open_file = open_file_mutex.lock();
-	let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+	let loc = open_file.get_location();
+	let sock_mutex = buffer::get(loc).ok_or_else(|| errno!(ENOENT))?;
 	let mut sock = sock_mutex.lock();
-	let _sock = (&mut *sock as &mut dyn Any)
+	let sock = (&mut *sock as &mut dyn Any)
 		.downcast_mut::<Socket>()
 		.ok_or_else(|| errno!(ENOTSOCK))?;
 
 	let mem_space_mutex = proc.get_mem_space().unwrap();
 	let mem_space = mem_space_mutex.lock();
-	let _addr_slice = addr
+	let addr_slice = addr
 		.get(&mem_space, addrlen as _)?
 		.ok_or_else(|| errno!(EFAULT))?;
 
-	// TODO connect socket
-	todo!();
+	sock.connect(addr_slice, loc)?;
+	Ok(0)
 }
@@ -2,6 +2,7 @@
 
 use crate::errno::Errno;
 use crate::file::buffer;
+use crate::file::buffer::pipe;
 use crate::file::buffer::pipe::PipeBuffer;
 use crate::file::fd::NewFDConstraint;
 use crate::file::FileContent;
@@ -120,11 +121,11 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 		return Err(errno!(EBADF));
 	}
 
-	let fds_mutex = {
+	let (fds_mutex, ap) = {
 		let proc_mutex = Process::current_assert();
 		let proc = proc_mutex.lock();
 
-		proc.get_fds().unwrap().clone()
+		(proc.get_fds().unwrap().clone(), proc.access_profile)
 	};
 	let mut fds = fds_mutex.lock();
 
@@ -256,8 +257,27 @@ pub fn do_fcntl(fd: i32, cmd: i32, arg: *mut c_void, _fcntl64: bool) -> Result<i
 			.get_id() as _),
 
 		F_SETPIPE_SZ => {
-			// TODO
-			todo!();
+			let size = arg as usize;
+			if size > pipe::PIPE_MAX_SIZE && !ap.is_privileged() {
+				return Err(errno!(EPERM));
+			}
+
+			let fd = fds.get_fd(fd as _).ok_or_else(|| errno!(EBADF))?;
+
+			let open_file_mutex = fd.get_open_file();
+			let open_file = open_file_mutex.lock();
+
+			let file_mutex = open_file.get_file();
+			let file = file_mutex.lock();
+
+			match file.get_content() {
+				FileContent::Fifo => {
+					let buf = buffer::get_or_default::<PipeBuffer>(file.get_location())?;
+					let cap = buf.lock().set_capacity(size)?;
+					Ok(cap as _)
+				}
+				_ => Err(errno!(EBADF)),
+			}
 		}
 
 		F_GETPIPE_SZ => {
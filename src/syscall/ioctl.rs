@@ -46,6 +46,8 @@ pub const TIOCGWINSZ: u32 = 0x00005413;
 pub const TIOCSWINSZ: u32 = 0x00005414;
 /// ioctl request: Returns the number of bytes available on the file descriptor.
 pub const FIONREAD: u32 = 0x0000541b;
+/// ioctl request: Enables or disables non-blocking I/O on the file descriptor.
+pub const FIONBIO: u32 = 0x00005421;
 
 /// Enumeration of IO directions for ioctl requests.
 #[derive(Eq, PartialEq)]
@@ -1,13 +1,58 @@
 //! The `madvise` system call gives advices to the kernel about the usage of
 //! memory in order to allow optimizations.
 
+use super::util;
+use crate::errno;
 use crate::errno::Errno;
+use crate::memory;
+use crate::process::Process;
+use crate::util::math;
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::num::NonZeroUsize;
 use macros::syscall;
 
+/// No special treatment.
+const MADV_NORMAL: c_int = 0;
+/// Pages are expected to be accessed in random order.
+const MADV_RANDOM: c_int = 1;
+/// Pages are expected to be accessed in sequential order.
+const MADV_SEQUENTIAL: c_int = 2;
+/// Pages are expected to be accessed soon.
+const MADV_WILLNEED: c_int = 3;
+/// Pages are not expected to be accessed soon. Their content may be dropped.
+const MADV_DONTNEED: c_int = 4;
+
 #[syscall]
-pub fn madvise(_addr: *mut c_void, _length: usize, _advice: c_int) -> Result<i32, Errno> {
-	// TODO
+pub fn madvise(addr: *mut c_void, length: usize, advice: c_int) -> Result<i32, Errno> {
+	if !addr.is_aligned_to(memory::PAGE_SIZE) {
+		return Err(errno!(EINVAL));
+	}
+	if !matches!(
+		advice,
+		MADV_NORMAL | MADV_RANDOM | MADV_SEQUENTIAL | MADV_WILLNEED | MADV_DONTNEED
+	) {
+		return Err(errno!(EINVAL));
+	}
+	let Some(pages) = NonZeroUsize::new(math::ceil_div(length, memory::PAGE_SIZE)) else {
+		return Ok(0);
+	};
+
+	util::check_user_range(addr, pages)?;
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space = mem_space.lock();
+
+	match advice {
+		MADV_WILLNEED => mem_space.populate(addr, pages)?,
+		MADV_DONTNEED => mem_space.discard(addr, pages)?,
+
+		// Access patterns are not used yet, but the range must be mapped
+		_ => mem_space.check_mapped(addr, pages)?,
+	}
+
 	Ok(0)
 }
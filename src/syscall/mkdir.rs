@@ -45,7 +45,7 @@ pub fn mkdir(pathname: SyscallString, mode: file::Mode) -> Result<i32, Errno> {
 			name,
 			&ap,
 			mode,
-			FileContent::Directory(HashMap::new()),
+			FileContent::Directory(HashMap::default()),
 		)?;
 	}
 
@@ -1,14 +1,20 @@
 //! The `mmap` system call allows the process to allocate memory.
 
+use super::util;
 use crate::errno;
+use crate::errno::AllocResult;
 use crate::errno::Errno;
+use crate::file::open_file::OpenFile;
 use crate::file::FileType;
 use crate::memory;
 use crate::process::mem_space;
+use crate::process::mem_space::AnonSharedObject;
 use crate::process::mem_space::MapResidence;
+use crate::process::mem_space::MemSpace;
 use crate::process::Process;
 use crate::syscall::mmap::mem_space::MapConstraint;
 use crate::util::math;
+use crate::util::ptr::arc::Arc;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use core::num::NonZeroUsize;
@@ -25,6 +31,12 @@ pub const PROT_EXEC: i32 = 0b100;
 const MAP_SHARED: i32 = 0b001;
 /// Interpret addr exactly.
 const MAP_FIXED: i32 = 0b010;
+/// The mapping is not backed by a file.
+const MAP_ANONYMOUS: i32 = 0x20;
+/// The mapping grows downward when the memory right below it is accessed.
+const MAP_GROWSDOWN: i32 = 0x100;
+/// Interpret addr exactly, but fail instead of replacing existing mappings.
+const MAP_FIXED_NOREPLACE: i32 = 0x100000;
 
 /// Converts mmap's `flags` and `prot` to mem space mapping flags.
 fn get_flags(flags: i32, prot: i32) -> u8 {
@@ -33,6 +45,9 @@ fn get_flags(flags: i32, prot: i32) -> u8 {
 	if flags & MAP_SHARED != 0 {
 		mem_flags |= mem_space::MAPPING_FLAG_SHARED;
 	}
+	if flags & MAP_GROWSDOWN != 0 {
+		mem_flags |= mem_space::MAPPING_FLAG_GROWSDOWN;
+	}
 
 	if prot & PROT_WRITE != 0 {
 		mem_flags |= mem_space::MAPPING_FLAG_WRITE;
@@ -44,6 +59,77 @@ fn get_flags(flags: i32, prot: i32) -> u8 {
 	mem_flags
 }
 
+/// Checks the arguments describing what backs the mapping.
+///
+/// Anonymous mappings ignore `fd`, but require `offset` to be zero. Other mappings require a
+/// file descriptor and a page-aligned `offset`.
+///
+/// Only private anonymous mappings can grow downward.
+fn check_source(flags: i32, fd: i32, offset: u64) -> Result<(), Errno> {
+	let private_anon = flags & MAP_ANONYMOUS != 0 && flags & MAP_SHARED == 0;
+	if flags & MAP_GROWSDOWN != 0 && !private_anon {
+		return Err(errno!(EINVAL));
+	}
+
+	if flags & MAP_ANONYMOUS != 0 {
+		if offset != 0 {
+			return Err(errno!(EINVAL));
+		}
+	} else {
+		if fd < 0 {
+			return Err(errno!(EBADF));
+		}
+		if offset as usize % memory::PAGE_SIZE != 0 {
+			return Err(errno!(EINVAL));
+		}
+	}
+
+	Ok(())
+}
+
+/// Checks that the open file description `open_file` allows a mapping with protection `prot` and
+/// flags `flags`.
+///
+/// A shared writable mapping writes back to the file, so the file must be open for writing.
+/// Private mappings are copy-on-write, so they are not concerned.
+fn check_open_mode(open_file: &OpenFile, prot: i32, flags: i32) -> Result<(), Errno> {
+	let shared_write = flags & MAP_SHARED != 0 && prot & PROT_WRITE != 0;
+	if shared_write && !open_file.can_write() {
+		return Err(errno!(EACCES));
+	}
+	Ok(())
+}
+
+/// Returns the residence of an anonymous mapping of `pages` pages.
+///
+/// Shared mappings get their own shared object so that their memory remains common to the
+/// processes inheriting them across `fork`.
+fn get_anon_residence(flags: i32, pages: NonZeroUsize) -> AllocResult<MapResidence> {
+	if flags & MAP_SHARED != 0 {
+		Ok(MapResidence::AnonShared {
+			obj: Arc::new(AnonSharedObject::new(pages)?)?,
+			page_off: 0,
+		})
+	} else {
+		Ok(MapResidence::Normal)
+	}
+}
+
+/// Checks that no page of the range of `pages` pages beginning at `addr` is already mapped in
+/// `mem_space`, for `MAP_FIXED_NOREPLACE`.
+///
+/// If a page is mapped, the function returns `EEXIST`.
+fn check_noreplace(
+	mem_space: &MemSpace,
+	addr: *const c_void,
+	pages: NonZeroUsize,
+) -> Result<(), Errno> {
+	if mem_space.is_range_mapped(addr, pages) {
+		return Err(errno!(EEXIST));
+	}
+	Ok(())
+}
+
 /// Performs the `mmap` system call.
 ///
 /// This function takes a `u64` for `offset` to allow implementing the `mmap2`
@@ -67,15 +153,15 @@ pub fn do_mmap(
 		return Err(errno!(EINVAL));
 	};
 
-	// Check for overflow
-	let end = (addr as usize).wrapping_add(pages.get() * memory::PAGE_SIZE);
-	if end < addr as usize {
-		return Err(errno!(EINVAL));
+	// A requested address must not overflow or lead into kernel space
+	if !addr.is_null() {
+		util::check_user_range(addr, pages)?;
 	}
 
+	let noreplace = flags & MAP_FIXED_NOREPLACE != 0;
 	let constraint = {
 		if !addr.is_null() {
-			if flags & MAP_FIXED != 0 {
+			if flags & (MAP_FIXED | MAP_FIXED_NOREPLACE) != 0 {
 				MapConstraint::Fixed(addr as _)
 			} else {
 				MapConstraint::Hint(addr as _)
@@ -89,24 +175,26 @@ pub fn do_mmap(
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
-	// The file the mapping points to
-	let file_mutex = if fd >= 0 {
-		// Check the alignment of the offset
-		if offset as usize % memory::PAGE_SIZE != 0 {
-			return Err(errno!(EINVAL));
-		}
+	check_source(flags, fd, offset)?;
 
-		proc.get_fds()
+	// The file the mapping points to
+	let file_mutex = if flags & MAP_ANONYMOUS == 0 {
+		let file_mutex = proc
+			.get_fds()
 			.unwrap()
 			.lock()
 			.get_fd(fd as _)
-			.map(|fd| fd.get_open_file().lock().get_file().clone())
+			.ok_or_else(|| errno!(EBADF))
+			.and_then(|fd| {
+				let open_file = fd.get_open_file().lock();
+				check_open_mode(&open_file, prot, flags)?;
+				Ok(open_file.get_file().clone())
+			})?;
+		Some(file_mutex)
 	} else {
 		None
 	};
 
-	// TODO anon flag
-
 	// Get residence
 	let residence = match file_mutex {
 		Some(file_mutex) => {
@@ -130,16 +218,22 @@ pub fn do_mmap(
 				off: offset,
 			}
 		}
-		None => {
-			// TODO If the mapping requires a fd, return an error
-			MapResidence::Normal
-		}
+		None => get_anon_residence(flags, pages)?,
 	};
 
 	// The process's memory space
 	let mem_space_mutex = proc.get_mem_space().unwrap();
 	let mut mem_space = mem_space_mutex.lock();
 
+	if noreplace && !addr.is_null() {
+		check_noreplace(&mem_space, addr, pages)?;
+	}
+	// A mapping at a fixed address must have room to grow
+	let fixed = matches!(constraint, MapConstraint::Fixed(_));
+	if flags & MAP_GROWSDOWN != 0 && fixed && !mem_space.can_grow_down(addr) {
+		return Err(errno!(EINVAL));
+	}
+
 	let flags = get_flags(flags, prot);
 
 	// The pointer on the virtual memory to the beginning of the mapping
@@ -147,7 +241,8 @@ pub fn do_mmap(
 	match result {
 		Ok(ptr) => Ok(ptr as _),
 		Err(e) => {
-			if constraint != MapConstraint::None {
+			// The address is mandatory with `MAP_FIXED_NOREPLACE`
+			if constraint != MapConstraint::None && !noreplace {
 				let ptr = mem_space.map(MapConstraint::None, pages, flags, residence)?;
 				Ok(ptr as _)
 			} else {
@@ -169,3 +264,186 @@ pub fn mmap(
 ) -> Result<i32, Errno> {
 	do_mmap(addr, length, prot, flags, fd, offset as _)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::file::open_file;
+	use crate::file::vfs;
+	use crate::file::FileLocation;
+
+	#[test_case]
+	fn mmap_anonymous() {
+		// The file descriptor is ignored
+		assert!(check_source(MAP_ANONYMOUS, -1, 0).is_ok());
+		assert!(check_source(MAP_ANONYMOUS | MAP_SHARED, 42, 0).is_ok());
+		// The offset must be zero
+		assert_eq!(
+			check_source(MAP_ANONYMOUS, -1, memory::PAGE_SIZE as _)
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+	}
+
+	#[test_case]
+	fn mmap_anonymous_shared() {
+		let pages = NonZeroUsize::new(2).unwrap();
+		let private = get_anon_residence(MAP_ANONYMOUS, pages).unwrap();
+		let shared = get_anon_residence(MAP_ANONYMOUS | MAP_SHARED, pages).unwrap();
+		assert!(private.is_normal());
+		assert!(matches!(
+			shared,
+			MapResidence::AnonShared {
+				page_off: 0,
+				..
+			}
+		));
+	}
+
+	#[test_case]
+	fn mmap_kernel_space() {
+		let pages = NonZeroUsize::new(2).unwrap();
+		let end = memory::PROCESS_END as usize;
+
+		// Just below the boundary
+		let addr = (end - 2 * memory::PAGE_SIZE) as *const c_void;
+		assert!(util::check_user_range(addr, pages).is_ok());
+		// Crossing the boundary
+		let addr = (end - memory::PAGE_SIZE) as *const c_void;
+		assert_eq!(
+			util::check_user_range(addr, pages).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		// In kernel space
+		assert_eq!(
+			util::check_user_range(end as _, pages)
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+		// Overflow
+		let addr = (usize::MAX - memory::PAGE_SIZE + 1) as *const c_void;
+		assert_eq!(
+			util::check_user_range(addr, pages).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+	}
+
+	#[test_case]
+	fn mmap_noreplace() {
+		let mut mem_space = MemSpace::new().unwrap();
+		let pages = NonZeroUsize::new(2).unwrap();
+		let flags = get_flags(0, PROT_READ | PROT_WRITE);
+		let addr = mem_space
+			.map(MapConstraint::None, pages, flags, MapResidence::Normal)
+			.unwrap();
+
+		// Right after the mapping
+		let free = unsafe { addr.add(2 * memory::PAGE_SIZE) };
+		assert!(check_noreplace(&mem_space, free, pages).is_ok());
+		// Overlapping the end of the mapping
+		let overlap = unsafe { addr.add(memory::PAGE_SIZE) };
+		assert_eq!(
+			check_noreplace(&mem_space, overlap, pages)
+				.unwrap_err()
+				.as_int(),
+			errno::EEXIST
+		);
+		assert_eq!(
+			check_noreplace(&mem_space, addr, pages)
+				.unwrap_err()
+				.as_int(),
+			errno::EEXIST
+		);
+	}
+
+	#[test_case]
+	fn mmap_growsdown() {
+		assert!(check_source(MAP_ANONYMOUS | MAP_GROWSDOWN, -1, 0).is_ok());
+		// Only private anonymous mappings can grow
+		assert_eq!(
+			check_source(MAP_ANONYMOUS | MAP_SHARED | MAP_GROWSDOWN, -1, 0)
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+		assert_eq!(
+			check_source(MAP_GROWSDOWN, 3, 0).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+
+		let mut mem_space = MemSpace::new().unwrap();
+		let pages = NonZeroUsize::new(2).unwrap();
+		let flags = get_flags(MAP_GROWSDOWN, PROT_READ | PROT_WRITE);
+		let addr = unsafe { memory::ALLOC_BEGIN.add(8 * memory::PAGE_SIZE) };
+		mem_space
+			.map(
+				MapConstraint::Fixed(addr),
+				pages,
+				flags,
+				MapResidence::Normal,
+			)
+			.unwrap();
+		let below = unsafe { addr.sub(memory::PAGE_SIZE) };
+		assert!(mem_space.can_grow_down(addr));
+		// The page below is reserved for the mapping to grow
+		assert!(mem_space
+			.map(
+				MapConstraint::Fixed(below),
+				NonZeroUsize::new(1).unwrap(),
+				get_flags(0, PROT_READ | PROT_WRITE),
+				MapResidence::Normal,
+			)
+			.is_err());
+		assert!(mem_space.can_grow_down(addr));
+
+		// The page below is taken
+		let addr = unsafe { addr.add(1024 * memory::PAGE_SIZE) };
+		let below = unsafe { addr.sub(memory::PAGE_SIZE) };
+		mem_space
+			.map(
+				MapConstraint::Fixed(below),
+				NonZeroUsize::new(1).unwrap(),
+				get_flags(0, PROT_READ | PROT_WRITE),
+				MapResidence::Normal,
+			)
+			.unwrap();
+		assert!(!mem_space.can_grow_down(addr));
+	}
+
+	#[test_case]
+	fn mmap_open_mode() {
+		let open_file = |flags| {
+			let location = FileLocation::Virtual {
+				id: 0,
+			};
+			let file = vfs::get_file_by_location(&location).unwrap();
+			OpenFile::new(file, flags).unwrap()
+		};
+		let read_only = open_file(open_file::O_RDONLY);
+		let read_write = open_file(open_file::O_RDWR);
+		let prot = PROT_READ | PROT_WRITE;
+
+		// Shared writable mapping on a read-only file
+		assert_eq!(
+			check_open_mode(&read_only, prot, MAP_SHARED)
+				.unwrap_err()
+				.as_int(),
+			errno::EACCES
+		);
+		assert!(check_open_mode(&read_only, PROT_READ, MAP_SHARED).is_ok());
+		assert!(check_open_mode(&read_write, prot, MAP_SHARED).is_ok());
+		// Private writable mappings are copy-on-write
+		assert!(check_open_mode(&read_only, prot, 0).is_ok());
+	}
+
+	#[test_case]
+	fn mmap_file() {
+		assert!(check_source(0, 3, memory::PAGE_SIZE as _).is_ok());
+		// A file descriptor is required
+		assert_eq!(check_source(0, -1, 0).unwrap_err().as_int(), errno::EBADF);
+		// The offset must be page-aligned
+		assert_eq!(check_source(0, 3, 1).unwrap_err().as_int(), errno::EINVAL);
+	}
+}
@@ -3,10 +3,20 @@
 
 use super::mmap;
 use crate::errno::Errno;
+use crate::memory;
 use core::ffi::c_int;
 use core::ffi::c_void;
 use macros::syscall;
 
+/// Converts the given offset in pages to an offset in bytes.
+///
+/// If the conversion overflows, the function returns an error.
+fn get_byte_offset(offset: u64) -> Result<u64, Errno> {
+	offset
+		.checked_mul(memory::PAGE_SIZE as u64)
+		.ok_or_else(|| errno!(EINVAL))
+}
+
 // TODO Check last argument type
 #[syscall]
 pub fn mmap2(
@@ -17,5 +27,63 @@ pub fn mmap2(
 	fd: c_int,
 	offset: u64,
 ) -> Result<i32, Errno> {
-	mmap::do_mmap(addr, length, prot, flags, fd, offset * 4096)
+	let offset = get_byte_offset(offset)?;
+	mmap::do_mmap(addr, length, prot, flags, fd, offset)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::errno;
+	use crate::file::fs::tmp;
+	use crate::memory::vmem;
+	use crate::process::mem_space::MapConstraint;
+	use crate::process::mem_space::MapResidence;
+	use crate::process::mem_space::MemSpace;
+	use crate::process::mem_space::MAPPING_FLAG_USER;
+	use core::num::NonZeroUsize;
+	use core::slice;
+
+	#[test_case]
+	fn mmap2_offset() {
+		assert_eq!(get_byte_offset(0).unwrap(), 0);
+		assert_eq!(get_byte_offset(3).unwrap(), 3 * 4096);
+		// Beyond the 4 GiB limit of `mmap` on 32 bits
+		assert_eq!(get_byte_offset(0x100000).unwrap(), 0x100000000);
+
+		assert_eq!(
+			get_byte_offset(u64::MAX / 4096 + 1).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+	}
+
+	#[test_case]
+	fn mmap2_residence() {
+		// Each page of the file is filled with its index
+		let mut content = crate::vec![0u8; 4 * memory::PAGE_SIZE].unwrap();
+		for (i, page) in content.chunks_mut(memory::PAGE_SIZE).enumerate() {
+			page.fill(i as u8);
+		}
+		let location = tmp::test_file(b"/mmap2_residence", &content);
+
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(2).unwrap();
+		let residence = MapResidence::File {
+			location,
+			off: get_byte_offset(2).unwrap(),
+		};
+		let ptr = mem_space
+			.map(MapConstraint::None, size, MAPPING_FLAG_USER, residence)
+			.unwrap();
+
+		// Page N of the mapping holds page N + 2 of the file
+		unsafe {
+			vmem::switch(mem_space.get_vmem().as_ref(), || {
+				let mapped = slice::from_raw_parts(ptr as *const u8, 2 * memory::PAGE_SIZE);
+				for (i, page) in mapped.chunks(memory::PAGE_SIZE).enumerate() {
+					assert!(page.iter().all(|b| *b as usize == i + 2));
+				}
+			});
+		}
+	}
 }
@@ -92,6 +92,7 @@ mod read;
 mod readlink;
 mod readv;
 mod reboot;
+mod recvmsg;
 mod rename;
 mod renameat2;
 mod rmdir;
@@ -235,6 +236,7 @@ use read::read;
 use readlink::readlink;
 use readv::readv;
 use reboot::reboot;
+use recvmsg::recvmsg;
 use rename::rename;
 use renameat2::renameat2;
 use rmdir::rmdir;
@@ -654,7 +656,7 @@ fn get_syscall(id: u32) -> Option<SyscallHandler> {
 		0x171 => Some(&sendto),
 		// TODO 0x172 => Some(&sendmsg),
 		// TODO 0x173 => Some(&recvfrom),
-		// TODO 0x174 => Some(&recvmsg),
+		0x174 => Some(&recvmsg),
 		0x175 => Some(&shutdown),
 		// TODO 0x176 => Some(&userfaultfd),
 		// TODO 0x177 => Some(&membarrier),
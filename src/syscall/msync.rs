@@ -4,9 +4,12 @@
 use crate::errno;
 use crate::errno::Errno;
 use crate::memory;
+use crate::process::mem_space::MemSpace;
 use crate::process::Process;
+use crate::util::math;
 use core::ffi::c_int;
 use core::ffi::c_void;
+use core::num::NonZeroUsize;
 use macros::syscall;
 
 /// Schedules a synchronization and returns directly.
@@ -16,31 +19,136 @@ const MS_SYNC: i32 = 0b010;
 /// Invalides other mappings of the same file so they can be updated.
 const MS_INVALIDATE: i32 = 0b100;
 
-#[syscall]
-pub fn msync(addr: *mut c_void, length: usize, flags: c_int) -> Result<i32, Errno> {
-	// Checking address alignment
-	if !addr.is_aligned_to(memory::PAGE_SIZE) {
+/// Checks the given flags are valid.
+///
+/// `MS_INVALIDATE` is not supported since each mapping of a file holds its own copy of the
+/// file's pages, which cannot be invalidated yet.
+fn check_flags(flags: i32) -> Result<(), Errno> {
+	// Checking for unknown flags
+	if flags & !(MS_ASYNC | MS_SYNC | MS_INVALIDATE) != 0 {
+		return Err(errno!(EINVAL));
+	}
+	// TODO drop the pages of other mappings of the same files
+	if flags & MS_INVALIDATE != 0 {
 		return Err(errno!(EINVAL));
 	}
 	// Checking for conflicts in flags
 	if flags & MS_ASYNC != 0 && flags & MS_SYNC != 0 {
 		return Err(errno!(EINVAL));
 	}
+	Ok(())
+}
 
+/// Performs the msync operation on the memory space `mem_space`.
+///
+/// The arguments are the same as the syscall's.
+fn do_msync(
+	mem_space: &MemSpace,
+	addr: *mut c_void,
+	length: usize,
+	flags: c_int,
+) -> Result<(), Errno> {
+	// Checking address alignment
+	if !addr.is_aligned_to(memory::PAGE_SIZE) {
+		return Err(errno!(EINVAL));
+	}
+	check_flags(flags)?;
+	let Some(pages) = NonZeroUsize::new(math::ceil_div(length, memory::PAGE_SIZE)) else {
+		return Ok(());
+	};
+
+	// No writeback is deferred, so `MS_ASYNC` synchronizes right away as well
+	mem_space.sync(addr, pages)
+}
+
+#[syscall]
+pub fn msync(addr: *mut c_void, length: usize, flags: c_int) -> Result<i32, Errno> {
 	let proc_mutex = Process::current_assert();
 	let proc = proc_mutex.lock();
 
 	// The process's memory space
 	let mem_space = proc.get_mem_space().unwrap();
-	let mut mem_space = mem_space.lock();
+	let mem_space = mem_space.lock();
+
+	do_msync(&mem_space, addr, length, flags)?;
+	Ok(0)
+}
 
-	let mut i = 0;
-	while i < length {
-		let mapping = mem_space.get_mapping_mut_for(addr).ok_or(errno!(ENOMEM))?;
-		mapping.fs_sync()?; // TODO Use flags
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::file::fs::tmp;
+	use crate::file::vfs;
+	use crate::memory::vmem;
+	use crate::process::mem_space::MapConstraint;
+	use crate::process::mem_space::MapResidence;
+	use crate::process::mem_space::MAPPING_FLAG_SHARED;
+	use crate::process::mem_space::MAPPING_FLAG_USER;
+	use crate::process::mem_space::MAPPING_FLAG_WRITE;
+	use crate::util::io::IO;
+	use core::slice;
 
-		i += mapping.get_size().get() * memory::PAGE_SIZE;
+	#[test_case]
+	fn msync_flags() {
+		assert!(check_flags(0).is_ok());
+		assert!(check_flags(MS_SYNC).is_ok());
+		assert!(check_flags(MS_ASYNC).is_ok());
+
+		assert_eq!(
+			check_flags(MS_SYNC | MS_ASYNC).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		assert_eq!(check_flags(0b1000).unwrap_err().as_int(), errno::EINVAL);
+		// Unsupported
+		assert_eq!(
+			check_flags(MS_SYNC | MS_INVALIDATE).unwrap_err().as_int(),
+			errno::EINVAL
+		);
 	}
 
-	Ok(0)
+	#[test_case]
+	fn msync_write_back() {
+		let content = crate::vec![0u8; 2 * memory::PAGE_SIZE].unwrap();
+		let location = tmp::test_file(b"/msync_write_back", &content);
+		let mut mem_space = MemSpace::new().unwrap();
+		let size = NonZeroUsize::new(2).unwrap();
+		let flags = MAPPING_FLAG_USER | MAPPING_FLAG_WRITE | MAPPING_FLAG_SHARED;
+		let residence = MapResidence::File {
+			location: location.clone(),
+			off: 0,
+		};
+		let ptr = mem_space
+			.map(MapConstraint::None, size, flags, residence)
+			.unwrap();
+
+		// Write through the mapping
+		mem_space.populate(ptr, size).unwrap();
+		unsafe {
+			vmem::switch(mem_space.get_vmem().as_ref(), || {
+				let page = slice::from_raw_parts_mut(ptr as *mut u8, 2 * memory::PAGE_SIZE);
+				page[..4].copy_from_slice(b"abcd");
+				page[memory::PAGE_SIZE..(memory::PAGE_SIZE + 4)].copy_from_slice(b"efgh");
+			});
+		}
+		// Only the first page is synchronized
+		do_msync(&mem_space, ptr, 1, MS_SYNC).unwrap();
+
+		let file_mutex = vfs::get_file_by_location(&location).unwrap();
+		let mut file = file_mutex.lock();
+		let mut buf = [0u8; 4];
+		file.read(0, &mut buf).unwrap();
+		assert_eq!(&buf, b"abcd");
+		file.read(memory::PAGE_SIZE as _, &mut buf).unwrap();
+		assert_eq!(buf, [0; 4]);
+		drop(file);
+
+		// Unmapped range
+		let len = 3 * memory::PAGE_SIZE;
+		assert_eq!(
+			do_msync(&mem_space, ptr, len, MS_SYNC)
+				.unwrap_err()
+				.as_int(),
+			errno::ENOMEM
+		);
+	}
 }
@@ -1,6 +1,7 @@
 //! The `munmap` system call allows the process to free memory that was
 //! allocated with `mmap`.
 
+use super::util;
 use crate::errno;
 use crate::errno::Errno;
 use crate::memory;
@@ -10,32 +11,80 @@ use core::ffi::c_void;
 use core::num::NonZeroUsize;
 use macros::syscall;
 
-#[syscall]
-pub fn munmap(addr: *mut c_void, length: usize) -> Result<i32, Errno> {
-	if !addr.is_aligned_to(memory::PAGE_SIZE) || length == 0 {
+/// Returns the number of pages in the range of `length` bytes beginning at `addr`, rounded up.
+///
+/// If the range is invalid, overflows or reaches kernel space, the function returns `EINVAL`.
+fn get_pages(addr: *const c_void, length: usize) -> Result<NonZeroUsize, Errno> {
+	if !addr.is_aligned_to(memory::PAGE_SIZE) {
 		return Err(errno!(EINVAL));
 	}
+	let pages = NonZeroUsize::new(math::ceil_div(length, memory::PAGE_SIZE))
+		.ok_or_else(|| errno!(EINVAL))?;
+	// Prevent from unmapping kernel memory
+	util::check_user_range(addr, pages)?;
 
-	let proc_mutex = Process::current_assert();
-	let proc = proc_mutex.lock();
-
-	let pages = math::ceil_div(length, memory::PAGE_SIZE);
-	let length = pages * memory::PAGE_SIZE;
+	Ok(pages)
+}
 
-	// Checking for overflow
-	let end = (addr as usize).wrapping_add(length);
-	if end < addr as usize {
-		return Err(errno!(EINVAL));
-	}
+#[syscall]
+pub fn munmap(addr: *mut c_void, length: usize) -> Result<i32, Errno> {
+	let pages = get_pages(addr, length)?;
 
-	// Prevent from unmapping kernel memory
-	if (addr as usize) >= (memory::PROCESS_END as usize) || end > (memory::PROCESS_END as usize) {
-		return Err(errno!(EINVAL));
-	}
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
 
+	// Unmapping a range with nothing mapped in it succeeds, as required by POSIX, with zero pages
+	// freed. The system call returns zero whatever the number of freed pages
 	proc.get_mem_space()
 		.unwrap()
 		.lock()
-		.unmap(addr, NonZeroUsize::new(pages).unwrap(), false)?;
+		.unmap(addr, pages, false)?;
 	Ok(0)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn munmap_boundary() {
+		let end = memory::PROCESS_END as usize;
+
+		// The last page touches the boundary
+		let addr = (end - 2 * memory::PAGE_SIZE) as *const c_void;
+		assert_eq!(get_pages(addr, 2 * memory::PAGE_SIZE).unwrap().get(), 2);
+		// Rounded up to the boundary
+		assert_eq!(get_pages(addr, memory::PAGE_SIZE + 1).unwrap().get(), 2);
+
+		// One page past the boundary
+		assert_eq!(
+			get_pages(addr, 3 * memory::PAGE_SIZE).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		// Rounded up past the boundary
+		assert_eq!(
+			get_pages(addr, 2 * memory::PAGE_SIZE + 1)
+				.unwrap_err()
+				.as_int(),
+			errno::EINVAL
+		);
+		// In kernel space
+		assert_eq!(
+			get_pages(end as _, memory::PAGE_SIZE).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+		// Wrapping around
+		assert_eq!(
+			get_pages(addr, usize::MAX).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+
+		// Invalid arguments
+		assert_eq!(get_pages(addr, 0).unwrap_err().as_int(), errno::EINVAL);
+		let addr = (addr as usize + 1) as *const c_void;
+		assert_eq!(
+			get_pages(addr, memory::PAGE_SIZE).unwrap_err().as_int(),
+			errno::EINVAL
+		);
+	}
+}
@@ -101,6 +101,11 @@ fn get_file(
 /// The function checks the system call's flags and performs the action associated with some of
 /// them.
 ///
+/// Socket files cannot be opened: a socket is reached only through the file descriptors returned
+/// by `socket`, `socketpair` or `accept`, which may be shared with `dup` or `fork`. Otherwise,
+/// every process opening the file would share the same endpoint, and a connection-oriented socket
+/// would end up with more than two sides.
+///
 /// Arguments:
 /// - `file` is the file
 /// - `flags` is the set of flags provided by userspace
@@ -112,6 +117,9 @@ pub fn handle_flags(file: &mut File, flags: i32, access_profile: &AccessProfile)
 		open_file::O_RDWR => (true, true),
 		_ => return Err(errno!(EINVAL)),
 	};
+	if file.get_type() == FileType::Socket {
+		return Err(errno!(ENXIO));
+	}
 	if read && !access_profile.can_read_file(file) {
 		return Err(errno!(EACCES));
 	}
@@ -183,3 +191,36 @@ pub fn open_(pathname: SyscallString, flags: i32, mode: file::Mode) -> EResult<i
 pub fn open(pathname: SyscallString, flags: c_int, mode: file::Mode) -> Result<i32, Errno> {
 	open_(pathname, flags, mode)
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::file::fs::tmp::TmpFS;
+	use crate::file::fs::Filesystem;
+	use crate::util::io::DummyIO;
+
+	/// Creates a file with the given content on a temporary filesystem for testing purpose.
+	fn dummy_file(content: FileContent) -> File {
+		let mut fs = TmpFS::new(usize::MAX, false).unwrap();
+		let root = fs.get_root_inode(&mut DummyIO {}).unwrap();
+		let name = b"file".as_slice().try_into().unwrap();
+		fs.add_file(&mut DummyIO {}, root, name, 0, 0, 0o666, content)
+			.unwrap()
+	}
+
+	#[test_case]
+	fn open_socket_file() {
+		let mut file = dummy_file(FileContent::Socket);
+		for flags in [open_file::O_RDONLY, open_file::O_WRONLY, open_file::O_RDWR] {
+			assert_eq!(
+				handle_flags(&mut file, flags, &AccessProfile::KERNEL)
+					.unwrap_err()
+					.as_int(),
+				errno::ENXIO
+			);
+		}
+
+		let mut file = dummy_file(FileContent::Regular);
+		assert!(handle_flags(&mut file, open_file::O_RDWR, &AccessProfile::KERNEL).is_ok());
+	}
+}
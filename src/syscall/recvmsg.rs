@@ -0,0 +1,222 @@
+//! The `recvmsg` system call receives a message on a socket, along with its ancillary data.
+
+use crate::errno::Errno;
+use crate::file::buffer;
+use crate::file::buffer::socket::Socket;
+use crate::file::buffer::socket::SCM_TIMESTAMP;
+use crate::file::buffer::socket::SOL_SOCKET;
+use crate::limits;
+use crate::process::iovec::IOVec;
+use crate::process::mem_space::ptr::SyscallPtr;
+use crate::process::mem_space::ptr::SyscallSlice;
+use crate::process::Process;
+use crate::time::unit::Timeval;
+use crate::util;
+use crate::util::container::vec::Vec;
+use core::any::Any;
+use core::cmp::min;
+use core::ffi::c_int;
+use core::ffi::c_void;
+use core::mem::size_of;
+use macros::syscall;
+
+/// Message flag: The datagram was larger than the buffer and has been truncated.
+const MSG_TRUNC: c_int = 0x20;
+/// Message flag: The control data did not fit in the buffer and has been truncated.
+const MSG_CTRUNC: c_int = 0x08;
+
+/// Structure describing a message to be received.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MsgHdr {
+	/// The buffer receiving the address of the sender.
+	msg_name: *mut c_void,
+	/// The size of `msg_name`, updated with the size of the address.
+	msg_namelen: u32,
+	/// The I/O vector receiving the data.
+	msg_iov: *mut IOVec,
+	/// The number of entries in `msg_iov`.
+	msg_iovlen: usize,
+	/// The buffer receiving control messages.
+	msg_control: *mut c_void,
+	/// The size of `msg_control`, updated with the size of the control messages.
+	msg_controllen: usize,
+	/// Flags on the received message.
+	msg_flags: c_int,
+}
+
+/// The header of a control message.
+#[repr(C)]
+struct CmsgHdr {
+	/// The length of the control message, including the header.
+	cmsg_len: usize,
+	/// The level (protocol) of the control message.
+	cmsg_level: c_int,
+	/// The type of the control message.
+	cmsg_type: c_int,
+}
+
+/// Rounds `len` up to the alignment of control messages and their data (`CMSG_ALIGN`).
+const fn cmsg_align(len: usize) -> usize {
+	len.next_multiple_of(size_of::<usize>())
+}
+
+/// Writes a `SCM_TIMESTAMP` control message holding `timestamp` into `control`.
+///
+/// If the buffer is too small, nothing is written.
+///
+/// The function returns the number of bytes written and whether the message has been truncated.
+fn write_timestamp(control: &mut [u8], timestamp: &Timeval) -> (usize, bool) {
+	let hdr_len = cmsg_align(size_of::<CmsgHdr>());
+	let data_len = size_of::<Timeval>();
+	let len = cmsg_align(hdr_len + data_len);
+	let Some(control) = control.get_mut(..len) else {
+		return (0, true);
+	};
+
+	let hdr = CmsgHdr {
+		cmsg_len: hdr_len + data_len,
+		cmsg_level: SOL_SOCKET,
+		cmsg_type: SCM_TIMESTAMP,
+	};
+	control.fill(0);
+	control[..size_of::<CmsgHdr>()].copy_from_slice(util::as_slice(&hdr));
+	control[hdr_len..(hdr_len + data_len)].copy_from_slice(util::as_slice(timestamp));
+	(len, false)
+}
+
+// TODO implement flags
+
+#[syscall]
+pub fn recvmsg(sockfd: c_int, msg: SyscallPtr<MsgHdr>, _flags: c_int) -> Result<i32, Errno> {
+	if sockfd < 0 {
+		return Err(errno!(EBADF));
+	}
+
+	let proc_mutex = Process::current_assert();
+	let proc = proc_mutex.lock();
+
+	// Get socket
+	let fds_mutex = proc.get_fds().unwrap();
+	let fds = fds_mutex.lock();
+	let fd = fds.get_fd(sockfd as _).ok_or_else(|| errno!(EBADF))?;
+	let open_file_mutex = fd.get_open_file();
+	let open_file = open_file_mutex.lock();
+	let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
+	let mut sock = sock_mutex.lock();
+	let sock = (&mut *sock as &mut dyn Any)
+		.downcast_mut::<Socket>()
+		.ok_or_else(|| errno!(ENOTSOCK))?;
+
+	let mem_space = proc.get_mem_space().unwrap();
+	let mut mem_space_guard = mem_space.lock();
+	let mut hdr = *msg.get(&mem_space_guard)?.ok_or_else(|| errno!(EFAULT))?;
+	if hdr.msg_iovlen > limits::IOV_MAX {
+		return Err(errno!(EMSGSIZE));
+	}
+	let iov = {
+		let iov_ptr = SyscallSlice::<IOVec>::from(hdr.msg_iov as usize);
+		let iov_slice = iov_ptr
+			.get(&mem_space_guard, hdr.msg_iovlen)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		let mut iov = Vec::new();
+		iov.extend_from_slice(iov_slice)?;
+		iov
+	};
+
+	// Receive the message
+	let total_len = iov
+		.iter()
+		.fold(0usize, |total, i| total.saturating_add(i.iov_len));
+	// TODO block until data arrives, unless non-blocking
+	let mut buf = crate::vec![0u8; min(total_len, sock.get_data_len())]?;
+	let received = sock.recv_msg(&mut buf)?;
+
+	// Scatter the data
+	let mut off = 0;
+	for i in iov.iter().filter(|i| i.iov_len > 0) {
+		if off >= received.len {
+			break;
+		}
+		let len = min(i.iov_len, received.len - off);
+		let ptr = SyscallSlice::<u8>::from(i.iov_base as usize);
+		let slice = ptr
+			.get_mut(&mut mem_space_guard, len)?
+			.ok_or_else(|| errno!(EFAULT))?;
+		slice.copy_from_slice(&buf[off..(off + len)]);
+		off += len;
+	}
+
+	// Write the address of the sender
+	let mut namelen = 0;
+	if let Some(src) = &received.src {
+		let mut addr = crate::vec![0u8; sock.desc().domain.get_sockaddr_len()]?;
+		namelen = src.to_bytes(&mut addr)?;
+		let len = min(namelen, hdr.msg_namelen as usize);
+		let name = SyscallSlice::<u8>::from(hdr.msg_name as usize);
+		if let Some(name) = name.get_mut(&mut mem_space_guard, len)? {
+			name.copy_from_slice(&addr[..len]);
+		}
+	}
+	hdr.msg_namelen = namelen as _;
+
+	// Write control messages
+	hdr.msg_flags = 0;
+	let mut controllen = 0;
+	if let Some(timestamp) = &received.timestamp {
+		let control = SyscallSlice::<u8>::from(hdr.msg_control as usize);
+		let truncated = match control.get_mut(&mut mem_space_guard, hdr.msg_controllen)? {
+			Some(control) => {
+				let (len, truncated) = write_timestamp(control, timestamp);
+				controllen = len;
+				truncated
+			}
+			None => true,
+		};
+		if truncated {
+			hdr.msg_flags |= MSG_CTRUNC;
+		}
+	}
+	hdr.msg_controllen = controllen;
+	if received.truncated {
+		hdr.msg_flags |= MSG_TRUNC;
+	}
+
+	*msg.get_mut(&mut mem_space_guard)?
+		.ok_or_else(|| errno!(EFAULT))? = hdr;
+	Ok(received.len as _)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn recvmsg_write_timestamp() {
+		let timestamp = Timeval {
+			tv_sec: 42,
+			tv_usec: 1337,
+		};
+		let hdr_len = cmsg_align(size_of::<CmsgHdr>());
+		let len = cmsg_align(hdr_len + size_of::<Timeval>());
+
+		let mut control = [0xffu8; 64];
+		assert_eq!(write_timestamp(&mut control, &timestamp), (len, false));
+		let cmsg_len = usize::from_ne_bytes(control[..size_of::<usize>()].try_into().unwrap());
+		assert_eq!(cmsg_len, hdr_len + size_of::<Timeval>());
+		let off = size_of::<usize>();
+		let level = c_int::from_ne_bytes(control[off..(off + 4)].try_into().unwrap());
+		let type_ = c_int::from_ne_bytes(control[(off + 4)..(off + 8)].try_into().unwrap());
+		assert_eq!((level, type_), (SOL_SOCKET, SCM_TIMESTAMP));
+		assert_eq!(
+			&control[hdr_len..(hdr_len + size_of::<Timeval>())],
+			util::as_slice(&timestamp)
+		);
+		// Bytes past the message are left untouched
+		assert!(control[len..].iter().all(|b| *b == 0xff));
+
+		// Truncated
+		let mut control = [0u8; 8];
+		assert_eq!(write_timestamp(&mut control, &timestamp), (0, true));
+	}
+}
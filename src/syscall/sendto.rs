@@ -38,18 +38,25 @@ pub fn sendto(
 	let open_file = open_file_mutex.lock();
 	let sock_mutex = buffer::get(open_file.get_location()).ok_or_else(|| errno!(ENOENT))?;
 	let mut sock = sock_mutex.lock();
-	let _sock = (&mut *sock as &mut dyn Any)
+	let sock = (&mut *sock as &mut dyn Any)
 		.downcast_mut::<Socket>()
 		.ok_or_else(|| errno!(ENOTSOCK))?;
 
 	// Get slices
 	let mem_space = proc.get_mem_space().unwrap();
 	let mem_space_guard = mem_space.lock();
-	let _buf_slice = buf.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
-	let _dest_addr_slice = dest_addr
-		.get(&mem_space_guard, addrlen as _)?
-		.ok_or(errno!(EFAULT))?;
+	let buf_slice = buf.get(&mem_space_guard, len)?.ok_or(errno!(EFAULT))?;
+	// Without a destination address, the socket's peer is used
+	let dest_addr_slice = if addrlen > 0 {
+		Some(
+			dest_addr
+				.get(&mem_space_guard, addrlen as _)?
+				.ok_or(errno!(EFAULT))?,
+		)
+	} else {
+		None
+	};
 
-	// TODO
-	todo!()
+	let len = sock.send_to(buf_slice, dest_addr_slice)?;
+	Ok(len as _)
 }
@@ -8,13 +8,6 @@ use core::any::Any;
 use core::ffi::c_int;
 use macros::syscall;
 
-/// Shutdown receive side of the connection.
-const SHUT_RD: c_int = 0;
-/// Shutdown receive side of the connection.
-const SHUT_WR: c_int = 1;
-/// Both sides are shutdown.
-const SHUT_RDWR: c_int = 2;
-
 #[syscall]
 pub fn shutdown(sockfd: c_int, how: c_int) -> Result<i32, Errno> {
 	if sockfd < 0 {
@@ -36,16 +29,6 @@ pub fn shutdown(sockfd: c_int, how: c_int) -> Result<i32, Errno> {
 		.downcast_mut::<Socket>()
 		.ok_or_else(|| errno!(ENOTSOCK))?;
 
-	match how {
-		SHUT_RD => sock.shutdown_receive(),
-		SHUT_WR => sock.shutdown_transmit(),
-
-		SHUT_RDWR => {
-			sock.shutdown_receive();
-			sock.shutdown_transmit();
-		}
-
-		_ => return Err(errno!(EINVAL)),
-	}
+	sock.shutdown(how)?;
 	Ok(0)
 }
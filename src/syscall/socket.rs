@@ -1,6 +1,5 @@
 //! The `socket` system call allows to create a socket.
 
-use crate::errno;
 use crate::errno::Errno;
 use crate::file::buffer;
 use crate::file::buffer::socket::Socket;
@@ -22,18 +21,13 @@ pub fn socket(domain: c_int, r#type: c_int, protocol: c_int) -> Result<i32, Errn
 
 	let sock_domain = SocketDomain::try_from(domain as u32)?;
 	let sock_type = SocketType::try_from(r#type as u32)?;
-	if !proc.access_profile.can_use_sock_domain(&sock_domain)
-		|| !proc.access_profile.can_use_sock_type(&sock_type)
-	{
-		return Err(errno!(EACCES));
-	}
 	let desc = SocketDesc {
 		domain: sock_domain,
 		type_: sock_type,
 		protocol,
 	};
 
-	let sock = Socket::new(desc)?;
+	let sock = Socket::new(desc, &proc.access_profile)?;
 
 	// Get file
 	let loc = buffer::register(None, sock)?;
@@ -32,18 +32,13 @@ pub fn socketpair(
 
 	let sock_domain = SocketDomain::try_from(domain as u32)?;
 	let sock_type = SocketType::try_from(r#type as u32)?;
-	if !proc.access_profile.can_use_sock_domain(&sock_domain)
-		|| !proc.access_profile.can_use_sock_type(&sock_type)
-	{
-		return Err(errno!(EACCES));
-	}
 	let desc = SocketDesc {
 		domain: sock_domain,
 		type_: sock_type,
 		protocol,
 	};
 
-	let sock = Socket::new(desc)?;
+	let sock = Socket::new(desc, &proc.access_profile)?;
 	let loc = buffer::register(None, sock)?;
 	let file = vfs::get_file_by_location(&loc)?;
 
@@ -8,6 +8,7 @@ use crate::file::vfs;
 use crate::file::File;
 use crate::file::FileContent;
 use crate::file::Mode;
+use crate::memory;
 use crate::process::mem_space::ptr::SyscallString;
 use crate::process::regs::Regs;
 use crate::process::scheduler;
@@ -18,7 +19,9 @@ use crate::util::container::vec::Vec;
 use crate::util::lock::Mutex;
 use crate::util::lock::MutexGuard;
 use crate::util::ptr::arc::Arc;
+use core::ffi::c_void;
 use core::mem::size_of;
+use core::num::NonZeroUsize;
 
 /// Returns the absolute path according to the process's current working
 /// directory.
@@ -239,6 +242,23 @@ pub fn create_file_at(
 	vfs::create_file(&mut parent, name, &ap, mode, content)
 }
 
+/// Checks that the range of `pages` pages beginning at `addr` lies entirely in userspace.
+///
+/// The range may end exactly at the end of userspace.
+///
+/// If the range overflows or reaches kernel space, the function returns `EINVAL`.
+pub fn check_user_range(addr: *const c_void, pages: NonZeroUsize) -> EResult<()> {
+	let end = pages
+		.get()
+		.checked_mul(memory::PAGE_SIZE)
+		.and_then(|len| (addr as usize).checked_add(len))
+		.ok_or_else(|| errno!(EINVAL))?;
+	if end > memory::PROCESS_END as usize {
+		return Err(errno!(EINVAL));
+	}
+	Ok(())
+}
+
 /// Updates the execution flow of the current process according to its state.
 ///
 /// When the state of the current process has been changed, execution may not
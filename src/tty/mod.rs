@@ -13,6 +13,7 @@ use crate::device::serial;
 use crate::errno::Errno;
 use crate::file::blocking::BlockHandler;
 use crate::memory::vmem;
+use crate::panic;
 use crate::process::pid::Pid;
 use crate::process::signal::Signal;
 use crate::process::Process;
@@ -454,8 +455,11 @@ impl TTY {
 	/// Writes string `buffer` to TTY.
 	pub fn write(&mut self, buffer: &[u8]) {
 		// TODO Add a compilation and/or runtime option for this
-		if let Some(serial) = serial::get(serial::COM1) {
-			serial.lock().write(buffer);
+		// During a panic, the report may already be written to the port directly
+		if !panic::owns_serial() {
+			if let Some(serial) = serial::get(serial::COM1) {
+				serial.lock().write(buffer);
+			}
 		}
 
 		let mut i = 0;
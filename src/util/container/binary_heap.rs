@@ -0,0 +1,253 @@
+//! A binary heap is a priority queue, giving access to its greatest element in constant time,
+//! and allowing to insert and remove elements in logarithmic time.
+//!
+//! The heap is a max-heap. To get the smallest element first (for example, the earliest
+//! deadline), wrap the elements in [`core::cmp::Reverse`].
+
+use super::vec;
+use super::vec::Vec;
+use crate::errno::AllocResult;
+use crate::util::AllocError;
+use crate::util::TryClone;
+use core::fmt;
+
+/// A priority queue implemented with a binary heap, stored in a [`Vec`].
+///
+/// For each element at index `i`, the children are at `2 * i + 1` and `2 * i + 2`, and are not
+/// greater than it.
+pub struct BinaryHeap<T: Ord> {
+	/// The elements, in heap order.
+	data: Vec<T>,
+}
+
+impl<T: Ord> Default for BinaryHeap<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Ord> BinaryHeap<T> {
+	/// Creates a new empty heap.
+	pub const fn new() -> Self {
+		Self {
+			data: Vec::new(),
+		}
+	}
+
+	/// Creates a new empty heap with enough room to hold at least `capacity` elements without
+	/// growing.
+	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
+		Ok(Self {
+			data: Vec::with_capacity(capacity)?,
+		})
+	}
+
+	/// Returns the number of elements in the heap.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	/// Tells whether the heap is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	/// Moves the element at index `i` up until its parent is not smaller.
+	fn sift_up(&mut self, mut i: usize) {
+		while i > 0 {
+			let parent = (i - 1) / 2;
+			if self.data[i] <= self.data[parent] {
+				break;
+			}
+			self.data.swap(i, parent);
+			i = parent;
+		}
+	}
+
+	/// Moves the element at index `i` down until none of its children is greater.
+	fn sift_down(&mut self, mut i: usize) {
+		let len = self.data.len();
+		loop {
+			let left = 2 * i + 1;
+			if left >= len {
+				break;
+			}
+			let right = left + 1;
+			let child = if right < len && self.data[right] > self.data[left] {
+				right
+			} else {
+				left
+			};
+			if self.data[i] >= self.data[child] {
+				break;
+			}
+			self.data.swap(i, child);
+			i = child;
+		}
+	}
+
+	/// Restores the heap order of the whole heap.
+	fn rebuild(&mut self) {
+		for i in (0..(self.data.len() / 2)).rev() {
+			self.sift_down(i);
+		}
+	}
+
+	/// Returns a reference to the greatest element of the heap.
+	///
+	/// If the heap is empty, the function returns `None`.
+	#[inline]
+	pub fn peek(&self) -> Option<&T> {
+		self.data.first()
+	}
+
+	/// Inserts the element `val` into the heap.
+	pub fn push(&mut self, val: T) -> AllocResult<()> {
+		self.data.push(val)?;
+		self.sift_up(self.data.len() - 1);
+		Ok(())
+	}
+
+	/// Removes the greatest element from the heap and returns it.
+	///
+	/// If the heap is empty, the function returns `None`.
+	pub fn pop(&mut self) -> Option<T> {
+		let last = self.data.len().checked_sub(1)?;
+		self.data.swap(0, last);
+		let val = self.data.pop();
+		self.sift_down(0);
+		val
+	}
+
+	/// Retains only the elements for which the predicate `f` returns `true`.
+	pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+		let len = self.data.len();
+		self.data.retain(|e| f(e));
+		if self.data.len() != len {
+			self.rebuild();
+		}
+	}
+
+	/// Returns an iterator on the elements of the heap, in no particular order.
+	#[inline]
+	pub fn iter(&self) -> vec::VecIterator<'_, T> {
+		self.data.iter()
+	}
+
+	/// Removes all the elements from the heap.
+	#[inline]
+	pub fn clear(&mut self) {
+		self.data.clear();
+	}
+
+	/// Returns the underlying vector, in no particular order.
+	#[inline]
+	pub fn into_vec(self) -> Vec<T> {
+		self.data
+	}
+
+	/// Returns the underlying vector, sorted in ascending order.
+	pub fn into_sorted_vec(mut self) -> Vec<T> {
+		self.data.sort_unstable();
+		self.data
+	}
+}
+
+impl<T: Ord> From<Vec<T>> for BinaryHeap<T> {
+	/// Turns the vector into a heap, in linear time.
+	fn from(data: Vec<T>) -> Self {
+		let mut heap = Self {
+			data,
+		};
+		heap.rebuild();
+		heap
+	}
+}
+
+impl<T: Ord + TryClone<Error = E>, E: From<AllocError>> TryClone for BinaryHeap<T> {
+	type Error = E;
+
+	fn try_clone(&self) -> Result<Self, Self::Error> {
+		Ok(Self {
+			data: self.data.try_clone()?,
+		})
+	}
+}
+
+impl<T: Ord + fmt::Debug> fmt::Debug for BinaryHeap<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use core::cmp::Reverse;
+
+	/// Returns pseudo-random values.
+	fn values(count: usize) -> Vec<u32> {
+		let mut values = Vec::new();
+		let mut seed = 1u32;
+		for _ in 0..count {
+			seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+			values.push((seed >> 16) % 1000).unwrap();
+		}
+		values
+	}
+
+	#[test_case]
+	fn binary_heap_push_pop() {
+		let mut heap = BinaryHeap::new();
+		assert!(heap.is_empty());
+		assert_eq!(heap.peek(), None);
+		assert_eq!(heap.pop(), None);
+
+		let mut sorted = values(500);
+		for v in sorted.iter() {
+			heap.push(*v).unwrap();
+		}
+		assert_eq!(heap.len(), 500);
+		sorted.sort_unstable();
+		for v in sorted.iter().rev() {
+			assert_eq!(heap.peek(), Some(v));
+			assert_eq!(heap.pop(), Some(*v));
+		}
+		assert!(heap.is_empty());
+	}
+
+	#[test_case]
+	fn binary_heap_min() {
+		let mut heap = BinaryHeap::new();
+		for v in [5, 1, 8, 3, 9, 2] {
+			heap.push(Reverse(v)).unwrap();
+		}
+		assert_eq!(heap.pop(), Some(Reverse(1)));
+		assert_eq!(heap.pop(), Some(Reverse(2)));
+		heap.push(Reverse(0)).unwrap();
+		assert_eq!(heap.peek(), Some(&Reverse(0)));
+	}
+
+	#[test_case]
+	fn binary_heap_from_vec() {
+		let heap = BinaryHeap::from(values(100));
+		let mut expected = values(100);
+		expected.sort_unstable();
+		assert_eq!(heap.into_sorted_vec(), expected);
+	}
+
+	#[test_case]
+	fn binary_heap_retain() {
+		let mut heap = BinaryHeap::from(values(200));
+		heap.retain(|v| v % 3 == 0);
+		assert!(heap.iter().all(|v| v % 3 == 0));
+		let mut prev = u32::MAX;
+		while let Some(v) = heap.pop() {
+			assert_eq!(v % 3, 0);
+			assert!(v <= prev);
+			prev = v;
+		}
+	}
+}
@@ -4,14 +4,21 @@ use crate::errno::AllocResult;
 use crate::util::bit_size_of;
 use crate::util::container::vec::Vec;
 use crate::util::math::ceil_div;
+use crate::util::AllocError;
 use crate::util::TryClone;
+use core::cmp::min;
+use core::ops::Range;
 
 /// A bitfield is a data structure meant to contain only boolean values.
 ///
 /// The size of the bitfield is specified at initialization.
-pub struct Bitfield {
+///
+/// Bit `i` is stored in byte `i / 8`, at position `i % 8`. The bits are stored in `B`, which
+/// can be either allocated on the heap with [`Bitfield::new`] or fixed-size with
+/// [`Bitfield::new_array`]. Searching for a bit scans the bitfield one word at a time.
+pub struct Bitfield<B: AsRef<[u8]> = Vec<u8>> {
 	/// The bitfield's data.
-	data: Vec<u8>,
+	data: B,
 	/// The number of bits in the bitfield.
 	len: usize,
 }
@@ -27,6 +34,32 @@ impl Bitfield {
 		};
 		Ok(bitfield)
 	}
+}
+
+impl<const N: usize> Bitfield<[u8; N]> {
+	/// Creates a new bitfield with the given number of bits `len`, stored in an array.
+	///
+	/// If `len` exceeds the number of bits of the array, the function panics.
+	pub const fn new_array(len: usize) -> Self {
+		assert!(len <= N * u8::BITS as usize);
+		Self {
+			data: [0; N],
+			len,
+		}
+	}
+}
+
+impl<B: AsRef<[u8]>> Bitfield<B> {
+	/// Creates a bitfield of `len` bits, stored in the given buffer.
+	///
+	/// If `len` exceeds the number of bits of the buffer, the function panics.
+	pub fn from_buffer(data: B, len: usize) -> Self {
+		assert!(len <= data.as_ref().len() * u8::BITS as usize);
+		Self {
+			data,
+			len,
+		}
+	}
 
 	/// Returns the number of bit in the bitfield.
 	#[inline]
@@ -34,16 +67,16 @@ impl Bitfield {
 		self.len
 	}
 
-	/// Returns an immutable reference to a slice containing the bitfield.
+	/// Tells whether the bitfield contains no bit.
 	#[inline]
-	pub fn as_slice(&self) -> &[u8] {
-		self.data.as_slice()
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
 	}
 
-	/// Returns a mutable reference to a slice containing the bitfield.
+	/// Returns an immutable reference to a slice containing the bitfield.
 	#[inline]
-	pub fn as_slice_mut(&mut self) -> &mut [u8] {
-		self.data.as_mut_slice()
+	pub fn as_slice(&self) -> &[u8] {
+		self.data.as_ref()
 	}
 
 	/// Returns the size of the memory region of the bitfield in bytes.
@@ -55,28 +88,31 @@ impl Bitfield {
 	/// Tells whether bit `index` is set.
 	#[inline]
 	pub fn is_set(&self, index: usize) -> bool {
-		let unit = self.data[index / u8::BITS as usize];
+		let unit = self.data.as_ref()[index / u8::BITS as usize];
 		(unit >> (index % u8::BITS as usize)) & 1 == 1
 	}
 
-	/// Sets bit `index`.
-	pub fn set(&mut self, index: usize) {
-		debug_assert!(index < self.len);
-
-		if !self.is_set(index) {
-			let unit = &mut self.data[index / u8::BITS as usize];
-			*unit |= 1 << (index % u8::BITS as usize);
-		}
-	}
-
-	/// Clears bit `index`.
-	pub fn clear(&mut self, index: usize) {
-		debug_assert!(index < self.len);
-
-		if self.is_set(index) {
-			let unit = &mut self.data[index / u8::BITS as usize];
-			*unit &= !(1 << (index % u8::BITS as usize));
+	/// Finds the first bit at or after `from` whose state is `set`.
+	fn find_next(&self, from: usize, set: bool) -> Option<usize> {
+		let data = self.data.as_ref();
+		// Inverting the bits allows to search for a set bit in both cases
+		let invert = if set { 0 } else { !0 };
+		let mut i = from;
+		while i < self.len {
+			// Read a word from the byte containing bit `i`
+			let off = i / u8::BITS as usize;
+			let end = min(off + 8, data.len());
+			let mut word = [0; 8];
+			word[..(end - off)].copy_from_slice(&data[off..end]);
+			let word = (u64::from_le_bytes(word) ^ invert) >> (i % u8::BITS as usize);
+			if word != 0 {
+				// Bits past the end may be found when looking for a clear bit
+				let index = i + word.trailing_zeros() as usize;
+				return (index < self.len).then_some(index);
+			}
+			i = (off + 8) * u8::BITS as usize;
 		}
+		None
 	}
 
 	/// Finds a set bit.
@@ -84,9 +120,9 @@ impl Bitfield {
 	/// The function returns the offset to the bit.
 	///
 	/// If none is found, the function returns `None`.
+	#[inline]
 	pub fn find_set(&self) -> Option<usize> {
-		// TODO optimize (using mask)
-		(0..self.len).find(|i| self.is_set(*i))
+		self.find_next_set(0)
 	}
 
 	/// Finds a clear bit.
@@ -94,23 +130,29 @@ impl Bitfield {
 	/// The function returns the offset to the bit.
 	///
 	/// If none is found, the function returns `None`.
+	#[inline]
 	pub fn find_clear(&self) -> Option<usize> {
-		// TODO optimize (using mask)
-		(0..self.len).find(|i| !self.is_set(*i))
+		self.find_next_clear(0)
 	}
 
-	/// Clears every elements in the bitfield.
-	pub fn clear_all(&mut self) {
-		self.data.fill(0);
+	/// Finds the first set bit at or after offset `from`.
+	///
+	/// If none is found, the function returns `None`.
+	#[inline]
+	pub fn find_next_set(&self, from: usize) -> Option<usize> {
+		self.find_next(from, true)
 	}
 
-	/// Clears every elements in the bitfield.
-	pub fn set_all(&mut self) {
-		self.data.fill(!0);
+	/// Finds the first clear bit at or after offset `from`.
+	///
+	/// If none is found, the function returns `None`.
+	#[inline]
+	pub fn find_next_clear(&self, from: usize) -> Option<usize> {
+		self.find_next(from, false)
 	}
 
 	/// Returns an immutable iterator over the bitfield.
-	pub fn iter(&self) -> BitfieldIterator {
+	pub fn iter(&self) -> BitfieldIterator<B> {
 		BitfieldIterator {
 			bitfield: self,
 			cursor: 0,
@@ -118,7 +160,85 @@ impl Bitfield {
 	}
 }
 
-impl TryClone for Bitfield {
+impl<B: AsRef<[u8]> + AsMut<[u8]>> Bitfield<B> {
+	/// Returns a mutable reference to a slice containing the bitfield.
+	#[inline]
+	pub fn as_slice_mut(&mut self) -> &mut [u8] {
+		self.data.as_mut()
+	}
+
+	/// Sets bit `index`.
+	pub fn set(&mut self, index: usize) {
+		debug_assert!(index < self.len);
+
+		if !self.is_set(index) {
+			let unit = &mut self.data.as_mut()[index / u8::BITS as usize];
+			*unit |= 1 << (index % u8::BITS as usize);
+		}
+	}
+
+	/// Clears bit `index`.
+	pub fn clear(&mut self, index: usize) {
+		debug_assert!(index < self.len);
+
+		if self.is_set(index) {
+			let unit = &mut self.data.as_mut()[index / u8::BITS as usize];
+			*unit &= !(1 << (index % u8::BITS as usize));
+		}
+	}
+
+	/// Sets the state of every bit in `range` to `set`.
+	fn fill_range(&mut self, range: Range<usize>, set: bool) {
+		debug_assert!(range.end <= self.len);
+
+		let bits = u8::BITS as usize;
+		let data = self.data.as_mut();
+		let mut i = range.start;
+		while i < range.end {
+			let off = i / bits;
+			let begin = i % bits;
+			let end = min(range.end - off * bits, bits);
+			if begin == 0 && end == bits {
+				// Fill whole bytes at once
+				let count = (range.end - i) / bits;
+				data[off..(off + count)].fill(if set { !0 } else { 0 });
+				i += count * bits;
+				continue;
+			}
+			let mask = ((1u16 << end) - (1u16 << begin)) as u8;
+			if set {
+				data[off] |= mask;
+			} else {
+				data[off] &= !mask;
+			}
+			i = (off + 1) * bits;
+		}
+	}
+
+	/// Sets every bit in `range`.
+	#[inline]
+	pub fn set_range(&mut self, range: Range<usize>) {
+		self.fill_range(range, true);
+	}
+
+	/// Clears every bit in `range`.
+	#[inline]
+	pub fn clear_range(&mut self, range: Range<usize>) {
+		self.fill_range(range, false);
+	}
+
+	/// Clears every elements in the bitfield.
+	pub fn clear_all(&mut self) {
+		self.data.as_mut().fill(0);
+	}
+
+	/// Clears every elements in the bitfield.
+	pub fn set_all(&mut self) {
+		self.data.as_mut().fill(!0);
+	}
+}
+
+impl<B: AsRef<[u8]> + TryClone<Error = AllocError>> TryClone for Bitfield<B> {
 	fn try_clone(&self) -> AllocResult<Self> {
 		Ok(Self {
 			data: self.data.try_clone()?,
@@ -128,14 +248,14 @@ impl TryClone for Bitfield {
 }
 
 /// An immutable iterator over a bitfield.
-pub struct BitfieldIterator<'b> {
+pub struct BitfieldIterator<'b, B: AsRef<[u8]> = Vec<u8>> {
 	/// The bitfield.
-	bitfield: &'b Bitfield,
+	bitfield: &'b Bitfield<B>,
 	/// The cursor of the iterator.
 	cursor: usize,
 }
 
-impl<'b> Iterator for BitfieldIterator<'b> {
+impl<'b, B: AsRef<[u8]>> Iterator for BitfieldIterator<'b, B> {
 	type Item = bool;
 
 	fn next(&mut self) -> Option<Self::Item> {
@@ -194,5 +314,72 @@ mod test {
 		}
 	}
 
-	// TODO Write more tests
+	#[test_case]
+	fn bitfield_find() {
+		let mut bitfield = Bitfield::new(200).unwrap();
+		assert_eq!(bitfield.find_set(), None);
+		assert_eq!(bitfield.find_clear(), Some(0));
+
+		for i in [3, 64, 65, 130, 199] {
+			bitfield.set(i);
+		}
+		assert_eq!(bitfield.find_set(), Some(3));
+		assert_eq!(bitfield.find_next_set(3), Some(3));
+		assert_eq!(bitfield.find_next_set(4), Some(64));
+		assert_eq!(bitfield.find_next_set(66), Some(130));
+		assert_eq!(bitfield.find_next_set(131), Some(199));
+		assert_eq!(bitfield.find_next_set(200), None);
+
+		bitfield.set_all();
+		assert_eq!(bitfield.find_clear(), None);
+		bitfield.clear(150);
+		assert_eq!(bitfield.find_clear(), Some(150));
+		assert_eq!(bitfield.find_next_clear(151), None);
+
+		// The padding bits past the end are never returned
+		let mut bitfield = Bitfield::new(42).unwrap();
+		assert_eq!(bitfield.find_next_clear(41), Some(41));
+		assert_eq!(bitfield.find_next_clear(42), None);
+		bitfield.set_all();
+		bitfield.clear(41);
+		assert_eq!(bitfield.find_next_set(41), None);
+	}
+
+	#[test_case]
+	fn bitfield_range() {
+		let mut bitfield = Bitfield::new(100).unwrap();
+		for (begin, end) in [
+			(0, 0),
+			(3, 5),
+			(7, 9),
+			(8, 16),
+			(10, 90),
+			(0, 100),
+			(99, 100),
+		] {
+			bitfield.set_range(begin..end);
+			for i in 0..100 {
+				assert_eq!(bitfield.is_set(i), (begin..end).contains(&i));
+			}
+			bitfield.clear_range(begin..end);
+			assert_eq!(bitfield.find_set(), None);
+		}
+
+		bitfield.set_all();
+		bitfield.clear_range(5..77);
+		assert_eq!(bitfield.find_clear(), Some(5));
+		assert_eq!(bitfield.find_next_set(5), Some(77));
+	}
+
+	#[test_case]
+	fn bitfield_array() {
+		let mut bitfield = Bitfield::<[u8; 8]>::new_array(64);
+		assert_eq!(bitfield.len(), 64);
+		bitfield.set_range(0..63);
+		assert_eq!(bitfield.find_clear(), Some(63));
+
+		let data = [0xff, 0xff, 0b1110_1111];
+		let bitfield = Bitfield::from_buffer(&data[..], 24);
+		assert_eq!(bitfield.find_clear(), Some(20));
+	}
 }
@@ -1,25 +1,149 @@
 //! A hashmap is a data structure that stores key/value pairs into buckets and
 //! uses the hash of the key to quickly get the bucket storing the value.
 
+use super::vec;
 use super::vec::Vec;
 use crate::errno::AllocResult;
+use crate::errno::CollectResult;
+use crate::memory::malloc::AllocTag;
+use crate::util::math;
 use crate::util::AllocError;
 use crate::util::TryClone;
 use core::borrow::Borrow;
 use core::fmt;
+use core::hash::BuildHasher;
 use core::hash::Hash;
 use core::hash::Hasher;
 use core::iter::FusedIterator;
 use core::iter::TrustedLen;
+use core::mem;
+use core::mem::size_of;
 use core::mem::size_of_val;
 use core::ops::Index;
 use core::ops::IndexMut;
+use core::slice;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
 
 /// The default number of buckets in a hashmap.
+///
+/// The number of buckets is a power of two, so that the index of a bucket can be computed with a
+/// mask instead of a modulo. The only exception is [`HashMap::reserve_exact`].
 const DEFAULT_BUCKETS_COUNT: usize = 64;
 
+/// The load configuration of a hashmap, telling when it grows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LoadConfig {
+	/// The maximum load factor, as a `(numerator, denominator)` fraction of the number of
+	/// elements per bucket. When inserting beyond this load, the number of buckets grows to the
+	/// next power of two.
+	max_load: (usize, usize),
+	/// The minimum load factor, as a fraction in the same form. When removing elements brings
+	/// the load below this factor, the number of buckets is reduced. If `None`, the hash map
+	/// never shrinks by itself.
+	min_load: Option<(usize, usize)>,
+}
+
+/// Returns `n` multiplied by the fraction `ratio`, rounded down.
+fn mul_ratio((num, den): (usize, usize), n: usize) -> usize {
+	// Divide first to avoid overflowing with large values of `n`
+	n / den * num + n % den * num / den
+}
+
+impl LoadConfig {
+	/// The default configuration, with a maximum load factor of `3/4`.
+	pub const DEFAULT: Self = Self {
+		max_load: (3, 4),
+		min_load: None,
+	};
+
+	/// Creates a configuration with a maximum load factor of `num/den` elements per bucket.
+	///
+	/// A higher load factor makes the hash map denser, at the cost of longer lookups.
+	///
+	/// If either number is zero, the function returns `None`.
+	pub const fn new(num: usize, den: usize) -> Option<Self> {
+		if num == 0 || den == 0 {
+			return None;
+		}
+		Some(Self {
+			max_load: (num, den),
+			min_load: None,
+		})
+	}
+
+	/// Returns the configuration with a minimum load factor of `num/den` elements per bucket.
+	///
+	/// When removing elements brings the load below this factor, the hash map shrinks to release
+	/// memory.
+	///
+	/// To avoid shrinking right after growing, the minimum load factor must be less than half
+	/// the maximum load factor. Otherwise, or if either number is zero, the function returns
+	/// `None`.
+	pub const fn with_min_load(self, num: usize, den: usize) -> Option<Self> {
+		if num == 0 || den == 0 {
+			return None;
+		}
+		let (max_num, max_den) = self.max_load;
+		let min = num as u128 * max_den as u128;
+		let max = max_num as u128 * den as u128;
+		match min.checked_mul(2) {
+			Some(min) if min < max => Some(Self {
+				max_load: self.max_load,
+				min_load: Some((num, den)),
+			}),
+			_ => None,
+		}
+	}
+
+	/// Returns the number of elements `buckets_count` buckets can hold before growing.
+	fn capacity(&self, buckets_count: usize) -> usize {
+		mul_ratio(self.max_load, buckets_count)
+	}
+
+	/// Returns the number of elements below which `buckets_count` buckets are shrunk.
+	///
+	/// If the configuration has no minimum load factor, the function returns `None`.
+	fn min_len(&self, buckets_count: usize) -> Option<usize> {
+		self.min_load.map(|ratio| mul_ratio(ratio, buckets_count))
+	}
+
+	/// Returns the minimum number of buckets required to hold `capacity` elements without
+	/// growing.
+	///
+	/// If the number overflows, the function returns an error.
+	fn exact_buckets_for_capacity(&self, capacity: usize) -> AllocResult<usize> {
+		let (num, den) = self.max_load;
+		capacity
+			.checked_mul(den)
+			.map(|n| math::ceil_div(n, num).max(1))
+			.ok_or(AllocError)
+	}
+
+	/// Returns the minimum number of buckets required to hold `capacity` elements without
+	/// growing, rounded up to a power of two.
+	///
+	/// If the number overflows, the function returns an error.
+	fn buckets_for_capacity(&self, capacity: usize) -> AllocResult<usize> {
+		self.exact_buckets_for_capacity(capacity)?
+			.checked_next_power_of_two()
+			.ok_or(AllocError)
+	}
+}
+
+impl Default for LoadConfig {
+	fn default() -> Self {
+		Self::DEFAULT
+	}
+}
+
 /// Bitwise XOR hasher.
-struct XORHasher {
+///
+/// This is the hasher used by default by [`HashMap`].
+#[derive(Default)]
+pub struct XORHasher {
 	/// The currently stored value.
 	value: u64,
 	/// The offset byte at which the next XOR operation shall be performed.
@@ -34,6 +158,17 @@ impl XORHasher {
 			off: 0,
 		}
 	}
+
+	/// XORs the `len` bytes of `word` at once. The result is the same as writing them one by
+	/// one.
+	///
+	/// Bytes are taken in little-endian order, that is in memory order for the `from_le_bytes`
+	/// conversion done by callers.
+	fn write_word(&mut self, word: u64, len: u8) {
+		let size = size_of_val(&self.value) as u8;
+		self.value ^= word.rotate_left(self.off as u32 * 8);
+		self.off = (self.off + len) % size;
+	}
 }
 
 impl Hasher for XORHasher {
@@ -44,11 +179,248 @@ impl Hasher for XORHasher {
 		}
 	}
 
+	fn write_u32(&mut self, i: u32) {
+		let word = u32::from_le_bytes(i.to_ne_bytes());
+		self.write_word(word as _, 4);
+	}
+
+	fn write_u64(&mut self, i: u64) {
+		let word = u64::from_le_bytes(i.to_ne_bytes());
+		self.write_word(word, 8);
+	}
+
+	fn write_usize(&mut self, i: usize) {
+		let mut bytes = [0u8; 8];
+		bytes[..size_of::<usize>()].copy_from_slice(&i.to_ne_bytes());
+		self.write_word(u64::from_le_bytes(bytes), size_of::<usize>() as _);
+	}
+
 	fn finish(&self) -> u64 {
 		self.value
 	}
 }
 
+/// Builder for [`XORHasher`], the default hasher builder of [`HashMap`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct XORHasherBuilder;
+
+impl BuildHasher for XORHasherBuilder {
+	type Hasher = XORHasher;
+
+	fn build_hasher(&self) -> Self::Hasher {
+		XORHasher::new()
+	}
+}
+
+/// SipHash-1-3 hasher.
+///
+/// Contrary to [`XORHasher`], the output of this hasher cannot be predicted without knowing
+/// its keys. With random keys, this prevents from crafting keys that all land in the same
+/// bucket, which would degrade lookups to linear scans.
+///
+/// This hasher should be used for maps whose keys are controlled by userspace.
+#[derive(Clone)]
+pub struct SipHasher13 {
+	/// The internal state.
+	v: [u64; 4],
+	/// The bytes that do not yet fill a whole word, in little-endian order.
+	tail: u64,
+	/// The number of bytes in `tail`.
+	ntail: usize,
+	/// The total number of bytes written.
+	length: usize,
+}
+
+impl SipHasher13 {
+	/// Creates a new instance with the keys `k0` and `k1`.
+	pub const fn new_with_keys(k0: u64, k1: u64) -> Self {
+		Self {
+			v: [
+				k0 ^ 0x736f6d6570736575,
+				k1 ^ 0x646f72616e646f6d,
+				k0 ^ 0x6c7967656e657261,
+				k1 ^ 0x7465646279746573,
+			],
+			tail: 0,
+			ntail: 0,
+			length: 0,
+		}
+	}
+
+	/// Performs a SipRound on the state `v`.
+	#[inline]
+	fn round(v: &mut [u64; 4]) {
+		v[0] = v[0].wrapping_add(v[1]);
+		v[1] = v[1].rotate_left(13) ^ v[0];
+		v[0] = v[0].rotate_left(32);
+		v[2] = v[2].wrapping_add(v[3]);
+		v[3] = v[3].rotate_left(16) ^ v[2];
+		v[0] = v[0].wrapping_add(v[3]);
+		v[3] = v[3].rotate_left(21) ^ v[0];
+		v[2] = v[2].wrapping_add(v[1]);
+		v[1] = v[1].rotate_left(17) ^ v[2];
+		v[2] = v[2].rotate_left(32);
+	}
+
+	/// Compresses the word `m` into the state.
+	#[inline]
+	fn compress(&mut self, m: u64) {
+		self.v[3] ^= m;
+		Self::round(&mut self.v);
+		self.v[0] ^= m;
+	}
+}
+
+impl Hasher for SipHasher13 {
+	fn write(&mut self, mut bytes: &[u8]) {
+		self.length = self.length.wrapping_add(bytes.len());
+		// Complete the pending word
+		while self.ntail > 0 {
+			let Some((b, rest)) = bytes.split_first() else {
+				return;
+			};
+			self.tail |= (*b as u64) << (self.ntail * 8);
+			self.ntail = (self.ntail + 1) % 8;
+			bytes = rest;
+			if self.ntail == 0 {
+				self.compress(self.tail);
+				self.tail = 0;
+			}
+		}
+		// Whole words
+		let mut chunks = bytes.chunks_exact(8);
+		for chunk in &mut chunks {
+			self.compress(u64::from_le_bytes(chunk.try_into().unwrap()));
+		}
+		// Keep the remaining bytes for later
+		for (i, b) in chunks.remainder().iter().enumerate() {
+			self.tail |= (*b as u64) << (i * 8);
+		}
+		self.ntail = chunks.remainder().len();
+	}
+
+	fn finish(&self) -> u64 {
+		let mut v = self.v;
+		let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+		v[3] ^= b;
+		Self::round(&mut v);
+		v[0] ^= b;
+		v[2] ^= 0xff;
+		for _ in 0..3 {
+			Self::round(&mut v);
+		}
+		v[0] ^ v[1] ^ v[2] ^ v[3]
+	}
+}
+
+/// The global keys, from which the keys of each [`SipHasherBuilder`] are derived.
+///
+/// Each key is split into its low and high halves, since 64 bits atomics are not available on
+/// every architecture.
+static SIP_KEYS: [AtomicU32; 4] = [
+	AtomicU32::new(0),
+	AtomicU32::new(0),
+	AtomicU32::new(0),
+	AtomicU32::new(0),
+];
+/// Tells whether [`SIP_KEYS`] have been initialized.
+static SIP_KEYS_INIT: AtomicBool = AtomicBool::new(false);
+/// The number of [`SipHasherBuilder`] created so far, each one deriving different keys.
+static SIP_BUILDERS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the global keys from which the keys of each [`SipHasherBuilder`] are derived.
+///
+/// This function is called once, with keys drawn from the entropy pool when it is initialized.
+pub fn init_sip_keys(k0: u64, k1: u64) {
+	for (i, key) in [k0, k1].into_iter().enumerate() {
+		SIP_KEYS[i * 2].store(key as u32, Ordering::Relaxed);
+		SIP_KEYS[i * 2 + 1].store((key >> 32) as u32, Ordering::Relaxed);
+	}
+	SIP_KEYS_INIT.store(true, Ordering::Release);
+}
+
+/// Builder for [`SipHasher13`].
+///
+/// Each instance has its own keys, so that two maps do not hash the same key to the same value.
+#[derive(Clone)]
+pub struct SipHasherBuilder {
+	/// The first key.
+	k0: u64,
+	/// The second key.
+	k1: u64,
+}
+
+impl SipHasherBuilder {
+	/// Creates a new instance with keys derived from the global keys, set by [`init_sip_keys`].
+	///
+	/// Each instance gets different keys, without having to access the entropy pool.
+	///
+	/// If the global keys are not initialized yet, the function panics.
+	pub fn new() -> Self {
+		assert!(
+			SIP_KEYS_INIT.load(Ordering::Acquire),
+			"hash map keys used before initialization"
+		);
+		let key = |i: usize| {
+			let low = SIP_KEYS[i * 2].load(Ordering::Relaxed) as u64;
+			let high = SIP_KEYS[i * 2 + 1].load(Ordering::Relaxed) as u64;
+			low | (high << 32)
+		};
+		let global = Self::with_keys(key(0), key(1));
+		let id = SIP_BUILDERS_COUNT.fetch_add(1, Ordering::Relaxed);
+		Self::with_keys(global.hash_one((id, 0u8)), global.hash_one((id, 1u8)))
+	}
+
+	/// Creates a new instance with the keys `k0` and `k1`.
+	pub const fn with_keys(k0: u64, k1: u64) -> Self {
+		Self {
+			k0,
+			k1,
+		}
+	}
+}
+
+impl Default for SipHasherBuilder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl BuildHasher for SipHasherBuilder {
+	type Hasher = SipHasher13;
+
+	fn build_hasher(&self) -> Self::Hasher {
+		SipHasher13::new_with_keys(self.k0, self.k1)
+	}
+}
+
+/// Returns the hash of the key `k`, using a hasher built by `hash_builder`.
+fn hash_key<S: BuildHasher, Q: ?Sized + Hash>(hash_builder: &S, k: &Q) -> u64 {
+	hash_builder.hash_one(k)
+}
+
+/// Returns the index of the bucket for the hash `hash` in a hashmap with `buckets_count` buckets.
+///
+/// If `buckets_count` is a power of two, the index is computed with a mask. Else, it falls back
+/// to a modulo.
+fn hash_bucket_index(hash: u64, buckets_count: usize) -> usize {
+	if buckets_count.is_power_of_two() {
+		(hash & (buckets_count as u64 - 1)) as usize
+	} else {
+		(hash % buckets_count as u64) as usize
+	}
+}
+
+/// Returns the index of the bucket for the key `k` in a hashmap with `buckets_count` buckets,
+/// using a hasher built by `hash_builder`.
+fn bucket_index<S: BuildHasher, Q: ?Sized + Hash>(
+	hash_builder: &S,
+	k: &Q,
+	buckets_count: usize,
+) -> usize {
+	hash_bucket_index(hash_key(hash_builder, k), buckets_count)
+}
+
 /// A bucket is a list storing elements that match a given hash range.
 ///
 /// Since hashing function have collisions, several elements can have the same
@@ -60,10 +432,10 @@ struct Bucket<K: Eq + Hash, V> {
 }
 
 impl<K: Eq + Hash, V> Bucket<K, V> {
-	/// Creates a new instance.
-	fn new() -> Self {
+	/// Creates a new instance whose allocations are attributed to `tag`, if any.
+	fn new(tag: Option<&'static AllocTag>) -> Self {
 		Self {
-			elements: Vec::new(),
+			elements: Vec::new_tagged(tag),
 		}
 	}
 
@@ -134,7 +506,7 @@ impl<K: Eq + Hash + TryClone<Error = E>, V: TryClone<Error = E>, E: From<AllocEr
 	type Error = E;
 
 	fn try_clone(&self) -> Result<Self, Self::Error> {
-		let mut v = Vec::with_capacity(self.elements.len())?;
+		let mut v = Vec::with_capacity_tagged(self.elements.len(), self.elements.tag())?;
 		for (key, value) in self.elements.iter() {
 			v.push((key.try_clone()?, value.try_clone()?))?;
 		}
@@ -145,21 +517,52 @@ impl<K: Eq + Hash + TryClone<Error = E>, V: TryClone<Error = E>, E: From<AllocEr
 	}
 }
 
+/// Statistics on the length of lookups in a hash map.
+///
+/// Since elements with colliding hashes are chained in the same bucket, the length of a lookup
+/// is the number of elements compared before finding the key.
+///
+/// For the same reason, removals leave no tombstones that lookups would have to skip. The space
+/// they leave over is reported as empty buckets, which the hash map releases when its load drops
+/// below the minimum load factor of its [`LoadConfig`], or on [`HashMap::compact`].
+#[cfg(config_debug_debug)]
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct ProbeStats {
+	/// The number of elements compared for the longest lookup.
+	pub max: usize,
+	/// The sum of the number of elements compared for the lookup of each element. The mean
+	/// lookup length is this value divided by the number of elements.
+	pub total: usize,
+	/// The number of buckets containing no element, standing for the tombstone count of open
+	/// addressing hash maps.
+	pub empty_buckets: usize,
+}
+
 /// Structure representing a hashmap.
-#[derive(Debug)]
-pub struct HashMap<K: Eq + Hash, V> {
+///
+/// Elements whose keys fall in the same bucket are chained in it (separate chaining). Contrary to
+/// open addressing, removing an element leaves no tombstone behind, only possibly empty buckets.
+///
+/// `S` is the builder of the hashers used to hash keys.
+pub struct HashMap<K: Eq + Hash, V, S = XORHasherBuilder> {
 	/// The number of buckets in the hashmap.
 	buckets_count: usize,
 	/// The vector containing buckets.
 	buckets: Vec<Bucket<K, V>>,
+	/// The load configuration.
+	load: LoadConfig,
+	/// The builder of the hashers used to hash keys.
+	hash_builder: S,
+	/// The tag the hash map's allocations are attributed to, if any.
+	tag: Option<&'static AllocTag>,
 
 	/// The number of elements in the container.
 	len: usize,
 }
 
-impl<K: Eq + Hash, V> Default for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: Default> Default for HashMap<K, V, S> {
 	fn default() -> Self {
-		Self::new()
+		Self::with_hasher(S::default())
 	}
 }
 
@@ -176,27 +579,124 @@ impl<K: Eq + Hash, V, const N: usize> TryFrom<[(K, V); N]> for HashMap<K, V> {
 	}
 }
 
+impl<K: Eq + Hash, V> FromIterator<(K, V)> for CollectResult<HashMap<K, V>> {
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		Self(HashMap::try_from_iter(iter).map_err(|(_, e)| e))
+	}
+}
+
 impl<K: Eq + Hash, V> HashMap<K, V> {
 	/// Creates a new instance with the default number of buckets.
 	pub const fn new() -> Self {
+		Self::with_config(LoadConfig::DEFAULT)
+	}
+
+	/// Creates a new instance with the default number of buckets and the given load
+	/// configuration.
+	pub const fn with_config(load: LoadConfig) -> Self {
 		Self {
 			buckets_count: DEFAULT_BUCKETS_COUNT,
 			buckets: Vec::new(),
+			load,
+			hash_builder: XORHasherBuilder,
+			tag: None,
+
+			len: 0,
+		}
+	}
+
+	/// Creates a new instance with the default number of buckets, whose allocations are
+	/// attributed to `tag` in the allocator's statistics.
+	pub const fn new_tagged(tag: &'static AllocTag) -> Self {
+		Self {
+			buckets_count: DEFAULT_BUCKETS_COUNT,
+			buckets: Vec::new_tagged(Some(tag)),
+			load: LoadConfig::DEFAULT,
+			hash_builder: XORHasherBuilder,
+			tag: Some(tag),
 
 			len: 0,
 		}
 	}
 
-	/// Creates a new instance with the given number of buckets.
+	/// Creates a new instance with the given number of buckets, rounded up to a power of two.
+	///
+	/// If the rounded number overflows, the largest power of two is used.
 	pub const fn with_buckets(buckets_count: usize) -> Self {
+		let buckets_count = match buckets_count.checked_next_power_of_two() {
+			Some(n) => n,
+			None => 1 << (usize::BITS - 1),
+		};
 		Self {
 			buckets_count,
 			buckets: Vec::new(),
+			load: LoadConfig::DEFAULT,
+			hash_builder: XORHasherBuilder,
+			tag: None,
+
+			len: 0,
+		}
+	}
+
+	/// Creates a new instance with enough buckets to hold at least `capacity` elements without
+	/// growing.
+	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
+		let load = LoadConfig::DEFAULT;
+		let buckets_count = load.buckets_for_capacity(capacity)?;
+		debug_assert!(buckets_count.is_power_of_two());
+
+		let mut buckets = Vec::with_capacity(buckets_count)?;
+		for _ in 0..buckets_count {
+			buckets.push(Bucket::new(None))?;
+		}
+
+		Ok(Self {
+			buckets_count,
+			buckets,
+			load,
+			hash_builder: XORHasherBuilder,
+			tag: None,
+
+			len: 0,
+		})
+	}
+
+	/// Creates a new instance from the key/value pairs of `iter`.
+	///
+	/// If a memory allocation fails, the function stops and returns the entries inserted so far
+	/// along with the error. The pair that could not be inserted is dropped.
+	pub fn try_from_iter<I: IntoIterator<Item = (K, V)>>(
+		iter: I,
+	) -> Result<Self, (Self, AllocError)> {
+		let iter = iter.into_iter();
+		let mut h = Self::with_capacity(iter.size_hint().0).unwrap_or_default();
+		for (key, value) in iter {
+			if let Err(e) = h.insert(key, value) {
+				return Err((h, e));
+			}
+		}
+
+		Ok(h)
+	}
+}
+
+impl<K: Eq + Hash, V, S> HashMap<K, V, S> {
+	/// Creates a new instance with the default number of buckets, hashing keys with the hashers
+	/// built by `hash_builder`.
+	pub const fn with_hasher(hash_builder: S) -> Self {
+		Self {
+			buckets_count: DEFAULT_BUCKETS_COUNT,
+			buckets: Vec::new(),
+			load: LoadConfig::DEFAULT,
+			hash_builder,
+			tag: None,
 
 			len: 0,
 		}
 	}
+}
 
+impl<K: Eq + Hash, V, S: BuildHasher> HashMap<K, V, S> {
 	/// Returns the number of elements in the hash map.
 	#[inline]
 	pub fn len(&self) -> usize {
@@ -215,15 +715,184 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 		self.buckets_count
 	}
 
+	/// Returns the number of elements the hash map can hold before growing.
+	#[inline]
+	pub fn capacity(&self) -> usize {
+		self.load.capacity(self.buckets_count)
+	}
+
+	/// Returns the load configuration of the hash map.
+	#[inline]
+	pub fn get_load_config(&self) -> LoadConfig {
+		self.load
+	}
+
+	/// Returns the hash of the key `k`, as used by the hash map.
+	///
+	/// This allows computing the hash once to use it with [`Self::raw_entry`] and
+	/// [`Self::raw_entry_mut`].
+	#[inline]
+	pub fn hash_key<Q: ?Sized + Hash>(&self, k: &Q) -> u64 {
+		hash_key(&self.hash_builder, k)
+	}
+
+	/// Returns the builder of the hashers used to hash keys.
+	#[inline]
+	pub fn get_hasher(&self) -> &S {
+		&self.hash_builder
+	}
+
 	/// Returns the bucket index for the key `k`.
 	fn get_bucket_index<Q: ?Sized>(&self, k: &Q) -> usize
 	where
 		K: Borrow<Q>,
 		Q: Hash,
 	{
-		let mut hasher = XORHasher::new();
-		k.hash(&mut hasher);
-		(hasher.finish() % (self.buckets_count as u64)) as usize
+		bucket_index(&self.hash_builder, k, self.buckets_count)
+	}
+
+	/// Redistributes the elements of the hash map into `buckets_count` buckets.
+	///
+	/// Every allocation is performed before moving any element. Thus, on failure, the hash map is
+	/// left untouched.
+	fn rehash(&mut self, buckets_count: usize) -> AllocResult<()> {
+		// Count the elements of each new bucket to allocate them with the right capacity
+		let mut counts = crate::vec![0usize; buckets_count]?;
+		for (k, _) in self.iter() {
+			counts[bucket_index(&self.hash_builder, k, buckets_count)] += 1;
+		}
+
+		let mut buckets = Vec::with_capacity_tagged(buckets_count, self.tag)?;
+		for count in counts.iter() {
+			buckets.push(Bucket {
+				elements: Vec::with_capacity_tagged(*count, self.tag)?,
+			})?;
+		}
+
+		for b in self.buckets.iter_mut() {
+			while let Some((k, v)) = b.elements.pop() {
+				let index = bucket_index(&self.hash_builder, &k, buckets_count);
+				// Cannot fail since the bucket has been allocated with enough capacity
+				buckets[index].elements.push((k, v)).unwrap();
+			}
+		}
+
+		self.buckets_count = buckets_count;
+		self.buckets = buckets;
+		Ok(())
+	}
+
+	/// Grows the number of buckets to the next power of two, which doubles it unless it has been
+	/// set by [`Self::reserve_exact`].
+	///
+	/// If the number of buckets overflows, the function returns an error.
+	fn grow(&mut self) -> AllocResult<()> {
+		let buckets_count = self
+			.buckets_count
+			.checked_add(1)
+			.and_then(usize::checked_next_power_of_two)
+			.ok_or(AllocError)?;
+		self.rehash(buckets_count)
+	}
+
+	/// Reserves space for at least `additional` more elements, so that inserting them does not
+	/// grow the hash map.
+	///
+	/// The number of buckets is computed the same way as [`Self::with_capacity`].
+	///
+	/// On allocation failure, the hash map is left untouched.
+	pub fn reserve(&mut self, additional: usize) -> AllocResult<()> {
+		let capacity = self.len.checked_add(additional).ok_or(AllocError)?;
+		if capacity <= self.capacity() {
+			return Ok(());
+		}
+		let buckets_count = self.load.buckets_for_capacity(capacity)?;
+		self.rehash(buckets_count)
+	}
+
+	/// Reserves space for exactly `additional` more elements, with the minimal load factor
+	/// headroom.
+	///
+	/// Contrary to [`Self::reserve`], the number of buckets is not rounded up to a power of two,
+	/// so bucket indexes are computed with a slower modulo. Inserting beyond the reserved space
+	/// grows the hash map back to a power of two.
+	///
+	/// On allocation failure, the hash map is left untouched.
+	pub fn reserve_exact(&mut self, additional: usize) -> AllocResult<()> {
+		let capacity = self.len.checked_add(additional).ok_or(AllocError)?;
+		if capacity <= self.capacity() {
+			return Ok(());
+		}
+		let buckets_count = self.load.exact_buckets_for_capacity(capacity)?;
+		self.rehash(buckets_count)
+	}
+
+	/// Returns the number of buckets containing no element.
+	///
+	/// Since removals leave no tombstones in this hash map, empty buckets are what measures the
+	/// space left over by removals, which [`Self::compact`] releases.
+	pub fn empty_buckets(&self) -> usize {
+		// Buckets are allocated lazily
+		let unallocated = self.buckets_count - self.buckets.len();
+		unallocated
+			+ self
+				.buckets
+				.iter()
+				.filter(|b| b.elements.is_empty())
+				.count()
+	}
+
+	/// Shrinks the hash map to the minimum number of buckets required to hold its elements,
+	/// releasing the memory left over by removals.
+	///
+	/// On allocation failure, the hash map is left untouched.
+	pub fn compact(&mut self) -> AllocResult<()> {
+		let buckets_count = self.load.buckets_for_capacity(self.len)?;
+		self.rehash(buckets_count)
+	}
+
+	/// Shrinks the hash map as much as possible.
+	///
+	/// This is the same as [`Self::compact`].
+	///
+	/// On allocation failure, the hash map is left untouched.
+	#[inline]
+	pub fn shrink_to_fit(&mut self) -> AllocResult<()> {
+		self.compact()
+	}
+
+	/// Shrinks the hash map if removals brought its load below the minimum load factor of its
+	/// configuration.
+	///
+	/// Since shrinking is only meant to release memory, allocation failures are ignored and the
+	/// hash map is left untouched.
+	fn shrink_if_sparse(&mut self) {
+		let Some(min_len) = self.load.min_len(self.buckets_count) else {
+			return;
+		};
+		if self.len >= min_len {
+			return;
+		}
+		if let Ok(buckets_count) = self.load.buckets_for_capacity(self.len) {
+			if buckets_count < self.buckets_count {
+				let _ = self.rehash(buckets_count);
+			}
+		}
+	}
+
+	/// Shrinks the hash map to the minimum number of buckets required to hold `min_capacity`
+	/// elements, or its elements if there are more of them.
+	///
+	/// Contrary to [`Self::compact`], this allows keeping room for elements to be inserted later.
+	/// If the hash map already has as few buckets, the function does nothing.
+	///
+	/// On allocation failure, the hash map is left untouched.
+	pub fn shrink_to(&mut self, min_capacity: usize) -> AllocResult<()> {
+		let buckets_count = self.load.buckets_for_capacity(self.len.max(min_capacity))?;
+		if buckets_count >= self.buckets_count {
+			return Ok(());
+		}
+		self.rehash(buckets_count)
 	}
 
 	/// Returns an immutable reference to the value with the given key `k`.
@@ -272,7 +941,7 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 
 	/// Creates an iterator of immutable references for the hash map.
 	#[inline]
-	pub fn iter(&self) -> Iter<K, V> {
+	pub fn iter(&self) -> Iter<K, V, S> {
 		Iter {
 			hm: self,
 
@@ -282,19 +951,84 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 		}
 	}
 
+	/// Creates an iterator of mutable references to the values of the hash map.
+	///
+	/// Keys cannot be modified since this would change their hash.
+	#[inline]
+	pub fn iter_mut(&mut self) -> IterMut<K, V> {
+		IterMut {
+			buckets: self.buckets.iter_mut(),
+			elements: None,
+			remaining: self.len,
+		}
+	}
+
+	/// Creates an iterator on the keys of the hash map.
+	#[inline]
+	pub fn keys(&self) -> Keys<K, V, S> {
+		Keys {
+			inner: self.iter(),
+		}
+	}
+
+	/// Creates an iterator on the values of the hash map.
+	#[inline]
+	pub fn values(&self) -> Values<K, V, S> {
+		Values {
+			inner: self.iter(),
+		}
+	}
+
+	/// Creates an iterator of mutable references to the values of the hash map.
+	#[inline]
+	pub fn values_mut(&mut self) -> ValuesMut<K, V> {
+		ValuesMut {
+			inner: self.iter_mut(),
+		}
+	}
+
+	/// Creates an iterator moving the elements out of the hash map, leaving it empty.
+	///
+	/// The buckets are not freed, so that they can be reused. If the iterator is dropped before
+	/// the end, the remaining elements are dropped.
+	#[inline]
+	pub fn drain(&mut self) -> Drain<K, V, S> {
+		Drain {
+			hm: self,
+			curr_bucket: 0,
+		}
+	}
+
+	/// Returns the first element for which the predicate `pred` returns `true`.
+	///
+	/// Since the hash map doesn't store elements in a specific order, the element returned among
+	/// several matching ones is arbitrary.
+	pub fn find_entry<F: FnMut(&K, &V) -> bool>(&self, mut pred: F) -> Option<(&K, &V)> {
+		self.iter().find(|(k, v)| pred(k, v))
+	}
+
+	/// Same as [`Self::find_entry`], but the returned value is mutable.
+	pub fn find_entry_mut<F: FnMut(&K, &V) -> bool>(
+		&mut self,
+		mut pred: F,
+	) -> Option<(&K, &mut V)> {
+		self.buckets
+			.iter_mut()
+			.flat_map(|b| b.elements.iter_mut())
+			.map(|(k, v)| (&*k, v))
+			.find(|(k, v)| pred(k, v))
+	}
+
 	/// Inserts a new element into the hash map.
 	///
 	/// If the key was already present, the function returns the previous value.
 	pub fn insert(&mut self, k: K, v: V) -> AllocResult<Option<V>> {
-		let index = self.get_bucket_index(&k);
-		if index >= self.buckets.len() {
-			// Creating buckets
-			let begin = self.buckets.len();
-			for i in begin..=index {
-				self.buckets.insert(i, Bucket::new())?;
-			}
+		if self.len >= self.capacity() && !self.contains_key(&k) {
+			self.grow()?;
 		}
 
+		let index = self.get_bucket_index(&k);
+		self.create_buckets(index)?;
 		let result = self.buckets[index].insert(k, v)?;
 
 		if result.is_none() {
@@ -304,10 +1038,186 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 		Ok(result)
 	}
 
-	/// Removes an element from the hash map.
+	/// Moves all the elements of `other` into the hash map, leaving `other` empty.
 	///
-	/// If the key was present, the function returns the previous value.
-	pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+	/// If a key is present in both, the value from `other` replaces the current one, which is
+	/// dropped.
+	///
+	/// On failure, the elements that have not been moved yet are left in `other`.
+	pub fn append(&mut self, other: &mut Self) -> AllocResult<()> {
+		self.reserve(other.len)?;
+		for b in other.buckets.iter_mut() {
+			while let Some((k, _)) = b.elements.last() {
+				// Allocate the destination before taking the element out of `other`, so that it
+				// is not lost on failure
+				let index = self.get_bucket_index(k);
+				self.create_buckets(index)?;
+				self.buckets[index].elements.reserve(1)?;
+				let Some((k, v)) = b.elements.pop() else {
+					break;
+				};
+				other.len -= 1;
+				// Cannot fail since space has been reserved
+				self.insert(k, v)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Inserts the key/value pairs of `iter` into the hash map. If a key is already present, its
+	/// value is replaced.
+	///
+	/// Space is reserved once beforehand according to the lower bound of the iterator's size
+	/// hint, so that the hash map grows at most once for iterators with an exact size.
+	///
+	/// If a memory allocation fails, the function stops and returns an error. The pairs inserted
+	/// so far are kept and the pair that could not be inserted is dropped.
+	pub fn try_extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) -> AllocResult<()> {
+		let iter = iter.into_iter();
+		self.reserve(iter.size_hint().0)?;
+		for (k, v) in iter {
+			self.insert(k, v)?;
+		}
+
+		Ok(())
+	}
+
+	/// Returns a mutable reference to the value with the given key `k`. If the key isn't
+	/// present, the value returned by `f` is inserted first.
+	///
+	/// The key is hashed only once, unless the hash map has to grow. `f` is called only if the key
+	/// isn't present.
+	pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, f: F) -> AllocResult<&mut V> {
+		let mut index = self.get_bucket_index(&k);
+		let pos = self
+			.buckets
+			.get(index)
+			.and_then(|b| b.elements.iter().position(|(key, _)| *key == k));
+		if let Some(pos) = pos {
+			return Ok(&mut self.buckets[index].elements[pos].1);
+		}
+
+		if self.len >= self.capacity() {
+			self.grow()?;
+			index = self.get_bucket_index(&k);
+		}
+		self.insert_vacant(index, k, f())
+	}
+
+	/// Returns the entry for the key `k`, which can be used to check and modify the value in
+	/// place.
+	///
+	/// The key is hashed only once, unless the hash map has to grow when inserting into a vacant
+	/// entry.
+	pub fn entry(&mut self, k: K) -> Entry<K, V, S> {
+		let index = self.get_bucket_index(&k);
+		let pos = self
+			.buckets
+			.get(index)
+			.and_then(|b| b.elements.iter().position(|(key, _)| *key == k));
+		match pos {
+			Some(pos) => Entry::Occupied(OccupiedEntry {
+				hm: self,
+				index,
+				pos,
+			}),
+			None => Entry::Vacant(VacantEntry {
+				hm: self,
+				index,
+				key: k,
+			}),
+		}
+	}
+
+	/// Returns the entry for the key `k`, which can be used to check and modify the value in
+	/// place.
+	///
+	/// Contrary to [`Self::insert`], the key is borrowed. It is converted into an owned key only
+	/// when inserting into a vacant entry.
+	pub fn entry_ref<'m, Q: ?Sized>(&'m mut self, k: &'m Q) -> EntryRef<'m, K, Q, V, S>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let index = self.get_bucket_index(k);
+		let pos = self
+			.buckets
+			.get(index)
+			.and_then(|b| b.elements.iter().position(|(key, _)| key.borrow() == k));
+		match pos {
+			Some(pos) => EntryRef::Occupied(OccupiedEntryRef {
+				value: &mut self.buckets[index].elements[pos].1,
+			}),
+			None => EntryRef::Vacant(VacantEntryRef {
+				hm: self,
+				key: k,
+			}),
+		}
+	}
+
+	/// Returns a builder to look up an element with a precomputed hash or a custom equality
+	/// function.
+	#[inline]
+	pub fn raw_entry(&self) -> RawEntryBuilder<'_, K, V, S> {
+		RawEntryBuilder {
+			hm: self,
+		}
+	}
+
+	/// Same as [`Self::raw_entry`], but the returned entry allows modifying the hash map.
+	#[inline]
+	pub fn raw_entry_mut(&mut self) -> RawEntryBuilderMut<'_, K, V, S> {
+		RawEntryBuilderMut {
+			hm: self,
+		}
+	}
+
+	/// Returns the entry whose key has the hash `hash` and for which `is_match` returns `true`.
+	///
+	/// This is a shorthand for [`RawEntryBuilderMut::from_hash`]. It allows looking up a key
+	/// from borrowed parts without building an owned key.
+	///
+	/// `hash` must be computed with the hasher of the hash map, as done by [`Self::hash_key`].
+	/// Hash maps with equal hasher builders, such as clones of the same [`SipHasherBuilder`],
+	/// compute the same hashes, which allows hashing a key once to look it up in several hash
+	/// maps.
+	#[inline]
+	pub fn entry_by_hash<F: FnMut(&K) -> bool>(
+		&mut self,
+		hash: u64,
+		is_match: F,
+	) -> RawEntryMut<'_, K, V, S> {
+		self.raw_entry_mut().from_hash(hash, is_match)
+	}
+
+	/// Inserts the key `k`, which must not be present, with the value `v` into the bucket at index
+	/// `index`.
+	///
+	/// The function returns a mutable reference to the inserted value.
+	fn insert_vacant(&mut self, index: usize, k: K, v: V) -> AllocResult<&mut V> {
+		self.create_buckets(index)?;
+		let elements = &mut self.buckets[index].elements;
+		elements.push((k, v))?;
+		self.len += 1;
+
+		let last = elements.len() - 1;
+		Ok(&mut elements[last].1)
+	}
+
+	/// Creates the buckets up to the one at index `index`, if they don't exist yet.
+	fn create_buckets(&mut self, index: usize) -> AllocResult<()> {
+		let begin = self.buckets.len();
+		for i in begin..=index {
+			self.buckets.insert(i, Bucket::new(self.tag))?;
+		}
+		Ok(())
+	}
+
+	/// Removes an element from the hash map.
+	///
+	/// If the key was present, the function returns the previous value.
+	pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
 	where
 		K: Borrow<Q>,
 		Q: Hash + Eq,
@@ -319,6 +1229,7 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 
 			if result.is_some() {
 				self.len -= 1;
+				self.shrink_if_sparse();
 			}
 
 			result
@@ -327,7 +1238,26 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 		}
 	}
 
+	/// Removes the elements with the given keys from the hash map, dropping their values.
+	///
+	/// This is equivalent to calling [`Self::remove`] for each key.
+	///
+	/// The function returns the number of elements that were present and have been removed.
+	pub fn remove_all<'k, Q, I>(&mut self, keys: I) -> usize
+	where
+		I: IntoIterator<Item = &'k Q>,
+		K: Borrow<Q>,
+		Q: 'k + ?Sized + Hash + Eq,
+	{
+		keys.into_iter()
+			.filter(|k| self.remove(*k).is_some())
+			.count()
+	}
+
 	/// Retains only the elements for which the given predicate returns `true`.
+	///
+	/// The predicate visits each element exactly once, in a single pass. It may modify the value
+	/// of the elements it keeps.
 	pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
 		let mut len = 0;
 
@@ -337,6 +1267,52 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 		}
 
 		self.len = len;
+		self.shrink_if_sparse();
+	}
+
+	/// Same as [`Self::retain`], but keeps at most `max` elements.
+	///
+	/// Once `max` elements have been kept, the remaining ones are removed without calling the
+	/// predicate. Since the iteration order is not defined, which elements are removed this way
+	/// is unspecified.
+	pub fn retain_n<F: FnMut(&K, &mut V) -> bool>(&mut self, max: usize, mut f: F) {
+		let mut len = 0;
+
+		for b in self.buckets.iter_mut() {
+			b.elements.retain(|(k, v): &mut (K, V)| {
+				let keep = len < max && f(k, &mut *v);
+				if keep {
+					len += 1;
+				}
+				keep
+			});
+		}
+
+		self.len = len;
+		self.shrink_if_sparse();
+	}
+
+	/// Computes statistics on the length of lookups for the elements in the hash map.
+	///
+	/// This function is available only in debug mode.
+	#[cfg(config_debug_debug)]
+	pub fn probe_stats(&self) -> ProbeStats {
+		let mut stats = ProbeStats {
+			empty_buckets: self.empty_buckets(),
+			..Default::default()
+		};
+
+		for (i, b) in self.buckets.iter().enumerate() {
+			for (j, (k, _)) in b.elements.iter().enumerate() {
+				debug_assert_eq!(self.get_bucket_index(k), i);
+				// The element is found after comparing every element before it
+				let len = j + 1;
+				stats.max = stats.max.max(len);
+				stats.total += len;
+			}
+		}
+
+		stats
 	}
 
 	/// Drops all elements in the hash map.
@@ -349,7 +1325,23 @@ impl<K: Eq + Hash, V> HashMap<K, V> {
 	}
 }
 
-impl<K: Eq + Hash, V> Index<K> for HashMap<K, V> {
+impl<K: Eq + Hash + Ord, V, S: BuildHasher> HashMap<K, V, S> {
+	/// Returns the elements of the hash map, sorted by key.
+	///
+	/// Contrary to [`Self::iter`], the order is deterministic, which is useful to produce
+	/// reproducible output. The elements are collected and sorted on each call.
+	pub fn iter_sorted_by_key(&self) -> AllocResult<Vec<(&K, &V)>> {
+		let mut elements = Vec::with_capacity(self.len)?;
+		for e in self.iter() {
+			elements.push(e)?;
+		}
+		// Keys are unique, so a stable sort is not required
+		elements.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+		Ok(elements)
+	}
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> Index<K> for HashMap<K, V, S> {
 	type Output = V;
 
 	#[inline]
@@ -358,15 +1350,19 @@ impl<K: Eq + Hash, V> Index<K> for HashMap<K, V> {
 	}
 }
 
-impl<K: Eq + Hash, V> IndexMut<K> for HashMap<K, V> {
+impl<K: Eq + Hash, V, S: BuildHasher> IndexMut<K> for HashMap<K, V, S> {
 	#[inline]
 	fn index_mut(&mut self, k: K) -> &mut Self::Output {
 		self.get_mut(&k).expect("no entry found for key")
 	}
 }
 
-impl<K: Eq + Hash + TryClone<Error = E>, V: TryClone<Error = E>, E: From<AllocError>> TryClone
-	for HashMap<K, V>
+impl<
+		K: Eq + Hash + TryClone<Error = E>,
+		V: TryClone<Error = E>,
+		S: Clone,
+		E: From<AllocError>,
+	> TryClone for HashMap<K, V, S>
 {
 	type Error = E;
 
@@ -374,19 +1370,458 @@ impl<K: Eq + Hash + TryClone<Error = E>, V: TryClone<Error = E>, E: From<AllocEr
 		Ok(Self {
 			buckets_count: self.buckets_count,
 			buckets: self.buckets.try_clone()?,
+			load: self.load,
+			hash_builder: self.hash_builder.clone(),
+			tag: self.tag,
 
 			len: self.len,
 		})
 	}
 }
 
+impl<
+		K: Eq + Hash + TryClone<Error = E>,
+		V: TryClone<Error = E>,
+		S: BuildHasher,
+		E: From<AllocError>,
+	> HashMap<K, V, S>
+{
+	/// Clones the elements of the hash map into `dest`, replacing its previous elements.
+	///
+	/// Contrary to [`TryClone::try_clone`], the allocation of `dest` is reused. It grows only if
+	/// it cannot hold all the elements.
+	///
+	/// On failure, `dest` is left with the elements cloned so far.
+	pub fn try_clone_into(&self, dest: &mut Self) -> Result<(), E> {
+		dest.clear();
+		dest.reserve(self.len)?;
+		for (k, v) in self.iter() {
+			dest.insert(k.try_clone()?, v.try_clone()?)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// An entry of a [`HashMap`], looked up with a borrowed key.
+///
+/// This structure is returned by [`HashMap::entry_ref`].
+pub enum EntryRef<'m, K: Eq + Hash, Q: ?Sized, V, S = XORHasherBuilder> {
+	/// The key is present.
+	Occupied(OccupiedEntryRef<'m, V>),
+	/// The key is not present.
+	Vacant(VacantEntryRef<'m, K, Q, V, S>),
+}
+
+impl<'m, K, Q, V, S> EntryRef<'m, K, Q, V, S>
+where
+	K: Eq + Hash + Borrow<Q> + TryFrom<&'m Q, Error = AllocError>,
+	Q: ?Sized + Hash + Eq,
+	S: BuildHasher,
+{
+	/// Returns a mutable reference to the value of the entry, inserting `v` first if the entry is
+	/// vacant.
+	pub fn or_insert(self, v: V) -> AllocResult<&'m mut V> {
+		self.or_insert_with(|| v)
+	}
+
+	/// Returns a mutable reference to the value of the entry, inserting the value returned by `f`
+	/// first if the entry is vacant.
+	///
+	/// `f` is called only if the entry is vacant.
+	pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> AllocResult<&'m mut V> {
+		match self {
+			Self::Occupied(e) => Ok(e.into_mut()),
+			Self::Vacant(e) => e.insert(f()),
+		}
+	}
+}
+
+/// An occupied entry of a [`HashMap`].
+pub struct OccupiedEntryRef<'m, V> {
+	/// The value of the entry.
+	value: &'m mut V,
+}
+
+impl<'m, V> OccupiedEntryRef<'m, V> {
+	/// Returns a reference to the value of the entry.
+	pub fn get(&self) -> &V {
+		self.value
+	}
+
+	/// Returns a mutable reference to the value of the entry.
+	pub fn get_mut(&mut self) -> &mut V {
+		self.value
+	}
+
+	/// Converts the entry into a mutable reference to its value, with the lifetime of the hash
+	/// map.
+	pub fn into_mut(self) -> &'m mut V {
+		self.value
+	}
+
+	/// Replaces the value of the entry with `v`, returning the previous value.
+	pub fn insert(&mut self, v: V) -> V {
+		mem::replace(self.value, v)
+	}
+}
+
+/// A vacant entry of a [`HashMap`].
+pub struct VacantEntryRef<'m, K: Eq + Hash, Q: ?Sized, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m mut HashMap<K, V, S>,
+	/// The borrowed key of the entry.
+	key: &'m Q,
+}
+
+impl<'m, K, Q, V, S> VacantEntryRef<'m, K, Q, V, S>
+where
+	K: Eq + Hash + Borrow<Q> + TryFrom<&'m Q, Error = AllocError>,
+	Q: ?Sized + Hash + Eq,
+	S: BuildHasher,
+{
+	/// Returns the borrowed key of the entry.
+	pub fn key(&self) -> &Q {
+		self.key
+	}
+
+	/// Inserts the value `v` into the entry, converting the key into an owned key.
+	///
+	/// The function returns a mutable reference to the inserted value.
+	pub fn insert(self, v: V) -> AllocResult<&'m mut V> {
+		let k = K::try_from(self.key)?;
+		let hm = self.hm;
+		if hm.len >= hm.capacity() {
+			hm.grow()?;
+		}
+		let index = hm.get_bucket_index(self.key);
+		hm.insert_vacant(index, k, v)
+	}
+}
+
+/// An entry of a [`HashMap`].
+///
+/// This structure is returned by [`HashMap::entry`].
+pub enum Entry<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The key is present.
+	Occupied(OccupiedEntry<'m, K, V, S>),
+	/// The key is not present.
+	Vacant(VacantEntry<'m, K, V, S>),
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> Entry<'m, K, V, S> {
+	/// Returns the key of the entry.
+	pub fn key(&self) -> &K {
+		match self {
+			Self::Occupied(e) => e.key(),
+			Self::Vacant(e) => e.key(),
+		}
+	}
+
+	/// Returns a mutable reference to the value of the entry, inserting `v` first if the entry is
+	/// vacant.
+	pub fn or_insert(self, v: V) -> AllocResult<&'m mut V> {
+		self.or_insert_with(|| v)
+	}
+
+	/// Returns a mutable reference to the value of the entry, inserting the value returned by `f`
+	/// first if the entry is vacant.
+	///
+	/// `f` is called only if the entry is vacant.
+	pub fn or_insert_with<F: FnOnce() -> V>(self, f: F) -> AllocResult<&'m mut V> {
+		match self {
+			Self::Occupied(e) => Ok(e.into_mut()),
+			Self::Vacant(e) => e.insert(f()),
+		}
+	}
+
+	/// Same as [`Self::or_insert_with`], except `f` can fail.
+	///
+	/// If `f` returns an error, nothing is inserted and the error is returned.
+	pub fn or_try_insert_with<E: From<AllocError>, F: FnOnce() -> Result<V, E>>(
+		self,
+		f: F,
+	) -> Result<&'m mut V, E> {
+		match self {
+			Self::Occupied(e) => Ok(e.into_mut()),
+			Self::Vacant(e) => Ok(e.insert(f()?)?),
+		}
+	}
+
+	/// Calls `f` on the value of the entry if it is occupied, then returns the entry.
+	pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+		if let Self::Occupied(e) = &mut self {
+			f(e.get_mut());
+		}
+		self
+	}
+}
+
+/// An occupied entry of a [`HashMap`].
+pub struct OccupiedEntry<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m mut HashMap<K, V, S>,
+	/// The index of the bucket containing the element.
+	index: usize,
+	/// The position of the element in its bucket.
+	pos: usize,
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> OccupiedEntry<'m, K, V, S> {
+	/// Returns the key of the entry.
+	pub fn key(&self) -> &K {
+		&self.hm.buckets[self.index].elements[self.pos].0
+	}
+
+	/// Returns a reference to the value of the entry.
+	pub fn get(&self) -> &V {
+		&self.hm.buckets[self.index].elements[self.pos].1
+	}
+
+	/// Returns a mutable reference to the value of the entry.
+	pub fn get_mut(&mut self) -> &mut V {
+		&mut self.hm.buckets[self.index].elements[self.pos].1
+	}
+
+	/// Converts the entry into a mutable reference to its value, with the lifetime of the hash
+	/// map.
+	pub fn into_mut(self) -> &'m mut V {
+		&mut self.hm.buckets[self.index].elements[self.pos].1
+	}
+
+	/// Replaces the value of the entry with `v`, returning the previous value.
+	pub fn insert(&mut self, v: V) -> V {
+		mem::replace(self.get_mut(), v)
+	}
+
+	/// Removes the element from the hash map, returning its value.
+	pub fn remove(self) -> V {
+		self.remove_entry().1
+	}
+
+	/// Removes the element from the hash map, returning its key and value.
+	pub fn remove_entry(self) -> (K, V) {
+		self.hm.len -= 1;
+		let entry = self.hm.buckets[self.index].elements.remove(self.pos);
+		self.hm.shrink_if_sparse();
+		entry
+	}
+}
+
+/// A vacant entry of a [`HashMap`].
+pub struct VacantEntry<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m mut HashMap<K, V, S>,
+	/// The index of the bucket the key belongs to, with the current number of buckets.
+	index: usize,
+	/// The key of the entry.
+	key: K,
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> VacantEntry<'m, K, V, S> {
+	/// Returns the key of the entry.
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Takes the ownership of the key.
+	pub fn into_key(self) -> K {
+		self.key
+	}
+
+	/// Inserts the value `v` into the entry.
+	///
+	/// If the hash map is full, it grows first, in which case the bucket of the key is looked up
+	/// again.
+	///
+	/// The function returns a mutable reference to the inserted value.
+	pub fn insert(self, v: V) -> AllocResult<&'m mut V> {
+		let hm = self.hm;
+		let mut index = self.index;
+		if hm.len >= hm.capacity() {
+			hm.grow()?;
+			index = hm.get_bucket_index(&self.key);
+		}
+		hm.insert_vacant(index, self.key, v)
+	}
+}
+
+/// A builder to look up an element of a [`HashMap`] with a precomputed hash.
+///
+/// This structure is returned by [`HashMap::raw_entry`].
+pub struct RawEntryBuilder<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m HashMap<K, V, S>,
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> RawEntryBuilder<'m, K, V, S> {
+	/// Returns the element with the key `k`.
+	pub fn from_key<Q: ?Sized>(self, k: &Q) -> Option<(&'m K, &'m V)>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let hash = self.hm.hash_key(k);
+		self.from_hash(hash, |key| key.borrow() == k)
+	}
+
+	/// Returns the element whose key has the hash `hash` and for which `is_match` returns `true`.
+	///
+	/// `hash` must be the hash of the key, as returned by [`HashMap::hash_key`]. Only keys
+	/// sharing its bucket are passed to `is_match`.
+	pub fn from_hash<F: FnMut(&K) -> bool>(
+		self,
+		hash: u64,
+		mut is_match: F,
+	) -> Option<(&'m K, &'m V)> {
+		let index = hash_bucket_index(hash, self.hm.buckets_count);
+		self.hm
+			.buckets
+			.get(index)?
+			.elements
+			.iter()
+			.find(|(k, _)| is_match(k))
+			.map(|(k, v)| (k, v))
+	}
+}
+
+/// A builder to look up an element of a [`HashMap`] with a precomputed hash, allowing to modify
+/// it.
+///
+/// This structure is returned by [`HashMap::raw_entry_mut`].
+pub struct RawEntryBuilderMut<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m mut HashMap<K, V, S>,
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> RawEntryBuilderMut<'m, K, V, S> {
+	/// Returns the entry with the key `k`.
+	pub fn from_key<Q: ?Sized>(self, k: &Q) -> RawEntryMut<'m, K, V, S>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let hash = self.hm.hash_key(k);
+		self.from_hash(hash, |key| key.borrow() == k)
+	}
+
+	/// Returns the entry whose key has the hash `hash` and for which `is_match` returns `true`.
+	///
+	/// `hash` must be the hash of the key, as returned by [`HashMap::hash_key`]. Only keys
+	/// sharing its bucket are passed to `is_match`.
+	pub fn from_hash<F: FnMut(&K) -> bool>(
+		self,
+		hash: u64,
+		mut is_match: F,
+	) -> RawEntryMut<'m, K, V, S> {
+		let hm = self.hm;
+		let index = hash_bucket_index(hash, hm.buckets_count);
+		let pos = hm
+			.buckets
+			.get(index)
+			.and_then(|b| b.elements.iter().position(|(k, _)| is_match(k)));
+		match pos {
+			Some(pos) => {
+				let (key, value) = &mut hm.buckets[index].elements[pos];
+				RawEntryMut::Occupied(RawOccupiedEntryMut {
+					key,
+					value,
+				})
+			}
+			None => RawEntryMut::Vacant(RawVacantEntryMut {
+				hm,
+			}),
+		}
+	}
+}
+
+/// An entry of a [`HashMap`], looked up with [`RawEntryBuilderMut`].
+pub enum RawEntryMut<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The key is present.
+	Occupied(RawOccupiedEntryMut<'m, K, V>),
+	/// The key is not present.
+	Vacant(RawVacantEntryMut<'m, K, V, S>),
+}
+
+/// An occupied entry of a [`HashMap`], looked up with [`RawEntryBuilderMut`].
+pub struct RawOccupiedEntryMut<'m, K, V> {
+	/// The key of the entry.
+	key: &'m K,
+	/// The value of the entry.
+	value: &'m mut V,
+}
+
+impl<'m, K, V> RawOccupiedEntryMut<'m, K, V> {
+	/// Returns a reference to the key of the entry.
+	pub fn key(&self) -> &K {
+		self.key
+	}
+
+	/// Returns a reference to the value of the entry.
+	pub fn get(&self) -> &V {
+		self.value
+	}
+
+	/// Returns a mutable reference to the value of the entry.
+	pub fn get_mut(&mut self) -> &mut V {
+		self.value
+	}
+
+	/// Converts the entry into references to its key and value, with the lifetime of the hash
+	/// map.
+	pub fn into_key_value(self) -> (&'m K, &'m mut V) {
+		(self.key, self.value)
+	}
+
+	/// Replaces the value of the entry with `v`, returning the previous value.
+	pub fn insert(&mut self, v: V) -> V {
+		mem::replace(self.value, v)
+	}
+}
+
+/// A vacant entry of a [`HashMap`], looked up with [`RawEntryBuilderMut`].
+pub struct RawVacantEntryMut<'m, K: Eq + Hash, V, S = XORHasherBuilder> {
+	/// The hash map.
+	hm: &'m mut HashMap<K, V, S>,
+}
+
+impl<'m, K: Eq + Hash, V, S: BuildHasher> RawVacantEntryMut<'m, K, V, S> {
+	/// Inserts the key `k` with the value `v` into the entry.
+	///
+	/// The function returns references to the inserted key and value.
+	pub fn insert(self, k: K, v: V) -> AllocResult<(&'m K, &'m mut V)> {
+		let hash = self.hm.hash_key(&k);
+		self.insert_hashed(hash, k, v)
+	}
+
+	/// Inserts the key `k` with the value `v` into the entry, using the precomputed hash `hash`
+	/// instead of hashing the key.
+	///
+	/// `hash` must be the hash of `k`, as returned by [`HashMap::hash_key`]. Otherwise, the
+	/// element may not be found afterwards.
+	///
+	/// The function returns references to the inserted key and value.
+	pub fn insert_hashed(self, hash: u64, k: K, v: V) -> AllocResult<(&'m K, &'m mut V)> {
+		let hm = self.hm;
+		if hm.len >= hm.capacity() {
+			hm.grow()?;
+		}
+		let index = hash_bucket_index(hash, hm.buckets_count);
+		hm.insert_vacant(index, k, v)?;
+		let elements = &mut hm.buckets[index].elements;
+		let last = elements.len() - 1;
+		let (key, value) = &mut elements[last];
+		Ok((key, value))
+	}
+}
+
 /// Iterator for the [`HashMap`] structure.
 ///
 /// This iterator doesn't guarantee any order since the HashMap itself doesn't store value in a
 /// specific order.
-pub struct Iter<'m, K: Hash + Eq, V> {
+pub struct Iter<'m, K: Hash + Eq, V, S = XORHasherBuilder> {
 	/// The hash map to iterate into.
-	hm: &'m HashMap<K, V>,
+	hm: &'m HashMap<K, V, S>,
 
 	/// The current bucket index.
 	curr_bucket: usize,
@@ -396,7 +1831,7 @@ pub struct Iter<'m, K: Hash + Eq, V> {
 	i: usize,
 }
 
-impl<'m, K: Hash + Eq, V> Iterator for Iter<'m, K, V> {
+impl<'m, K: Hash + Eq, V, S> Iterator for Iter<'m, K, V, S> {
 	type Item = (&'m K, &'m V);
 
 	fn next(&mut self) -> Option<Self::Item> {
@@ -431,46 +1866,523 @@ impl<'m, K: Hash + Eq, V> Iterator for Iter<'m, K, V> {
 	}
 
 	fn count(self) -> usize {
-		self.hm.len() - self.i
+		self.hm.len - self.i
 	}
 
 	fn size_hint(&self) -> (usize, Option<usize>) {
-		let len = self.hm.len() - self.i;
+		let len = self.hm.len - self.i;
 		(len, Some(len))
 	}
 }
 
 // TODO implement DoubleEndedIterator
 
-impl<'m, K: Hash + Eq, V> ExactSizeIterator for Iter<'m, K, V> {
+impl<'m, K: Hash + Eq, V, S> ExactSizeIterator for Iter<'m, K, V, S> {
 	fn len(&self) -> usize {
-		self.hm.len()
+		self.hm.len
 	}
 }
 
-impl<'m, K: Hash + Eq, V> FusedIterator for Iter<'m, K, V> {}
+impl<'m, K: Hash + Eq, V, S> FusedIterator for Iter<'m, K, V, S> {}
 
-unsafe impl<'m, K: Hash + Eq, V> TrustedLen for Iter<'m, K, V> {}
+unsafe impl<'m, K: Hash + Eq, V, S> TrustedLen for Iter<'m, K, V, S> {}
 
-impl<K: Eq + Hash + fmt::Display, V: fmt::Display> fmt::Display for HashMap<K, V> {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		write!(f, "[")?;
+/// Iterator of mutable references for the [`HashMap`] structure.
+///
+/// This iterator doesn't guarantee any order since the HashMap itself doesn't store value in a
+/// specific order.
+pub struct IterMut<'m, K: Hash + Eq, V> {
+	/// The iterator on the buckets that have not been visited yet.
+	buckets: slice::IterMut<'m, Bucket<K, V>>,
+	/// The iterator on the elements of the current bucket.
+	elements: Option<slice::IterMut<'m, (K, V)>>,
+	/// The number of elements remaining.
+	remaining: usize,
+}
 
-		for (i, (key, value)) in self.iter().enumerate() {
-			write!(f, "{}: {}", key, value)?;
+impl<'m, K: Hash + Eq, V> Iterator for IterMut<'m, K, V> {
+	type Item = (&'m K, &'m mut V);
 
-			if i + 1 < self.len() {
-				write!(f, ", ")?;
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some((k, v)) = self.elements.as_mut().and_then(Iterator::next) {
+				self.remaining -= 1;
+				return Some((&*k, v));
 			}
+			// Go to the next bucket
+			self.elements = Some(self.buckets.next()?.elements.iter_mut());
 		}
+	}
 
-		write!(f, "]")
+	fn count(self) -> usize {
+		self.remaining
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
 	}
 }
 
-#[cfg(test)]
-mod test {
+impl<'m, K: Hash + Eq, V> ExactSizeIterator for IterMut<'m, K, V> {
+	fn len(&self) -> usize {
+		self.remaining
+	}
+}
+
+impl<'m, K: Hash + Eq, V> FusedIterator for IterMut<'m, K, V> {}
+
+unsafe impl<'m, K: Hash + Eq, V> TrustedLen for IterMut<'m, K, V> {}
+
+/// Iterator on the keys of a [`HashMap`].
+///
+/// This structure is returned by [`HashMap::keys`].
+pub struct Keys<'m, K: Hash + Eq, V, S = XORHasherBuilder> {
+	/// The iterator on the elements.
+	inner: Iter<'m, K, V, S>,
+}
+
+impl<'m, K: Hash + Eq, V, S> Iterator for Keys<'m, K, V, S> {
+	type Item = &'m K;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next().map(|(k, _)| k)
+	}
+
+	fn count(self) -> usize {
+		self.inner.count()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S> ExactSizeIterator for Keys<'m, K, V, S> {}
+
+impl<'m, K: Hash + Eq, V, S> FusedIterator for Keys<'m, K, V, S> {}
+
+/// Iterator on the values of a [`HashMap`].
+///
+/// This structure is returned by [`HashMap::values`].
+pub struct Values<'m, K: Hash + Eq, V, S = XORHasherBuilder> {
+	/// The iterator on the elements.
+	inner: Iter<'m, K, V, S>,
+}
+
+impl<'m, K: Hash + Eq, V, S> Iterator for Values<'m, K, V, S> {
+	type Item = &'m V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next().map(|(_, v)| v)
+	}
+
+	fn count(self) -> usize {
+		self.inner.count()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S> ExactSizeIterator for Values<'m, K, V, S> {}
+
+impl<'m, K: Hash + Eq, V, S> FusedIterator for Values<'m, K, V, S> {}
+
+/// Iterator of mutable references to the values of a [`HashMap`].
+///
+/// This structure is returned by [`HashMap::values_mut`].
+pub struct ValuesMut<'m, K: Hash + Eq, V> {
+	/// The iterator on the elements.
+	inner: IterMut<'m, K, V>,
+}
+
+impl<'m, K: Hash + Eq, V> Iterator for ValuesMut<'m, K, V> {
+	type Item = &'m mut V;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next().map(|(_, v)| v)
+	}
+
+	fn count(self) -> usize {
+		self.inner.count()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+}
+
+impl<'m, K: Hash + Eq, V> ExactSizeIterator for ValuesMut<'m, K, V> {}
+
+impl<'m, K: Hash + Eq, V> FusedIterator for ValuesMut<'m, K, V> {}
+
+/// Consuming iterator for the [`HashMap`] structure.
+///
+/// The memory of the hash map is freed once the iterator is dropped.
+pub struct IntoIter<K: Hash + Eq, V> {
+	/// The iterator on the buckets that have not been visited yet.
+	buckets: vec::IntoIter<Bucket<K, V>>,
+	/// The iterator on the elements of the current bucket.
+	elements: Option<vec::IntoIter<(K, V)>>,
+	/// The number of elements remaining.
+	remaining: usize,
+}
+
+impl<K: Hash + Eq, V> Iterator for IntoIter<K, V> {
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(e) = self.elements.as_mut().and_then(Iterator::next) {
+				self.remaining -= 1;
+				return Some(e);
+			}
+			// Go to the next bucket
+			self.elements = Some(self.buckets.next()?.elements.into_iter());
+		}
+	}
+
+	fn count(self) -> usize {
+		self.remaining
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.remaining, Some(self.remaining))
+	}
+}
+
+impl<K: Hash + Eq, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Hash + Eq, V> FusedIterator for IntoIter<K, V> {}
+
+impl<K: Hash + Eq, V, S> IntoIterator for HashMap<K, V, S> {
+	type IntoIter = IntoIter<K, V>;
+	type Item = (K, V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		IntoIter {
+			buckets: self.buckets.into_iter(),
+			elements: None,
+			remaining: self.len,
+		}
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S: BuildHasher> IntoIterator for &'m HashMap<K, V, S> {
+	type IntoIter = Iter<'m, K, V, S>;
+	type Item = (&'m K, &'m V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S: BuildHasher> IntoIterator for &'m mut HashMap<K, V, S> {
+	type IntoIter = IterMut<'m, K, V>;
+	type Item = (&'m K, &'m mut V);
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter_mut()
+	}
+}
+
+/// Iterator moving the elements out of a [`HashMap`].
+///
+/// This structure is returned by [`HashMap::drain`].
+pub struct Drain<'m, K: Hash + Eq, V, S = XORHasherBuilder> {
+	/// The hash map to drain.
+	hm: &'m mut HashMap<K, V, S>,
+	/// The current bucket index.
+	curr_bucket: usize,
+}
+
+impl<'m, K: Hash + Eq, V, S> Iterator for Drain<'m, K, V, S> {
+	type Item = (K, V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while let Some(b) = self.hm.buckets.get_mut(self.curr_bucket) {
+			if let Some(e) = b.elements.pop() {
+				self.hm.len -= 1;
+				return Some(e);
+			}
+			self.curr_bucket += 1;
+		}
+		None
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.hm.len, Some(self.hm.len))
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S> ExactSizeIterator for Drain<'m, K, V, S> {
+	fn len(&self) -> usize {
+		self.hm.len
+	}
+}
+
+impl<'m, K: Hash + Eq, V, S> FusedIterator for Drain<'m, K, V, S> {}
+
+impl<'m, K: Hash + Eq, V, S> Drop for Drain<'m, K, V, S> {
+	fn drop(&mut self) {
+		for b in self.hm.buckets[self.curr_bucket..].iter_mut() {
+			b.elements.clear();
+		}
+		self.hm.len = 0;
+	}
+}
+
+impl<K: Eq + Hash + fmt::Debug, V: fmt::Debug, S: BuildHasher> fmt::Debug for HashMap<K, V, S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<K: Eq + Hash + fmt::Display, V: fmt::Display, S: BuildHasher> fmt::Display
+	for HashMap<K, V, S>
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "[")?;
+
+		for (i, (key, value)) in self.iter().enumerate() {
+			write!(f, "{}: {}", key, value)?;
+
+			if i + 1 < self.len() {
+				write!(f, ", ")?;
+			}
+		}
+
+		write!(f, "]")
+	}
+}
+
+/// A hash map with a fixed capacity of `N` elements, stored inline.
+///
+/// Contrary to [`HashMap`], this map never allocates memory, which allows using it where
+/// allocation is not possible, such as early boot or interrupt handlers. Collisions are resolved
+/// by storing the element in the next free slot.
+pub struct ArrayHashMap<K: Eq + Hash, V, const N: usize> {
+	/// The slots storing the key/value pairs.
+	slots: [Option<(K, V)>; N],
+	/// The number of elements in the map.
+	len: usize,
+}
+
+impl<K: Eq + Hash, V, const N: usize> Default for ArrayHashMap<K, V, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Eq + Hash, V, const N: usize> ArrayHashMap<K, V, N> {
+	/// Creates a new empty instance.
+	pub fn new() -> Self {
+		Self {
+			slots: core::array::from_fn(|_| None),
+			len: 0,
+		}
+	}
+
+	/// Returns the number of elements in the hash map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Tells whether the hash map is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the number of elements the hash map can hold.
+	#[inline]
+	pub fn capacity(&self) -> usize {
+		N
+	}
+
+	/// Returns the index of the slot where the search for the key `k` begins.
+	///
+	/// Since `N` is not necessarily a power of two, the index is computed with a modulo.
+	fn home_slot<Q: ?Sized + Hash>(k: &Q) -> usize {
+		(hash_key(&XORHasherBuilder, k) % N as u64) as usize
+	}
+
+	/// Looks for the key `k`.
+	///
+	/// If found, the function returns the index of its slot. Else, it returns the index of the
+	/// slot where it would be inserted, or `None` if the map is full.
+	fn find<Q: ?Sized>(&self, k: &Q) -> Result<usize, Option<usize>>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		if N == 0 {
+			return Err(None);
+		}
+		let begin = Self::home_slot(k);
+		for i in 0..N {
+			let index = (begin + i) % N;
+			match &self.slots[index] {
+				Some((key, _)) if key.borrow() == k => return Ok(index),
+				Some(_) => {}
+				None => return Err(Some(index)),
+			}
+		}
+
+		Err(None)
+	}
+
+	/// Returns an immutable reference to the value with the given key `k`.
+	///
+	/// If the key isn't present, the function return `None`.
+	pub fn get<Q: ?Sized>(&self, k: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let index = self.find(k).ok()?;
+		self.slots[index].as_ref().map(|(_, v)| v)
+	}
+
+	/// Returns a mutable reference to the value with the given key `k`.
+	///
+	/// If the key isn't present, the function return `None`.
+	pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let index = self.find(k).ok()?;
+		self.slots[index].as_mut().map(|(_, v)| v)
+	}
+
+	/// Tells whether the hash map contains the given key `k`.
+	#[inline]
+	pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		self.find(k).is_ok()
+	}
+
+	/// Returns an iterator on the hash map's elements.
+	pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+		self.slots.iter().flatten().map(|(k, v)| (k, v))
+	}
+
+	/// Inserts a new element into the hash map.
+	///
+	/// If the key was already present, the function returns the previous value.
+	///
+	/// If the key is not present and the map is full, the function returns an error.
+	pub fn insert(&mut self, k: K, v: V) -> AllocResult<Option<V>> {
+		match self.find(&k) {
+			Ok(index) => {
+				let (_, old) = self.slots[index].replace((k, v)).unwrap();
+				Ok(Some(old))
+			}
+			Err(Some(index)) => {
+				self.slots[index] = Some((k, v));
+				self.len += 1;
+				Ok(None)
+			}
+			Err(None) => Err(AllocError),
+		}
+	}
+
+	/// Removes an element from the hash map.
+	///
+	/// If the key was present, the function returns the previous value.
+	pub fn remove<Q: ?Sized>(&mut self, k: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		let mut hole = self.find(k).ok()?;
+		let (_, v) = self.slots[hole].take().unwrap();
+		self.len -= 1;
+
+		// Move back the following elements that can no longer be reached past the hole
+		let mut i = hole;
+		loop {
+			i = (i + 1) % N;
+			let Some((key, _)) = &self.slots[i] else {
+				break;
+			};
+			let home = Self::home_slot(key);
+			if (i + N - home) % N >= (i + N - hole) % N {
+				self.slots[hole] = self.slots[i].take();
+				hole = i;
+			}
+		}
+
+		Some(v)
+	}
+
+	/// Drops all elements in the hash map.
+	pub fn clear(&mut self) {
+		self.slots.iter_mut().for_each(|slot| *slot = None);
+		self.len = 0;
+	}
+}
+
+#[cfg(test)]
+mod test {
 	use super::*;
+	use crate::util::container::string::String;
+	use core::hash::BuildHasherDefault;
+	use core::sync::atomic;
+	use core::sync::atomic::AtomicUsize;
+
+	/// A hasher returning the first eight bytes written to it verbatim as the hash, in
+	/// little-endian order.
+	///
+	/// This allows choosing the hash of keys, to create collisions on purpose.
+	#[derive(Default)]
+	struct IdentityHasher {
+		/// The hash.
+		value: u64,
+		/// The number of bytes written so far.
+		off: usize,
+	}
+
+	impl Hasher for IdentityHasher {
+		fn write(&mut self, bytes: &[u8]) {
+			for b in bytes {
+				if self.off < size_of::<u64>() {
+					self.value |= (*b as u64) << (self.off * 8);
+				}
+				self.off += 1;
+			}
+		}
+
+		fn finish(&self) -> u64 {
+			self.value
+		}
+	}
+
+	/// A key with a chosen hash, compared by its identifier.
+	#[derive(Debug, Eq, PartialEq)]
+	struct CollidingKey {
+		/// The hash of the key.
+		hash: u64,
+		/// The identifier of the key.
+		id: u32,
+	}
+
+	impl Hash for CollidingKey {
+		fn hash<H: Hasher>(&self, state: &mut H) {
+			state.write(&self.hash.to_le_bytes());
+		}
+	}
+
+	/// Creates a hash map returning the chosen hash of each key.
+	fn colliding_hash_map() -> HashMap<CollidingKey, u32, BuildHasherDefault<IdentityHasher>> {
+		HashMap::with_hasher(BuildHasherDefault::default())
+	}
 
 	#[test_case]
 	fn hash_map0() {
@@ -509,4 +2421,1228 @@ mod test {
 			assert_eq!(hash_map.len(), i);
 		}
 	}
+
+	#[test_case]
+	fn hash_map_grow() {
+		let mut hash_map = HashMap::<u32, u32>::with_buckets(1);
+
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		assert_eq!(hash_map.len(), 1000);
+		assert!(hash_map.capacity() >= 1000);
+
+		for i in 0..1000 {
+			assert_eq!(hash_map[i], i);
+		}
+	}
+
+	#[test_case]
+	fn hash_map_bucket_index_mask() {
+		for buckets_count in [1, 2, 64, 1024, 1 << 20] {
+			for i in (0..10000u32).chain([u32::MAX - 1, u32::MAX]) {
+				let mut hasher = XORHasher::new();
+				i.hash(&mut hasher);
+				let expected = (hasher.finish() % (buckets_count as u64)) as usize;
+				assert_eq!(bucket_index(&XORHasherBuilder, &i, buckets_count), expected);
+			}
+		}
+		for n in [0, 1, 3, 100, 1000] {
+			assert!(LoadConfig::DEFAULT
+				.buckets_for_capacity(n)
+				.unwrap()
+				.is_power_of_two());
+		}
+		assert!(HashMap::<u32, u32>::with_buckets(0)
+			.get_buckets_count()
+			.is_power_of_two());
+	}
+
+	#[test_case]
+	fn hash_map_lookup_many() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..1000 {
+			hash_map.insert(i, i * 2).unwrap();
+		}
+		let mut found = 0;
+		for i in 0..100000 {
+			if let Some(v) = hash_map.get(&(i % 2000)) {
+				assert_eq!(*v, i % 2000 * 2);
+				found += 1;
+			}
+		}
+		assert_eq!(found, 50000);
+	}
+
+	#[test_case]
+	fn hash_map_raw_entry() {
+		let mut hash_map = HashMap::<u32, u32>::with_buckets(16);
+		// With 16 buckets, these keys share the same bucket
+		let hash = hash_map.hash_key(&1);
+		for k in [1, 17, 33] {
+			assert_eq!(hash_bucket_index(hash_map.hash_key(&k), 16), 1);
+		}
+
+		for k in [1, 17] {
+			let key_hash = hash_map.hash_key(&k);
+			match hash_map.raw_entry_mut().from_hash(hash, |key| *key == k) {
+				RawEntryMut::Occupied(_) => panic!(),
+				RawEntryMut::Vacant(e) => {
+					let (key, value) = e.insert_hashed(key_hash, k, k * 10).unwrap();
+					assert_eq!((*key, *value), (k, k * 10));
+				}
+			}
+		}
+		assert_eq!(hash_map.len(), 2);
+
+		// The equality closure tells colliding keys apart
+		let raw = hash_map.raw_entry();
+		assert_eq!(raw.from_hash(hash, |k| *k == 1), Some((&1, &10)));
+		let raw = hash_map.raw_entry();
+		assert_eq!(raw.from_hash(hash, |k| *k == 17), Some((&17, &170)));
+		let raw = hash_map.raw_entry();
+		assert_eq!(raw.from_hash(hash, |k| *k == 33), None);
+		assert_eq!(hash_map.raw_entry().from_key(&17), Some((&17, &170)));
+		assert_eq!(hash_map.get(&17), Some(&170));
+
+		match hash_map.raw_entry_mut().from_hash(hash, |k| *k == 17) {
+			RawEntryMut::Occupied(mut e) => {
+				assert_eq!(*e.key(), 17);
+				assert_eq!(e.insert(171), 170);
+			}
+			RawEntryMut::Vacant(_) => panic!(),
+		}
+		assert_eq!(hash_map.get(&17), Some(&171));
+		assert_eq!(hash_map.get(&1), Some(&10));
+		match hash_map.raw_entry_mut().from_key(&33) {
+			RawEntryMut::Occupied(_) => panic!(),
+			RawEntryMut::Vacant(e) => {
+				e.insert(33, 330).unwrap();
+			}
+		}
+		assert_eq!(hash_map.get(&33), Some(&330));
+		assert_eq!(hash_map.len(), 3);
+	}
+
+	#[test_case]
+	fn hash_map_try_extend() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		hash_map.try_extend((0..1000).map(|i| (i, i))).unwrap();
+		assert_eq!(hash_map.len(), 1000);
+		// Space is reserved once for the whole iterator
+		assert_eq!(
+			hash_map.get_buckets_count(),
+			LoadConfig::DEFAULT.buckets_for_capacity(1000).unwrap()
+		);
+
+		// Existing keys are replaced
+		hash_map
+			.try_extend((500..1500).map(|i| (i, i * 2)))
+			.unwrap();
+		assert_eq!(hash_map.len(), 1500);
+		for i in 0..1500 {
+			let value = if i < 500 { i } else { i * 2 };
+			assert_eq!(hash_map.get(&i), Some(&value));
+		}
+
+		// Without a size hint, the hash map grows as needed
+		let mut hash_map = HashMap::<u32, u32>::new();
+		hash_map
+			.try_extend((0..1000).filter(|i| i % 2 == 0).map(|i| (i, i)))
+			.unwrap();
+		assert_eq!(hash_map.len(), 500);
+		assert!(hash_map.capacity() >= 500);
+		hash_map.try_extend([]).unwrap();
+		assert_eq!(hash_map.len(), 500);
+	}
+
+	#[test_case]
+	fn hash_map_entry_by_hash() {
+		// Two maps sharing the same hasher keys compute the same hashes
+		let hasher = SipHasherBuilder::with_keys(1, 2);
+		let mut a = HashMap::<(u32, String), u32, _>::with_hasher(hasher.clone());
+		let mut b = HashMap::<(u32, String), u32, _>::with_hasher(hasher);
+
+		// The hash of the borrowed parts is the same as the hash of the owned key, since
+		// `String` hashes as its bytes
+		let hash = a.hash_key(&(1u32, b"foo".as_slice()));
+		let owned = (1u32, String::try_from("foo").unwrap());
+		assert_eq!(hash, a.hash_key(&owned));
+		assert_eq!(hash, b.hash_key(&owned));
+
+		let is_match = |(parent, name): &(u32, String)| *parent == 1 && name.as_bytes() == b"foo";
+		for (hash_map, value) in [(&mut a, 10), (&mut b, 20)] {
+			let RawEntryMut::Vacant(e) = hash_map.entry_by_hash(hash, is_match) else {
+				panic!();
+			};
+			let key = (1u32, String::try_from("foo").unwrap());
+			e.insert_hashed(hash, key, value).unwrap();
+		}
+
+		// Hashed once, probed in both maps without building an owned key
+		let RawEntryMut::Occupied(e) = a.entry_by_hash(hash, is_match) else {
+			panic!();
+		};
+		assert_eq!(*e.get(), 10);
+		let RawEntryMut::Occupied(e) = b.entry_by_hash(hash, is_match) else {
+			panic!();
+		};
+		assert_eq!(*e.get(), 20);
+		assert_eq!(a.get(&owned), Some(&10));
+		assert_eq!(b.get(&owned), Some(&20));
+
+		let other = a.hash_key(&(2u32, b"foo".as_slice()));
+		let is_match = |(parent, name): &(u32, String)| *parent == 2 && name.as_bytes() == b"foo";
+		assert!(matches!(
+			a.entry_by_hash(other, is_match),
+			RawEntryMut::Vacant(_)
+		));
+	}
+
+	/// Inserts elements into a hash map with the load configuration `load` until it grows, then
+	/// returns the highest number of elements reached before growing.
+	///
+	/// Since all hash maps start with the same number of buckets, the results are comparable.
+	fn max_len_before_growing(load: LoadConfig) -> usize {
+		let mut hash_map = HashMap::<u32, u32>::with_config(load);
+		let buckets_count = hash_map.get_buckets_count();
+		let mut i = 0;
+		while hash_map.get_buckets_count() == buckets_count {
+			hash_map.insert(i, i).unwrap();
+			i += 1;
+		}
+		hash_map.len() - 1
+	}
+
+	#[test_case]
+	fn hash_map_load_config() {
+		assert!(LoadConfig::new(0, 1).is_none());
+		assert!(LoadConfig::new(1, 0).is_none());
+		assert_eq!(
+			HashMap::<u32, u32>::new().get_load_config(),
+			LoadConfig::default()
+		);
+
+		let dense = LoadConfig::new(7, 8).unwrap();
+		let sparse = LoadConfig::new(1, 2).unwrap();
+		let dense_len = max_len_before_growing(dense);
+		let sparse_len = max_len_before_growing(sparse);
+		assert_eq!(dense_len, DEFAULT_BUCKETS_COUNT * 7 / 8);
+		assert_eq!(sparse_len, DEFAULT_BUCKETS_COUNT / 2);
+		assert!(dense_len > max_len_before_growing(LoadConfig::DEFAULT));
+		assert!(sparse_len < max_len_before_growing(LoadConfig::DEFAULT));
+
+		// Reserving honors the configuration
+		let mut hash_map = HashMap::<u32, u32>::with_config(dense);
+		hash_map.reserve(700).unwrap();
+		assert_eq!(hash_map.get_buckets_count(), 1024);
+		assert!(hash_map.capacity() >= 700);
+		let mut hash_map = HashMap::<u32, u32>::with_config(sparse);
+		hash_map.reserve(700).unwrap();
+		assert_eq!(hash_map.get_buckets_count(), 2048);
+		// The configuration is kept when cloning
+		let hash_map = hash_map.try_clone().unwrap();
+		assert_eq!(hash_map.get_load_config(), sparse);
+	}
+
+	#[test_case]
+	fn hash_map_min_load() {
+		assert!(LoadConfig::DEFAULT.with_min_load(0, 1).is_none());
+		assert!(LoadConfig::DEFAULT.with_min_load(1, 0).is_none());
+		// Must be less than half the maximum load factor
+		assert!(LoadConfig::DEFAULT.with_min_load(3, 8).is_none());
+		assert!(LoadConfig::DEFAULT.with_min_load(1, 2).is_none());
+		let config = LoadConfig::DEFAULT.with_min_load(1, 4).unwrap();
+
+		// Without a minimum load factor, the hash map never shrinks by itself
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let buckets_count = hash_map.get_buckets_count();
+		for i in 10..1000 {
+			hash_map.remove(&i);
+		}
+		assert_eq!(hash_map.get_buckets_count(), buckets_count);
+		hash_map.shrink_to_fit().unwrap();
+		assert!(hash_map.get_buckets_count() < buckets_count);
+
+		let mut hash_map = HashMap::<u32, u32>::with_config(config);
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		assert_eq!(hash_map.get_buckets_count(), buckets_count);
+		for i in 10..1000 {
+			hash_map.remove(&i);
+			// The load never goes below the minimum
+			assert!(hash_map.len() * 4 >= hash_map.get_buckets_count() - 4);
+		}
+		assert!(hash_map.get_buckets_count() <= 32);
+		for i in 0..10 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+
+		// Through entries and retain
+		for i in 10..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		for i in 10..500 {
+			let Entry::Occupied(e) = hash_map.entry(i) else {
+				panic!();
+			};
+			assert_eq!(e.remove(), i);
+		}
+		assert!(hash_map.get_buckets_count() < buckets_count);
+		hash_map.retain(|k, _| *k < 10);
+		assert!(hash_map.get_buckets_count() <= 32);
+		assert_eq!(hash_map.len(), 10);
+		for i in 0..10 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+	}
+
+	#[test_case]
+	fn sip_hasher() {
+		let builder = SipHasherBuilder::with_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+		let msg: [u8; 64] = core::array::from_fn(|i| i as u8);
+		for (len, hash) in [
+			(0, 0xabac0158050fc4dc),
+			(1, 0xc9f49bf37d57ca93),
+			(7, 0xd3927d989bb11140),
+			(8, 0x369095118d299a8e),
+			(15, 0xd320d86d2a519956),
+			(63, 0x9d199062b7bbb3a8),
+		] {
+			let mut hasher = builder.build_hasher();
+			hasher.write(&msg[..len]);
+			assert_eq!(hasher.finish(), hash);
+		}
+		// Writing in several parts gives the same result
+		let mut hasher = builder.build_hasher();
+		for part in [&msg[..3], &msg[3..12], &msg[12..13], &msg[13..63]] {
+			hasher.write(part);
+		}
+		assert_eq!(hasher.finish(), 0x9d199062b7bbb3a8);
+	}
+
+	#[test_case]
+	fn hash_map_tagged() {
+		static TAG: AllocTag = AllocTag::new("hash_map_tagged");
+
+		let mut hash_map = HashMap::<u32, u32>::new_tagged(&TAG);
+		assert_eq!(TAG.usage(), 0);
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let usage = TAG.usage();
+		assert!(usage >= 100 * size_of::<(u32, u32)>());
+
+		// Growing keeps the tag
+		hash_map.reserve(1000).unwrap();
+		assert!(TAG.usage() > usage);
+		let usage = TAG.usage();
+		// Untagged maps are not accounted for
+		let mut untagged = HashMap::<u32, u32>::new();
+		untagged.insert(0, 0).unwrap();
+		assert_eq!(TAG.usage(), usage);
+		// Clones keep the tag
+		let clone = hash_map.try_clone().unwrap();
+		assert_eq!(TAG.usage(), usage * 2);
+
+		drop(clone);
+		assert_eq!(TAG.usage(), usage);
+		drop(hash_map);
+		assert_eq!(TAG.usage(), 0);
+	}
+
+	#[test_case]
+	fn hash_map_sip_builder_keys() {
+		init_sip_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+		let a = SipHasherBuilder::new();
+		let b = SipHasherBuilder::new();
+		assert_ne!((a.k0, a.k1), (0, 0));
+		assert_ne!((a.k0, a.k1), (b.k0, b.k1));
+		assert_ne!(a.hash_one(42u32), b.hash_one(42u32));
+		// Clones hash the same way
+		assert_eq!(a.hash_one(42u32), a.clone().hash_one(42u32));
+	}
+
+	#[test_case]
+	fn hash_map_sip_hasher() {
+		init_sip_keys(0x0706050403020100, 0x0f0e0d0c0b0a0908);
+		let mut hash_map = HashMap::<u32, u32, _>::with_hasher(SipHasherBuilder::new());
+		for i in 0..100 {
+			hash_map.insert(i, i * 2).unwrap();
+		}
+		for i in 0..100 {
+			assert_eq!(hash_map.get(&i), Some(&(i * 2)));
+		}
+		assert_eq!(hash_map.get(&100), None);
+		assert_eq!(hash_map.remove(&42), Some(84));
+		assert_eq!(hash_map.len(), 99);
+	}
+
+	#[test_case]
+	fn hash_map_identity_hasher() {
+		let mut hash_map = colliding_hash_map();
+		let key = |hash, id| CollidingKey {
+			hash,
+			id,
+		};
+		assert_eq!(hash_map.hash_key(&key(0x1234, 0)), 0x1234);
+
+		// `a` and `b` have the same hash, `c` has a different hash in the same bucket
+		let buckets_count = hash_map.get_buckets_count() as u64;
+		let (a, b, c) = (key(7, 1), key(7, 2), key(7 + buckets_count, 3));
+		for (i, k) in [a, b, c].into_iter().enumerate() {
+			assert_eq!(
+				hash_bucket_index(hash_map.hash_key(&k), buckets_count as _),
+				7
+			);
+			hash_map.insert(k, i as u32).unwrap();
+		}
+		assert_eq!(hash_map.len(), 3);
+		assert_eq!(hash_map.get(&key(7, 1)), Some(&0));
+		assert_eq!(hash_map.get(&key(7, 2)), Some(&1));
+		assert_eq!(hash_map.get(&key(7 + buckets_count, 3)), Some(&2));
+		assert_eq!(hash_map.get(&key(7, 3)), None);
+
+		// Remove and reinsert
+		assert_eq!(hash_map.remove(&key(7, 2)), Some(1));
+		assert_eq!(hash_map.get(&key(7, 2)), None);
+		assert_eq!(hash_map.get(&key(7, 1)), Some(&0));
+		assert_eq!(hash_map.get(&key(7 + buckets_count, 3)), Some(&2));
+		assert_eq!(hash_map.insert(key(7, 2), 42).unwrap(), None);
+		assert_eq!(hash_map.get(&key(7, 2)), Some(&42));
+		assert_eq!(hash_map.len(), 3);
+
+		// Keys with the same hash stay together when growing
+		for id in 4..200 {
+			hash_map.insert(key(7, id), id).unwrap();
+		}
+		assert!(hash_map.get_buckets_count() as u64 > buckets_count);
+		assert_eq!(hash_map.len(), 199);
+		for id in 4..200 {
+			assert_eq!(hash_map.get(&key(7, id)), Some(&id));
+		}
+		assert_eq!(hash_map.get(&key(7 + buckets_count, 3)), Some(&2));
+		for id in (4..200).step_by(2) {
+			assert_eq!(hash_map.remove(&key(7, id)), Some(id));
+		}
+		for id in (5..200).step_by(2) {
+			assert_eq!(hash_map.get(&key(7, id)), Some(&id));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_iter_mut() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		assert_eq!(hash_map.iter_mut().next(), None);
+
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let mut iter = hash_map.iter_mut();
+		assert_eq!(iter.len(), 1000);
+		iter.next().unwrap();
+		assert_eq!(iter.len(), 999);
+		assert_eq!(iter.count(), 999);
+
+		for (k, v) in hash_map.iter_mut() {
+			*v = *k * 2;
+		}
+		assert_eq!(hash_map.len(), 1000);
+		for i in 0..1000 {
+			assert_eq!(hash_map[i], i * 2);
+		}
+
+		let mut iter = hash_map.iter_mut();
+		assert_eq!(iter.by_ref().count(), 1000);
+		assert!(iter.next().is_none());
+		assert!(iter.next().is_none());
+	}
+
+	#[test_case]
+	fn hash_map_drain() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		assert_eq!(hash_map.drain().next(), None);
+
+		for i in 0..100 {
+			hash_map.insert(i, i * 2).unwrap();
+		}
+		let buckets_count = hash_map.get_buckets_count();
+		let mut drain = hash_map.drain();
+		assert_eq!(drain.len(), 100);
+		let mut seen = crate::vec![false; 100].unwrap();
+		for (k, v) in drain.by_ref() {
+			assert_eq!(v, k * 2);
+			assert!(!seen[k as usize]);
+			seen[k as usize] = true;
+		}
+		assert_eq!(drain.len(), 0);
+		assert!(drain.next().is_none());
+		drop(drain);
+		assert!(seen.iter().all(|s| *s));
+		assert!(hash_map.is_empty());
+		assert_eq!(hash_map.get_buckets_count(), buckets_count);
+
+		// Still usable
+		hash_map.insert(1, 1).unwrap();
+		assert_eq!(hash_map.get(&1), Some(&1));
+
+		// Dropping the iterator early drops the remaining elements
+		static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+		/// Counts its drops.
+		struct Counted;
+
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				DROPS.fetch_add(1, atomic::Ordering::Relaxed);
+			}
+		}
+
+		let mut hash_map = HashMap::<u32, Counted>::new();
+		for i in 0..10 {
+			hash_map.insert(i, Counted).unwrap();
+		}
+		let mut drain = hash_map.drain();
+		drop(drain.next());
+		assert_eq!(DROPS.load(atomic::Ordering::Relaxed), 1);
+		drop(drain);
+		assert_eq!(DROPS.load(atomic::Ordering::Relaxed), 10);
+		assert!(hash_map.is_empty());
+		assert_eq!(hash_map.iter().count(), 0);
+	}
+
+	#[test_case]
+	fn hash_map_entry() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+
+		// Vacant
+		match hash_map.entry(1) {
+			Entry::Occupied(_) => panic!("the key should not be present"),
+			Entry::Vacant(e) => {
+				assert_eq!(*e.key(), 1);
+				assert_eq!(*e.insert(10).unwrap(), 10);
+			}
+		}
+		assert_eq!(*hash_map.entry(2).or_insert(20).unwrap(), 20);
+		assert_eq!(hash_map.len(), 2);
+
+		// Occupied
+		assert_eq!(*hash_map.entry(1).key(), 1);
+		assert_eq!(*hash_map.entry(1).or_insert(0).unwrap(), 10);
+		hash_map.entry(1).and_modify(|v| *v += 1);
+		assert_eq!(hash_map.get(&1), Some(&11));
+		// Not called on vacant entries
+		hash_map
+			.entry(3)
+			.and_modify(|_| panic!("called on a vacant entry"));
+		assert_eq!(hash_map.get(&3), None);
+		match hash_map.entry(1) {
+			Entry::Occupied(mut e) => {
+				assert_eq!(*e.get(), 11);
+				*e.get_mut() += 1;
+				assert_eq!(e.insert(42), 12);
+				assert_eq!(e.remove(), 42);
+			}
+			Entry::Vacant(_) => panic!("the key should be present"),
+		}
+		assert_eq!(hash_map.get(&1), None);
+		assert_eq!(hash_map.len(), 1);
+
+		// Fallible insertion
+		let res: AllocResult<&mut u32> = hash_map.entry(4).or_try_insert_with(|| Err(AllocError));
+		assert!(res.is_err());
+		assert_eq!(hash_map.get(&4), None);
+		let v = hash_map
+			.entry(4)
+			.or_try_insert_with::<AllocError, _>(|| Ok(40))
+			.unwrap();
+		assert_eq!(*v, 40);
+		let v = hash_map
+			.entry(4)
+			.or_try_insert_with::<AllocError, _>(|| panic!("called on an existing key"))
+			.unwrap();
+		assert_eq!(*v, 40);
+
+		// Inserting through entries grows the hash map
+		let buckets_count = hash_map.get_buckets_count();
+		for i in 100..1100 {
+			*hash_map.entry(i).or_insert(0).unwrap() += i;
+		}
+		assert!(hash_map.get_buckets_count() > buckets_count);
+		assert_eq!(hash_map.len(), 1002);
+		for i in 100..1100 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_keys_values() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		assert_eq!(hash_map.keys().next(), None);
+		assert_eq!(hash_map.values().next(), None);
+
+		for i in 0..100 {
+			hash_map.insert(i, i * 2).unwrap();
+		}
+		assert_eq!(hash_map.keys().len(), 100);
+		assert_eq!(hash_map.values().len(), 100);
+		assert_eq!(hash_map.keys().map(|k| *k as usize).sum::<usize>(), 4950);
+		assert_eq!(hash_map.values().map(|v| *v as usize).sum::<usize>(), 9900);
+
+		for v in hash_map.values_mut() {
+			*v += 1;
+		}
+		for (k, v) in &hash_map {
+			assert_eq!(*v, k * 2 + 1);
+		}
+		for (_, v) in &mut hash_map {
+			*v -= 1;
+		}
+		for i in 0..100 {
+			assert_eq!(hash_map[i], i * 2);
+		}
+	}
+
+	#[test_case]
+	fn hash_map_into_iter() {
+		let mut hash_map = HashMap::<u32, String>::new();
+		for i in 0..100 {
+			hash_map.insert(i, crate::format!("{i}").unwrap()).unwrap();
+		}
+
+		let mut iter = hash_map.into_iter();
+		assert_eq!(iter.len(), 100);
+		let mut seen = crate::vec![false; 100].unwrap();
+		// Values are moved out of the hash map
+		for (k, v) in iter.by_ref().take(50) {
+			assert_eq!(v, crate::format!("{k}").unwrap());
+			seen[k as usize] = true;
+		}
+		assert_eq!(iter.len(), 50);
+		// The remaining elements are dropped with the iterator
+		drop(iter);
+		assert_eq!(seen.iter().filter(|s| **s).count(), 50);
+
+		assert_eq!(HashMap::<u32, u32>::new().into_iter().next(), None);
+	}
+
+	#[test_case]
+	fn hash_map_with_capacity() {
+		let mut hash_map = HashMap::<u32, u32>::with_capacity(100).unwrap();
+		let capacity = hash_map.capacity();
+		assert!(capacity >= 100);
+
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+			assert_eq!(hash_map.capacity(), capacity);
+		}
+		assert_eq!(hash_map.len(), 100);
+	}
+
+	#[test_case]
+	fn hash_map_capacity_overflow() {
+		assert!(HashMap::<u32, u32>::with_capacity(usize::MAX / 2).is_err());
+		assert!(HashMap::<u32, u32>::with_capacity(usize::MAX).is_err());
+
+		let mut hash_map = HashMap::<u32, u32>::new();
+		hash_map.insert(0, 0).unwrap();
+		assert!(hash_map.reserve(usize::MAX).is_err());
+		assert!(hash_map.reserve(usize::MAX / 2).is_err());
+		// Left untouched
+		assert_eq!(hash_map.get(&0), Some(&0));
+		assert_eq!(hash_map.len(), 1);
+
+		// The capacity does not overflow
+		let hash_map = HashMap::<u32, u32>::with_buckets(usize::MAX);
+		assert_eq!(hash_map.get_buckets_count(), 1 << (usize::BITS - 1));
+		assert_eq!(hash_map.capacity(), (1 << (usize::BITS - 1)) / 4 * 3);
+		let hash_map = HashMap::<u32, u32>::with_buckets(7);
+		assert_eq!(hash_map.get_buckets_count(), 8);
+		assert_eq!(hash_map.capacity(), 6);
+	}
+
+	#[test_case]
+	fn hash_map_reserve() {
+		for n in [1, 2, 3, 17, 48, 49, 100, 1000] {
+			let mut hash_map = HashMap::<usize, usize>::with_capacity(n).unwrap();
+			let buckets_count = hash_map.get_buckets_count();
+			for i in 0..n {
+				hash_map.insert(i, i).unwrap();
+			}
+			assert_eq!(hash_map.get_buckets_count(), buckets_count);
+
+			// Both entry points agree
+			let mut reserved = HashMap::<usize, usize>::new();
+			reserved.reserve(n).unwrap();
+			assert!(reserved.capacity() >= n);
+			if n > HashMap::<usize, usize>::new().capacity() {
+				assert_eq!(reserved.get_buckets_count(), buckets_count);
+			}
+			let buckets_count = reserved.get_buckets_count();
+			for i in 0..n {
+				reserved.insert(i, i).unwrap();
+			}
+			assert_eq!(reserved.get_buckets_count(), buckets_count);
+
+			// Reserving room that is already there does nothing
+			reserved.reserve(0).unwrap();
+			assert_eq!(reserved.get_buckets_count(), buckets_count);
+		}
+	}
+
+	#[test_case]
+	fn hash_map_reserve_exact() {
+		for n in [1, 17, 100, 1000] {
+			let mut reserved = HashMap::<usize, usize>::with_buckets(1);
+			reserved.reserve(n).unwrap();
+			let mut exact = HashMap::<usize, usize>::with_buckets(1);
+			exact.reserve_exact(n).unwrap();
+			assert!(exact.capacity() >= n);
+			assert!(exact.capacity() <= reserved.capacity());
+		}
+
+		// 100 elements need 134 buckets, instead of 256 with `reserve`
+		let mut hash_map = HashMap::<usize, usize>::with_buckets(1);
+		hash_map.reserve_exact(100).unwrap();
+		assert_eq!(hash_map.get_buckets_count(), 134);
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+		assert_eq!(hash_map.get_buckets_count(), 134);
+		for i in 0..100 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+		// Inserting beyond the reserved space grows back to a power of two
+		hash_map.insert(100, 100).unwrap();
+		assert_eq!(hash_map.get_buckets_count(), 256);
+		for i in 0..=100 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_shrink_to() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let capacity = hash_map.capacity();
+		hash_map.retain(|k, _| *k < 10);
+
+		hash_map.shrink_to(50).unwrap();
+		assert!(hash_map.capacity() >= 50);
+		assert!(hash_map.capacity() < capacity / 4);
+		assert_eq!(hash_map.len(), 10);
+		for i in 0..10 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+
+		// Never grows
+		let buckets_count = hash_map.get_buckets_count();
+		hash_map.shrink_to(10000).unwrap();
+		assert_eq!(hash_map.get_buckets_count(), buckets_count);
+
+		// Never below the number of elements
+		hash_map.shrink_to(0).unwrap();
+		assert!(hash_map.capacity() >= 10);
+		assert_eq!(hash_map.len(), 10);
+		for i in 0..10 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_retain() {
+		let mut hash_map = HashMap::<usize, usize>::new();
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+
+		hash_map.retain(|k, _| k % 2 == 0);
+		assert_eq!(hash_map.len(), 50);
+		for i in 0..100 {
+			assert_eq!(hash_map.get(&i).is_some(), i % 2 == 0);
+		}
+	}
+
+	#[test_case]
+	fn hash_map_retain_mut() {
+		let mut hash_map = HashMap::<usize, usize>::new();
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+
+		let mut visited = 0;
+		hash_map.retain(|k, v| {
+			visited += 1;
+			*v += 1000;
+			k % 3 != 0
+		});
+		assert_eq!(visited, 100);
+		assert_eq!(hash_map.len(), 66);
+		for i in 0..100 {
+			if i % 3 == 0 {
+				assert_eq!(hash_map.get(&i), None);
+			} else {
+				assert_eq!(hash_map.get(&i), Some(&(i + 1000)));
+			}
+		}
+	}
+
+	#[test_case]
+	fn hash_map_retain_n() {
+		let mut hash_map = HashMap::<usize, usize>::new();
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+
+		hash_map.retain_n(10, |_, _| true);
+		assert_eq!(hash_map.len(), 10);
+		assert_eq!(hash_map.iter().count(), 10);
+
+		// The predicate still applies
+		hash_map.retain_n(10, |k, _| k % 2 == 0);
+		assert!(hash_map.len() <= 10);
+		assert!(hash_map.iter().all(|(k, _)| k % 2 == 0));
+
+		hash_map.retain_n(0, |_, _| true);
+		assert!(hash_map.is_empty());
+	}
+
+	#[cfg(config_debug_debug)]
+	#[test_case]
+	fn hash_map_probe_stats() {
+		let mut hash_map = HashMap::<usize, usize>::with_capacity(100).unwrap();
+		let buckets_count = hash_map.get_buckets_count();
+		assert_eq!(
+			hash_map.probe_stats(),
+			ProbeStats {
+				max: 0,
+				total: 0,
+				empty_buckets: buckets_count,
+			}
+		);
+
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let stats = hash_map.probe_stats();
+		assert!(stats.max >= 1);
+		assert!(stats.total >= 100);
+		assert!(stats.empty_buckets < buckets_count);
+
+		// Removing elements shortens lookups
+		for i in 0..50 {
+			hash_map.remove(&i);
+		}
+		let after = hash_map.probe_stats();
+		assert!(after.total < stats.total);
+		assert!(after.empty_buckets >= stats.empty_buckets);
+
+		// With a minimum load factor, removals shrink the hash map instead of leaving empty
+		// buckets over
+		let config = LoadConfig::DEFAULT.with_min_load(1, 4).unwrap();
+		let mut hash_map = HashMap::<usize, usize>::with_config(config);
+		for i in 0..100 {
+			hash_map.insert(i, i).unwrap();
+		}
+		let buckets_count = hash_map.get_buckets_count();
+		for i in 0..90 {
+			hash_map.remove(&i);
+		}
+		assert!(hash_map.get_buckets_count() < buckets_count);
+		let stats = hash_map.probe_stats();
+		assert!(stats.total >= 10);
+		assert!(stats.empty_buckets < hash_map.get_buckets_count());
+		// The load is kept above the minimum
+		assert!(hash_map.len() * 4 >= hash_map.get_buckets_count() - 4);
+	}
+
+	#[test_case]
+	fn hash_map_get_or_insert_with() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+
+		// Vacant
+		let v = hash_map.get_or_insert_with(1, || 10).unwrap();
+		assert_eq!(*v, 10);
+		*v += 1;
+		assert_eq!(hash_map.get(&1), Some(&11));
+		assert_eq!(hash_map.len(), 1);
+
+		// Occupied: `f` is not called
+		let v = hash_map
+			.get_or_insert_with(1, || panic!("called on an existing key"))
+			.unwrap();
+		assert_eq!(*v, 11);
+		*v = 42;
+		assert_eq!(hash_map[1], 42);
+		assert_eq!(hash_map.len(), 1);
+
+		// Growing
+		let capacity = hash_map.capacity() as u32;
+		for i in 0..(capacity * 2) {
+			*hash_map.get_or_insert_with(i, || 0).unwrap() += 1;
+		}
+		assert_eq!(hash_map.len(), capacity as usize * 2);
+		assert_eq!(hash_map[1], 43);
+		assert_eq!(hash_map[0], 1);
+	}
+
+	#[test_case]
+	fn hash_map_try_from_iter() {
+		let hash_map = HashMap::try_from_iter((0..100u32).map(|i| (i, i * 2))).unwrap();
+		assert_eq!(hash_map.len(), 100);
+		for i in 0..100 {
+			assert_eq!(hash_map[i], i * 2);
+		}
+
+		// Duplicate keys keep the last value
+		let hash_map = HashMap::try_from_iter([(1u32, 1u32), (2, 2), (1, 3)]).unwrap();
+		assert_eq!(hash_map.len(), 2);
+		assert_eq!(hash_map[1], 3);
+
+		let hash_map = (0..10u32)
+			.map(|i| (i, i))
+			.collect::<CollectResult<HashMap<_, _>>>()
+			.0
+			.unwrap();
+		assert_eq!(hash_map.len(), 10);
+	}
+
+	/// A key counting its conversions from a borrowed key.
+	#[derive(Eq, Hash, PartialEq)]
+	struct CountedKey(u32);
+
+	static KEY_CLONES: AtomicUsize = AtomicUsize::new(0);
+
+	impl TryFrom<&CountedKey> for CountedKey {
+		type Error = AllocError;
+
+		fn try_from(k: &CountedKey) -> Result<Self, Self::Error> {
+			KEY_CLONES.fetch_add(1, atomic::Ordering::Relaxed);
+			Ok(Self(k.0))
+		}
+	}
+
+	#[test_case]
+	fn hash_map_entry_ref() {
+		let mut hash_map = HashMap::<CountedKey, u32>::new();
+		let clones = KEY_CLONES.load(atomic::Ordering::Relaxed);
+
+		// Vacant: the key is converted once
+		*hash_map.entry_ref(&CountedKey(1)).or_insert(10).unwrap() += 1;
+		assert_eq!(KEY_CLONES.load(atomic::Ordering::Relaxed), clones + 1);
+		assert_eq!(hash_map.get(&CountedKey(1)), Some(&11));
+		assert_eq!(hash_map.len(), 1);
+
+		// Occupied: the key is not converted
+		match hash_map.entry_ref(&CountedKey(1)) {
+			EntryRef::Occupied(mut e) => {
+				assert_eq!(*e.get(), 11);
+				assert_eq!(e.insert(42), 11);
+			}
+			EntryRef::Vacant(_) => panic!("the key should be present"),
+		}
+		let v = hash_map
+			.entry_ref(&CountedKey(1))
+			.or_insert_with(|| panic!("called on an existing key"))
+			.unwrap();
+		assert_eq!(*v, 42);
+		assert_eq!(KEY_CLONES.load(atomic::Ordering::Relaxed), clones + 1);
+		assert_eq!(hash_map.len(), 1);
+
+		// Borrowed keys of another type
+		let mut hash_map = HashMap::<String, u32>::new();
+		*hash_map.entry_ref(b"foo".as_slice()).or_insert(0).unwrap() += 1;
+		*hash_map.entry_ref(b"foo".as_slice()).or_insert(0).unwrap() += 1;
+		assert_eq!(hash_map.get(b"foo".as_slice()), Some(&2));
+		assert_eq!(hash_map.len(), 1);
+
+		// Growing
+		let mut hash_map = HashMap::<CountedKey, u32>::new();
+		let capacity = hash_map.capacity() as u32;
+		for i in 0..(capacity * 2) {
+			hash_map.entry_ref(&CountedKey(i)).or_insert(i).unwrap();
+		}
+		assert_eq!(hash_map.len(), capacity as usize * 2);
+		for i in 0..(capacity * 2) {
+			assert_eq!(hash_map.get(&CountedKey(i)), Some(&i));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_remove_all() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..10 {
+			hash_map.insert(i, i).unwrap();
+		}
+
+		// Present, absent and duplicate keys
+		assert_eq!(hash_map.remove_all(&[1, 3, 42, 5, 3, 100]), 3);
+		assert_eq!(hash_map.len(), 7);
+		for i in [1, 3, 5] {
+			assert!(!hash_map.contains_key(&i));
+		}
+		for i in [0, 2, 4, 6, 7, 8, 9] {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+
+		assert_eq!(hash_map.remove_all(&[]), 0);
+		assert_eq!(hash_map.remove_all(&[0, 2, 4, 6, 7, 8, 9]), 7);
+		assert!(hash_map.is_empty());
+	}
+
+	#[test_case]
+	fn hash_map_try_clone_into() {
+		let mut src = HashMap::<u32, u32>::new();
+		for i in 0..100 {
+			src.insert(i, i * 2).unwrap();
+		}
+
+		// Large enough destination with previous elements
+		let mut dest = HashMap::<u32, u32>::with_capacity(200).unwrap();
+		for i in 1000..1050 {
+			dest.insert(i, i).unwrap();
+		}
+		let buckets_count = dest.get_buckets_count();
+		let buckets_ptr = dest.buckets.as_slice().as_ptr();
+		src.try_clone_into(&mut dest).unwrap();
+		// No reallocation
+		assert_eq!(dest.get_buckets_count(), buckets_count);
+		assert_eq!(dest.buckets.as_slice().as_ptr(), buckets_ptr);
+		assert_eq!(dest.len(), 100);
+		for i in 0..100 {
+			assert_eq!(dest.get(&i), Some(&(i * 2)));
+		}
+		assert!(!dest.contains_key(&1000));
+
+		// Destination too small
+		let mut dest = HashMap::<u32, u32>::with_buckets(4);
+		src.try_clone_into(&mut dest).unwrap();
+		assert!(dest.capacity() >= 100);
+		assert_eq!(dest.len(), 100);
+		for i in 0..100 {
+			assert_eq!(dest.get(&i), Some(&(i * 2)));
+		}
+	}
+
+	#[test_case]
+	fn hash_map_find_entry() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..10 {
+			hash_map.insert(i, i * 10).unwrap();
+		}
+
+		assert_eq!(hash_map.find_entry(|_, v| *v == 70), Some((&7, &70)));
+		assert_eq!(hash_map.find_entry(|_, v| *v == 75), None);
+		// Any of the matching elements
+		let (k, v) = hash_map.find_entry(|k, _| *k % 2 == 1).unwrap();
+		assert_eq!(*k % 2, 1);
+		assert_eq!(*v, *k * 10);
+
+		let (k, v) = hash_map.find_entry_mut(|_, v| *v == 30).unwrap();
+		assert_eq!(*k, 3);
+		*v = 31;
+		assert_eq!(hash_map[3], 31);
+		assert!(hash_map.find_entry_mut(|k, _| *k >= 10).is_none());
+
+		let mut hash_map = HashMap::<u32, u32>::new();
+		assert_eq!(hash_map.find_entry(|_, _| true), None);
+		assert!(hash_map.find_entry_mut(|_, _| true).is_none());
+	}
+
+	#[test_case]
+	fn hash_map_append() {
+		let mut a = HashMap::<u32, u32>::new();
+		let mut b = HashMap::<u32, u32>::new();
+		for i in 0..100 {
+			a.insert(i, i).unwrap();
+		}
+		for i in 50..150 {
+			b.insert(i, i * 10).unwrap();
+		}
+
+		a.append(&mut b).unwrap();
+		assert!(b.is_empty());
+		assert_eq!(b.iter().count(), 0);
+		assert_eq!(a.len(), 150);
+		for i in 0..50 {
+			assert_eq!(a.get(&i), Some(&i));
+		}
+		// Values from the other map win
+		for i in 50..150 {
+			assert_eq!(a.get(&i), Some(&(i * 10)));
+		}
+
+		// Both maps can still be used
+		b.insert(1, 1).unwrap();
+		a.append(&mut b).unwrap();
+		assert_eq!(a.len(), 150);
+		assert_eq!(a[1], 1);
+		a.append(&mut b).unwrap();
+		assert_eq!(a.len(), 150);
+	}
+
+	#[test_case]
+	fn hash_map_iter_sorted_by_key() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		// Pseudo-random order
+		for i in 0..200u32 {
+			let k = i.wrapping_mul(7919) % 1009;
+			hash_map.insert(k, k * 2).unwrap();
+		}
+
+		let sorted = hash_map.iter_sorted_by_key().unwrap();
+		assert_eq!(sorted.len(), 200);
+		assert!(sorted.windows(2).all(|w| w[0].0 < w[1].0));
+		assert!(sorted.iter().all(|(k, v)| **v == **k * 2));
+
+		let hash_map = HashMap::<u32, u32>::new();
+		assert!(hash_map.iter_sorted_by_key().unwrap().is_empty());
+	}
+
+	#[test_case]
+	fn hash_map_fmt() {
+		let hash_map = HashMap::<u32, u32>::try_from([(1, 10), (2, 20)]).unwrap();
+
+		let s = crate::format!("{:?}", hash_map).unwrap();
+		assert!(s.starts_with(b"{"));
+		assert!(s.ends_with(b"}"));
+		assert!(s.windows(5).any(|w| w == b"1: 10"));
+		assert!(s.windows(5).any(|w| w == b"2: 20"));
+		// Alternate form
+		let s = crate::format!("{:#?}", hash_map).unwrap();
+		assert!(s.windows(7).any(|w| w == b"{\n    1"));
+
+		let hash_map = HashMap::<u32, u32>::new();
+		assert_eq!(crate::format!("{:?}", hash_map).unwrap(), "{}");
+	}
+
+	#[test_case]
+	fn array_hash_map() {
+		let mut hash_map = ArrayHashMap::<u32, u32, 8>::new();
+		for i in 0..8 {
+			assert_eq!(hash_map.insert(i, i).unwrap(), None);
+		}
+		assert_eq!(hash_map.len(), hash_map.capacity());
+
+		// Full
+		assert!(hash_map.insert(8, 8).is_err());
+		assert_eq!(hash_map.len(), 8);
+		assert!(!hash_map.contains_key(&8));
+		// Replacing an existing key doesn't require room
+		assert_eq!(hash_map.insert(0, 42).unwrap(), Some(0));
+		assert_eq!(hash_map.get(&0), Some(&42));
+
+		assert_eq!(hash_map.remove(&0), Some(42));
+		assert_eq!(hash_map.remove(&0), None);
+		hash_map.insert(8, 8).unwrap();
+		for i in 1..9 {
+			assert_eq!(hash_map.get(&i), Some(&i));
+		}
+		assert_eq!(hash_map.iter().count(), 8);
+
+		hash_map.clear();
+		assert!(hash_map.is_empty());
+		assert_eq!(hash_map.get(&1), None);
+	}
+
+	#[test_case]
+	fn array_hash_map_collisions() {
+		let mut hash_map = ArrayHashMap::<u32, u32, 4>::new();
+		// All keys have the same home slot
+		let keys = [1, 5, 9, 13];
+		for k in keys {
+			hash_map.insert(k, k).unwrap();
+		}
+
+		// Removing an element keeps the following ones reachable
+		assert_eq!(hash_map.remove(&5), Some(5));
+		for k in [1, 9, 13] {
+			assert_eq!(hash_map.get(&k), Some(&k));
+		}
+		assert_eq!(hash_map.remove(&1), Some(1));
+		assert_eq!(hash_map.get(&9), Some(&9));
+		assert_eq!(hash_map.get(&13), Some(&13));
+		assert_eq!(hash_map.len(), 2);
+
+		let mut empty = ArrayHashMap::<u32, u32, 0>::new();
+		assert!(empty.insert(0, 0).is_err());
+		assert_eq!(empty.remove(&0), None);
+
+		// The capacity does not have to be a power of two
+		let mut hash_map = ArrayHashMap::<u32, u32, 5>::new();
+		for k in 0..5 {
+			hash_map.insert(k, k).unwrap();
+		}
+		assert!(hash_map.insert(5, 5).is_err());
+		assert_eq!(hash_map.remove(&2), Some(2));
+		for k in [0, 1, 3, 4] {
+			assert_eq!(hash_map.get(&k), Some(&k));
+		}
+	}
+
+	#[test_case]
+	fn xor_hasher_words() {
+		let values = [0u64, 1, 0xdeadbeef, 0x0123456789abcdef, u64::MAX];
+		// Start from every offset
+		for prefix in 0..8 {
+			for x in values {
+				let mut fast = XORHasher::new();
+				let mut slow = XORHasher::new();
+				fast.write(&[0xaa; 8][..prefix]);
+				slow.write(&[0xaa; 8][..prefix]);
+
+				fast.write_u64(x);
+				slow.write(&x.to_ne_bytes());
+				assert_eq!(fast.finish(), slow.finish());
+
+				fast.write_u32(x as u32);
+				slow.write(&(x as u32).to_ne_bytes());
+				assert_eq!(fast.finish(), slow.finish());
+
+				fast.write_usize(x as usize);
+				slow.write(&(x as usize).to_ne_bytes());
+				assert_eq!(fast.finish(), slow.finish());
+				assert_eq!(fast.off, slow.off);
+			}
+		}
+	}
+
+	#[test_case]
+	fn hash_map_compact() {
+		let mut hash_map = HashMap::<u32, u32>::new();
+		for i in 0..1000 {
+			hash_map.insert(i, i).unwrap();
+		}
+		for i in 10..1000 {
+			hash_map.remove(&i);
+		}
+		let buckets_count = hash_map.get_buckets_count();
+		let empty_buckets = hash_map.empty_buckets();
+		assert!(empty_buckets >= buckets_count - 10);
+
+		hash_map.compact().unwrap();
+		assert!(hash_map.get_buckets_count() < buckets_count);
+		assert!(hash_map.empty_buckets() < empty_buckets);
+		assert!(hash_map.empty_buckets() < hash_map.get_buckets_count());
+		assert!(hash_map.capacity() >= 10);
+		assert_eq!(hash_map.len(), 10);
+		for i in 0..10 {
+			assert_eq!(hash_map[i], i);
+		}
+		assert!(!hash_map.contains_key(&10));
+
+		// Still usable
+		hash_map.insert(42, 42).unwrap();
+		assert_eq!(hash_map[42], 42);
+
+		hash_map.clear();
+		hash_map.compact().unwrap();
+		assert_eq!(hash_map.get_buckets_count(), 1);
+	}
 }
@@ -0,0 +1,249 @@
+//! A hashset is a data structure that stores unique values, using the hash of each value to
+//! quickly check whether it is present.
+//!
+//! It is implemented as a [`HashMap`] whose values are empty.
+
+use super::hashmap;
+use super::hashmap::HashMap;
+use crate::errno::AllocResult;
+use crate::util::AllocError;
+use crate::util::TryClone;
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::Hash;
+use core::iter::FusedIterator;
+
+/// Structure representing a hashset.
+pub struct HashSet<T: Eq + Hash> {
+	/// The underlying hash map.
+	inner: HashMap<T, ()>,
+}
+
+impl<T: Eq + Hash> Default for HashSet<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: Eq + Hash> HashSet<T> {
+	/// Creates a new empty instance.
+	pub const fn new() -> Self {
+		Self {
+			inner: HashMap::new(),
+		}
+	}
+
+	/// Creates a new instance with enough room to hold at least `capacity` values without
+	/// growing.
+	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
+		Ok(Self {
+			inner: HashMap::with_capacity(capacity)?,
+		})
+	}
+
+	/// Returns the number of values in the set.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.inner.len()
+	}
+
+	/// Tells whether the set is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.inner.is_empty()
+	}
+
+	/// Tells whether the set contains the value `value`.
+	#[inline]
+	pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		self.inner.contains_key(value)
+	}
+
+	/// Creates an iterator on the values of the set.
+	///
+	/// The iterator doesn't guarantee any order.
+	#[inline]
+	pub fn iter(&self) -> Iter<T> {
+		Iter {
+			inner: self.inner.iter(),
+		}
+	}
+
+	/// Inserts the value `value` into the set.
+	///
+	/// If the value was already present, the set is left unchanged and the function returns
+	/// `false`.
+	pub fn insert(&mut self, value: T) -> AllocResult<bool> {
+		Ok(self.inner.insert(value, ())?.is_none())
+	}
+
+	/// Removes the value `value` from the set.
+	///
+	/// The function returns `true` if the value was present.
+	pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Hash + Eq,
+	{
+		self.inner.remove(value).is_some()
+	}
+
+	/// Retains only the values for which the predicate `f` returns `true`.
+	pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+		self.inner.retain(|v, _| f(v));
+	}
+
+	/// Returns an iterator on the values that are in `self`, `other`, or both, without
+	/// duplicates.
+	pub fn union<'s>(&'s self, other: &'s Self) -> impl Iterator<Item = &'s T> {
+		self.iter()
+			.chain(other.iter().filter(|v| !self.contains(*v)))
+	}
+
+	/// Returns an iterator on the values that are both in `self` and `other`.
+	pub fn intersection<'s>(&'s self, other: &'s Self) -> impl Iterator<Item = &'s T> {
+		self.iter().filter(|v| other.contains(*v))
+	}
+
+	/// Removes all the values from the set.
+	pub fn clear(&mut self) {
+		self.inner.clear();
+	}
+}
+
+impl<T: Eq + Hash, const N: usize> TryFrom<[T; N]> for HashSet<T> {
+	type Error = AllocError;
+
+	fn try_from(arr: [T; N]) -> Result<Self, Self::Error> {
+		let mut set = Self::with_capacity(N)?;
+		for value in arr {
+			set.insert(value)?;
+		}
+
+		Ok(set)
+	}
+}
+
+impl<T: Eq + Hash + TryClone<Error = AllocError>> TryClone for HashSet<T> {
+	fn try_clone(&self) -> AllocResult<Self> {
+		Ok(Self {
+			inner: self.inner.try_clone()?,
+		})
+	}
+}
+
+impl<T: Eq + Hash + fmt::Debug> fmt::Debug for HashSet<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_set().entries(self.iter()).finish()
+	}
+}
+
+/// Iterator for the [`HashSet`] structure.
+pub struct Iter<'s, T: Eq + Hash> {
+	/// The iterator on the underlying hash map.
+	inner: hashmap::Iter<'s, T, ()>,
+}
+
+impl<'s, T: Eq + Hash> Iterator for Iter<'s, T> {
+	type Item = &'s T;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next().map(|(v, _)| v)
+	}
+
+	fn count(self) -> usize {
+		self.inner.count()
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+}
+
+impl<'s, T: Eq + Hash> ExactSizeIterator for Iter<'s, T> {}
+
+impl<'s, T: Eq + Hash> FusedIterator for Iter<'s, T> {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::util::container::vec::Vec;
+
+	/// Returns the values of `iter`, sorted.
+	fn sorted<'s, I: Iterator<Item = &'s u32>>(iter: I) -> Vec<u32> {
+		let mut values = Vec::new();
+		for v in iter {
+			values.push(*v).unwrap();
+		}
+		values.sort_unstable();
+		values
+	}
+
+	#[test_case]
+	fn hash_set_insert_remove() {
+		let mut set = HashSet::<u32>::new();
+		assert!(set.is_empty());
+		assert!(!set.contains(&1));
+
+		assert!(set.insert(1).unwrap());
+		assert!(set.insert(2).unwrap());
+		// Already present
+		assert!(!set.insert(1).unwrap());
+		assert_eq!(set.len(), 2);
+		assert!(set.contains(&1));
+		assert!(set.contains(&2));
+		assert_eq!(set.iter().len(), 2);
+
+		assert!(set.remove(&1));
+		assert!(!set.remove(&1));
+		assert!(!set.contains(&1));
+		assert_eq!(set.len(), 1);
+
+		set.clear();
+		assert!(set.is_empty());
+		assert_eq!(set.iter().next(), None);
+	}
+
+	#[test_case]
+	fn hash_set_retain() {
+		let mut set = HashSet::<u32>::new();
+		for i in 0..100 {
+			set.insert(i).unwrap();
+		}
+		set.retain(|v| v % 2 == 0);
+		assert_eq!(set.len(), 50);
+		for i in 0..100 {
+			assert_eq!(set.contains(&i), i % 2 == 0);
+		}
+	}
+
+	#[test_case]
+	fn hash_set_union_intersection() {
+		let a = HashSet::try_from([1u32, 2, 3, 4]).unwrap();
+		let b = HashSet::try_from([3u32, 4, 5]).unwrap();
+
+		assert_eq!(sorted(a.union(&b)).as_slice(), &[1, 2, 3, 4, 5]);
+		assert_eq!(sorted(b.union(&a)).as_slice(), &[1, 2, 3, 4, 5]);
+		assert_eq!(sorted(a.intersection(&b)).as_slice(), &[3, 4]);
+		assert_eq!(sorted(b.intersection(&a)).as_slice(), &[3, 4]);
+
+		let empty = HashSet::new();
+		assert_eq!(sorted(a.union(&empty)).as_slice(), &[1, 2, 3, 4]);
+		assert_eq!(a.intersection(&empty).count(), 0);
+	}
+
+	#[test_case]
+	fn hash_set_try_clone() {
+		let a = HashSet::try_from([1u32, 2, 3]).unwrap();
+		let mut b = a.try_clone().unwrap();
+		b.insert(4).unwrap();
+		assert_eq!(a.len(), 3);
+		assert_eq!(b.len(), 4);
+		assert_eq!(sorted(a.iter()).as_slice(), &[1, 2, 3]);
+		assert_eq!(sorted(b.iter()).as_slice(), &[1, 2, 3, 4]);
+	}
+}
@@ -1,14 +1,22 @@
 //! This module implements an identifier allocator, allowing to allocate and
-//! free indexes in range `0..=max`, where `max` is given.
+//! free indexes in range `min..=max`, where `min` and `max` are given.
 
 use crate::errno::AllocError;
 use crate::errno::AllocResult;
 use crate::util::container::bitfield::Bitfield;
 
 /// Structure representing an identifier allocator.
+///
+/// Allocation is next-fit: the search for a free identifier starts after the last allocated
+/// one and wraps around. This makes allocation fast in the common case and delays the reuse of
+/// freed identifiers.
 pub struct IDAllocator {
-	/// The bitfield keeping track of used identifiers.
+	/// The bitfield keeping track of used identifiers, offset by `min`.
 	used: Bitfield,
+	/// The minimum ID.
+	min: u32,
+	/// The offset in the bitfield at which the next search begins.
+	cursor: usize,
 }
 
 impl IDAllocator {
@@ -16,15 +24,43 @@ impl IDAllocator {
 	///
 	/// `max` is the maximum ID.
 	pub fn new(max: u32) -> AllocResult<Self> {
+		Self::new_range(0, max)
+	}
+
+	/// Creates a new instance, allocating identifiers in range `min..=max`.
+	pub fn new_range(min: u32, max: u32) -> AllocResult<Self> {
+		debug_assert!(min <= max);
+
 		Ok(Self {
-			used: Bitfield::new((max + 1) as _)?,
+			used: Bitfield::new((max - min) as usize + 1)?,
+			min,
+			cursor: 0,
 		})
 	}
 
+	/// Returns the offset of `id` in the bitfield.
+	///
+	/// If out of range, the function returns `None`.
+	fn get_offset(&self, id: u32) -> Option<usize> {
+		let off = id.checked_sub(self.min)? as usize;
+		(off < self.used.len()).then_some(off)
+	}
+
+	/// Tells whether the id `id` is used.
+	///
+	/// Identifiers out of range are never used.
+	pub fn is_used(&self, id: u32) -> bool {
+		self.get_offset(id)
+			.map(|off| self.used.is_set(off))
+			.unwrap_or(false)
+	}
+
 	/// Sets the id `id` as used.
+	///
+	/// If out of range, the function does nothing.
 	pub fn set_used(&mut self, id: u32) {
-		if id <= self.used.len() as _ {
-			self.used.set(id as _);
+		if let Some(off) = self.get_offset(id) {
+			self.used.set(off);
 		}
 	}
 
@@ -36,24 +72,78 @@ impl IDAllocator {
 	#[must_use = "not freeing a PID shall cause a leak"]
 	pub fn alloc(&mut self, id: Option<u32>) -> AllocResult<u32> {
 		if let Some(i) = id {
-			if !self.used.is_set(i as _) {
-				self.used.set(i as _);
+			let off = self.get_offset(i).ok_or(AllocError)?;
+			if !self.used.is_set(off) {
+				self.used.set(off);
 				Ok(i)
 			} else {
 				Err(AllocError)
 			}
-		} else if let Some(i) = self.used.find_clear() {
-			self.used.set(i);
-			Ok(i as _)
 		} else {
-			Err(AllocError)
+			let off = self
+				.used
+				.find_next_clear(self.cursor)
+				.or_else(|| self.used.find_clear())
+				.ok_or(AllocError)?;
+			self.used.set(off);
+			self.cursor = off + 1;
+			Ok(self.min + off as u32)
 		}
 	}
 
 	/// Frees the given identifier `id`.
+	///
+	/// If out of range, the function does nothing.
 	pub fn free(&mut self, id: u32) {
-		if id <= self.used.len() as _ {
-			self.used.clear(id as _);
+		if let Some(off) = self.get_offset(id) {
+			self.used.clear(off);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn id_allocator_alloc_free() {
+		let mut allocator = IDAllocator::new(3).unwrap();
+		for i in 0..=3 {
+			assert_eq!(allocator.alloc(None).unwrap(), i);
+			assert!(allocator.is_used(i));
+		}
+		assert!(allocator.alloc(None).is_err());
+
+		// Freed identifiers are reused once the search wraps around
+		allocator.free(1);
+		assert!(!allocator.is_used(1));
+		assert_eq!(allocator.alloc(None).unwrap(), 1);
+		allocator.free(0);
+		allocator.free(2);
+		assert_eq!(allocator.alloc(None).unwrap(), 2);
+		assert_eq!(allocator.alloc(None).unwrap(), 0);
+	}
+
+	#[test_case]
+	fn id_allocator_fixed() {
+		let mut allocator = IDAllocator::new_range(10, 20).unwrap();
+		assert_eq!(allocator.alloc(Some(15)).unwrap(), 15);
+		assert!(allocator.alloc(Some(15)).is_err());
+		// Out of range
+		assert!(allocator.alloc(Some(9)).is_err());
+		assert!(allocator.alloc(Some(21)).is_err());
+		assert!(!allocator.is_used(21));
+		allocator.set_used(21);
+		allocator.free(21);
+
+		allocator.set_used(10);
+		assert_eq!(allocator.alloc(None).unwrap(), 11);
+		// 15 is already used
+		for _ in 12..20 {
+			allocator.alloc(None).unwrap();
 		}
+		assert!(allocator.alloc(None).is_err());
+		allocator.free(15);
+		assert_eq!(allocator.alloc(None).unwrap(), 15);
 	}
 }
@@ -0,0 +1,563 @@
+//! An interval tree associates values with ranges, which may overlap, and allows to quickly find
+//! the ranges overlapping a given range.
+//!
+//! It is implemented as a left-leaning red-black tree sorted by the beginning of the ranges, each
+//! node storing the greatest end of the ranges in its subtree. This allows a query to skip the
+//! subtrees that cannot contain an overlapping range, so that it runs in `O(log n + k)`, where `k`
+//! is the number of ranges found.
+//!
+//! Ranges are half-open. An empty range never overlaps anything.
+
+use crate::errno::AllocResult;
+use crate::util::boxed::Box;
+use core::cmp::max;
+use core::cmp::Ordering;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::mem;
+use core::ops::Range;
+
+/// The maximum height of a tree.
+///
+/// The height of a red-black tree is at most twice the logarithm of its number of nodes, which
+/// cannot exceed the size of the address space.
+const MAX_HEIGHT: usize = 2 * usize::BITS as usize;
+
+/// A link to a child node.
+type Link<K, V> = Option<Box<Node<K, V>>>;
+
+/// A node of the tree.
+struct Node<K, V> {
+	/// The range of the node.
+	range: Range<K>,
+	/// The value associated with the range.
+	value: V,
+	/// The greatest end of the ranges in the subtree of the node.
+	max_end: K,
+
+	/// Tells whether the link from the parent to the node is red.
+	red: bool,
+	/// The left child.
+	left: Link<K, V>,
+	/// The right child.
+	right: Link<K, V>,
+}
+
+impl<K: Ord + Copy, V> Node<K, V> {
+	/// Updates the greatest end of the subtree from the node's children.
+	fn update(&mut self) {
+		let mut max_end = self.range.end;
+		if let Some(left) = &self.left {
+			max_end = max(max_end, left.max_end);
+		}
+		if let Some(right) = &self.right {
+			max_end = max(max_end, right.max_end);
+		}
+		self.max_end = max_end;
+	}
+}
+
+/// Compares two ranges, by beginning then by end.
+fn cmp_range<K: Ord>(a: &Range<K>, b: &Range<K>) -> Ordering {
+	a.start.cmp(&b.start).then_with(|| a.end.cmp(&b.end))
+}
+
+/// Tells whether the given link is red.
+fn is_red<K, V>(link: &Link<K, V>) -> bool {
+	link.as_ref().is_some_and(|n| n.red)
+}
+
+/// Tells whether the left child of the given link is red.
+fn is_left_red<K, V>(link: &Link<K, V>) -> bool {
+	link.as_ref().is_some_and(|n| is_red(&n.left))
+}
+
+/// Rotates the subtree of `h` to the left, returning its new root.
+fn rotate_left<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> Box<Node<K, V>> {
+	let mut x = h.right.take().unwrap();
+	h.right = x.left.take();
+	x.red = h.red;
+	h.red = true;
+	h.update();
+	x.left = Some(h);
+	x.update();
+	x
+}
+
+/// Rotates the subtree of `h` to the right, returning its new root.
+fn rotate_right<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> Box<Node<K, V>> {
+	let mut x = h.left.take().unwrap();
+	h.left = x.right.take();
+	x.red = h.red;
+	h.red = true;
+	h.update();
+	x.right = Some(h);
+	x.update();
+	x
+}
+
+/// Flips the colors of `h` and its children.
+fn flip_colors<K, V>(h: &mut Node<K, V>) {
+	h.red = !h.red;
+	if let Some(left) = &mut h.left {
+		left.red = !left.red;
+	}
+	if let Some(right) = &mut h.right {
+		right.red = !right.red;
+	}
+}
+
+/// Restores the invariants of the subtree of `h` on the way up, returning its new root.
+fn fix_up<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> Box<Node<K, V>> {
+	if is_red(&h.right) && !is_red(&h.left) {
+		h = rotate_left(h);
+	}
+	if is_red(&h.left) && is_left_red(&h.left) {
+		h = rotate_right(h);
+	}
+	if is_red(&h.left) && is_red(&h.right) {
+		flip_colors(&mut h);
+	}
+	h.update();
+	h
+}
+
+/// Makes the left child of `h` or one of its children red, returning the new root of the
+/// subtree.
+fn move_red_left<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> Box<Node<K, V>> {
+	flip_colors(&mut h);
+	if is_left_red(&h.right) {
+		h.right = h.right.take().map(rotate_right);
+		h = rotate_left(h);
+		flip_colors(&mut h);
+	}
+	h
+}
+
+/// Makes the right child of `h` or one of its children red, returning the new root of the
+/// subtree.
+fn move_red_right<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> Box<Node<K, V>> {
+	flip_colors(&mut h);
+	if is_left_red(&h.left) {
+		h = rotate_right(h);
+		flip_colors(&mut h);
+	}
+	h
+}
+
+/// Inserts `node` in the subtree of `h`, returning its new root.
+///
+/// If a node with the same range is already present, its value is replaced and the old value is
+/// returned.
+fn insert_node<K: Ord + Copy, V>(
+	h: Link<K, V>,
+	node: Box<Node<K, V>>,
+) -> (Box<Node<K, V>>, Option<V>) {
+	let Some(mut h) = h else {
+		return (node, None);
+	};
+	let old = match cmp_range(&node.range, &h.range) {
+		Ordering::Less => {
+			let (left, old) = insert_node(h.left.take(), node);
+			h.left = Some(left);
+			old
+		}
+		Ordering::Greater => {
+			let (right, old) = insert_node(h.right.take(), node);
+			h.right = Some(right);
+			old
+		}
+		Ordering::Equal => Some(mem::replace(&mut h.value, node.take().value)),
+	};
+	(fix_up(h), old)
+}
+
+/// Removes the node with the smallest range in the subtree of `h`.
+///
+/// The function returns the new root of the subtree and the removed node.
+fn remove_min<K: Ord + Copy, V>(mut h: Box<Node<K, V>>) -> (Link<K, V>, Box<Node<K, V>>) {
+	if h.left.is_none() {
+		return (None, h);
+	}
+	if !is_red(&h.left) && !is_left_red(&h.left) {
+		h = move_red_left(h);
+	}
+	let (left, min) = remove_min(h.left.take().unwrap());
+	h.left = left;
+	(Some(fix_up(h)), min)
+}
+
+/// Removes the node with the range `range` from the subtree of `h`, which must contain it.
+///
+/// The function returns the new root of the subtree and the value of the removed node.
+fn remove_node<K: Ord + Copy, V>(mut h: Box<Node<K, V>>, range: &Range<K>) -> (Link<K, V>, V) {
+	let value = if cmp_range(range, &h.range) == Ordering::Less {
+		if !is_red(&h.left) && !is_left_red(&h.left) {
+			h = move_red_left(h);
+		}
+		let (left, value) = remove_node(h.left.take().unwrap(), range);
+		h.left = left;
+		value
+	} else {
+		if is_red(&h.left) {
+			h = rotate_right(h);
+		}
+		if cmp_range(range, &h.range) == Ordering::Equal && h.right.is_none() {
+			// The node is a leaf since the tree is balanced
+			return (None, h.take().value);
+		}
+		if !is_red(&h.right) && !is_left_red(&h.right) {
+			h = move_red_right(h);
+		}
+		if cmp_range(range, &h.range) == Ordering::Equal {
+			// Replace the node with its successor
+			let (right, min) = remove_min(h.right.take().unwrap());
+			h.right = right;
+			let min = min.take();
+			h.range = min.range;
+			mem::replace(&mut h.value, min.value)
+		} else {
+			let (right, value) = remove_node(h.right.take().unwrap(), range);
+			h.right = right;
+			value
+		}
+	};
+	(Some(fix_up(h)), value)
+}
+
+/// A tree associating values with ranges, which may overlap.
+///
+/// Each range can be present only once.
+pub struct IntervalTree<K: Ord + Copy, V> {
+	/// The root of the tree.
+	root: Link<K, V>,
+	/// The number of ranges in the tree.
+	len: usize,
+}
+
+impl<K: Ord + Copy, V> Default for IntervalTree<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K: Ord + Copy, V> IntervalTree<K, V> {
+	/// Creates a new empty tree.
+	pub const fn new() -> Self {
+		Self {
+			root: None,
+			len: 0,
+		}
+	}
+
+	/// Returns the number of ranges in the tree.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Tells whether the tree is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the node with the range `range`.
+	fn get_node(&self, range: &Range<K>) -> Option<&Node<K, V>> {
+		let mut node = self.root.as_deref();
+		while let Some(n) = node {
+			node = match cmp_range(range, &n.range) {
+				Ordering::Less => n.left.as_deref(),
+				Ordering::Greater => n.right.as_deref(),
+				Ordering::Equal => return Some(n),
+			};
+		}
+		None
+	}
+
+	/// Returns an immutable reference to the value associated with the range `range`.
+	///
+	/// If the range is not in the tree, the function returns `None`.
+	pub fn get(&self, range: &Range<K>) -> Option<&V> {
+		self.get_node(range).map(|n| &n.value)
+	}
+
+	/// Returns a mutable reference to the value associated with the range `range`.
+	///
+	/// If the range is not in the tree, the function returns `None`.
+	pub fn get_mut(&mut self, range: &Range<K>) -> Option<&mut V> {
+		let mut node = self.root.as_deref_mut();
+		while let Some(n) = node {
+			node = match cmp_range(range, &n.range) {
+				Ordering::Less => n.left.as_deref_mut(),
+				Ordering::Greater => n.right.as_deref_mut(),
+				Ordering::Equal => return Some(&mut n.value),
+			};
+		}
+		None
+	}
+
+	/// Inserts the range `range` with the associated value `value`.
+	///
+	/// If the range is already present, its value is replaced and the old value is returned.
+	pub fn insert(&mut self, range: Range<K>, value: V) -> AllocResult<Option<V>> {
+		// Allocate before modifying the tree so that it is left untouched on failure
+		let node = Box::new(Node {
+			max_end: range.end,
+			range,
+			value,
+
+			red: true,
+			left: None,
+			right: None,
+		})?;
+		let (mut root, old) = insert_node(self.root.take(), node);
+		root.red = false;
+		self.root = Some(root);
+		if old.is_none() {
+			self.len += 1;
+		}
+		Ok(old)
+	}
+
+	/// Removes the range `range` from the tree and returns its associated value.
+	///
+	/// If the range is not in the tree, the function returns `None`.
+	pub fn remove(&mut self, range: &Range<K>) -> Option<V> {
+		self.get_node(range)?;
+		let mut root = self.root.take()?;
+		if !is_red(&root.left) && !is_red(&root.right) {
+			root.red = true;
+		}
+		let (mut root, value) = remove_node(root, range);
+		if let Some(root) = &mut root {
+			root.red = false;
+		}
+		self.root = root;
+		self.len -= 1;
+		Some(value)
+	}
+
+	/// Returns an iterator on the ranges overlapping `range`, along with their associated values.
+	///
+	/// Ranges are returned in ascending order of beginning.
+	pub fn overlapping(&self, range: Range<K>) -> Overlapping<'_, K, V> {
+		let mut iter = Overlapping {
+			range,
+			stack: [None; MAX_HEIGHT],
+			depth: 0,
+		};
+		if iter.range.start < iter.range.end {
+			iter.push_left(self.root.as_deref());
+		}
+		iter
+	}
+
+	/// Tells whether at least one range in the tree overlaps `range`.
+	#[inline]
+	pub fn overlaps(&self, range: Range<K>) -> bool {
+		self.overlapping(range).next().is_some()
+	}
+
+	/// Removes all the ranges from the tree.
+	pub fn clear(&mut self) {
+		self.root = None;
+		self.len = 0;
+	}
+}
+
+impl<K: Ord + Copy + fmt::Debug, V: fmt::Debug> fmt::Debug for IntervalTree<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		/// Writes the entries of the subtree of `node` in order.
+		fn entries<K: fmt::Debug, V: fmt::Debug>(
+			map: &mut fmt::DebugMap<'_, '_>,
+			node: Option<&Node<K, V>>,
+		) {
+			if let Some(n) = node {
+				entries(map, n.left.as_deref());
+				map.entry(&n.range, &n.value);
+				entries(map, n.right.as_deref());
+			}
+		}
+
+		let mut map = f.debug_map();
+		entries(&mut map, self.root.as_deref());
+		map.finish()
+	}
+}
+
+/// Iterator on the ranges of an [`IntervalTree`] overlapping a given range.
+pub struct Overlapping<'t, K: Ord + Copy, V> {
+	/// The range to find overlaps with.
+	range: Range<K>,
+
+	/// The nodes whose left subtree has already been visited, from the root.
+	stack: [Option<&'t Node<K, V>>; MAX_HEIGHT],
+	/// The number of nodes on the stack.
+	depth: usize,
+}
+
+impl<'t, K: Ord + Copy, V> Overlapping<'t, K, V> {
+	/// Pushes `node` and its chain of left children on the stack, stopping at the first subtree
+	/// that cannot overlap the range.
+	fn push_left(&mut self, mut node: Option<&'t Node<K, V>>) {
+		while let Some(n) = node {
+			if n.max_end <= self.range.start {
+				break;
+			}
+			self.stack[self.depth] = Some(n);
+			self.depth += 1;
+			node = n.left.as_deref();
+		}
+	}
+}
+
+impl<'t, K: Ord + Copy, V> Iterator for Overlapping<'t, K, V> {
+	type Item = (&'t Range<K>, &'t V);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		while self.depth > 0 {
+			self.depth -= 1;
+			let n = self.stack[self.depth].unwrap();
+			// The ranges that follow in order all begin after the end of the range
+			if n.range.start >= self.range.end {
+				self.depth = 0;
+				break;
+			}
+			self.push_left(n.right.as_deref());
+			if n.range.start < n.range.end && n.range.end > self.range.start {
+				return Some((&n.range, &n.value));
+			}
+		}
+		None
+	}
+}
+
+impl<'t, K: Ord + Copy, V> FusedIterator for Overlapping<'t, K, V> {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::util::container::vec::Vec;
+
+	/// Checks the invariants of the subtree of `node`, returning its black height.
+	fn check<K: Ord + Copy, V>(node: Option<&Node<K, V>>, parent_red: bool) -> usize {
+		let Some(n) = node else {
+			return 1;
+		};
+		assert!(!(parent_red && n.red));
+		assert!(!is_red(&n.right));
+		let mut max_end = n.range.end;
+		if let Some(left) = n.left.as_deref() {
+			assert_eq!(cmp_range(&left.range, &n.range), Ordering::Less);
+			max_end = max(max_end, left.max_end);
+		}
+		if let Some(right) = n.right.as_deref() {
+			assert_eq!(cmp_range(&right.range, &n.range), Ordering::Greater);
+			max_end = max(max_end, right.max_end);
+		}
+		assert!(n.max_end == max_end);
+		let left_height = check(n.left.as_deref(), n.red);
+		let right_height = check(n.right.as_deref(), n.red);
+		assert_eq!(left_height, right_height);
+		left_height + !n.red as usize
+	}
+
+	/// Returns the ranges overlapping `range` in `tree`, as a vector.
+	fn overlapping(tree: &IntervalTree<u32, u32>, range: Range<u32>) -> Vec<Range<u32>> {
+		let mut ranges = Vec::new();
+		for (r, v) in tree.overlapping(range) {
+			assert_eq!(*v, r.start * 1000 + r.end);
+			ranges.push(r.clone()).unwrap();
+		}
+		ranges
+	}
+
+	#[test_case]
+	fn interval_tree_insert_remove() {
+		let mut tree = IntervalTree::<u32, u32>::new();
+		assert!(tree.is_empty());
+		assert_eq!(tree.get(&(0..1)), None);
+		assert_eq!(tree.remove(&(0..1)), None);
+
+		for i in 0..100 {
+			assert_eq!(tree.insert(i..(i + 10), i).unwrap(), None);
+			check(tree.root.as_deref(), false);
+		}
+		assert_eq!(tree.len(), 100);
+		assert_eq!(tree.insert(5..15, 42).unwrap(), Some(5));
+		assert_eq!(tree.len(), 100);
+		*tree.get_mut(&(5..15)).unwrap() -= 37;
+		assert_eq!(tree.get(&(5..15)), Some(&5));
+		assert_eq!(tree.get(&(5..16)), None);
+
+		for i in (0..100).step_by(2) {
+			assert!(tree.remove(&(i..(i + 10))).is_some());
+			check(tree.root.as_deref(), false);
+		}
+		assert_eq!(tree.len(), 50);
+		for i in 0..100 {
+			assert_eq!(tree.get(&(i..(i + 10))).is_some(), i % 2 == 1);
+		}
+		for i in (1..100).step_by(2) {
+			assert_eq!(tree.remove(&(i..(i + 10))), Some(i));
+			check(tree.root.as_deref(), false);
+		}
+		assert!(tree.is_empty());
+		assert!(tree.root.is_none());
+	}
+
+	#[test_case]
+	fn interval_tree_overlapping() {
+		let mut tree = IntervalTree::<u32, u32>::new();
+		let mut ranges: Vec<Range<u32>> = Vec::new();
+		// Pseudo-random overlapping ranges
+		let mut seed = 1u32;
+		for _ in 0..200 {
+			seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+			let start = (seed >> 16) % 1000;
+			let len = (seed >> 8) % 50;
+			let range = start..(start + len);
+			let value = range.start * 1000 + range.end;
+			if tree.insert(range.clone(), value).unwrap().is_none() {
+				ranges.push(range).unwrap();
+			}
+		}
+		check(tree.root.as_deref(), false);
+		assert_eq!(tree.len(), ranges.len());
+
+		ranges.sort_unstable_by(cmp_range);
+		for start in (0..1100).step_by(7) {
+			for len in [0, 1, 10, 100] {
+				let query = start..(start + len);
+				let mut expected = Vec::new();
+				for r in ranges.iter() {
+					// Empty ranges never overlap
+					let empty = r.is_empty() || query.is_empty();
+					if !empty && r.start < query.end && query.start < r.end {
+						expected.push(r.clone()).unwrap();
+					}
+				}
+				assert_eq!(overlapping(&tree, query.clone()), expected);
+				assert_eq!(tree.overlaps(query), !expected.is_empty());
+			}
+		}
+	}
+
+	#[test_case]
+	fn interval_tree_empty_ranges() {
+		let mut tree = IntervalTree::<u32, u32>::new();
+		tree.insert(10..10, 10010).unwrap();
+		tree.insert(0..20, 20).unwrap();
+		assert_eq!(tree.len(), 2);
+		// Empty ranges never overlap
+		let ranges = overlapping(&tree, 5..15);
+		assert_eq!(ranges.len(), 1);
+		assert_eq!(ranges[0], 0..20);
+		assert!(!tree.overlaps(10..10));
+		assert!(!tree.overlaps(20..30));
+		tree.clear();
+		assert!(tree.is_empty());
+		assert!(!tree.overlaps(0..20));
+	}
+}
@@ -395,6 +395,16 @@ impl<K: 'static + Ord, V: 'static> Map<K, V> {
 		n
 	}
 
+	/// Returns an reference to the rightmost node in the tree.
+	fn get_rightmost_node(node: &'static mut Node<K, V>) -> &'static mut Node<K, V> {
+		let mut n = node;
+		while let Some(right) = n.get_right() {
+			n = right;
+		}
+
+		n
+	}
+
 	/// Searches for a node with the given key in the tree and returns a
 	/// reference.
 	///
@@ -465,6 +475,55 @@ impl<K: 'static + Ord, V: 'static> Map<K, V> {
 		Some((key, value))
 	}
 
+	/// Returns the last key/value pair of the tree. The returned key is the maximum present in
+	/// the tree.
+	///
+	/// If the tree is empty, the function returns `None`.
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		let node = Self::get_rightmost_node(self.get_root()?);
+		Some((&node.key, &node.value))
+	}
+
+	/// Removes and returns the last key/value pair of the tree. The returned key is the maximum
+	/// present in the tree.
+	///
+	/// If the tree is empty, the function returns `None`.
+	pub fn pop_last(&mut self) -> Option<(K, V)> {
+		let node = Self::get_rightmost_node(self.get_root()?);
+		let (key, value) = self.remove_node(node);
+		Some((key, value))
+	}
+
+	/// Returns the key/value pair with the greatest key less than or equal to `key`.
+	///
+	/// If no such key is present, the function returns `None`.
+	pub fn floor_key_value(&self, key: &K) -> Option<(&K, &V)> {
+		let mut node = self.get_root();
+		// The last node encountered with a key less than or equal to `key`
+		let mut last = None;
+
+		while let Some(n) = node {
+			match n.key.cmp(key) {
+				Ordering::Less => {
+					node = n.get_right();
+					last = Some(n);
+				}
+				Ordering::Greater => node = n.get_left(),
+				Ordering::Equal => return Some((&n.key, &n.value)),
+			}
+		}
+
+		last.map(|n| (&n.key, &n.value))
+	}
+
+	/// Returns the key/value pair with the smallest key greater than or equal to `key`.
+	///
+	/// If no such key is present, the function returns `None`.
+	pub fn ceil_key_value(&self, key: &K) -> Option<(&K, &V)> {
+		let node = unwrap_pointer(self.get_start_node(Bound::Included(key)))?;
+		Some((&node.key, &node.value))
+	}
+
 	/// Searches for the given key in the tree and returns a reference.
 	///
 	/// `key` is the key to find.
@@ -1583,6 +1642,56 @@ mod test {
 		assert!(b.range(0..).is_sorted());
 	}
 
+	#[test_case]
+	fn binary_tree_first_last() {
+		let mut b = Map::<i32, i32>::new();
+		assert_eq!(b.first_key_value(), None);
+		assert_eq!(b.last_key_value(), None);
+		assert_eq!(b.pop_last(), None);
+
+		for i in -9..10 {
+			b.insert(i, i * 2).unwrap();
+		}
+		assert_eq!(b.first_key_value(), Some((&-9, &-18)));
+		assert_eq!(b.last_key_value(), Some((&9, &18)));
+
+		for i in (-9..10).rev() {
+			assert_eq!(b.pop_last(), Some((i, i * 2)));
+			assert_eq!(b.len(), (i + 9) as usize);
+		}
+		assert!(b.is_empty());
+	}
+
+	#[test_case]
+	fn binary_tree_nearest() {
+		let mut b = Map::<i32, i32>::new();
+		assert_eq!(b.floor_key_value(&0), None);
+		assert_eq!(b.ceil_key_value(&0), None);
+
+		for i in -9..10 {
+			b.insert(i * 10, i).unwrap();
+		}
+
+		// Exact matches
+		for i in -9..10 {
+			assert_eq!(b.floor_key_value(&(i * 10)), Some((&(i * 10), &i)));
+			assert_eq!(b.ceil_key_value(&(i * 10)), Some((&(i * 10), &i)));
+		}
+		// Between keys
+		for i in -9..9 {
+			for off in 1..10 {
+				let key = i * 10 + off;
+				assert_eq!(b.floor_key_value(&key), Some((&(i * 10), &i)));
+				assert_eq!(b.ceil_key_value(&key), Some((&(i * 10 + 10), &(i + 1))));
+			}
+		}
+		// Out of bounds
+		assert_eq!(b.floor_key_value(&-91), None);
+		assert_eq!(b.ceil_key_value(&-91), Some((&-90, &-9)));
+		assert_eq!(b.floor_key_value(&91), Some((&90, &9)));
+		assert_eq!(b.ceil_key_value(&91), None);
+	}
+
 	#[test_case]
 	fn binary_tree_drain0() {
 		let mut b = Map::<i32, i32>::new();
@@ -1,10 +1,17 @@
 //! This files implements containers. These are different from data structures
 //! in the fact that they require a memory allocator.
 
+pub mod binary_heap;
 pub mod bitfield;
 pub mod hashmap;
+pub mod hashset;
 pub mod id_allocator;
+pub mod interval_tree;
 pub mod map;
+pub mod mpsc_queue;
+pub mod radix_tree;
 pub mod ring_buffer;
+pub mod spsc_ring_buffer;
 pub mod string;
 pub mod vec;
+pub mod vec_deque;
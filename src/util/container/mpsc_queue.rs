@@ -0,0 +1,283 @@
+//! A multi-producer single-consumer intrusive queue, which can be fed from several CPUs or
+//! interrupt handlers without a lock.
+//!
+//! This is Dmitry Vyukov's algorithm: a producer links its node with a single atomic swap,
+//! which never fails nor waits, and the consumer follows the links from the other end. A stub
+//! node embedded in the queue allows the consumer to remove the last element without racing
+//! with producers.
+//!
+//! The queue is intrusive: elements embed an [`MpscLink`] and are linked through it, so that
+//! pushing never allocates memory. The queue does not own its elements.
+//!
+//! Since the stub is linked to elements, the queue must not move once used. Thus, its functions
+//! require it to be pinned, which is the case of a `static` with [`Pin::static_ref`].
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::marker::PhantomPinned;
+use core::pin::Pin;
+use core::ptr::null_mut;
+use core::ptr::NonNull;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering;
+
+/// A link embedded in the elements of an [`MpscQueue`].
+pub struct MpscLink {
+	/// The next link in the queue.
+	next: AtomicPtr<MpscLink>,
+}
+
+impl Default for MpscLink {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl MpscLink {
+	/// Creates a new unlinked instance.
+	pub const fn new() -> Self {
+		Self {
+			next: AtomicPtr::new(null_mut()),
+		}
+	}
+}
+
+impl fmt::Debug for MpscLink {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MpscLink").finish_non_exhaustive()
+	}
+}
+
+/// Trait for elements that can be inserted in an [`MpscQueue`].
+///
+/// # Safety
+///
+/// `get_link` must always return the same link, embedded in `self`, and `from_link` must return
+/// the element in which the given link is embedded.
+pub unsafe trait MpscNode {
+	/// Returns the link of the element.
+	fn get_link(&self) -> &MpscLink;
+
+	/// Returns the element in which the link `link` is embedded.
+	///
+	/// # Safety
+	///
+	/// `link` must have been returned by [`Self::get_link`].
+	unsafe fn from_link(link: NonNull<MpscLink>) -> NonNull<Self>;
+}
+
+/// A lock-free multi-producer single-consumer intrusive queue.
+pub struct MpscQueue<T: MpscNode> {
+	/// The last link of the queue, on the producers side. If null, this is the stub.
+	head: AtomicPtr<MpscLink>,
+	/// The first link of the queue, on the consumer side. If null, this is the stub.
+	tail: UnsafeCell<*mut MpscLink>,
+	/// The stub, which is in the queue when the consumer reaches the last element.
+	stub: MpscLink,
+
+	_phantom: PhantomData<NonNull<T>>,
+	/// The stub is referenced by the links, so the queue must not move.
+	_pin: PhantomPinned,
+}
+
+// Elements are moved from the producers to the consumer, which may be in different contexts
+unsafe impl<T: MpscNode + Send> Send for MpscQueue<T> {}
+
+unsafe impl<T: MpscNode + Send> Sync for MpscQueue<T> {}
+
+impl<T: MpscNode> Default for MpscQueue<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T: MpscNode> MpscQueue<T> {
+	/// Creates a new empty queue.
+	pub const fn new() -> Self {
+		Self {
+			head: AtomicPtr::new(null_mut()),
+			tail: UnsafeCell::new(null_mut()),
+			stub: MpscLink::new(),
+
+			_phantom: PhantomData,
+			_pin: PhantomPinned,
+		}
+	}
+
+	/// Returns a pointer to the stub.
+	#[inline]
+	fn get_stub(&self) -> *mut MpscLink {
+		&self.stub as *const _ as *mut _
+	}
+
+	/// Appends the link `link` at the end of the queue.
+	///
+	/// # Safety
+	///
+	/// The link must not be in a queue already and must remain valid until popped.
+	unsafe fn push_link(&self, link: *mut MpscLink) {
+		(*link).next.store(null_mut(), Ordering::Relaxed);
+		// Take the place of the last link. Until the previous one points to the new one, the
+		// consumer cannot reach it
+		let prev = self.head.swap(link, Ordering::AcqRel);
+		let prev = if prev.is_null() {
+			self.get_stub()
+		} else {
+			prev
+		};
+		// Publish the link to the consumer
+		(*prev).next.store(link, Ordering::Release);
+	}
+
+	/// Appends the element `node` at the end of the queue.
+	///
+	/// This function never blocks and can be called concurrently from any number of contexts.
+	///
+	/// # Safety
+	///
+	/// The element must not be in a queue already and must remain valid until popped.
+	pub unsafe fn push(self: Pin<&Self>, node: NonNull<T>) {
+		let link = node.as_ref().get_link() as *const _ as *mut _;
+		self.push_link(link);
+	}
+
+	/// Removes the element at the beginning of the queue and returns it.
+	///
+	/// If the queue is empty, the function returns `None`.
+	///
+	/// The function may also return `None` while a producer is in the middle of pushing the
+	/// only remaining element. Then, the element is returned by a subsequent call, after the
+	/// producer is done.
+	///
+	/// # Safety
+	///
+	/// Only one context at a time may pop from the queue.
+	pub unsafe fn pop(self: Pin<&Self>) -> Option<NonNull<T>> {
+		let stub = self.get_stub();
+		let tail_ptr = self.tail.get();
+		let mut tail = *tail_ptr;
+		if tail.is_null() {
+			tail = stub;
+		}
+		let mut next = (*tail).next.load(Ordering::Acquire);
+		// Skip the stub
+		if tail == stub {
+			if next.is_null() {
+				return None;
+			}
+			*tail_ptr = next;
+			tail = next;
+			next = (*next).next.load(Ordering::Acquire);
+		}
+		if !next.is_null() {
+			*tail_ptr = next;
+			return Some(T::from_link(NonNull::new_unchecked(tail)));
+		}
+		// `tail` is the last element, unless a producer is pushing another one
+		let mut head = self.head.load(Ordering::Acquire);
+		if head.is_null() {
+			head = stub;
+		}
+		if tail != head {
+			return None;
+		}
+		// Put the stub behind the last element so that it can be removed
+		self.push_link(stub);
+		next = (*tail).next.load(Ordering::Acquire);
+		if !next.is_null() {
+			*tail_ptr = next;
+			return Some(T::from_link(NonNull::new_unchecked(tail)));
+		}
+		None
+	}
+}
+
+impl<T: MpscNode> fmt::Debug for MpscQueue<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("MpscQueue").finish_non_exhaustive()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use core::pin::pin;
+
+	/// An element of the queue.
+	#[repr(C)]
+	struct Item {
+		link: MpscLink,
+		value: u32,
+	}
+
+	unsafe impl MpscNode for Item {
+		fn get_link(&self) -> &MpscLink {
+			&self.link
+		}
+
+		unsafe fn from_link(link: NonNull<MpscLink>) -> NonNull<Self> {
+			// The link is the first field
+			link.cast()
+		}
+	}
+
+	/// Pops an element from `queue` and returns its value.
+	fn pop(queue: Pin<&MpscQueue<Item>>) -> Option<u32> {
+		unsafe { queue.pop().map(|i| i.as_ref().value) }
+	}
+
+	#[test_case]
+	fn mpsc_queue_push_pop() {
+		let items: [Item; 8] = core::array::from_fn(|i| Item {
+			link: MpscLink::new(),
+			value: i as u32,
+		});
+		let queue = pin!(MpscQueue::<Item>::new());
+		let queue = queue.as_ref();
+		assert_eq!(pop(queue), None);
+
+		for item in &items {
+			unsafe {
+				queue.push(NonNull::from(item));
+			}
+		}
+		for i in 0..8 {
+			assert_eq!(pop(queue), Some(i));
+		}
+		assert_eq!(pop(queue), None);
+
+		// Interleave, reusing the popped elements and passing through the stub several times
+		for round in 0..3 {
+			for item in &items[..(round + 1)] {
+				unsafe {
+					queue.push(NonNull::from(item));
+				}
+			}
+			assert_eq!(pop(queue), Some(0));
+			unsafe {
+				queue.push(NonNull::from(&items[7]));
+			}
+			for i in 1..=round {
+				assert_eq!(pop(queue), Some(i as u32));
+			}
+			assert_eq!(pop(queue), Some(7));
+			assert_eq!(pop(queue), None);
+		}
+	}
+
+	#[test_case]
+	fn mpsc_queue_static() {
+		static QUEUE: MpscQueue<Item> = MpscQueue::new();
+		let queue = Pin::static_ref(&QUEUE);
+		let item = Item {
+			link: MpscLink::new(),
+			value: 42,
+		};
+		unsafe {
+			queue.push(NonNull::from(&item));
+		}
+		assert_eq!(pop(queue), Some(42));
+		assert_eq!(pop(queue), None);
+	}
+}
@@ -0,0 +1,454 @@
+//! A radix tree maps integer keys to values, storing sparse keys efficiently and allowing to
+//! iterate on a range of keys in order.
+//!
+//! Each node has [`FANOUT`] slots, indexed by a group of [`BITS`] bits of the key, from the most
+//! significant to the least significant. The leaves store the values. The height of the tree is
+//! the minimum required to hold the greatest key inserted so far, so that small keys only
+//! require a few levels.
+
+use crate::errno::AllocResult;
+use crate::util::boxed::Box;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::Bound;
+use core::ops::RangeBounds;
+
+/// The number of bits of the key consumed by each level of the tree.
+const BITS: u32 = 6;
+/// The number of slots in a node.
+const FANOUT: usize = 1 << BITS;
+/// The mask to get the index of a slot from a shifted key.
+const MASK: u64 = FANOUT as u64 - 1;
+/// The maximum height of the tree, for which all the keys fit.
+const MAX_HEIGHT: u32 = u64::BITS.div_ceil(BITS);
+
+/// The slots of a node.
+// Nodes are always boxed, so the size of the largest variant does not matter
+#[allow(clippy::large_enum_variant)]
+enum Slots<T> {
+	/// Slots pointing to the next level.
+	Inner([Option<Box<Node<T>>>; FANOUT]),
+	/// Slots storing values.
+	Leaf([Option<T>; FANOUT]),
+}
+
+/// A node of the tree.
+struct Node<T> {
+	/// The number of used slots.
+	count: usize,
+	/// The slots.
+	slots: Slots<T>,
+}
+
+impl<T> Node<T> {
+	/// Allocates a new empty node.
+	///
+	/// If `leaf` is `true`, the node stores values. Else, it points to other nodes.
+	fn new(leaf: bool) -> AllocResult<Box<Self>> {
+		let slots = if leaf {
+			Slots::Leaf(core::array::from_fn(|_| None))
+		} else {
+			Slots::Inner(core::array::from_fn(|_| None))
+		};
+		Box::new(Self {
+			count: 0,
+			slots,
+		})
+	}
+
+	/// Returns the first entry of the subtree whose key is greater than or equal to `from`.
+	///
+	/// Arguments:
+	/// - `base` is the first key covered by the node
+	/// - `shift` is the position of the bits of the key used to index the node's slots
+	/// - `from` is the key to start from, which must be covered by the node
+	fn first_from(&self, base: u64, shift: u32, from: u64) -> Option<(u64, &T)> {
+		let start = ((from >> shift) & MASK) as usize;
+		match &self.slots {
+			Slots::Leaf(values) => values[start..]
+				.iter()
+				.enumerate()
+				.find_map(|(i, v)| Some((base | (start + i) as u64, v.as_ref()?))),
+			Slots::Inner(children) => {
+				children[start..].iter().enumerate().find_map(|(i, child)| {
+					let child = child.as_ref()?;
+					let child_base = base | (((start + i) as u64) << shift);
+					// Only the first child can begin before `from`
+					let from = if i == 0 { from } else { child_base };
+					child.first_from(child_base, shift - BITS, from)
+				})
+			}
+		}
+	}
+
+	/// Removes the value with the key `key` from the subtree and returns it.
+	///
+	/// `shift` is the position of the bits of the key used to index the node's slots.
+	///
+	/// Nodes that become empty are freed.
+	fn remove(&mut self, key: u64, shift: u32) -> Option<T> {
+		let index = ((key >> shift) & MASK) as usize;
+		match &mut self.slots {
+			Slots::Leaf(values) => {
+				let value = values[index].take()?;
+				self.count -= 1;
+				Some(value)
+			}
+			Slots::Inner(children) => {
+				let child = children[index].as_mut()?;
+				let value = child.remove(key, shift - BITS)?;
+				if child.count == 0 {
+					children[index] = None;
+					self.count -= 1;
+				}
+				Some(value)
+			}
+		}
+	}
+}
+
+/// Returns the position of the bits of the key used to index the root of a tree of height
+/// `height`.
+#[inline]
+fn root_shift(height: u32) -> u32 {
+	(height - 1) * BITS
+}
+
+/// Tells whether a tree of height `height` can hold the key `key`.
+#[inline]
+fn fits(height: u32, key: u64) -> bool {
+	key.checked_shr(height * BITS).unwrap_or(0) == 0
+}
+
+/// A radix tree mapping `u64` keys to values of type `T`.
+pub struct RadixTree<T> {
+	/// The root of the tree.
+	root: Option<Box<Node<T>>>,
+	/// The number of levels in the tree. If the tree is empty, the height is zero.
+	height: u32,
+	/// The number of values in the tree.
+	len: usize,
+}
+
+impl<T> Default for RadixTree<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> RadixTree<T> {
+	/// Creates a new empty tree.
+	pub const fn new() -> Self {
+		Self {
+			root: None,
+			height: 0,
+			len: 0,
+		}
+	}
+
+	/// Returns the number of values in the tree.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Tells whether the tree is empty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns an immutable reference to the value with the key `key`.
+	///
+	/// If the key is not in the tree, the function returns `None`.
+	pub fn get(&self, key: u64) -> Option<&T> {
+		if !fits(self.height, key) {
+			return None;
+		}
+		let mut node = self.root.as_deref()?;
+		let mut shift = root_shift(self.height);
+		loop {
+			let index = ((key >> shift) & MASK) as usize;
+			match &node.slots {
+				Slots::Leaf(values) => break values[index].as_ref(),
+				Slots::Inner(children) => node = children[index].as_deref()?,
+			}
+			shift -= BITS;
+		}
+	}
+
+	/// Returns a mutable reference to the value with the key `key`.
+	///
+	/// If the key is not in the tree, the function returns `None`.
+	pub fn get_mut(&mut self, key: u64) -> Option<&mut T> {
+		if !fits(self.height, key) {
+			return None;
+		}
+		let mut node = self.root.as_deref_mut()?;
+		let mut shift = root_shift(self.height);
+		loop {
+			let index = ((key >> shift) & MASK) as usize;
+			match &mut node.slots {
+				Slots::Leaf(values) => break values[index].as_mut(),
+				Slots::Inner(children) => node = children[index].as_deref_mut()?,
+			}
+			shift -= BITS;
+		}
+	}
+
+	/// Tells whether the tree contains the key `key`.
+	#[inline]
+	pub fn contains_key(&self, key: u64) -> bool {
+		self.get(key).is_some()
+	}
+
+	/// Inserts the value `value` with the key `key`.
+	///
+	/// If the key is already present, its value is replaced and the old value is returned.
+	///
+	/// On allocation failure, empty nodes may be left in the tree. They are freed along with the
+	/// tree or when a value is removed from them.
+	pub fn insert(&mut self, key: u64, value: T) -> AllocResult<Option<T>> {
+		// Add levels on top of the root until the key fits
+		if self.root.is_none() {
+			self.root = Some(Node::new(true)?);
+			self.height = 1;
+		}
+		while !fits(self.height, key) {
+			let mut node = Node::new(false)?;
+			if let Slots::Inner(children) = &mut node.slots {
+				children[0] = self.root.take();
+			}
+			node.count = 1;
+			self.root = Some(node);
+			self.height += 1;
+		}
+		// Walk down, creating the missing nodes
+		let mut node = self.root.as_deref_mut().unwrap();
+		let mut shift = root_shift(self.height);
+		loop {
+			let index = ((key >> shift) & MASK) as usize;
+			match &mut node.slots {
+				Slots::Leaf(values) => {
+					let old = values[index].replace(value);
+					if old.is_none() {
+						node.count += 1;
+						self.len += 1;
+					}
+					break Ok(old);
+				}
+				Slots::Inner(children) => {
+					if children[index].is_none() {
+						children[index] = Some(Node::new(shift == BITS)?);
+						node.count += 1;
+					}
+					node = children[index].as_deref_mut().unwrap();
+				}
+			}
+			shift -= BITS;
+		}
+	}
+
+	/// Removes the value with the key `key` from the tree and returns it.
+	///
+	/// If the key is not in the tree, the function returns `None`.
+	pub fn remove(&mut self, key: u64) -> Option<T> {
+		if !fits(self.height, key) {
+			return None;
+		}
+		let root = self.root.as_mut()?;
+		let value = root.remove(key, root_shift(self.height))?;
+		self.len -= 1;
+		// Remove the levels that are not required anymore
+		if root.count == 0 {
+			self.root = None;
+			self.height = 0;
+		}
+		while let Some(root) = &mut self.root {
+			if root.count != 1 {
+				break;
+			}
+			let Slots::Inner(children) = &mut root.slots else {
+				break;
+			};
+			if children[0].is_none() {
+				break;
+			}
+			self.root = children[0].take();
+			self.height -= 1;
+		}
+		Some(value)
+	}
+
+	/// Returns an iterator on the values whose keys are in the range `range`, in ascending order
+	/// of keys.
+	pub fn lookup_range<R: RangeBounds<u64>>(&self, range: R) -> RadixTreeRange<'_, T> {
+		let next = match range.start_bound() {
+			Bound::Included(start) => Some(*start),
+			Bound::Excluded(start) => start.checked_add(1),
+			Bound::Unbounded => Some(0),
+		};
+		RadixTreeRange {
+			tree: self,
+			next,
+			end: range.end_bound().cloned(),
+		}
+	}
+
+	/// Returns an iterator on the values of the tree, in ascending order of keys.
+	#[inline]
+	pub fn iter(&self) -> RadixTreeRange<'_, T> {
+		self.lookup_range(..)
+	}
+
+	/// Removes all the values from the tree.
+	pub fn clear(&mut self) {
+		self.root = None;
+		self.height = 0;
+		self.len = 0;
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for RadixTree<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+/// Iterator on a range of keys of a [`RadixTree`].
+pub struct RadixTreeRange<'t, T> {
+	/// The tree.
+	tree: &'t RadixTree<T>,
+	/// The key to start the next lookup from. If `None`, the iterator is exhausted.
+	next: Option<u64>,
+	/// The end bound of the range.
+	end: Bound<u64>,
+}
+
+impl<'t, T> Iterator for RadixTreeRange<'t, T> {
+	type Item = (u64, &'t T);
+
+	fn next(&mut self) -> Option<Self::Item> {
+		let from = self.next.take()?;
+		if !fits(self.tree.height, from) {
+			return None;
+		}
+		let root = self.tree.root.as_deref()?;
+		let (key, value) = root.first_from(0, root_shift(self.tree.height), from)?;
+		let in_range = match self.end {
+			Bound::Included(end) => key <= end,
+			Bound::Excluded(end) => key < end,
+			Bound::Unbounded => true,
+		};
+		if !in_range {
+			return None;
+		}
+		self.next = key.checked_add(1);
+		Some((key, value))
+	}
+}
+
+impl<'t, T> FusedIterator for RadixTreeRange<'t, T> {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::util::container::vec::Vec;
+
+	/// Returns the keys of the entries returned by `iter`, checking their values.
+	fn keys<'t, I: Iterator<Item = (u64, &'t u64)>>(iter: I) -> Vec<u64> {
+		let mut keys = Vec::new();
+		for (k, v) in iter {
+			assert_eq!(k, *v);
+			keys.push(k).unwrap();
+		}
+		keys
+	}
+
+	#[test_case]
+	fn radix_tree_insert_remove() {
+		let mut tree = RadixTree::<u64>::new();
+		assert!(tree.is_empty());
+		assert_eq!(tree.get(0), None);
+		assert_eq!(tree.remove(0), None);
+
+		for i in 0..1000 {
+			assert_eq!(tree.insert(i, i).unwrap(), None);
+		}
+		assert_eq!(tree.len(), 1000);
+		assert_eq!(tree.height, 2);
+		assert_eq!(tree.insert(10, 0).unwrap(), Some(10));
+		*tree.get_mut(10).unwrap() = 10;
+		for i in 0..1000 {
+			assert_eq!(tree.get(i), Some(&i));
+		}
+		assert_eq!(tree.get(1000), None);
+		assert_eq!(tree.get(u64::MAX), None);
+
+		for i in (0..1000).step_by(2) {
+			assert_eq!(tree.remove(i), Some(i));
+		}
+		assert_eq!(tree.len(), 500);
+		for i in 0..1000 {
+			assert_eq!(tree.contains_key(i), i % 2 == 1);
+		}
+		for i in (1..1000).step_by(2) {
+			assert_eq!(tree.remove(i), Some(i));
+		}
+		assert!(tree.is_empty());
+		assert!(tree.root.is_none());
+		assert_eq!(tree.height, 0);
+	}
+
+	#[test_case]
+	fn radix_tree_sparse() {
+		let mut tree = RadixTree::<u64>::new();
+		let sparse = [0, 63, 64, 1 << 20, 1 << 40, u64::MAX - 1, u64::MAX];
+		for k in sparse {
+			tree.insert(k, k).unwrap();
+		}
+		assert_eq!(tree.height, MAX_HEIGHT);
+		assert_eq!(keys(tree.iter()).as_slice(), &sparse);
+		for k in sparse {
+			assert_eq!(tree.get(k), Some(&k));
+		}
+		assert_eq!(tree.get(1 << 30), None);
+
+		// The height shrinks back once the large keys are gone
+		for k in sparse[3..].iter().rev() {
+			assert_eq!(tree.remove(*k), Some(*k));
+		}
+		assert_eq!(tree.height, 2);
+		assert_eq!(keys(tree.iter()).as_slice(), &sparse[..3]);
+	}
+
+	#[test_case]
+	fn radix_tree_lookup_range() {
+		let mut tree = RadixTree::<u64>::new();
+		for i in (0..10000).step_by(3) {
+			tree.insert(i, i).unwrap();
+		}
+		assert_eq!(keys(tree.lookup_range(10..20)).as_slice(), &[12, 15, 18]);
+		assert_eq!(keys(tree.lookup_range(9..=18)).as_slice(), &[9, 12, 15, 18]);
+		assert_eq!(
+			keys(tree.lookup_range((Bound::Excluded(9), Bound::Included(18)))).as_slice(),
+			&[12, 15, 18]
+		);
+		assert_eq!(keys(tree.lookup_range(9995..)).as_slice(), &[9996, 9999]);
+		assert_eq!(tree.lookup_range(10000..).next(), None);
+		assert_eq!(tree.lookup_range(u64::MAX..).next(), None);
+		assert_eq!(tree.lookup_range(13..15).next(), None);
+		assert_eq!(tree.lookup_range(..).count(), 3334);
+
+		// Iterate across nodes
+		let mut prev = None;
+		for (k, _) in tree.lookup_range(60..5000) {
+			assert_eq!(k % 3, 0);
+			if let Some(prev) = prev {
+				assert_eq!(k, prev + 3);
+			}
+			prev = Some(k);
+		}
+		assert_eq!(prev, Some(4998));
+	}
+}
@@ -7,39 +7,106 @@
 //!
 //! When a cursor reaches the end of the linear buffer, it goes back to the
 //! beginning. This is why it's called a "ring".
+//!
+//! Since both cursors are at the same position when the buffer is either empty or full, a flag
+//! tells the two cases apart. This allows the whole linear buffer to be filled.
+//!
+//! The slots of the linear buffer are [`MaybeUninit`]: only the slots holding the data are
+//! initialized. Elements are moved out by reads and the remaining ones are dropped along with the
+//! buffer, so the element type needs neither a default value nor to implement [`Copy`].
+//!
+//! Elements are moved in and out one at a time with [`RingBuffer::push`] and [`RingBuffer::pop`].
+//! Only the functions copying elements in bulk require the element type to implement [`Clone`].
 
+use crate::errno::AllocError;
+use crate::errno::AllocResult;
+use crate::errno::Errno;
+use crate::util::boxed::Box;
+use crate::util::container::vec::Vec;
+use crate::util::io::IO;
+use crate::util::TryClone;
 use core::cmp::min;
+use core::fmt;
 use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+/// A readiness transition of a ring buffer.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadyEvent {
+	/// The buffer went from empty to non-empty: data is available for reading.
+	Readable,
+	/// The buffer went from full to non-full: space is available for writing.
+	Writable,
+	/// A write pushed the length of the data above the high watermark.
+	HighWatermark,
+	/// A read dropped the length of the data below the low watermark.
+	LowWatermark,
+}
+
+/// Callback invoked on readiness transitions of a ring buffer.
+type ReadyCallback = Box<dyn FnMut(ReadyEvent)>;
+
+/// Returns the given slots as a slice of elements.
+///
+/// # Safety
+///
+/// Every slot of `slots` must be initialized.
+#[inline(always)]
+unsafe fn assume_init_slice<T>(slots: &[MaybeUninit<T>]) -> &[T] {
+	// `MaybeUninit<T>` has the same layout as `T`
+	&*(slots as *const [MaybeUninit<T>] as *const [T])
+}
+
+/// Allocates a vector of `len` uninitialized slots.
+fn uninit_vec<T>(len: usize) -> AllocResult<Vec<MaybeUninit<T>>> {
+	let mut vec = Vec::with_capacity(len)?;
+	for _ in 0..len {
+		// Cannot fail since the vector has been allocated with enough capacity
+		vec.push(MaybeUninit::uninit()).unwrap();
+	}
+	Ok(vec)
+}
 
 /// A ring buffer.
 ///
 /// The ring buffer has a static size which is given at initialization.
 ///
 /// The buffer used to store the data is specified by the generic argument `B`.
-#[derive(Debug)]
-pub struct RingBuffer<T, B: AsRef<[T]> + AsMut<[T]>> {
-	/// The linear buffer.
+pub struct RingBuffer<T, B: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> {
+	/// The linear buffer. Only the slots holding the data are initialized.
 	buffer: B,
 
 	/// The offset of the read cursor in the buffer.
 	read_cursor: usize,
 	/// The offset of the write cursor in the buffer.
 	write_cursor: usize,
+	/// Tells whether the buffer is full. Required since cursors are equal both when the buffer is
+	/// empty and when it is full.
+	full: bool,
+
+	/// The callback invoked on readiness transitions, if any.
+	ready_callback: Option<ReadyCallback>,
+	/// The low and high watermarks, in number of elements, if any.
+	watermarks: Option<(usize, usize)>,
 
 	/// Allowing the argument T.
 	_phantom: PhantomData<T>,
 }
 
-impl<T: Default + Copy, B: AsRef<[T]> + AsMut<[T]>> RingBuffer<T, B> {
+impl<T, B: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> RingBuffer<T, B> {
 	/// Creates a new instance.
 	///
-	/// `buffer` is the buffer to be used.
+	/// `buffer` is the buffer to be used. Its slots are considered uninitialized.
 	pub fn new(buffer: B) -> Self {
 		Self {
 			buffer,
 
 			read_cursor: 0,
 			write_cursor: 0,
+			full: false,
+
+			ready_callback: None,
+			watermarks: None,
 
 			_phantom: PhantomData,
 		}
@@ -54,14 +121,16 @@ impl<T: Default + Copy, B: AsRef<[T]> + AsMut<[T]>> RingBuffer<T, B> {
 	/// Tells whether the ring is empty.
 	#[inline(always)]
 	pub fn is_empty(&self) -> bool {
-		self.read_cursor == self.write_cursor
+		self.read_cursor == self.write_cursor && !self.full
 	}
 
 	/// Returns the length of the data in the buffer.
 	///
 	/// If the buffer is empty, the function returns zero.
 	pub fn get_data_len(&self) -> usize {
-		if self.read_cursor <= self.write_cursor {
+		if self.full {
+			self.get_size()
+		} else if self.read_cursor <= self.write_cursor {
 			self.write_cursor - self.read_cursor
 		} else {
 			self.get_size() - (self.read_cursor - self.write_cursor)
@@ -71,50 +140,340 @@ impl<T: Default + Copy, B: AsRef<[T]> + AsMut<[T]>> RingBuffer<T, B> {
 	/// Returns the length of the available space in the buffer.
 	#[inline(always)]
 	pub fn get_available_len(&self) -> usize {
-		self.get_size() - self.get_data_len() - 1
+		self.get_size() - self.get_data_len()
+	}
+
+	/// Sets the callback invoked on readiness transitions.
+	///
+	/// The callback is called with [`ReadyEvent::Readable`] when the buffer goes from empty to
+	/// non-empty, and with [`ReadyEvent::Writable`] when it goes from full to non-full. Later
+	/// reads or writes that do not cross one of these boundaries do not invoke it.
+	pub fn set_ready_callback<F: 'static + FnMut(ReadyEvent)>(
+		&mut self,
+		callback: F,
+	) -> AllocResult<()> {
+		self.ready_callback = Some(Box::new(callback)?);
+		Ok(())
+	}
+
+	/// Removes the readiness callback, if any.
+	pub fn clear_ready_callback(&mut self) {
+		self.ready_callback = None;
+	}
+
+	/// Sets the fill level watermarks, in number of elements.
+	///
+	/// Once set, the readiness callback is also called with [`ReadyEvent::HighWatermark`] when a
+	/// write makes the length of the data go from at most `high` to above it, and with
+	/// [`ReadyEvent::LowWatermark`] when a read makes it go from at least `low` to below it.
+	pub fn set_watermarks(&mut self, low: usize, high: usize) {
+		self.watermarks = Some((low, high));
+	}
+
+	/// Removes the watermarks, if any.
+	pub fn clear_watermarks(&mut self) {
+		self.watermarks = None;
+	}
+
+	/// Invokes the readiness callback with the given event, if set.
+	#[inline(always)]
+	fn notify(&mut self, event: ReadyEvent) {
+		if let Some(callback) = &mut self.ready_callback {
+			callback(event);
+		}
+	}
+
+	/// Advances the read cursor past `len` elements of data.
+	///
+	/// The elements must have been moved out or dropped before.
+	fn consume(&mut self, len: usize) {
+		if len == 0 {
+			return;
+		}
+		let was_full = self.full;
+		let prev_len = self.get_data_len();
+		self.read_cursor = (self.read_cursor + len) % self.get_size();
+		self.full = false;
+		if was_full {
+			self.notify(ReadyEvent::Writable);
+		}
+		if let Some((low, _)) = self.watermarks {
+			if prev_len >= low && prev_len - len < low {
+				self.notify(ReadyEvent::LowWatermark);
+			}
+		}
+	}
+
+	/// Advances the write cursor past `len` elements that have been written to the free space.
+	fn commit(&mut self, len: usize) {
+		if len == 0 {
+			return;
+		}
+		let was_empty = self.is_empty();
+		let prev_len = self.get_data_len();
+		self.write_cursor = (self.write_cursor + len) % self.get_size();
+		self.full = self.write_cursor == self.read_cursor;
+		if was_empty {
+			self.notify(ReadyEvent::Readable);
+		}
+		if let Some((_, high)) = self.watermarks {
+			if prev_len <= high && prev_len + len > high {
+				self.notify(ReadyEvent::HighWatermark);
+			}
+		}
 	}
 
 	/// Returns a slice representing the ring buffer's linear storage.
 	#[inline(always)]
-	fn get_buffer(&mut self) -> &mut [T] {
+	fn get_buffer(&mut self) -> &mut [MaybeUninit<T>] {
 		self.buffer.as_mut()
 	}
 
-	/// Peeks dat afrom the buffer and writes it in `buf`.
+	/// Returns an iterator over the elements in the buffer, in the order they would be read.
 	///
 	/// Contrary to `read`, this function doesn't consume the data.
+	pub fn iter(&self) -> impl ExactSizeIterator<Item = &T> + '_ {
+		let cursor = self.read_cursor;
+		let buffer_size = self.get_size();
+		let buffer = self.buffer.as_ref();
+
+		// The slots holding the data are initialized
+		(0..self.get_data_len())
+			.map(move |i| unsafe { buffer[(cursor + i) % buffer_size].assume_init_ref() })
+	}
+
+	/// Returns the data in the buffer as two slices, in the order it would be read.
 	///
-	/// The function returns the number of elements read.
-	pub fn peek(&mut self, buf: &mut [T]) -> usize {
+	/// The first slice is the data before the end of the linear buffer, the second is the data
+	/// wrapping back to its beginning. The second slice is empty if the data does not wrap.
+	///
+	/// Once the data has been used, [`Self::commit_read`] consumes it.
+	pub fn as_read_slices(&self) -> (&[T], &[T]) {
 		let cursor = self.read_cursor;
-		let len = min(buf.len(), self.get_data_len());
+		let len = self.get_data_len();
+		let l0 = min(cursor + len, self.get_size()) - cursor;
+		let buffer = self.buffer.as_ref();
+		// The slots holding the data are initialized
+		unsafe {
+			(
+				assume_init_slice(&buffer[cursor..(cursor + l0)]),
+				assume_init_slice(&buffer[..(len - l0)]),
+			)
+		}
+	}
+
+	/// Returns the free space of the buffer as two slices of uninitialized slots, in the order it
+	/// would be written.
+	///
+	/// The first slice is the space before the end of the linear buffer, the second is the space
+	/// wrapping back to its beginning. The second slice is empty if the space does not wrap.
+	///
+	/// Once the slots have been initialized, [`Self::commit_write`] makes the data available for
+	/// reading.
+	pub fn as_write_slices(&mut self) -> (&mut [MaybeUninit<T>], &mut [MaybeUninit<T>]) {
+		let cursor = self.write_cursor;
+		let len = self.get_available_len();
+		let l0 = min(cursor + len, self.get_size()) - cursor;
+		let (begin, end) = self.get_buffer().split_at_mut(cursor);
+		(&mut end[..l0], &mut begin[..(len - l0)])
+	}
+
+	/// Consumes `len` elements of data, after they have been used through
+	/// [`Self::as_read_slices`].
+	///
+	/// The consumed elements are dropped. `len` is clamped to the length of the data.
+	///
+	/// The function returns the number of elements consumed.
+	pub fn commit_read(&mut self, len: usize) -> usize {
+		let len = min(len, self.get_data_len());
+		self.drop_data(len);
+		self.consume(len);
+		len
+	}
+
+	/// Makes `len` elements available for reading, after they have been written through
+	/// [`Self::as_write_slices`].
+	///
+	/// `len` is clamped to the available space.
+	///
+	/// The function returns the number of elements committed.
+	///
+	/// # Safety
+	///
+	/// The first `len` slots of the free space must have been initialized.
+	pub unsafe fn commit_write(&mut self, len: usize) -> usize {
+		let len = min(len, self.get_available_len());
+		self.commit(len);
+		len
+	}
+
+	/// Writes data to the buffer in place, without going through an intermediate buffer.
+	///
+	/// Up to `count` slots of free space are exposed to `f` as two slices: the space before the
+	/// end of the linear buffer, then the space wrapping back to its beginning. `f` initializes
+	/// them in order and returns the number of elements it wrote.
+	///
+	/// The returned count is clamped to the exposed space.
+	///
+	/// The function returns the number of elements written.
+	///
+	/// # Safety
+	///
+	/// `f` must initialize at least as many slots as the count it returns.
+	pub unsafe fn write_with<F: FnOnce(&mut [MaybeUninit<T>], &mut [MaybeUninit<T>]) -> usize>(
+		&mut self,
+		count: usize,
+		f: F,
+	) -> usize {
+		let cursor = self.write_cursor;
+		let len = min(count, self.get_available_len());
 		let buffer_size = self.get_size();
 		let buffer = self.get_buffer();
 
-		// The length of the first read, before going back to the beginning of the
-		// buffer
+		// The length of the first slice, before going back to the beginning of the buffer
 		let l0 = min(cursor + len, buffer_size) - cursor;
-		for i in 0..l0 {
-			buf[i] = buffer[cursor + i];
+		// The length of the second slice, from the beginning of the buffer
+		let l1 = len - l0;
+		let (begin, end) = buffer.split_at_mut(cursor);
+		let len = min(f(&mut end[..l0], &mut begin[..l1]), len);
+
+		self.commit(len);
+		len
+	}
+
+	/// Rearranges the buffer so that its data is contiguous in memory, then returns it as a single
+	/// slice.
+	///
+	/// If the data wraps around the end of the linear buffer, it is moved to the beginning of the
+	/// linear buffer. Else, the function leaves the buffer untouched.
+	pub fn make_contiguous(&mut self) -> &[T] {
+		let cursor = self.read_cursor;
+		let len = self.get_data_len();
+		let buffer_size = self.get_size();
+
+		if cursor + len > buffer_size {
+			self.get_buffer().rotate_left(cursor);
+			self.read_cursor = 0;
+			self.write_cursor = len % buffer_size;
 		}
+		let cursor = self.read_cursor;
 
-		// The length of the second read, from the beginning of the buffer
-		let l1 = len - l0;
-		for i in 0..l1 {
-			buf[l0 + i] = buffer[i];
+		// The slots holding the data are initialized
+		unsafe { assume_init_slice(&self.buffer.as_ref()[cursor..(cursor + len)]) }
+	}
+
+	/// Drops the first `len` elements of data in place, without moving the read cursor.
+	fn drop_data(&mut self, len: usize) {
+		let cursor = self.read_cursor;
+		let buffer_size = self.get_size();
+		let buffer = self.get_buffer();
+		for i in 0..len {
+			// The slots holding the data are initialized
+			unsafe {
+				buffer[(cursor + i) % buffer_size].assume_init_drop();
+			}
 		}
+	}
 
-		len
+	/// Appends the element `val` at the end of the data.
+	///
+	/// If the buffer is full, the function returns the element back as an error.
+	pub fn push(&mut self, val: T) -> Result<(), T> {
+		if self.get_available_len() == 0 {
+			return Err(val);
+		}
+		let cursor = self.write_cursor;
+		self.get_buffer()[cursor].write(val);
+		self.commit(1);
+		Ok(())
+	}
+
+	/// Removes the element at the beginning of the data and returns it.
+	///
+	/// If the buffer is empty, the function returns `None`.
+	pub fn pop(&mut self) -> Option<T> {
+		if self.is_empty() {
+			return None;
+		}
+		let cursor = self.read_cursor;
+		// The slot holds the first element of the data. It is considered uninitialized after
+		// consuming it
+		let val = unsafe { self.get_buffer()[cursor].assume_init_read() };
+		self.consume(1);
+		Some(val)
 	}
 
 	/// Reads data from the buffer and writes it in `buf`.
 	///
+	/// The elements are moved out of the buffer. The previous elements of `buf` are dropped.
+	///
 	/// The function returns the number of elements read.
 	pub fn read(&mut self, buf: &mut [T]) -> usize {
-		let len = self.peek(buf);
+		let cursor = self.read_cursor;
+		let len = min(buf.len(), self.get_data_len());
 		let buffer_size = self.get_size();
+		let buffer = self.get_buffer();
+
+		for (i, b) in buf[..len].iter_mut().enumerate() {
+			// The slots holding the data are initialized. They are considered uninitialized after
+			// consuming them
+			*b = unsafe { buffer[(cursor + i) % buffer_size].assume_init_read() };
+		}
+
+		self.consume(len);
+		len
+	}
+
+	/// Clears the buffer.
+	///
+	/// The elements in the buffer are dropped.
+	pub fn clear(&mut self) {
+		self.drop_data(self.get_data_len());
+
+		self.read_cursor = 0;
+		self.write_cursor = 0;
+		self.full = false;
+	}
+}
+
+impl<T: Clone, B: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> RingBuffer<T, B> {
+	/// Peeks dat afrom the buffer and writes it in `buf`.
+	///
+	/// Contrary to `read`, this function doesn't consume the data.
+	///
+	/// The function returns the number of elements read.
+	pub fn peek(&mut self, buf: &mut [T]) -> usize {
+		self.peek_at(0, buf)
+	}
+
+	/// Same as [`Self::peek`], but starts reading `offset` elements past the read cursor.
+	///
+	/// If `offset` is beyond the data in the buffer, the function reads nothing.
+	///
+	/// The function returns the number of elements read.
+	pub fn peek_at(&self, offset: usize, buf: &mut [T]) -> usize {
+		let data_len = self.get_data_len();
+		if offset >= data_len {
+			return 0;
+		}
+		let buffer_size = self.get_size();
+		let cursor = (self.read_cursor + offset) % buffer_size;
+		let len = min(buf.len(), data_len - offset);
+		let buffer = self.buffer.as_ref();
+
+		// The length of the first read, before going back to the beginning of the
+		// buffer
+		let l0 = min(cursor + len, buffer_size) - cursor;
+		for i in 0..l0 {
+			buf[i] = unsafe { buffer[cursor + i].assume_init_ref() }.clone();
+		}
+
+		// The length of the second read, from the beginning of the buffer
+		let l1 = len - l0;
+		for i in 0..l1 {
+			buf[l0 + i] = unsafe { buffer[i].assume_init_ref() }.clone();
+		}
 
-		self.read_cursor = (self.read_cursor + len) % buffer_size;
 		len
 	}
 
@@ -131,72 +490,863 @@ impl<T: Default + Copy, B: AsRef<[T]> + AsMut<[T]>> RingBuffer<T, B> {
 		// buffer
 		let l0 = min(cursor + len, buffer_size) - cursor;
 		for i in 0..l0 {
-			buffer[cursor + i] = buf[i];
+			buffer[cursor + i].write(buf[i].clone());
 		}
 
 		// The length of the second read, from the beginning of the buffer
 		let l1 = len - l0;
 		for i in 0..l1 {
-			buffer[i] = buf[l0 + i];
+			buffer[i].write(buf[l0 + i].clone());
 		}
 
-		self.write_cursor = (self.write_cursor + len) % buffer_size;
+		self.commit(len);
 		len
 	}
 
-	/// Clears the buffer.
-	#[inline(always)]
-	pub fn clear(&mut self) {
-		// FIXME: Elements in the container must be dropped here. However, using another container
-		// for storage might result in double dropping
+	/// Writes all the data in `buf` to the buffer.
+	///
+	/// Contrary to [`Self::write`], the data is never truncated: if `buf` doesn't fit in the
+	/// available space, nothing is written and the function returns an error with the number of
+	/// available elements.
+	pub fn write_all(&mut self, buf: &[T]) -> Result<(), usize> {
+		let available = self.get_available_len();
+		if buf.len() > available {
+			return Err(available);
+		}
+		self.write(buf);
+		Ok(())
+	}
+
+	/// Copies `len` elements of data from offset `src` to offset `dst`, in place.
+	///
+	/// Offsets are relative to the read cursor. The source and destination regions may overlap.
+	///
+	/// # Panics
+	///
+	/// The function panics if either region exceeds the data in the buffer.
+	pub fn copy_within(&mut self, src: usize, dst: usize, len: usize) {
+		let data_len = self.get_data_len();
+		assert!(
+			src.checked_add(len).is_some_and(|end| end <= data_len),
+			"source is out of bounds"
+		);
+		assert!(dst <= data_len - len, "destination is out of bounds");
 
+		let cursor = self.read_cursor;
+		let buffer_size = self.get_size();
+		let buffer = self.get_buffer();
+		let off = |i: usize| (cursor + i) % buffer_size;
+		// Both regions are within the data, so every slot involved is initialized
+		let mut copy = |i: usize| unsafe {
+			let val = buffer[off(src + i)].assume_init_ref().clone();
+			*buffer[off(dst + i)].assume_init_mut() = val;
+		};
+
+		// Copy in the direction that doesn't overwrite source elements before they are read
+		if dst <= src {
+			(0..len).for_each(&mut copy);
+		} else {
+			(0..len).rev().for_each(&mut copy);
+		}
+	}
+}
+
+impl<T> RingBuffer<T, Vec<MaybeUninit<T>>> {
+	/// Creates a new instance, allocating a linear buffer of `capacity` elements.
+	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
+		Ok(Self::new(uninit_vec(capacity)?))
+	}
+
+	/// Resizes the linear buffer so that it holds `capacity` elements.
+	///
+	/// The data in the buffer is kept, in the same order. Thus, the buffer cannot shrink below the
+	/// length of its data: a lower `capacity` is rounded up to it.
+	///
+	/// The readiness callback and the watermarks are kept. If the buffer was full and the new
+	/// capacity leaves free space, the callback is called with [`ReadyEvent::Writable`].
+	///
+	/// On allocation failure, the buffer is left untouched.
+	pub fn resize(&mut self, capacity: usize) -> AllocResult<()> {
+		let len = self.get_data_len();
+		let capacity = capacity.max(len);
+		let mut buffer = uninit_vec(capacity)?;
+
+		let cursor = self.read_cursor;
+		let buffer_size = self.get_size();
+		let old = self.get_buffer();
+		for (i, slot) in buffer[..len].iter_mut().enumerate() {
+			// The slots holding the data are initialized. Dropping the old linear buffer does not
+			// drop the elements moved out of it
+			slot.write(unsafe { old[(cursor + i) % buffer_size].assume_init_read() });
+		}
+
+		let was_full = self.full;
+		self.buffer = buffer;
 		self.read_cursor = 0;
-		self.write_cursor = 0;
+		self.write_cursor = if capacity > 0 { len % capacity } else { 0 };
+		self.full = capacity > 0 && len == capacity;
+		if was_full && !self.full {
+			self.notify(ReadyEvent::Writable);
+		}
+
+		Ok(())
+	}
+}
+
+impl<B: AsRef<[MaybeUninit<u8>]> + AsMut<[MaybeUninit<u8>]>> RingBuffer<u8, B> {
+	/// Drains data from the buffer into `io`, without going through an intermediate buffer.
+	///
+	/// Data is written with consecutive offsets, starting at zero. The function stops when `io`
+	/// doesn't accept all the data it is given.
+	///
+	/// On error, the data accepted before is still consumed.
+	///
+	/// The function returns the number of bytes read.
+	pub fn read_into(&mut self, io: &mut dyn IO) -> Result<usize, Errno> {
+		let mut total = 0;
+		// At most two contiguous slices: before the end of the linear buffer, then from its
+		// beginning
+		for _ in 0..2 {
+			let cursor = self.read_cursor;
+			let len = min(self.get_data_len(), self.get_size() - cursor);
+			if len == 0 {
+				break;
+			}
+			// The slots holding the data are initialized
+			let slice =
+				unsafe { assume_init_slice(&self.buffer.as_ref()[cursor..(cursor + len)]) };
+			let written = min(io.write(total as _, slice)? as usize, len);
+			self.consume(written);
+			total += written;
+			if written < len {
+				break;
+			}
+		}
+
+		Ok(total)
+	}
+
+	/// Fills the free space of the buffer with data read from `io`, without going through an
+	/// intermediate buffer.
+	///
+	/// Data is read with consecutive offsets, starting at zero. The function stops when `io`
+	/// doesn't fill all the space it is given, or reaches the end of its input.
+	///
+	/// On error, the data read before is still kept.
+	///
+	/// The function returns the number of bytes written.
+	pub fn write_from(&mut self, io: &mut dyn IO) -> Result<usize, Errno> {
+		let mut total = 0;
+		for _ in 0..2 {
+			let cursor = self.write_cursor;
+			let len = min(self.get_available_len(), self.get_size() - cursor);
+			if len == 0 {
+				break;
+			}
+			let slice = &mut self.buffer.as_mut()[cursor..(cursor + len)];
+			// The free space may be uninitialized, so it is zeroed before being handed to `io`
+			slice.fill(MaybeUninit::new(0));
+			let slice = unsafe { &mut *(slice as *mut [MaybeUninit<u8>] as *mut [u8]) };
+			let (read, eof) = io.read(total as _, slice)?;
+			let read = min(read as usize, len);
+			self.commit(read);
+			total += read;
+			if eof || read < len {
+				break;
+			}
+		}
+
+		Ok(total)
+	}
+}
+
+impl<T, B: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> Drop for RingBuffer<T, B> {
+	fn drop(&mut self) {
+		self.drop_data(self.get_data_len());
+	}
+}
+
+/// The clone has the same layout as the original: the elements are cloned at the same positions
+/// in the linear buffer and the cursors keep their positions.
+///
+/// The readiness callback is not cloned.
+impl<T: TryClone<Error = E>, E: From<AllocError>> TryClone for RingBuffer<T, Vec<MaybeUninit<T>>> {
+	type Error = E;
+
+	fn try_clone(&self) -> Result<Self, Self::Error> {
+		let mut clone = Self {
+			buffer: uninit_vec(self.get_size())?,
+
+			read_cursor: self.read_cursor,
+			write_cursor: self.read_cursor,
+			full: false,
+
+			ready_callback: None,
+			watermarks: self.watermarks,
+
+			_phantom: PhantomData,
+		};
+		// If cloning an element fails, the clone only holds the elements cloned before, which
+		// are dropped along with it. The clone has no readiness callback to notify
+		for val in self.iter() {
+			// Cannot fail since the clone has the same size
+			let _ = clone.push(val.try_clone()?);
+		}
+
+		Ok(clone)
+	}
+}
+
+impl<T, B: AsRef<[MaybeUninit<T>]> + AsMut<[MaybeUninit<T>]>> fmt::Debug for RingBuffer<T, B> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RingBuffer")
+			.field("size", &self.buffer.as_ref().len())
+			.field("read_cursor", &self.read_cursor)
+			.field("write_cursor", &self.write_cursor)
+			.field("full", &self.full)
+			.field("ready_callback", &self.ready_callback.is_some())
+			.finish()
 	}
 }
 
 #[cfg(test)]
 mod test {
 	use super::*;
+	use core::sync::atomic::{AtomicUsize, Ordering};
 
 	#[test_case]
 	fn ring_buffer0() {
-		let mut rb = RingBuffer::new([0u8; 10]);
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
 		let mut buf: [u8; 0] = [0; 0];
 		assert_eq!(rb.read(&mut buf), 0);
 	}
 
 	#[test_case]
 	fn ring_buffer1() {
-		let mut rb = RingBuffer::new([0u8; 10]);
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
 		let mut buf: [u8; 10] = [0; 10];
 		assert_eq!(rb.read(&mut buf), 0);
 	}
 
 	#[test_case]
 	fn ring_buffer2() {
-		let mut rb = RingBuffer::new([0u8; 10]);
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
 		let mut buf: [u8; 10] = [0; 10];
 		for i in 0..buf.len() {
 			buf[i] = 42;
 		}
 
-		assert_eq!(rb.write(&buf), 9);
-		assert_eq!(rb.get_data_len(), 9);
+		assert_eq!(rb.write(&buf), 10);
+		assert_eq!(rb.get_data_len(), 10);
 		assert_eq!(rb.get_available_len(), 0);
+		assert!(!rb.is_empty());
 
 		for i in 0..buf.len() {
 			buf[i] = 0;
 		}
 
-		assert_eq!(rb.read(&mut buf), 9);
+		assert_eq!(rb.read(&mut buf), 10);
 		assert_eq!(rb.get_data_len(), 0);
-		assert_eq!(rb.get_available_len(), 9);
+		assert_eq!(rb.get_available_len(), 10);
+		assert!(rb.is_empty());
 
-		for i in 0..9 {
+		for i in 0..10 {
 			assert_eq!(buf[i], 42);
 		}
 	}
 
+	#[test_case]
+	fn ring_buffer_write_all() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
+		let mut buf = [0u8; 10];
+		// Move the cursors so that writes wrap around
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut buf[..6]), 6);
+
+		// Exact fit
+		assert_eq!(rb.write_all(&[1, 2, 3, 4, 5, 6, 7]), Ok(()));
+		assert_eq!(rb.get_available_len(), 3);
+
+		// One element too large: nothing is written
+		assert_eq!(rb.write_all(&[8, 9, 10, 11]), Err(3));
+		assert_eq!(rb.get_data_len(), 7);
+		assert_eq!(rb.get_available_len(), 3);
+
+		assert_eq!(rb.write_all(&[8, 9, 10]), Ok(()));
+		assert_eq!(rb.get_available_len(), 0);
+		assert_eq!(rb.write_all(&[]), Ok(()));
+		assert_eq!(rb.write_all(&[11]), Err(0));
+
+		assert_eq!(rb.read(&mut buf), 10);
+		assert_eq!(buf, [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+	}
+
+	#[test_case]
+	fn ring_buffer_ready_callback() {
+		static READABLE: AtomicUsize = AtomicUsize::new(0);
+		static WRITABLE: AtomicUsize = AtomicUsize::new(0);
+		let events = || {
+			(
+				READABLE.load(Ordering::Relaxed),
+				WRITABLE.load(Ordering::Relaxed),
+			)
+		};
+
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
+		rb.set_ready_callback(|event| {
+			let counter = match event {
+				ReadyEvent::Readable => &READABLE,
+				ReadyEvent::Writable => &WRITABLE,
+				ReadyEvent::HighWatermark | ReadyEvent::LowWatermark => return,
+			};
+			counter.fetch_add(1, Ordering::Relaxed);
+		})
+		.unwrap();
+
+		// Empty to non-empty
+		assert_eq!(rb.write(&[1, 2, 3]), 3);
+		assert_eq!(events(), (1, 0));
+		// Already non-empty
+		assert_eq!(rb.write(&[4, 5]), 2);
+		assert_eq!(events(), (1, 0));
+
+		// Fill the buffer, then read from it
+		assert_eq!(rb.write(&[0; 10]), 5);
+		let mut buf = [0u8; 2];
+		assert_eq!(rb.read(&mut buf), 2);
+		assert_eq!(events(), (1, 1));
+		// Not full anymore
+		assert_eq!(rb.read(&mut buf), 2);
+		assert_eq!(events(), (1, 1));
+	}
+
+	#[test_case]
+	fn ring_buffer_watermarks() {
+		static HIGH: AtomicUsize = AtomicUsize::new(0);
+		static LOW: AtomicUsize = AtomicUsize::new(0);
+		let events = || (HIGH.load(Ordering::Relaxed), LOW.load(Ordering::Relaxed));
+
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 16]);
+		rb.set_ready_callback(|event| {
+			let counter = match event {
+				ReadyEvent::HighWatermark => &HIGH,
+				ReadyEvent::LowWatermark => &LOW,
+				ReadyEvent::Readable | ReadyEvent::Writable => return,
+			};
+			counter.fetch_add(1, Ordering::Relaxed);
+		})
+		.unwrap();
+		rb.set_watermarks(4, 12);
+		let mut buf = [0u8; 16];
+
+		// Up to the high watermark, without crossing it
+		assert_eq!(rb.write(&[0; 12]), 12);
+		assert_eq!(events(), (0, 0));
+		// Crossing upward
+		assert_eq!(rb.write(&[0; 2]), 2);
+		assert_eq!(events(), (1, 0));
+		// Already above
+		assert_eq!(rb.write(&[0; 2]), 2);
+		assert_eq!(events(), (1, 0));
+
+		// Back down to the low watermark, without crossing it
+		assert_eq!(rb.read(&mut buf[..12]), 12);
+		assert_eq!(events(), (1, 0));
+		// Crossing downward
+		assert_eq!(rb.read(&mut buf[..1]), 1);
+		assert_eq!(events(), (1, 1));
+		// Already below
+		assert_eq!(rb.read(&mut buf[..3]), 3);
+		assert_eq!(events(), (1, 1));
+
+		// Crossing each watermark again, wrapping around the buffer
+		assert_eq!(rb.write(&[0; 13]), 13);
+		assert_eq!(events(), (2, 1));
+		assert_eq!(rb.read(&mut buf), 13);
+		assert_eq!(events(), (2, 2));
+
+		// Crossing the low watermark upward is not reported
+		assert_eq!(rb.write(&[0; 8]), 8);
+		assert_eq!(events(), (2, 2));
+		assert_eq!(rb.read(&mut buf[..8]), 8);
+		assert_eq!(events(), (2, 3));
+
+		rb.clear_watermarks();
+		assert_eq!(rb.write(&[0; 16]), 16);
+		assert_eq!(rb.read(&mut buf), 16);
+		assert_eq!(events(), (2, 3));
+	}
+
+	#[test_case]
+	fn ring_buffer_peek_at() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 16]);
+		// Make the data wrap around
+		assert_eq!(rb.write(&[0; 12]), 12);
+		assert_eq!(rb.read(&mut [0; 12]), 12);
+		let data: [u8; 10] = core::array::from_fn(|i| i as u8);
+		assert_eq!(rb.write(&data), 10);
+
+		let mut buf = [0u8; 3];
+		assert_eq!(rb.peek_at(5, &mut buf), 3);
+		assert_eq!(buf, [5, 6, 7]);
+		// Truncated at the end of the data
+		assert_eq!(rb.peek_at(8, &mut buf), 2);
+		assert_eq!(&buf[..2], &[8, 9]);
+		assert_eq!(rb.peek_at(10, &mut buf), 0);
+		assert_eq!(rb.peek_at(usize::MAX, &mut buf), 0);
+
+		// Nothing has been consumed
+		assert_eq!(rb.get_data_len(), 10);
+		let mut buf = [0u8; 10];
+		assert_eq!(rb.read(&mut buf), 10);
+		assert_eq!(buf, data);
+	}
+
+	#[test_case]
+	fn ring_buffer_iter() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		assert_eq!(rb.iter().len(), 0);
+
+		// Make the data wrap around
+		assert_eq!(rb.write(&[0; 5]), 5);
+		assert_eq!(rb.read(&mut [0; 5]), 5);
+		assert_eq!(rb.write(&[1, 2, 3, 4, 5, 6]), 6);
+
+		let iter = rb.iter();
+		assert_eq!(iter.len(), 6);
+		let mut elements = [0u8; 6];
+		for (e, v) in elements.iter_mut().zip(iter) {
+			*e = *v;
+		}
+
+		// The data is still there, in the same order
+		let mut buf = [0u8; 6];
+		for i in 0..6 {
+			assert_eq!(rb.read(&mut buf[i..(i + 1)]), 1);
+		}
+		assert_eq!(elements, buf);
+		assert_eq!(rb.iter().len(), 0);
+	}
+
+	#[test_case]
+	fn ring_buffer_write_with() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		// Move the cursors close to the end
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+
+		let len = unsafe {
+			rb.write_with(5, |a, b| {
+				assert_eq!((a.len(), b.len()), (2, 3));
+				for (i, e) in a.iter_mut().chain(b.iter_mut()).enumerate() {
+					e.write(i as u8 + 1);
+				}
+				a.len() + b.len()
+			})
+		};
+		assert_eq!(len, 5);
+
+		let mut buf = [0u8; 8];
+		assert_eq!(rb.read(&mut buf), 5);
+		assert_eq!(&buf[..5], &[1, 2, 3, 4, 5]);
+	}
+
+	#[test_case]
+	fn ring_buffer_write_with_clamp() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		// Only the available space is exposed, and overreporting is clamped
+		let len = unsafe {
+			rb.write_with(100, |a, b| {
+				assert_eq!(a.len() + b.len(), 8);
+				a.fill(MaybeUninit::new(42));
+				100
+			})
+		};
+		assert_eq!(len, 8);
+		assert_eq!(rb.get_available_len(), 0);
+
+		// Partial writes
+		let mut buf = [0u8; 8];
+		assert_eq!(rb.read(&mut buf[..4]), 4);
+		let len = unsafe {
+			rb.write_with(4, |a, _| {
+				a[0].write(42);
+				1
+			})
+		};
+		assert_eq!(len, 1);
+		assert_eq!(rb.get_data_len(), 5);
+	}
+
+	#[test_case]
+	fn ring_buffer_copy_within() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 10]);
+		// Move the cursors so that the data wraps around the end of the linear buffer
+		rb.write(&[0; 6]);
+		rb.read(&mut [0; 6]);
+		rb.write(&[0, 1, 2, 3, 4, 5, 6, 7]);
+
+		let mut buf = [0u8; 8];
+		// Backward, across the wrap
+		rb.copy_within(3, 1, 4);
+		rb.peek(&mut buf);
+		assert_eq!(buf, [0, 3, 4, 5, 6, 5, 6, 7]);
+		// Forward, overlapping, across the wrap
+		rb.copy_within(1, 3, 4);
+		rb.peek(&mut buf);
+		assert_eq!(buf, [0, 3, 4, 3, 4, 5, 6, 7]);
+		// Empty
+		rb.copy_within(8, 0, 0);
+		rb.peek(&mut buf);
+		assert_eq!(buf, [0, 3, 4, 3, 4, 5, 6, 7]);
+	}
+
+	#[test_case]
+	fn ring_buffer_full_wrap() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 4]);
+		// Fill the buffer with the cursors in the middle
+		assert_eq!(rb.write(&[0; 2]), 2);
+		assert_eq!(rb.read(&mut [0; 2]), 2);
+		assert_eq!(rb.write(&[1, 2, 3, 4, 5]), 4);
+		assert_eq!(rb.get_data_len(), 4);
+		assert_eq!(rb.write(&[5]), 0);
+
+		let mut buf = [0u8; 4];
+		assert_eq!(rb.peek(&mut buf), 4);
+		assert_eq!(buf, [1, 2, 3, 4]);
+		assert_eq!(rb.read(&mut buf[..1]), 1);
+		assert_eq!(rb.write(&[5]), 1);
+		assert_eq!(rb.read(&mut buf), 4);
+		assert_eq!(buf, [2, 3, 4, 5]);
+		assert!(rb.is_empty());
+	}
+
+	#[test_case]
+	fn ring_buffer_try_clone() {
+		let mut rb = RingBuffer::<u8, _>::with_capacity(8).unwrap();
+		// Make the data wrap around
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+		assert_eq!(rb.write(&[1, 2, 3, 4]), 4);
+
+		let mut clone = rb.try_clone().unwrap();
+		assert_eq!(clone.get_data_len(), 4);
+		assert_eq!(clone.get_available_len(), 4);
+
+		let mut a = [0u8; 8];
+		let mut b = [0u8; 8];
+		assert_eq!(rb.read(&mut a), 4);
+		assert_eq!(clone.read(&mut b), 4);
+		assert_eq!(a, b);
+		// Both buffers are independent
+		assert_eq!(clone.write(&[5]), 1);
+		assert!(rb.is_empty());
+	}
+
+	#[test_case]
+	fn ring_buffer_make_contiguous() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		// Not wrapping
+		assert_eq!(rb.write(&[0, 0, 1, 2]), 4);
+		assert_eq!(rb.read(&mut [0; 2]), 2);
+		assert_eq!(rb.make_contiguous(), &[1, 2]);
+		assert_eq!(rb.read_cursor, 2);
+
+		// Wrapping
+		assert_eq!(rb.write(&[3, 4, 5, 6, 7, 8]), 6);
+		assert_eq!(rb.make_contiguous(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+		assert_eq!(rb.get_available_len(), 0);
+
+		let mut buf = [0u8; 3];
+		assert_eq!(rb.read(&mut buf), 3);
+		assert_eq!(buf, [1, 2, 3]);
+		assert_eq!(rb.write(&[9, 10]), 2);
+		let mut buf = [0u8; 8];
+		assert_eq!(rb.read(&mut buf), 7);
+		assert_eq!(&buf[..7], &[4, 5, 6, 7, 8, 9, 10]);
+	}
+
+	#[test_case]
+	fn ring_buffer_slices() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		assert_eq!(rb.as_read_slices(), (&[][..], &[][..]));
+		{
+			let (a, b) = rb.as_write_slices();
+			assert_eq!((a.len(), b.len()), (8, 0));
+		}
+
+		// Move the cursors so that both the data and the free space wrap
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+		{
+			let (a, b) = rb.as_write_slices();
+			assert_eq!((a.len(), b.len()), (2, 6));
+			for (e, val) in a.iter_mut().chain(b.iter_mut()).zip(1..=5) {
+				e.write(val);
+			}
+		}
+		// Nothing is readable before committing
+		assert!(rb.is_empty());
+		assert_eq!(unsafe { rb.commit_write(5) }, 5);
+		assert_eq!(rb.as_read_slices(), (&[1, 2][..], &[3, 4, 5][..]));
+		{
+			let (a, b) = rb.as_write_slices();
+			assert_eq!((a.len(), b.len()), (3, 0));
+		}
+
+		assert_eq!(rb.commit_read(3), 3);
+		assert_eq!(rb.as_read_slices(), (&[4, 5][..], &[][..]));
+		{
+			let (a, b) = rb.as_write_slices();
+			a.fill(MaybeUninit::new(0));
+			b.fill(MaybeUninit::new(0));
+		}
+		// Clamped to the data and the available space
+		assert_eq!(unsafe { rb.commit_write(100) }, 6);
+		assert_eq!(rb.get_data_len(), 8);
+		assert_eq!(unsafe { rb.commit_write(1) }, 0);
+		assert_eq!(rb.commit_read(100), 8);
+		assert!(rb.is_empty());
+	}
+
+	#[test_case]
+	fn ring_buffer_resize() {
+		static WRITABLE: AtomicUsize = AtomicUsize::new(0);
+		static HIGH: AtomicUsize = AtomicUsize::new(0);
+		let events = || {
+			(
+				WRITABLE.load(Ordering::Relaxed),
+				HIGH.load(Ordering::Relaxed),
+			)
+		};
+
+		let mut rb = RingBuffer::<u8, _>::with_capacity(8).unwrap();
+		rb.set_ready_callback(|event| {
+			let counter = match event {
+				ReadyEvent::Writable => &WRITABLE,
+				ReadyEvent::HighWatermark => &HIGH,
+				ReadyEvent::Readable | ReadyEvent::LowWatermark => return,
+			};
+			counter.fetch_add(1, Ordering::Relaxed);
+		})
+		.unwrap();
+		rb.set_watermarks(2, 12);
+
+		// Make the data wrap around, then fill the buffer
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+		let data: [u8; 8] = core::array::from_fn(|i| i as u8);
+		assert_eq!(rb.write(&data), 8);
+		assert_eq!(events(), (0, 0));
+
+		// Growing keeps the data and frees space
+		rb.resize(16).unwrap();
+		assert_eq!(rb.get_size(), 16);
+		assert_eq!(rb.get_data_len(), 8);
+		assert_eq!(rb.get_available_len(), 8);
+		assert_eq!(events(), (1, 0));
+		assert_eq!(rb.write(&[8, 9, 10, 11, 12]), 5);
+		// The watermarks are kept
+		assert_eq!(events(), (1, 1));
+		let mut buf = [0u8; 13];
+		assert_eq!(rb.peek(&mut buf), 13);
+		assert!(buf.iter().copied().eq(0..13));
+
+		// Cannot shrink below the data
+		assert_eq!(rb.read(&mut [0; 9]), 9);
+		rb.resize(2).unwrap();
+		assert_eq!(rb.get_size(), 4);
+		assert_eq!(rb.get_available_len(), 0);
+		let mut buf = [0u8; 4];
+		assert_eq!(rb.read(&mut buf), 4);
+		assert_eq!(buf, [9, 10, 11, 12]);
+
+		rb.resize(0).unwrap();
+		assert_eq!(rb.get_size(), 0);
+		assert!(rb.is_empty());
+		assert_eq!(rb.write(&[0; 1]), 0);
+	}
+
+	#[test_case]
+	fn ring_buffer_push_pop() {
+		/// An element type that is neither `Copy` nor `Clone`, and has no default value.
+		#[derive(Debug, Eq, PartialEq)]
+		struct Packet(u32);
+
+		let mut rb = RingBuffer::with_capacity(4).unwrap();
+		assert_eq!(rb.pop(), None);
+		for i in 0..4 {
+			rb.push(Packet(i)).unwrap();
+		}
+		assert_eq!(rb.get_data_len(), 4);
+		assert_eq!(rb.push(Packet(4)), Err(Packet(4)));
+
+		// Wrap around
+		assert_eq!(rb.pop(), Some(Packet(0)));
+		assert_eq!(rb.pop(), Some(Packet(1)));
+		rb.push(Packet(4)).unwrap();
+		rb.push(Packet(5)).unwrap();
+		assert!(rb.iter().map(|p| p.0).eq(2..6));
+		for i in 2..6 {
+			assert_eq!(rb.pop(), Some(Packet(i)));
+		}
+		assert_eq!(rb.pop(), None);
+		assert!(rb.is_empty());
+
+		// Empty linear buffer
+		let mut rb = RingBuffer::<Packet, _>::with_capacity(0).unwrap();
+		assert_eq!(rb.push(Packet(0)), Err(Packet(0)));
+		assert_eq!(rb.pop(), None);
+	}
+
+	#[test_case]
+	fn ring_buffer_drop() {
+		static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+		/// Counts the drops of elements that have been in a ring buffer. Default values are
+		/// placeholders for the elements read out of it and are not counted.
+		#[derive(Default)]
+		struct Counted(bool);
+
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				if self.0 {
+					DROPS.fetch_add(1, Ordering::Relaxed);
+				}
+			}
+		}
+
+		// Moves `count` new elements into the buffer
+		let write = |rb: &mut RingBuffer<Counted, Vec<MaybeUninit<Counted>>>, count: usize| {
+			for _ in 0..count {
+				assert!(rb.push(Counted(true)).is_ok());
+			}
+		};
+		let drops = || DROPS.load(Ordering::Relaxed);
+
+		let mut rb = RingBuffer::with_capacity(8).unwrap();
+		write(&mut rb, 6);
+		// Partial read
+		let mut buf = <[Counted; 4]>::default();
+		assert_eq!(rb.read(&mut buf), 4);
+		assert_eq!(drops(), 0);
+		drop(buf);
+		assert_eq!(drops(), 4);
+
+		// Wrap around
+		write(&mut rb, 4);
+		assert!(rb.pop().is_some());
+		assert_eq!(drops(), 5);
+		// Committing a read drops the elements
+		assert_eq!(rb.commit_read(2), 2);
+		assert_eq!(drops(), 7);
+
+		// Resizing moves the elements without dropping them
+		rb.resize(16).unwrap();
+		assert_eq!(rb.get_data_len(), 3);
+		assert_eq!(drops(), 7);
+
+		// Dropping the buffer drops the remaining elements only
+		drop(rb);
+		assert_eq!(drops(), 10);
+
+		let mut rb = RingBuffer::with_capacity(8).unwrap();
+		write(&mut rb, 8);
+		rb.clear();
+		assert_eq!(drops(), 18);
+		drop(rb);
+		assert_eq!(drops(), 18);
+	}
+
+	/// An I/O interface over a fixed buffer, accepting at most `limit` bytes per operation.
+	struct MockIO {
+		data: [u8; 16],
+		len: usize,
+		limit: usize,
+	}
+
+	impl IO for MockIO {
+		fn get_size(&self) -> u64 {
+			self.len as _
+		}
+
+		fn read(&mut self, offset: u64, buf: &mut [u8]) -> Result<(u64, bool), Errno> {
+			let offset = offset as usize;
+			let len = min(min(buf.len(), self.limit), self.len - offset);
+			buf[..len].copy_from_slice(&self.data[offset..(offset + len)]);
+			Ok((len as _, offset + len >= self.len))
+		}
+
+		fn write(&mut self, offset: u64, buf: &[u8]) -> Result<u64, Errno> {
+			let offset = offset as usize;
+			let len = min(min(buf.len(), self.limit), self.data.len() - offset);
+			self.data[offset..(offset + len)].copy_from_slice(&buf[..len]);
+			self.len = offset + len;
+			Ok(len as _)
+		}
+
+		fn poll(&mut self, _mask: u32) -> Result<u32, Errno> {
+			Ok(0)
+		}
+	}
+
+	#[test_case]
+	fn ring_buffer_read_into() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		let mut io = MockIO {
+			data: [0; 16],
+			len: 0,
+			limit: usize::MAX,
+		};
+
+		// Wrapping
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+		assert_eq!(rb.write(&[1, 2, 3, 4, 5]), 5);
+		assert_eq!(rb.read_into(&mut io).unwrap(), 5);
+		assert_eq!(&io.data[..io.len], &[1, 2, 3, 4, 5]);
+		assert!(rb.is_empty());
+
+		// The sink accepts less than given
+		io.limit = 3;
+		assert_eq!(rb.write(&[6, 7, 8, 9, 10]), 5);
+		assert_eq!(rb.read_into(&mut io).unwrap(), 3);
+		assert_eq!(&io.data[..io.len], &[6, 7, 8]);
+		let mut buf = [0u8; 8];
+		assert_eq!(rb.read(&mut buf), 2);
+		assert_eq!(&buf[..2], &[9, 10]);
+	}
+
+	#[test_case]
+	fn ring_buffer_write_from() {
+		let mut rb = RingBuffer::new([MaybeUninit::uninit(); 8]);
+		let mut io = MockIO {
+			data: [0; 16],
+			len: 5,
+			limit: usize::MAX,
+		};
+		io.data[..5].copy_from_slice(&[1, 2, 3, 4, 5]);
+
+		// Wrapping
+		assert_eq!(rb.write(&[0; 6]), 6);
+		assert_eq!(rb.read(&mut [0; 6]), 6);
+		assert_eq!(rb.write_from(&mut io).unwrap(), 5);
+		let mut buf = [0u8; 8];
+		assert_eq!(rb.read(&mut buf), 5);
+		assert_eq!(&buf[..5], &[1, 2, 3, 4, 5]);
+
+		// Limited by the free space
+		io.len = 16;
+		assert_eq!(rb.write_from(&mut io).unwrap(), 8);
+		assert_eq!(rb.get_available_len(), 0);
+		assert_eq!(rb.write_from(&mut io).unwrap(), 0);
+
+		// The source provides less than asked
+		rb.clear();
+		io.limit = 3;
+		assert_eq!(rb.write_from(&mut io).unwrap(), 3);
+		assert_eq!(rb.get_data_len(), 3);
+	}
+
 	// TODO peek
 }
@@ -0,0 +1,297 @@
+//! A single-producer single-consumer ring buffer, which can be shared between an interrupt
+//! handler and process context without a lock.
+//!
+//! Contrary to [`super::ring_buffer::RingBuffer`], the cursors are atomic: the producer is the
+//! only one to move the write cursor and the consumer is the only one to move the read cursor.
+//! Thus, an interrupt handler can push elements while the interrupted code is popping them,
+//! without the risk of deadlocking on a lock taken with interrupts disabled.
+//!
+//! The storage is a fixed-size array, so the buffer does not require a memory allocator and can
+//! be placed in a `static`.
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// A lock-free single-producer single-consumer ring buffer of `N` elements.
+///
+/// `N` must be a power of two.
+///
+/// The cursors are free-running counters, wrapped on overflow. The number of elements in the
+/// buffer is their difference, which tells an empty buffer from a full one.
+pub struct SpscRingBuffer<T, const N: usize> {
+	/// The linear buffer. Only the slots between the read and write cursors are initialized.
+	buffer: UnsafeCell<[MaybeUninit<T>; N]>,
+
+	/// The read cursor, moved only by the consumer.
+	read_cursor: AtomicUsize,
+	/// The write cursor, moved only by the producer.
+	write_cursor: AtomicUsize,
+}
+
+// Elements are moved from the producer to the consumer, which may be in different contexts
+unsafe impl<T: Send, const N: usize> Sync for SpscRingBuffer<T, N> {}
+
+impl<T, const N: usize> Default for SpscRingBuffer<T, N> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T, const N: usize> SpscRingBuffer<T, N> {
+	/// Creates a new empty instance.
+	///
+	/// If `N` is not a power of two, the function panics.
+	pub const fn new() -> Self {
+		assert!(N.is_power_of_two(), "the size must be a power of two");
+		Self {
+			// An array of `MaybeUninit` does not require initialization
+			buffer: UnsafeCell::new(unsafe { MaybeUninit::uninit().assume_init() }),
+
+			read_cursor: AtomicUsize::new(0),
+			write_cursor: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns the size of the buffer in number of elements.
+	#[inline(always)]
+	pub const fn get_size(&self) -> usize {
+		N
+	}
+
+	/// Returns the number of elements in the buffer.
+	///
+	/// Since the other side may be running concurrently, the value may already be outdated when
+	/// the function returns.
+	pub fn get_data_len(&self) -> usize {
+		let read = self.read_cursor.load(Ordering::Acquire);
+		let write = self.write_cursor.load(Ordering::Acquire);
+		write.wrapping_sub(read)
+	}
+
+	/// Tells whether the buffer is empty.
+	///
+	/// Since the other side may be running concurrently, the value may already be outdated when
+	/// the function returns.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.get_data_len() == 0
+	}
+
+	/// Returns a pointer to the slot for the cursor `cursor`.
+	#[inline(always)]
+	fn get_slot(&self, cursor: usize) -> *mut MaybeUninit<T> {
+		// `N` is a power of two, so the index stays consistent when the cursor wraps
+		unsafe { self.buffer.get().cast::<MaybeUninit<T>>().add(cursor % N) }
+	}
+
+	/// Appends the element `val` at the end of the buffer.
+	///
+	/// If the buffer is full, the function returns the element back as an error.
+	///
+	/// # Safety
+	///
+	/// Only one context at a time may push to the buffer. [`Self::split`] allows enforcing it at
+	/// compile time.
+	pub unsafe fn push(&self, val: T) -> Result<(), T> {
+		let write = self.write_cursor.load(Ordering::Relaxed);
+		// Acquire the slots freed by the consumer
+		let read = self.read_cursor.load(Ordering::Acquire);
+		if write.wrapping_sub(read) == N {
+			return Err(val);
+		}
+		// The slot is free and only the producer writes to free slots
+		self.get_slot(write).write(MaybeUninit::new(val));
+		// Release the element to the consumer
+		self.write_cursor
+			.store(write.wrapping_add(1), Ordering::Release);
+		Ok(())
+	}
+
+	/// Removes the element at the beginning of the buffer and returns it.
+	///
+	/// If the buffer is empty, the function returns `None`.
+	///
+	/// # Safety
+	///
+	/// Only one context at a time may pop from the buffer. [`Self::split`] allows enforcing it at
+	/// compile time.
+	pub unsafe fn pop(&self) -> Option<T> {
+		let read = self.read_cursor.load(Ordering::Relaxed);
+		// Acquire the elements written by the producer
+		let write = self.write_cursor.load(Ordering::Acquire);
+		if read == write {
+			return None;
+		}
+		// The slot has been initialized by the producer and only the consumer reads from it
+		let val = self.get_slot(read).read().assume_init();
+		// Release the slot to the producer
+		self.read_cursor
+			.store(read.wrapping_add(1), Ordering::Release);
+		Some(val)
+	}
+
+	/// Splits the buffer into its producer and consumer sides.
+	///
+	/// Since the buffer is borrowed mutably, each side is guaranteed to be unique.
+	pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+		(
+			Producer {
+				buffer: self,
+				_phantom: PhantomData,
+			},
+			Consumer {
+				buffer: self,
+				_phantom: PhantomData,
+			},
+		)
+	}
+}
+
+impl<T, const N: usize> Drop for SpscRingBuffer<T, N> {
+	fn drop(&mut self) {
+		// Safe since the buffer is borrowed mutably
+		while unsafe { self.pop() }.is_some() {}
+	}
+}
+
+impl<T, const N: usize> fmt::Debug for SpscRingBuffer<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("SpscRingBuffer")
+			.field("size", &N)
+			.field("read_cursor", &self.read_cursor)
+			.field("write_cursor", &self.write_cursor)
+			.finish()
+	}
+}
+
+/// The producer side of a [`SpscRingBuffer`].
+pub struct Producer<'b, T, const N: usize> {
+	/// The buffer.
+	buffer: &'b SpscRingBuffer<T, N>,
+
+	/// Prevents from sharing the producer between contexts.
+	_phantom: PhantomData<*const ()>,
+}
+
+// The producer can be moved to the context that pushes
+unsafe impl<'b, T: Send, const N: usize> Send for Producer<'b, T, N> {}
+
+impl<'b, T, const N: usize> Producer<'b, T, N> {
+	/// Appends the element `val` at the end of the buffer.
+	///
+	/// If the buffer is full, the function returns the element back as an error.
+	#[inline]
+	pub fn push(&mut self, val: T) -> Result<(), T> {
+		// Safe since the producer is unique
+		unsafe { self.buffer.push(val) }
+	}
+}
+
+/// The consumer side of a [`SpscRingBuffer`].
+pub struct Consumer<'b, T, const N: usize> {
+	/// The buffer.
+	buffer: &'b SpscRingBuffer<T, N>,
+
+	/// Prevents from sharing the consumer between contexts.
+	_phantom: PhantomData<*const ()>,
+}
+
+// The consumer can be moved to the context that pops
+unsafe impl<'b, T: Send, const N: usize> Send for Consumer<'b, T, N> {}
+
+impl<'b, T, const N: usize> Consumer<'b, T, N> {
+	/// Returns the number of elements in the buffer.
+	///
+	/// Since the producer may be running concurrently, more elements may have been pushed when
+	/// the function returns.
+	#[inline]
+	pub fn get_data_len(&self) -> usize {
+		self.buffer.get_data_len()
+	}
+
+	/// Removes the element at the beginning of the buffer and returns it.
+	///
+	/// If the buffer is empty, the function returns `None`.
+	#[inline]
+	pub fn pop(&mut self) -> Option<T> {
+		// Safe since the consumer is unique
+		unsafe { self.buffer.pop() }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn spsc_ring_buffer_push_pop() {
+		let mut rb = SpscRingBuffer::<u32, 4>::new();
+		assert!(rb.is_empty());
+		let (mut producer, mut consumer) = rb.split();
+		assert_eq!(consumer.pop(), None);
+
+		for i in 0..4 {
+			producer.push(i).unwrap();
+		}
+		assert_eq!(consumer.get_data_len(), 4);
+		assert_eq!(producer.push(4), Err(4));
+
+		// Wrap around several times
+		for i in 4..100 {
+			assert_eq!(consumer.pop(), Some(i - 4));
+			producer.push(i).unwrap();
+			assert_eq!(consumer.get_data_len(), 4);
+		}
+		for i in 96..100 {
+			assert_eq!(consumer.pop(), Some(i));
+		}
+		assert_eq!(consumer.pop(), None);
+		assert!(rb.is_empty());
+	}
+
+	#[test_case]
+	fn spsc_ring_buffer_static() {
+		static RB: SpscRingBuffer<u8, 8> = SpscRingBuffer::new();
+		unsafe {
+			RB.push(1).unwrap();
+			RB.push(2).unwrap();
+			assert_eq!(RB.get_data_len(), 2);
+			assert_eq!(RB.pop(), Some(1));
+			assert_eq!(RB.pop(), Some(2));
+			assert_eq!(RB.pop(), None);
+		}
+	}
+
+	#[test_case]
+	fn spsc_ring_buffer_drop() {
+		use core::sync::atomic::AtomicUsize;
+
+		static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+		struct Counted;
+
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				DROPS.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+
+		let mut rb = SpscRingBuffer::<Counted, 8>::new();
+		{
+			let (mut producer, mut consumer) = rb.split();
+			for _ in 0..6 {
+				assert!(producer.push(Counted).is_ok());
+			}
+			drop(consumer.pop());
+			drop(consumer.pop());
+		}
+		assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+		// Only the remaining elements are dropped along with the buffer
+		drop(rb);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 6);
+	}
+}
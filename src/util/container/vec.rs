@@ -65,6 +65,8 @@ pub struct Vec<T> {
 	len: usize,
 	/// The vector's data
 	data: Option<malloc::Alloc<T>>,
+	/// The tag the vector's allocations are attributed to, if any.
+	tag: Option<&'static malloc::AllocTag>,
 }
 
 impl<T> Default for Vec<T> {
@@ -72,6 +74,7 @@ impl<T> Default for Vec<T> {
 		Self {
 			len: 0,
 			data: None,
+			tag: None,
 		}
 	}
 }
@@ -82,9 +85,25 @@ impl<T> Vec<T> {
 		Self {
 			len: 0,
 			data: None,
+			tag: None,
 		}
 	}
 
+	/// Creates a new empty vector whose allocations are attributed to `tag`, if any.
+	pub const fn new_tagged(tag: Option<&'static malloc::AllocTag>) -> Self {
+		Self {
+			len: 0,
+			data: None,
+			tag,
+		}
+	}
+
+	/// Returns the tag the vector's allocations are attributed to, if any.
+	#[inline(always)]
+	pub fn tag(&self) -> Option<&'static malloc::AllocTag> {
+		self.tag
+	}
+
 	/// Reallocates the vector's data with the vector's capacity.
 	///
 	/// `capacity` is the new capacity in number of elements.
@@ -105,7 +124,7 @@ impl<T> Vec<T> {
 		} else {
 			// Safe because the memory is rewritten when the object is placed into the
 			// vector
-			let data_ptr = unsafe { malloc::Alloc::new(capacity)? };
+			let data_ptr = unsafe { malloc::Alloc::new_tagged(capacity, self.tag)? };
 			self.data = Some(data_ptr);
 		};
 
@@ -124,9 +143,25 @@ impl<T> Vec<T> {
 		self.realloc(capacity)
 	}
 
+	/// Reserves capacity for at least `additional` more elements, so that pushing them does not
+	/// reallocate.
+	#[inline]
+	pub fn reserve(&mut self, additional: usize) -> AllocResult<()> {
+		self.increase_capacity(additional)
+	}
+
 	/// Creates a new emoty vector with the given capacity.
 	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
-		let mut vec = Self::new();
+		Self::with_capacity_tagged(capacity, None)
+	}
+
+	/// Same as [`Self::with_capacity`], except the vector's allocations are attributed to `tag`,
+	/// if any.
+	pub fn with_capacity_tagged(
+		capacity: usize,
+		tag: Option<&'static malloc::AllocTag>,
+	) -> AllocResult<Self> {
+		let mut vec = Self::new_tagged(tag);
 		vec.realloc(capacity)?;
 		Ok(vec)
 	}
@@ -514,7 +549,7 @@ impl<T: TryClone<Error = E>, E: From<AllocError>> TryClone for Vec<T> {
 	type Error = E;
 
 	fn try_clone(&self) -> Result<Self, Self::Error> {
-		let mut v = Self::with_capacity(self.len)?;
+		let mut v = Self::with_capacity_tagged(self.len, self.tag)?;
 
 		for i in 0..self.len {
 			v.push(self[i].try_clone()?)?;
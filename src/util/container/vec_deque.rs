@@ -0,0 +1,506 @@
+//! A double-ended queue, implemented as a growable ring buffer.
+//!
+//! Contrary to [`super::ring_buffer::RingBuffer`], the queue grows when it is full instead of
+//! rejecting new elements. Like [`super::vec::Vec`], memory allocation failures are returned to
+//! the caller instead of panicking.
+
+use crate::errno::AllocResult;
+use crate::memory::malloc;
+use crate::util::AllocError;
+use crate::util::TryClone;
+use core::cmp::max;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::mem::MaybeUninit;
+use core::num::NonZeroUsize;
+use core::ops::Index;
+use core::ops::IndexMut;
+use core::ptr;
+use core::ptr::drop_in_place;
+use core::slice;
+
+/// A double-ended queue of elements.
+///
+/// The elements are stored in a single buffer, starting at the head and wrapping around at the
+/// end of the buffer. Thus, the elements can be split in two slices (see
+/// [`VecDeque::as_slices`]).
+pub struct VecDeque<T> {
+	/// The queue's data. Only the `len` slots starting at `head`, wrapping around, are
+	/// initialized.
+	data: Option<malloc::Alloc<T>>,
+	/// The offset of the first element in the buffer.
+	head: usize,
+	/// The number of elements in the queue.
+	len: usize,
+}
+
+impl<T> Default for VecDeque<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> VecDeque<T> {
+	/// Creates a new empty queue.
+	pub const fn new() -> Self {
+		Self {
+			data: None,
+			head: 0,
+			len: 0,
+		}
+	}
+
+	/// Creates a new empty queue with enough room to hold at least `capacity` elements without
+	/// growing.
+	pub fn with_capacity(capacity: usize) -> AllocResult<Self> {
+		let mut deque = Self::new();
+		deque.reserve(capacity)?;
+		Ok(deque)
+	}
+
+	/// Returns the number of elements in the queue.
+	#[inline(always)]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Tells whether the queue is empty.
+	#[inline(always)]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Returns the number of elements that can be stored in the queue without growing.
+	#[inline(always)]
+	pub fn capacity(&self) -> usize {
+		self.data.as_ref().map(|d| d.len()).unwrap_or(0)
+	}
+
+	/// Returns the offset in the buffer of the element at position `index` in the queue.
+	///
+	/// `index` must not exceed the capacity.
+	#[inline]
+	fn to_physical(&self, index: usize) -> usize {
+		let off = self.head + index;
+		let capacity = self.capacity();
+		if off >= capacity {
+			off - capacity
+		} else {
+			off
+		}
+	}
+
+	/// Returns a pointer to the buffer.
+	///
+	/// The buffer must be allocated.
+	#[inline]
+	fn buf_ptr(&mut self) -> *mut T {
+		unsafe { self.data.as_mut().unwrap().as_ptr_mut() }
+	}
+
+	/// Moves the elements that wrap around after the capacity of the buffer increased from
+	/// `old_capacity`, so that they are consecutive again.
+	fn handle_capacity_increase(&mut self, old_capacity: usize) {
+		if self.head + self.len <= old_capacity {
+			return;
+		}
+		let new_capacity = self.capacity();
+		let head_len = old_capacity - self.head;
+		let tail_len = self.len - head_len;
+		let ptr = self.buf_ptr();
+		unsafe {
+			if tail_len <= new_capacity - old_capacity {
+				// Move the elements at the beginning of the buffer after the old end
+				ptr::copy_nonoverlapping(ptr, ptr.add(old_capacity), tail_len);
+			} else {
+				// Move the elements at the end of the old buffer to the end of the new one
+				let new_head = new_capacity - head_len;
+				ptr::copy(ptr.add(self.head), ptr.add(new_head), head_len);
+				self.head = new_head;
+			}
+		}
+	}
+
+	/// Reserves room for at least `additional` more elements.
+	///
+	/// If the allocation fails, the queue is left unchanged.
+	pub fn reserve(&mut self, additional: usize) -> AllocResult<()> {
+		let old_capacity = self.capacity();
+		let min = self.len.checked_add(additional).ok_or(AllocError)?;
+		if min <= old_capacity {
+			return Ok(());
+		}
+		// Grow the same way as `Vec`
+		let capacity = max(old_capacity + (old_capacity / 4), min);
+		let capacity = NonZeroUsize::new(capacity).unwrap();
+		// Safe because the memory is rewritten when an element is placed into the queue
+		match &mut self.data {
+			Some(data) => unsafe { data.realloc(capacity)? },
+			None => self.data = Some(unsafe { malloc::Alloc::new(capacity)? }),
+		}
+		self.handle_capacity_increase(old_capacity);
+		Ok(())
+	}
+
+	/// Appends the element `val` at the back of the queue.
+	pub fn push_back(&mut self, val: T) -> AllocResult<()> {
+		self.reserve(1)?;
+		let off = self.to_physical(self.len);
+		unsafe {
+			self.buf_ptr().add(off).write(val);
+		}
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Prepends the element `val` at the front of the queue.
+	pub fn push_front(&mut self, val: T) -> AllocResult<()> {
+		self.reserve(1)?;
+		self.head = self.to_physical(self.capacity() - 1);
+		let off = self.head;
+		unsafe {
+			self.buf_ptr().add(off).write(val);
+		}
+		self.len += 1;
+		Ok(())
+	}
+
+	/// Removes the element at the front of the queue and returns it.
+	///
+	/// If the queue is empty, the function returns `None`.
+	pub fn pop_front(&mut self) -> Option<T> {
+		if self.len == 0 {
+			return None;
+		}
+		let off = self.head;
+		let val = unsafe { self.buf_ptr().add(off).read() };
+		self.head = self.to_physical(1);
+		self.len -= 1;
+		Some(val)
+	}
+
+	/// Removes the element at the back of the queue and returns it.
+	///
+	/// If the queue is empty, the function returns `None`.
+	pub fn pop_back(&mut self) -> Option<T> {
+		if self.len == 0 {
+			return None;
+		}
+		self.len -= 1;
+		let off = self.to_physical(self.len);
+		Some(unsafe { self.buf_ptr().add(off).read() })
+	}
+
+	/// Returns an immutable reference to the element at position `index`.
+	///
+	/// If out of bounds, the function returns `None`.
+	pub fn get(&self, index: usize) -> Option<&T> {
+		if index >= self.len {
+			return None;
+		}
+		let off = self.to_physical(index);
+		self.data.as_ref().map(|d| &d.as_slice()[off])
+	}
+
+	/// Returns a mutable reference to the element at position `index`.
+	///
+	/// If out of bounds, the function returns `None`.
+	pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+		if index >= self.len {
+			return None;
+		}
+		let off = self.to_physical(index);
+		self.data.as_mut().map(|d| &mut d.as_slice_mut()[off])
+	}
+
+	/// Returns an immutable reference to the element at the front of the queue.
+	#[inline]
+	pub fn front(&self) -> Option<&T> {
+		self.get(0)
+	}
+
+	/// Returns a mutable reference to the element at the front of the queue.
+	#[inline]
+	pub fn front_mut(&mut self) -> Option<&mut T> {
+		self.get_mut(0)
+	}
+
+	/// Returns an immutable reference to the element at the back of the queue.
+	#[inline]
+	pub fn back(&self) -> Option<&T> {
+		self.get(self.len.checked_sub(1)?)
+	}
+
+	/// Returns a mutable reference to the element at the back of the queue.
+	#[inline]
+	pub fn back_mut(&mut self) -> Option<&mut T> {
+		self.get_mut(self.len.checked_sub(1)?)
+	}
+
+	/// Returns the elements of the queue, in order, as two slices.
+	///
+	/// The second slice is empty if the elements do not wrap around the end of the buffer.
+	pub fn as_slices(&self) -> (&[T], &[T]) {
+		let Some(data) = &self.data else {
+			return (&[], &[]);
+		};
+		let buf = data.as_slice();
+		let end = self.head + self.len;
+		if end <= buf.len() {
+			(&buf[self.head..end], &[])
+		} else {
+			(&buf[self.head..], &buf[..(end - buf.len())])
+		}
+	}
+
+	/// Same as [`Self::as_slices`], but the slices are mutable.
+	pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+		let head = self.head;
+		let len = self.len;
+		let Some(data) = &mut self.data else {
+			return (&mut [], &mut []);
+		};
+		let buf = data.as_slice_mut();
+		let end = head + len;
+		if end <= buf.len() {
+			(&mut buf[head..end], &mut [])
+		} else {
+			let tail_len = end - buf.len();
+			let (tail, head) = buf.split_at_mut(head);
+			(head, &mut tail[..tail_len])
+		}
+	}
+
+	/// Moves the elements so that they are stored contiguously in the buffer, and returns them
+	/// as a single slice.
+	pub fn make_contiguous(&mut self) -> &mut [T] {
+		let capacity = self.capacity();
+		if self.head + self.len > capacity {
+			// Uninitialized slots are moved along with the elements, without being read
+			let ptr = self.buf_ptr() as *mut MaybeUninit<T>;
+			unsafe { slice::from_raw_parts_mut(ptr, capacity) }.rotate_left(self.head);
+			self.head = 0;
+		}
+		self.as_mut_slices().0
+	}
+
+	/// Returns an iterator on the elements of the queue, from front to back.
+	pub fn iter(&self) -> Iter<'_, T> {
+		let (first, second) = self.as_slices();
+		Iter {
+			first: first.iter(),
+			second: second.iter(),
+		}
+	}
+
+	/// Removes all the elements from the queue.
+	///
+	/// The capacity is kept.
+	pub fn clear(&mut self) {
+		let (first, second) = self.as_mut_slices();
+		let (first, second) = (first as *mut [T], second as *mut [T]);
+		// Reset the queue first so that a panicking destructor does not lead to a double drop
+		self.head = 0;
+		self.len = 0;
+		unsafe {
+			drop_in_place(first);
+			drop_in_place(second);
+		}
+	}
+}
+
+impl<T> Index<usize> for VecDeque<T> {
+	type Output = T;
+
+	#[inline]
+	fn index(&self, index: usize) -> &Self::Output {
+		self.get(index).expect("index out of bounds")
+	}
+}
+
+impl<T> IndexMut<usize> for VecDeque<T> {
+	#[inline]
+	fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+		self.get_mut(index).expect("index out of bounds")
+	}
+}
+
+impl<T: TryClone<Error = E>, E: From<AllocError>> TryClone for VecDeque<T> {
+	type Error = E;
+
+	fn try_clone(&self) -> Result<Self, Self::Error> {
+		let mut deque = Self::with_capacity(self.len)?;
+		for e in self.iter() {
+			deque.push_back(e.try_clone()?)?;
+		}
+		Ok(deque)
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for VecDeque<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
+
+impl<T> Drop for VecDeque<T> {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+impl<'d, T> IntoIterator for &'d VecDeque<T> {
+	type IntoIter = Iter<'d, T>;
+	type Item = &'d T;
+
+	fn into_iter(self) -> Self::IntoIter {
+		self.iter()
+	}
+}
+
+/// Iterator on the elements of a [`VecDeque`].
+pub struct Iter<'d, T> {
+	/// The elements from the head to the end of the buffer.
+	first: slice::Iter<'d, T>,
+	/// The elements that wrap around to the beginning of the buffer.
+	second: slice::Iter<'d, T>,
+}
+
+impl<'d, T> Iterator for Iter<'d, T> {
+	type Item = &'d T;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		self.first.next().or_else(|| self.second.next())
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.first.len() + self.second.len();
+		(len, Some(len))
+	}
+}
+
+impl<'d, T> DoubleEndedIterator for Iter<'d, T> {
+	fn next_back(&mut self) -> Option<Self::Item> {
+		self.second.next_back().or_else(|| self.first.next_back())
+	}
+}
+
+impl<'d, T> ExactSizeIterator for Iter<'d, T> {}
+
+impl<'d, T> FusedIterator for Iter<'d, T> {}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use core::sync::atomic::AtomicUsize;
+	use core::sync::atomic::Ordering;
+
+	#[test_case]
+	fn vec_deque_push_pop() {
+		let mut deque = VecDeque::<usize>::new();
+		assert!(deque.is_empty());
+		assert_eq!(deque.pop_front(), None);
+		assert_eq!(deque.pop_back(), None);
+		assert_eq!(deque.front(), None);
+		assert_eq!(deque.back(), None);
+
+		// Grow while the elements wrap around
+		for i in 0..100 {
+			deque.push_back(i).unwrap();
+			deque.push_front(1000 + i).unwrap();
+		}
+		assert_eq!(deque.len(), 200);
+		assert_eq!(deque.front(), Some(&1099));
+		assert_eq!(deque.back(), Some(&99));
+		for i in 0..100 {
+			assert_eq!(deque[i], 1099 - i);
+			assert_eq!(deque[100 + i], i);
+		}
+		assert!(deque.iter().copied().eq((1000..1100).rev().chain(0..100)));
+		assert!(deque
+			.iter()
+			.rev()
+			.copied()
+			.eq((0..100).rev().chain(1000..1100)));
+
+		for i in 0..100 {
+			assert_eq!(deque.pop_back(), Some(99 - i));
+			assert_eq!(deque.pop_front(), Some(1099 - i));
+		}
+		assert!(deque.is_empty());
+		assert_eq!(deque.iter().next(), None);
+	}
+
+	#[test_case]
+	fn vec_deque_queue() {
+		let mut deque = VecDeque::with_capacity(8).unwrap();
+		let capacity = deque.capacity();
+		assert!(capacity >= 8);
+		// Use as a FIFO, wrapping around without growing
+		for i in 0..1000 {
+			deque.push_back(i).unwrap();
+			if i >= 5 {
+				assert_eq!(deque.pop_front(), Some(i - 5));
+			}
+		}
+		assert_eq!(deque.capacity(), capacity);
+		assert!(deque.iter().copied().eq(995..1000));
+		*deque.front_mut().unwrap() = 0;
+		*deque.back_mut().unwrap() = 1;
+		deque[1] = 2;
+		assert!(deque.iter().copied().eq([0, 2, 997, 998, 1]));
+	}
+
+	#[test_case]
+	fn vec_deque_slices() {
+		let mut deque = VecDeque::with_capacity(4).unwrap();
+		let capacity = deque.capacity();
+		for i in 0..capacity {
+			deque.push_back(i).unwrap();
+		}
+		deque.pop_front();
+		deque.pop_front();
+		deque.push_back(capacity).unwrap();
+		let (first, second) = deque.as_slices();
+		assert_eq!(first.len() + second.len(), capacity - 1);
+		assert_eq!(second, &[capacity]);
+
+		let contiguous = deque.make_contiguous();
+		assert!(contiguous.iter().copied().eq(2..=capacity));
+		let (first, second) = deque.as_slices();
+		assert_eq!(first.len(), capacity - 1);
+		assert!(second.is_empty());
+
+		let clone = deque.try_clone().unwrap();
+		assert!(clone.iter().eq(deque.iter()));
+	}
+
+	#[test_case]
+	fn vec_deque_drop() {
+		static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+		struct Counted;
+
+		impl Drop for Counted {
+			fn drop(&mut self) {
+				DROPS.fetch_add(1, Ordering::Relaxed);
+			}
+		}
+
+		let mut deque = VecDeque::new();
+		for _ in 0..10 {
+			deque.push_front(Counted).unwrap();
+			deque.push_back(Counted).unwrap();
+		}
+		drop(deque.pop_front());
+		drop(deque.pop_back());
+		assert_eq!(DROPS.load(Ordering::Relaxed), 2);
+		deque.clear();
+		assert_eq!(DROPS.load(Ordering::Relaxed), 20);
+		for _ in 0..5 {
+			deque.push_front(Counted).unwrap();
+		}
+		drop(deque);
+		assert_eq!(DROPS.load(Ordering::Relaxed), 25);
+	}
+}
@@ -1,5 +1,6 @@
 //! This module implements the Input/Output interface trait.
 
+use crate::errno;
 use crate::errno::Errno;
 
 /// Poll event: There is data to read.
@@ -26,6 +27,17 @@ pub const POLLWRBAND: u32 = 0x200;
 /// of connection.
 pub const POLLRDHUP: u32 = 0x2000;
 
+/// The outcome of a read on an I/O interface.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadOutcome {
+	/// The given number of bytes has been read. More data may come.
+	Data(u64),
+	/// The end of the input stream has been reached. No data has been read.
+	Eof,
+	/// No data is available yet, and the I/O interface is in non-blocking mode.
+	WouldBlock,
+}
+
 /// Trait representing a data I/O interface.
 pub trait IO {
 	/// Returns the size of the underlying data.
@@ -41,6 +53,23 @@ pub trait IO {
 	/// value of `true` is equivalent to the End Of File (EOF).
 	fn read(&mut self, offset: u64, buff: &mut [u8]) -> Result<(u64, bool), Errno>;
 
+	/// Same as [`Self::read`], but tells apart the end of the input stream from the absence of
+	/// data in non-blocking mode.
+	///
+	/// If data is read along with reaching the end of the input stream, the function returns
+	/// [`ReadOutcome::Data`]. The next read returns [`ReadOutcome::Eof`].
+	///
+	/// The default implementation relies on [`Self::read`] returning `EAGAIN` when it would
+	/// block.
+	fn read_outcome(&mut self, offset: u64, buff: &mut [u8]) -> Result<ReadOutcome, Errno> {
+		match self.read(offset, buff) {
+			Ok((0, true)) => Ok(ReadOutcome::Eof),
+			Ok((len, _)) => Ok(ReadOutcome::Data(len)),
+			Err(e) if e.as_int() == errno::EAGAIN => Ok(ReadOutcome::WouldBlock),
+			Err(e) => Err(e),
+		}
+	}
+
 	/// Reads data from `buff` and writes it into the I/O.
 	///
 	/// `offset` is the offset in the I/O to the beginning of the data to write.
@@ -16,7 +16,11 @@
 //!
 //! If an exception is raised while a mutex that disables interruptions is
 //! acquired, the behaviour is undefined.
+//!
+//! For data that is read often but rarely modified, [`rwlock::RwLock`] allows
+//! several readers at the same time.
 
+pub mod rwlock;
 pub mod spinlock;
 
 use crate::idt;
@@ -46,6 +50,52 @@ static mut INT_DISABLE_REFS: State = State {
 	enabled: false,
 };
 
+/// Disables interruptions before acquiring a lock that must not be interrupted, saving their
+/// previous state.
+pub(crate) fn int_disable() {
+	let state = idt::is_interrupt_enabled();
+
+	// Here is assumed that no interruption will change eflags' INT. Which could
+	// cause a race condition
+
+	// Disabling interrupts before locking to ensure no interrupt will occure while
+	// locking
+	crate::cli!();
+
+	// Updating the current thread's state
+	// Safe because interrupts are disabled and the value can be accessed only by
+	// the current core
+	unsafe {
+		if INT_DISABLE_REFS.ref_count == 0 {
+			INT_DISABLE_REFS.enabled = state;
+		}
+		INT_DISABLE_REFS.ref_count += 1;
+	}
+}
+
+/// Restores the state of interruptions saved by [`int_disable`], after releasing the lock.
+///
+/// # Safety
+///
+/// The function must be called once for each call to [`int_disable`], with interruptions
+/// still disabled.
+pub(crate) unsafe fn int_restore() {
+	// Updating references count
+	INT_DISABLE_REFS.ref_count -= 1;
+	let state = if INT_DISABLE_REFS.ref_count == 0 {
+		INT_DISABLE_REFS.enabled
+	} else {
+		false
+	};
+
+	// Restoring interrupts state after unlocking
+	if state {
+		crate::sti!();
+	} else {
+		crate::cli!();
+	}
+}
+
 /// Type used to declare a guard meant to unlock the associated `Mutex` at the
 /// moment the execution gets out of the scope of its declaration.
 pub struct MutexGuard<'a, T: ?Sized, const INT: bool> {
@@ -142,29 +192,9 @@ impl<T: ?Sized, const INT: bool> Mutex<T, INT> {
 		};
 
 		if !INT {
-			let state = idt::is_interrupt_enabled();
-
-			// Here is assumed that no interruption will change eflags' INT. Which could
-			// cause a race condition
-
-			// Disabling interrupts before locking to ensure no interrupt will occure while
-			// locking
-			crate::cli!();
-
-			inner.spin.lock();
-
-			// Updating the current thread's state
-			// Safe because interrupts are disabled and the value can be accessed only by
-			// the current core
-			unsafe {
-				if INT_DISABLE_REFS.ref_count == 0 {
-					INT_DISABLE_REFS.enabled = state;
-				}
-				INT_DISABLE_REFS.ref_count += 1;
-			}
-		} else {
-			inner.spin.lock();
+			int_disable();
 		}
+		inner.spin.lock();
 
 		MutexGuard {
 			mutex: self,
@@ -182,26 +212,9 @@ impl<T: ?Sized, const INT: bool> Mutex<T, INT> {
 	pub unsafe fn unlock(&self) {
 		let inner = &mut (*self.inner.get());
 
+		inner.spin.unlock();
 		if !INT {
-			// Updating references count
-			INT_DISABLE_REFS.ref_count -= 1;
-			let state = if INT_DISABLE_REFS.ref_count == 0 {
-				INT_DISABLE_REFS.enabled
-			} else {
-				false
-			};
-
-			// The state to restore
-			inner.spin.unlock();
-
-			// Restoring interrupts state after unlocking
-			if state {
-				crate::sti!();
-			} else {
-				crate::cli!();
-			}
-		} else {
-			inner.spin.unlock();
+			int_restore();
 		}
 	}
 }
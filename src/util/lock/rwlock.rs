@@ -0,0 +1,315 @@
+//! This module implements the `RwLock` structure, a lock allowing either several readers or a
+//! single writer to access the data at the same time.
+//!
+//! It is meant for data that is read often but rarely modified, for which a `Mutex` would
+//! needlessly serialize readers.
+
+use super::int_disable;
+use super::int_restore;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::ops::Deref;
+use core::ops::DerefMut;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+/// The bit of the state telling whether a writer holds the lock. The other bits are the number
+/// of readers.
+const WRITER: usize = 1 << (usize::BITS - 1);
+
+/// Type used to declare a guard giving shared access to the data of a `RwLock`, which is
+/// released when the guard is dropped.
+pub struct RwLockReadGuard<'a, T: ?Sized, const INT: bool> {
+	/// The lock associated to the guard
+	lock: &'a RwLock<T, INT>,
+}
+
+impl<T: ?Sized, const INT: bool> Deref for RwLockReadGuard<'_, T, INT> {
+	type Target = T;
+
+	fn deref(&self) -> &Self::Target {
+		unsafe { &*self.lock.data.get() }
+	}
+}
+
+unsafe impl<T: ?Sized + Sync, const INT: bool> Sync for RwLockReadGuard<'_, T, INT> {}
+
+impl<T: ?Sized, const INT: bool> Drop for RwLockReadGuard<'_, T, INT> {
+	fn drop(&mut self) {
+		unsafe {
+			self.lock.read_unlock();
+		}
+	}
+}
+
+/// Type used to declare a guard giving exclusive access to the data of a `RwLock`, which is
+/// released when the guard is dropped.
+pub struct RwLockWriteGuard<'a, T: ?Sized, const INT: bool> {
+	/// The lock associated to the guard
+	lock: &'a RwLock<T, INT>,
+}
+
+impl<T: ?Sized, const INT: bool> Deref for RwLockWriteGuard<'_, T, INT> {
+	type Target = T;
+
+	fn deref(&self) -> &Self::Target {
+		unsafe { &*self.lock.data.get() }
+	}
+}
+
+impl<T: ?Sized, const INT: bool> DerefMut for RwLockWriteGuard<'_, T, INT> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		unsafe { &mut *self.lock.data.get() }
+	}
+}
+
+unsafe impl<T: ?Sized + Sync, const INT: bool> Sync for RwLockWriteGuard<'_, T, INT> {}
+
+impl<T: ?Sized, const INT: bool> Drop for RwLockWriteGuard<'_, T, INT> {
+	fn drop(&mut self) {
+		unsafe {
+			self.lock.write_unlock();
+		}
+	}
+}
+
+/// The object wrapped in a `RwLock` can be accessed either by several readers or by a single
+/// writer at a time.
+///
+/// Writers have the preference: while a writer is waiting, new readers wait too, so that a
+/// constant flow of readers cannot starve writers. Thus, trying to acquire the lock for reading
+/// while already holding it for reading may deadlock.
+///
+/// The `INT` generic parameter tells whether interrupts are allowed while
+/// the lock is held. The default value is `true`.
+pub struct RwLock<T: ?Sized, const INT: bool = true> {
+	/// The number of readers, or [`WRITER`] if a writer holds the lock.
+	state: AtomicUsize,
+	/// The number of writers waiting for the lock.
+	writers_waiting: AtomicUsize,
+
+	/// The data associated to the lock.
+	data: UnsafeCell<T>,
+}
+
+impl<T, const INT: bool> RwLock<T, INT> {
+	/// Creates a new lock with the given data to be owned.
+	pub const fn new(data: T) -> Self {
+		Self {
+			state: AtomicUsize::new(0),
+			writers_waiting: AtomicUsize::new(0),
+
+			data: UnsafeCell::new(data),
+		}
+	}
+
+	/// Consumes the lock and returns the inner value.
+	pub fn into_inner(self) -> T {
+		self.data.into_inner()
+	}
+}
+
+impl<T: ?Sized, const INT: bool> RwLock<T, INT> {
+	/// Tries to register a reader.
+	fn try_read_lock(&self) -> bool {
+		// Give way to waiting writers
+		if self.writers_waiting.load(Ordering::Relaxed) > 0 {
+			return false;
+		}
+		let state = self.state.load(Ordering::Relaxed);
+		state & WRITER == 0
+			&& self
+				.state
+				.compare_exchange_weak(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
+				.is_ok()
+	}
+
+	/// Tries to register a writer.
+	fn try_write_lock(&self) -> bool {
+		self.state
+			.compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+			.is_ok()
+	}
+
+	/// Acquires the lock for reading.
+	///
+	/// If a writer holds the lock or is waiting for it, the thread shall wait until it is
+	/// released.
+	///
+	/// The function returns a `RwLockReadGuard` associated with the `RwLock`. When dropped, the
+	/// lock is released.
+	pub fn read(&self) -> RwLockReadGuard<T, INT> {
+		if !INT {
+			int_disable();
+		}
+		while !self.try_read_lock() {
+			hint::spin_loop();
+		}
+		RwLockReadGuard {
+			lock: self,
+		}
+	}
+
+	/// Acquires the lock for writing.
+	///
+	/// If the lock is held, the thread shall wait until it is released.
+	///
+	/// The function returns a `RwLockWriteGuard` associated with the `RwLock`. When dropped, the
+	/// lock is released.
+	pub fn write(&self) -> RwLockWriteGuard<T, INT> {
+		if !INT {
+			int_disable();
+		}
+		if !self.try_write_lock() {
+			// Prevent new readers from acquiring the lock while waiting
+			self.writers_waiting.fetch_add(1, Ordering::Relaxed);
+			while !self.try_write_lock() {
+				hint::spin_loop();
+			}
+			self.writers_waiting.fetch_sub(1, Ordering::Relaxed);
+		}
+		RwLockWriteGuard {
+			lock: self,
+		}
+	}
+
+	/// Tries to acquire the lock for reading without waiting.
+	///
+	/// If a writer holds the lock or is waiting for it, the function returns `None`.
+	pub fn try_read(&self) -> Option<RwLockReadGuard<T, INT>> {
+		if !INT {
+			int_disable();
+		}
+		if self.try_read_lock() {
+			Some(RwLockReadGuard {
+				lock: self,
+			})
+		} else {
+			if !INT {
+				unsafe {
+					int_restore();
+				}
+			}
+			None
+		}
+	}
+
+	/// Tries to acquire the lock for writing without waiting.
+	///
+	/// If the lock is held, the function returns `None`.
+	pub fn try_write(&self) -> Option<RwLockWriteGuard<T, INT>> {
+		if !INT {
+			int_disable();
+		}
+		if self.try_write_lock() {
+			Some(RwLockWriteGuard {
+				lock: self,
+			})
+		} else {
+			if !INT {
+				unsafe {
+					int_restore();
+				}
+			}
+			None
+		}
+	}
+
+	/// Returns a mutable reference to the data.
+	///
+	/// Since the lock is borrowed mutably, no locking is required.
+	pub fn get_mut(&mut self) -> &mut T {
+		self.data.get_mut()
+	}
+
+	/// Releases the lock held for reading.
+	///
+	/// # Safety
+	///
+	/// The lock must be held for reading by the caller.
+	unsafe fn read_unlock(&self) {
+		self.state.fetch_sub(1, Ordering::Release);
+		if !INT {
+			int_restore();
+		}
+	}
+
+	/// Releases the lock held for writing.
+	///
+	/// # Safety
+	///
+	/// The lock must be held for writing by the caller.
+	unsafe fn write_unlock(&self) {
+		self.state.store(0, Ordering::Release);
+		if !INT {
+			int_restore();
+		}
+	}
+}
+
+unsafe impl<T: ?Sized + Send, const INT: bool> Send for RwLock<T, INT> {}
+
+unsafe impl<T: ?Sized + Send + Sync, const INT: bool> Sync for RwLock<T, INT> {}
+
+impl<T: ?Sized + fmt::Debug, const INT: bool> fmt::Debug for RwLock<T, INT> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.try_read() {
+			Some(guard) => f.debug_struct("RwLock").field("data", &&*guard).finish(),
+			None => f.debug_struct("RwLock").finish_non_exhaustive(),
+		}
+	}
+}
+
+/// Type alias on `RwLock` representing a lock which blocks interrupts.
+pub type IntRwLock<T> = RwLock<T, false>;
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test_case]
+	fn rwlock_readers() {
+		let lock = RwLock::<u32>::new(42);
+		let a = lock.read();
+		let b = lock.read();
+		assert_eq!(*a + *b, 84);
+		assert!(lock.try_read().is_some());
+		assert!(lock.try_write().is_none());
+		drop(a);
+		assert!(lock.try_write().is_none());
+		drop(b);
+
+		*lock.write() += 1;
+		assert_eq!(*lock.read(), 43);
+	}
+
+	#[test_case]
+	fn rwlock_writer() {
+		let mut lock = RwLock::<u32>::new(0);
+		{
+			let mut guard = lock.write();
+			*guard = 1;
+			assert!(lock.try_read().is_none());
+			assert!(lock.try_write().is_none());
+		}
+		assert!(lock.try_write().is_some());
+		*lock.get_mut() += 1;
+		assert_eq!(lock.into_inner(), 2);
+	}
+
+	#[test_case]
+	fn rwlock_writer_preference() {
+		let lock = RwLock::<u32>::new(0);
+		let guard = lock.read();
+		// Simulate a writer waiting for the readers to finish
+		lock.writers_waiting.fetch_add(1, Ordering::Relaxed);
+		assert!(lock.try_read().is_none());
+		drop(guard);
+		let guard = lock.try_write();
+		assert!(guard.is_some());
+		lock.writers_waiting.fetch_sub(1, Ordering::Relaxed);
+		drop(guard);
+		assert!(lock.try_read().is_some());
+	}
+}
@@ -134,17 +134,18 @@ pub fn slice_copy(src: &[u8], dst: &mut [u8]) {
 
 /// Reinterprets the given slice of bytes as another type.
 ///
-/// If the type is too large in size to fit in the slice, the function returns `None`.
+/// If the type is too large in size to fit in the slice, or if the slice is not aligned
+/// properly for the type, the function returns `None`.
 ///
 /// # Safety
 ///
 /// Not every types are defined for every possible memory representations. Thus, some values
 /// passed as input to this function might be invalid for a given type, which is undefined.
 pub unsafe fn reinterpret<T>(slice: &[u8]) -> Option<&T> {
-	if size_of::<T>() <= slice.len() {
-		// Safe because the slice is large enough
-		let val = &*(slice.as_ptr() as *const T);
-		Some(val)
+	let ptr = slice.as_ptr() as *const T;
+	if size_of::<T>() <= slice.len() && ptr.is_aligned() {
+		// Safe because the slice is large enough and aligned
+		Some(&*ptr)
 	} else {
 		None
 	}
@@ -306,5 +307,24 @@ mod test {
 
 	// TODO More tests on memcmp
 
+	#[test_case]
+	fn reinterpret_short() {
+		let buf = [0u32; 1];
+		let bytes = unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, 4) };
+		assert_eq!(unsafe { reinterpret::<u32>(bytes) }, Some(&0));
+		assert_eq!(unsafe { reinterpret::<u32>(&bytes[..3]) }, None);
+		assert_eq!(unsafe { reinterpret::<u32>(&[]) }, None);
+	}
+
+	#[test_case]
+	fn reinterpret_misaligned() {
+		let buf = [0u32; 2];
+		let bytes = unsafe { slice::from_raw_parts(buf.as_ptr() as *const u8, 8) };
+		assert_eq!(unsafe { reinterpret::<u32>(&bytes[4..]) }, Some(&0));
+		for off in 1..4 {
+			assert_eq!(unsafe { reinterpret::<u32>(&bytes[off..]) }, None);
+		}
+	}
+
 	// TODO Test `memset`
 }